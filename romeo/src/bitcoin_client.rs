@@ -1,28 +1,54 @@
 //! RPC Bitcoin client
 
 use std::{
+	collections::HashMap,
 	sync::{Arc, Mutex},
-	time::Duration,
+	time::{Duration, Instant},
 };
 
 use anyhow::anyhow;
+use async_trait::async_trait;
 use bdk::{
-	bitcoin::{Block, PrivateKey, Script, Transaction, Txid},
+	bitcoin::{
+		psbt::PartiallySignedTransaction, Block, OutPoint, PrivateKey, Script,
+		Transaction, Txid, Witness,
+	},
 	bitcoincore_rpc::{self, Auth, Client as RPCClient, RpcApi},
 	blockchain::{
 		ConfigurableBlockchain, ElectrumBlockchain, ElectrumBlockchainConfig,
 	},
 	database::MemoryDatabase,
 	template::P2TR,
-	SignOptions, SyncOptions, Wallet,
+	wallet::tx_builder::TxOrdering,
+	FeeRate, SignOptions, SyncOptions, Wallet,
 };
+use futures::{stream, Future, StreamExt, TryStreamExt};
 use sbtc_core::operations::op_return::utils::reorder_outputs;
 use tokio::{task::spawn_blocking, time::sleep};
-use tracing::trace;
+use tracing::{trace, warn};
+use url::Url;
+
+use crate::{
+	config::{BitcoinBackend, Config},
+	esplora_client::EsploraClient,
+	event::TransactionStatus,
+};
+
+/// How long a wallet sync can be relied on instead of syncing again
+const WALLET_SYNC_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// Factor applied to the fee rate of a transaction replaced by a fee bump
+const FEE_BUMP_FACTOR: f32 = 1.5;
+
+/// Number of blocks a fee bump aims to confirm within
+const FEE_BUMP_TARGET_BLOCKS: u16 = 2;
 
-use crate::{config::Config, event::TransactionStatus};
+/// How many blocks are fetched at once from a Bitcoin node RPC
+const RPC_BLOCK_FETCH_CONCURRENCY: usize = 8;
 
-const BLOCK_POLLING_INTERVAL: Duration = Duration::from_secs(5);
+/// How many blocks are fetched at once from Esplora, whose public instances
+/// rate limit clients
+const ESPLORA_BLOCK_FETCH_CONCURRENCY: usize = 2;
 
 /// Bitcoin RPC client
 #[derive(Clone)]
@@ -31,6 +57,9 @@ pub struct Client {
 	blockchain: Arc<ElectrumBlockchain>,
 	// required for fulfillment txs
 	wallet: Arc<Mutex<Wallet<MemoryDatabase>>>,
+	// when the wallet was last synced without spending from it since
+	last_wallet_sync: Arc<Mutex<Option<Instant>>>,
+	missing_transactions: Arc<Mutex<MissingTransactions>>,
 }
 
 impl Client {
@@ -63,6 +92,8 @@ impl Client {
 			config,
 			blockchain: Arc::new(blockchain),
 			wallet: Arc::new(Mutex::new(wallet)),
+			last_wallet_sync: Default::default(),
+			missing_transactions: Default::default(),
 		})
 	}
 
@@ -74,8 +105,17 @@ impl Client {
 		F: FnOnce(RPCClient) -> bitcoincore_rpc::Result<T> + Send + 'static,
 		T: Send + 'static,
 	{
-		let mut url = self.config.bitcoin_node_url.clone();
+		Self::execute_at(self.config.bitcoin_node_url.clone(), f).await
+	}
 
+	async fn execute_at<F, T>(
+		mut url: Url,
+		f: F,
+	) -> anyhow::Result<bitcoincore_rpc::Result<T>>
+	where
+		F: FnOnce(RPCClient) -> bitcoincore_rpc::Result<T> + Send + 'static,
+		T: Send + 'static,
+	{
 		let username = url.username().to_string();
 		let password = url.password().unwrap_or_default().to_string();
 
@@ -96,12 +136,34 @@ impl Client {
 		Ok(spawn_blocking(move || f(client)).await?)
 	}
 
-	/// Broadcast a transaction
-	pub async fn broadcast(&self, tx: Transaction) -> anyhow::Result<()> {
-		self.execute(move |client| client.send_raw_transaction(&tx))
-			.await??;
+	/// Broadcast a transaction to every configured node, succeeding if any
+	/// of them accepts it
+	pub async fn broadcast(&self, tx: Transaction) -> anyhow::Result<Txid> {
+		let urls = std::iter::once(self.config.bitcoin_node_url.clone())
+			.chain(self.config.additional_bitcoin_node_urls.iter().cloned())
+			.collect::<Vec<_>>();
+
+		if self.config.bitcoin_backend == BitcoinBackend::Esplora {
+			return broadcast_to_any(urls, move |url| {
+				let tx = tx.clone();
+
+				async move { EsploraClient::new(url).broadcast(&tx).await }
+			})
+			.await;
+		}
 
-		Ok(())
+		broadcast_to_any(urls, move |url| {
+			let tx = tx.clone();
+
+			async move {
+				Self::execute_at(url, move |client| {
+					client.send_raw_transaction(&tx)
+				})
+				.await?
+				.map_err(Into::into)
+			}
+		})
+		.await
 	}
 
 	/// Get transaction status
@@ -109,6 +171,45 @@ impl Client {
 		&self,
 		txid: Txid,
 	) -> anyhow::Result<TransactionStatus> {
+		let presence = match self.config.bitcoin_backend {
+			BitcoinBackend::Rpc => {
+				check_presence(
+					|| self.get_rpc_tx_presence(txid),
+					|| self.spends_spent_outputs(txid),
+				)
+				.await?
+			}
+			BitcoinBackend::Esplora => {
+				let presence = self.esplora().get_tx_presence(txid).await?;
+
+				Some((presence.is_confirmed, presence.in_mempool))
+			}
+		};
+
+		let mut missing_transactions = self
+			.missing_transactions
+			.lock()
+			.map_err(|_| anyhow!("Cannot get missing transactions lock"))?;
+
+		let res = match presence {
+			Some((is_confirmed, in_mempool)) => missing_transactions.status(
+				txid,
+				is_confirmed,
+				in_mempool,
+				self.config.bitcoin_rejection_grace_polls,
+			),
+			None => missing_transactions.conflicted(txid),
+		};
+
+		tracing::debug!("BTC TX {} IS {:?}", txid, res);
+
+		Ok(res)
+	}
+
+	async fn get_rpc_tx_presence(
+		&self,
+		txid: Txid,
+	) -> anyhow::Result<(bool, bool)> {
 		let is_confirmed = self
 			.execute(move |client| client.get_raw_transaction_info(&txid, None))
 			.await?
@@ -122,31 +223,71 @@ impl Client {
 			.await?
 			.is_ok();
 
-		let res = match (is_confirmed, in_mempool) {
-			(true, false) => TransactionStatus::Confirmed,
-			(false, true) => TransactionStatus::Broadcasted,
-			(false, false) => TransactionStatus::Rejected,
-			(true, true) => {
-				panic!("Transaction cannot be both confirmed and pending")
-			}
+		Ok((is_confirmed, in_mempool))
+	}
+
+	/// Whether an output spent by a wallet transaction is no longer unspent
+	async fn spends_spent_outputs(&self, txid: Txid) -> anyhow::Result<bool> {
+		let tx = self
+			.wallet
+			.lock()
+			.map_err(|_| anyhow!("Cannot get wallet read lock"))?
+			.get_tx(&txid, true)?
+			.and_then(|details| details.transaction);
+
+		// Only the inputs of transactions built by the wallet are known
+		let Some(tx) = tx else {
+			return Ok(false);
 		};
 
-		tracing::debug!("BTC TX {} IS {:?}", txid, res);
+		let spent_outputs: Vec<OutPoint> =
+			tx.input.iter().map(|input| input.previous_output).collect();
 
-		Ok(res)
+		let unspent_outputs = self
+			.execute(move |client| {
+				spent_outputs
+					.iter()
+					.map(|outpoint| {
+						client.get_tx_out(
+							&outpoint.txid,
+							outpoint.vout,
+							Some(true),
+						)
+					})
+					.collect::<bitcoincore_rpc::Result<Vec<_>>>()
+			})
+			.await??;
+
+		Ok(unspent_outputs.iter().any(Option::is_none))
 	}
 
 	/// Get block
 	pub async fn get_block(
 		&self,
 		block_height: u32,
+	) -> anyhow::Result<(u32, Block)> {
+		if self.config.bitcoin_backend == BitcoinBackend::Esplora {
+			return self.get_esplora_block(block_height).await;
+		}
+
+		Self::get_rpc_block_at(
+			self.config.bitcoin_node_url.clone(),
+			block_height,
+			self.config.block_polling_interval(),
+		)
+		.await
+	}
+
+	async fn get_rpc_block_at(
+		url: Url,
+		block_height: u32,
+		polling_interval: Duration,
 	) -> anyhow::Result<(u32, Block)> {
 		let block_hash = loop {
-			let res = self
-				.execute(move |client| {
-					client.get_block_hash(block_height as u64)
-				})
-				.await?;
+			let res = Self::execute_at(url.clone(), move |client| {
+				client.get_block_hash(block_height as u64)
+			})
+			.await?;
 
 			match res {
 				Ok(hash) => {
@@ -179,18 +320,104 @@ impl Client {
 				}
 			};
 
-			sleep(BLOCK_POLLING_INTERVAL).await;
+			sleep(polling_interval).await;
 		};
 
-		let block = self
-			.execute(move |client| client.get_block(&block_hash))
-			.await??;
+		let block =
+			Self::execute_at(url, move |client| client.get_block(&block_hash))
+				.await??;
 
 		Ok((block_height, block))
 	}
 
+	/// Get the blocks from height `from` to height `to` inclusive, in
+	/// ascending height order
+	pub async fn get_blocks(
+		&self,
+		from: u32,
+		to: u32,
+	) -> anyhow::Result<Vec<(u32, Block)>> {
+		if self.config.bitcoin_backend == BitcoinBackend::Esplora {
+			return fetch_blocks(
+				from,
+				to,
+				ESPLORA_BLOCK_FETCH_CONCURRENCY,
+				|block_height| self.get_esplora_block(block_height),
+			)
+			.await;
+		}
+
+		Self::get_rpc_blocks_at(
+			self.config.bitcoin_node_url.clone(),
+			from,
+			to,
+			self.config.block_polling_interval(),
+		)
+		.await
+	}
+
+	async fn get_rpc_blocks_at(
+		url: Url,
+		from: u32,
+		to: u32,
+		polling_interval: Duration,
+	) -> anyhow::Result<Vec<(u32, Block)>> {
+		fetch_blocks(
+			from,
+			to,
+			RPC_BLOCK_FETCH_CONCURRENCY,
+			move |block_height| {
+				Self::get_rpc_block_at(
+					url.clone(),
+					block_height,
+					polling_interval,
+				)
+			},
+		)
+		.await
+	}
+
+	async fn get_esplora_block(
+		&self,
+		block_height: u32,
+	) -> anyhow::Result<(u32, Block)> {
+		let esplora = self.esplora();
+
+		let block_hash = loop {
+			match esplora.get_block_hash(block_height).await {
+				Ok(Some(hash)) => {
+					trace!(
+						"Got Bitcoin block hash at height {}: {}",
+						block_height,
+						hash
+					);
+					break hash;
+				}
+				Ok(None) => trace!("Bitcoin block not found, retrying..."),
+				Err(err) => {
+					trace!(
+						"Esplora error fetching block hash, retrying: {}",
+						err
+					)
+				}
+			}
+
+			sleep(self.config.block_polling_interval()).await;
+		};
+
+		Ok((block_height, esplora.get_block(block_hash).await?))
+	}
+
+	fn esplora(&self) -> EsploraClient {
+		EsploraClient::new(self.config.bitcoin_node_url.clone())
+	}
+
 	/// Get current block height
 	pub async fn get_height(&self) -> anyhow::Result<u32> {
+		if self.config.bitcoin_backend == BitcoinBackend::Esplora {
+			return self.esplora().get_height().await;
+		}
+
 		let info = self
 			.execute(|client| client.get_blockchain_info())
 			.await??;
@@ -198,15 +425,142 @@ impl Client {
 		Ok(info.blocks as u32)
 	}
 
+	/// Estimate the fee rate, in sats per vbyte, for a transaction to confirm
+	/// within the target number of blocks, never below the configured floor
+	pub async fn estimate_smart_fee(
+		&self,
+		target_blocks: u16,
+	) -> anyhow::Result<u64> {
+		if self.config.bitcoin_backend == BitcoinBackend::Esplora {
+			anyhow::bail!("Fee estimation requires the RPC Bitcoin backend");
+		}
+
+		Self::estimate_smart_fee_at(
+			self.config.bitcoin_node_url.clone(),
+			target_blocks,
+			self.config.bitcoin_fee_rate_floor,
+		)
+		.await
+	}
+
+	async fn estimate_smart_fee_at(
+		url: Url,
+		target_blocks: u16,
+		fee_rate_floor: u64,
+	) -> anyhow::Result<u64> {
+		let estimate = Self::execute_at(url, move |client| {
+			client.estimate_smart_fee(target_blocks, None)
+		})
+		.await??;
+
+		// Nodes without enough fee data, such as regtest ones, report errors
+		// instead of a fee rate
+		let Some(fee_rate) = estimate.fee_rate else {
+			trace!(
+				"No fee estimate for {} blocks, using the floor: {:?}",
+				target_blocks,
+				estimate.errors
+			);
+			return Ok(fee_rate_floor);
+		};
+
+		// The node estimates fee rates in BTC per kvB
+		Ok(((fee_rate.to_sat() + 999) / 1000).max(fee_rate_floor))
+	}
+
+	/// Sync the wallet UTXOs so the next transaction can be built without
+	/// syncing first
+	pub async fn refresh_wallet_utxos(&self) -> anyhow::Result<()> {
+		let blockchain = self.blockchain.clone();
+		let wallet = self.wallet.clone();
+		let last_wallet_sync = self.last_wallet_sync.clone();
+
+		spawn_blocking::<_, anyhow::Result<()>>(move || {
+			let wallet = wallet
+				.lock()
+				.map_err(|_| anyhow!("Cannot get wallet read lock"))?;
+
+			wallet.sync(&blockchain, SyncOptions::default())?;
+
+			*last_wallet_sync
+				.lock()
+				.map_err(|_| anyhow!("Cannot get wallet sync lock"))? =
+				Some(Instant::now());
+
+			Ok(())
+		})
+		.await?
+	}
+
+	/// Sign the inputs of an externally built transaction which spend wallet
+	/// UTXOs, leaving other inputs untouched
+	pub async fn sign(&self, tx: Transaction) -> anyhow::Result<Transaction> {
+		let blockchain = self.blockchain.clone();
+		let wallet = self.wallet.clone();
+
+		spawn_blocking(move || {
+			let wallet = wallet
+				.lock()
+				.map_err(|_| anyhow!("Cannot get wallet read lock"))?;
+
+			wallet.sync(&blockchain, SyncOptions::default())?;
+
+			sign_transaction(&wallet, tx)
+		})
+		.await?
+	}
+
 	/// Sign and broadcast a transaction
 	pub async fn sign_and_broadcast(
 		&self,
 		outputs: Vec<(Script, u64)>,
 	) -> anyhow::Result<Txid> {
+		self.sign_with_synced_wallet(move |wallet| {
+			build_transaction(wallet, outputs, None)
+		})
+		.await
+	}
+
+	/// Sign and broadcast a transaction paying the given fee rate, signalling
+	/// BIP125 replaceability so its fee can be bumped later
+	pub async fn sign_and_broadcast_rbf(
+		&self,
+		outputs: Vec<(Script, u64)>,
+		fee_rate: FeeRate,
+	) -> anyhow::Result<Txid> {
+		self.sign_with_synced_wallet(move |wallet| {
+			build_transaction(wallet, outputs, Some(fee_rate))
+		})
+		.await
+	}
+
+	/// Replace a replaceable transaction with one paying a higher fee rate
+	pub async fn bump_fee(&self, txid: Txid) -> anyhow::Result<Txid> {
+		let estimated_fee_rate = self
+			.estimate_smart_fee(FEE_BUMP_TARGET_BLOCKS)
+			.await
+			.unwrap_or_else(|err| {
+				warn!("Bumping fee without a fee estimate: {}", err);
+				self.config.bitcoin_fee_rate_floor
+			});
+
+		self.sign_with_synced_wallet(move |wallet| {
+			build_fee_bump_transaction(wallet, txid, estimated_fee_rate)
+		})
+		.await
+	}
+
+	async fn sign_with_synced_wallet<F>(&self, build: F) -> anyhow::Result<Txid>
+	where
+		F: FnOnce(&Wallet<MemoryDatabase>) -> anyhow::Result<Transaction>
+			+ Send
+			+ 'static,
+	{
 		sleep(Duration::from_secs(3)).await;
 
 		let blockchain = self.blockchain.clone();
 		let wallet = self.wallet.clone();
+		let last_wallet_sync = self.last_wallet_sync.clone();
 
 		let tx: Transaction =
 			spawn_blocking::<_, anyhow::Result<Transaction>>(move || {
@@ -214,30 +568,366 @@ impl Client {
 					.lock()
 					.map_err(|_| anyhow!("Cannot get wallet read lock"))?;
 
-				wallet.sync(&blockchain, SyncOptions::default())?;
-
-				let mut tx_builder = wallet.build_tx();
+				// The wallet doesn't track the UTXOs it spends until it is
+				// synced again, so a sync is only used by one transaction
+				let recently_synced = last_wallet_sync
+					.lock()
+					.map_err(|_| anyhow!("Cannot get wallet sync lock"))?
+					.take()
+					.is_some_and(|synced_at| {
+						synced_at.elapsed() < WALLET_SYNC_MAX_AGE
+					});
 
-				for (script, amount) in outputs.clone() {
-					tx_builder.add_recipient(script, amount);
+				if !recently_synced {
+					wallet.sync(&blockchain, SyncOptions::default())?;
 				}
 
-				let (mut partial_tx, _) = tx_builder.finish()?;
+				build(&wallet)
+			})
+			.await??;
+
+		self.broadcast(tx).await
+	}
+}
+
+/// Build and sign a transaction paying the outputs in order. Transactions
+/// built with a fee rate signal BIP125 replaceability.
+fn build_transaction(
+	wallet: &Wallet<MemoryDatabase>,
+	outputs: Vec<(Script, u64)>,
+	rbf_fee_rate: Option<FeeRate>,
+) -> anyhow::Result<Transaction> {
+	let mut tx_builder = wallet.build_tx();
+
+	for (script, amount) in outputs.clone() {
+		tx_builder.add_recipient(script, amount);
+	}
+
+	if let Some(fee_rate) = rbf_fee_rate {
+		tx_builder.enable_rbf().fee_rate(fee_rate);
+	}
 
-				partial_tx.unsigned_tx.output =
-					reorder_outputs(partial_tx.unsigned_tx.output, outputs);
+	let (mut partial_tx, _) = tx_builder.finish()?;
 
-				wallet.sign(&mut partial_tx, SignOptions::default())?;
+	reorder_outputs(&mut partial_tx, outputs);
+
+	wallet.sign(&mut partial_tx, SignOptions::default())?;
+
+	Ok(partial_tx.extract_tx())
+}
+
+/// Build and sign a replacement of a wallet transaction, paying the same
+/// outputs in the same order at a higher fee rate
+fn build_fee_bump_transaction(
+	wallet: &Wallet<MemoryDatabase>,
+	txid: Txid,
+	estimated_fee_rate: u64,
+) -> anyhow::Result<Transaction> {
+	let details = wallet
+		.get_tx(&txid, true)?
+		.ok_or_else(|| anyhow!("Transaction {} is not in the wallet", txid))?;
+
+	let (Some(fee), Some(tx)) = (details.fee, details.transaction) else {
+		anyhow::bail!("Cannot compute the fee rate of transaction {}", txid);
+	};
+
+	let fee_rate =
+		bumped_fee_rate(fee as f32 / tx.vsize() as f32, estimated_fee_rate);
+
+	let mut tx_builder = wallet.build_fee_bump(txid)?;
+	tx_builder
+		.enable_rbf()
+		.fee_rate(FeeRate::from_sat_per_vb(fee_rate))
+		.ordering(TxOrdering::Untouched);
+
+	let (mut partial_tx, _) = tx_builder.finish()?;
+
+	wallet.sign(&mut partial_tx, SignOptions::default())?;
+
+	Ok(partial_tx.extract_tx())
+}
 
-				Ok(partial_tx.extract_tx())
+/// Fee rate of a replacement, in sats per vbyte: the current fee rate bumped by
+/// a fixed factor, or the estimated fee rate when that is higher
+fn bumped_fee_rate(fee_rate: f32, estimated_fee_rate: u64) -> f32 {
+	// Replacements must pay at least the minimum relay fee on top
+	(fee_rate * FEE_BUMP_FACTOR)
+		.max(fee_rate + 1.0)
+		.max(estimated_fee_rate as f32)
+}
+
+/// Sign the inputs of a transaction spending wallet UTXOs. Inputs the wallet
+/// doesn't own keep their script sig and witness.
+fn sign_transaction(
+	wallet: &Wallet<MemoryDatabase>,
+	tx: Transaction,
+) -> anyhow::Result<Transaction> {
+	let mut unsigned_tx = tx.clone();
+	for input in &mut unsigned_tx.input {
+		input.script_sig = Script::new();
+		input.witness = Witness::new();
+	}
+
+	let mut psbt = PartiallySignedTransaction::from_unsigned_tx(unsigned_tx)?;
+
+	for (psbt_input, input) in psbt.inputs.iter_mut().zip(&tx.input) {
+		if let Some(utxo) = wallet.get_utxo(input.previous_output)? {
+			*psbt_input = wallet.get_psbt_input(utxo, None, false)?;
+		}
+	}
+
+	// Inputs the wallet doesn't own carry no previous transaction to check
+	wallet.sign(
+		&mut psbt,
+		SignOptions {
+			trust_witness_utxo: true,
+			..Default::default()
+		},
+	)?;
+
+	let signed_inputs = psbt.extract_tx().input;
+
+	Ok(Transaction {
+		input: tx
+			.input
+			.into_iter()
+			.zip(signed_inputs)
+			.map(|(input, signed_input)| {
+				if signed_input.witness.is_empty()
+					&& signed_input.script_sig.is_empty()
+				{
+					input
+				} else {
+					signed_input
+				}
 			})
-			.await??;
+			.collect(),
+		..tx
+	})
+}
 
-		let txid: Txid = self
-			.execute(move |client| client.send_raw_transaction(&tx))
-			.await??;
+/// Check whether a transaction is confirmed or in the mempool, returning
+/// `None` if another transaction conflicts with it. A missing transaction
+/// conflicts with another one when an output it spends was spent, unless it
+/// shows up when checked again, as it may have been the spender.
+async fn check_presence<P, PFut, S, SFut>(
+	mut presence: P,
+	spends_spent_outputs: S,
+) -> anyhow::Result<Option<(bool, bool)>>
+where
+	P: FnMut() -> PFut,
+	PFut: Future<Output = anyhow::Result<(bool, bool)>>,
+	S: FnOnce() -> SFut,
+	SFut: Future<Output = anyhow::Result<bool>>,
+{
+	let (is_confirmed, in_mempool) = presence().await?;
+
+	if is_confirmed || in_mempool || !spends_spent_outputs().await? {
+		return Ok(Some((is_confirmed, in_mempool)));
+	}
+
+	match presence().await? {
+		(false, false) => Ok(None),
+		found => Ok(Some(found)),
+	}
+}
+
+/// Fetch the blocks in the height range with up to `concurrency` requests in
+/// flight, returning them in ascending height order
+async fn fetch_blocks<F, Fut>(
+	from: u32,
+	to: u32,
+	concurrency: usize,
+	fetch: F,
+) -> anyhow::Result<Vec<(u32, Block)>>
+where
+	F: FnMut(u32) -> Fut,
+	Fut: Future<Output = anyhow::Result<(u32, Block)>>,
+{
+	stream::iter(from..=to)
+		.map(fetch)
+		.buffered(concurrency)
+		.try_collect()
+		.await
+}
+
+/// Submit a transaction to all nodes, returning the txid reported by the
+/// first node that accepted it or the last error if none did
+async fn broadcast_to_any<F, Fut>(
+	urls: Vec<Url>,
+	mut send: F,
+) -> anyhow::Result<Txid>
+where
+	F: FnMut(Url) -> Fut,
+	Fut: Future<Output = anyhow::Result<Txid>>,
+{
+	let mut accepted = None;
+	let mut last_error = anyhow!("No bitcoin node configured");
 
-		Ok(txid)
+	for url in urls {
+		match send(url.clone()).await {
+			Ok(txid) => {
+				accepted.get_or_insert(txid);
+			}
+			Err(err) => {
+				warn!("Bitcoin node {} rejected transaction: {}", url, err);
+				last_error = err;
+			}
+		}
+	}
+
+	accepted.ok_or(last_error)
+}
+
+/// Bitcoin operations performed by the system, abstracted so the system can
+/// run against other implementations than a Bitcoin node
+#[async_trait]
+pub trait BitcoinApi: Clone + Send + Sync + 'static {
+	/// Sign and broadcast a transaction
+	async fn sign_and_broadcast(
+		&self,
+		outputs: Vec<(Script, u64)>,
+	) -> anyhow::Result<Txid>;
+
+	/// Sign and broadcast a replaceable transaction paying the given fee rate
+	async fn sign_and_broadcast_rbf(
+		&self,
+		outputs: Vec<(Script, u64)>,
+		fee_rate: FeeRate,
+	) -> anyhow::Result<Txid>;
+
+	/// Replace a replaceable transaction with one paying a higher fee rate
+	async fn bump_fee(&self, txid: Txid) -> anyhow::Result<Txid>;
+
+	/// Get transaction status
+	async fn get_tx_status(
+		&self,
+		txid: Txid,
+	) -> anyhow::Result<TransactionStatus>;
+
+	/// Get block
+	async fn get_block(
+		&self,
+		block_height: u32,
+	) -> anyhow::Result<(u32, Block)>;
+
+	/// Get the blocks from height `from` to height `to` inclusive, in
+	/// ascending height order
+	async fn get_blocks(
+		&self,
+		from: u32,
+		to: u32,
+	) -> anyhow::Result<Vec<(u32, Block)>>;
+
+	/// Get current block height
+	async fn get_height(&self) -> anyhow::Result<u32>;
+
+	/// Sync the wallet used to sign transactions
+	async fn refresh_wallet_utxos(&self) -> anyhow::Result<()>;
+}
+
+#[async_trait]
+impl BitcoinApi for Client {
+	async fn sign_and_broadcast(
+		&self,
+		outputs: Vec<(Script, u64)>,
+	) -> anyhow::Result<Txid> {
+		Client::sign_and_broadcast(self, outputs).await
+	}
+
+	async fn sign_and_broadcast_rbf(
+		&self,
+		outputs: Vec<(Script, u64)>,
+		fee_rate: FeeRate,
+	) -> anyhow::Result<Txid> {
+		Client::sign_and_broadcast_rbf(self, outputs, fee_rate).await
+	}
+
+	async fn bump_fee(&self, txid: Txid) -> anyhow::Result<Txid> {
+		Client::bump_fee(self, txid).await
+	}
+
+	async fn get_tx_status(
+		&self,
+		txid: Txid,
+	) -> anyhow::Result<TransactionStatus> {
+		Client::get_tx_status(self, txid).await
+	}
+
+	async fn get_block(
+		&self,
+		block_height: u32,
+	) -> anyhow::Result<(u32, Block)> {
+		Client::get_block(self, block_height).await
+	}
+
+	async fn get_blocks(
+		&self,
+		from: u32,
+		to: u32,
+	) -> anyhow::Result<Vec<(u32, Block)>> {
+		Client::get_blocks(self, from, to).await
+	}
+
+	async fn get_height(&self) -> anyhow::Result<u32> {
+		Client::get_height(self).await
+	}
+
+	async fn refresh_wallet_utxos(&self) -> anyhow::Result<()> {
+		Client::refresh_wallet_utxos(self).await
+	}
+}
+
+/// Keeps track of how many consecutive polls a transaction has been missing
+/// from both the mempool and the chain. A freshly broadcasted transaction can
+/// briefly be in neither while it propagates.
+#[derive(Debug, Default)]
+pub(crate) struct MissingTransactions(HashMap<Txid, u32>);
+
+impl MissingTransactions {
+	pub(crate) fn status(
+		&mut self,
+		txid: Txid,
+		is_confirmed: bool,
+		in_mempool: bool,
+		grace_polls: u32,
+	) -> TransactionStatus {
+		match (is_confirmed, in_mempool) {
+			(true, false) => {
+				self.0.remove(&txid);
+				TransactionStatus::Confirmed
+			}
+			(false, true) => {
+				self.0.remove(&txid);
+				TransactionStatus::Broadcasted
+			}
+			(false, false) => {
+				let missing_polls = self.0.entry(txid).or_default();
+				*missing_polls += 1;
+
+				if *missing_polls > grace_polls {
+					self.0.remove(&txid);
+					TransactionStatus::Rejected
+				} else {
+					trace!(
+						"BTC TX {} missing for {} polls, still within grace period",
+						txid,
+						missing_polls
+					);
+					TransactionStatus::Broadcasted
+				}
+			}
+			(true, true) => {
+				panic!("Transaction cannot be both confirmed and pending")
+			}
+		}
+	}
+
+	/// Stops tracking a transaction whose spent outputs were spent by
+	/// another transaction, which needs no grace period
+	pub(crate) fn conflicted(&mut self, txid: Txid) -> TransactionStatus {
+		self.0.remove(&txid);
+		TransactionStatus::Conflicted
 	}
 }
 
@@ -245,38 +935,36 @@ impl Client {
 // test that wallet returns correct address
 mod tests {
 
-	use std::path::Path;
+	use std::time::Duration;
 
-	use bdk::bitcoin::Network as BitcoinNetwork;
-	use blockstack_lib::vm::ContractName;
-	use stacks_core::{wallet::Wallet, Network};
+	use bdk::{
+		bitcoin::{
+			blockdata::constants::genesis_block,
+			consensus::encode::serialize_hex, hashes::Hash,
+			secp256k1::SecretKey, Address as BitcoinAddress,
+			Network as BitcoinNetwork, OutPoint, PackedLockTime, PrivateKey,
+			Script, Transaction, TxIn, TxOut, Txid, Witness,
+		},
+		database::MemoryDatabase,
+		template::P2TR,
+		wallet::AddressIndex,
+		FeeRate, Wallet as BdkWallet,
+	};
+	use sbtc_core::test_utils;
 
-	use super::Client;
-	use crate::config::Config;
+	use super::{
+		broadcast_to_any, build_transaction, check_presence, sign_transaction,
+		Client, MissingTransactions,
+	};
+	use crate::{
+		event::TransactionStatus,
+		test_server::{serve, serve_with},
+		test_util::test_config,
+	};
 
 	#[test]
 	fn test_wallet_address() {
-		let wallet = Wallet::new("twice kind fence tip hidden tilt action fragile skin nothing glory cousin green tomorrow spring wrist shed math olympic multiply hip blue scout claw").unwrap();
-
-		let stacks_network = Network::Testnet;
-		let stacks_credentials = wallet.credentials(stacks_network, 0).unwrap();
-		let bitcoin_credentials = wallet
-			.bitcoin_credentials(BitcoinNetwork::Testnet, 0)
-			.unwrap();
-
-		let conf = Config {
-			state_directory: Path::new("/tmp/romeo").to_path_buf(),
-			bitcoin_credentials,
-			bitcoin_node_url: "http://localhost:18443".parse().unwrap(),
-			electrum_node_url: "ssl://blockstream.info:993".parse().unwrap(),
-			bitcoin_network: "testnet".parse().unwrap(),
-			contract_name: ContractName::from("asset"),
-			stacks_node_url: "http://localhost:20443".parse().unwrap(),
-			stacks_credentials,
-			stacks_network,
-			hiro_api_key: None,
-			strict: true,
-		};
+		let conf = test_config();
 
 		let client = Client::new(conf.clone()).unwrap();
 
@@ -299,4 +987,326 @@ mod tests {
 			expected_sbtc_wallet
 		);
 	}
+
+	#[test]
+	fn missing_transaction_should_be_rejected_only_after_grace_period() {
+		let txid = Txid::all_zeros();
+		let mut missing = MissingTransactions::default();
+
+		// Propagation delay: not yet seen by the node
+		assert_eq!(
+			missing.status(txid, false, false, 2),
+			TransactionStatus::Broadcasted
+		);
+		// Appears in the mempool, which resets the counter
+		assert_eq!(
+			missing.status(txid, false, true, 2),
+			TransactionStatus::Broadcasted
+		);
+		assert_eq!(
+			missing.status(txid, false, false, 2),
+			TransactionStatus::Broadcasted
+		);
+		assert_eq!(
+			missing.status(txid, false, false, 2),
+			TransactionStatus::Broadcasted
+		);
+		assert_eq!(
+			missing.status(txid, false, false, 2),
+			TransactionStatus::Rejected
+		);
+	}
+
+	#[test]
+	fn replaced_transaction_should_be_conflicted_within_grace_period() {
+		let txid = Txid::all_zeros();
+		let mut missing = MissingTransactions::default();
+
+		assert_eq!(
+			missing.status(txid, false, false, 2),
+			TransactionStatus::Broadcasted
+		);
+		// The replacement spending its outputs is found
+		assert_eq!(missing.conflicted(txid), TransactionStatus::Conflicted);
+		// A transaction with the same txid is tracked from scratch
+		assert_eq!(
+			missing.status(txid, false, false, 1),
+			TransactionStatus::Broadcasted
+		);
+	}
+
+	#[tokio::test]
+	async fn transaction_mined_while_checking_should_not_be_conflicted() {
+		// Missing at first, then confirmed once it has spent its outputs
+		let mut presences = vec![(true, false), (false, false)];
+
+		let presence = check_presence(
+			|| {
+				let presence = presences.pop().unwrap();
+				async move { Ok(presence) }
+			},
+			|| async { Ok(true) },
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(presence, Some((true, false)));
+	}
+
+	#[tokio::test]
+	async fn missing_transaction_with_spent_outputs_should_be_conflicted() {
+		let presence = check_presence(
+			|| async { Ok((false, false)) },
+			|| async { Ok(true) },
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(presence, None);
+
+		let presence = check_presence(
+			|| async { Ok((false, false)) },
+			|| async { Ok(false) },
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(presence, Some((false, false)));
+	}
+
+	#[test]
+	fn missing_transaction_should_be_rejected_immediately_without_grace() {
+		let txid = Txid::all_zeros();
+		let mut missing = MissingTransactions::default();
+
+		assert_eq!(
+			missing.status(txid, false, false, 0),
+			TransactionStatus::Rejected
+		);
+		assert_eq!(
+			missing.status(txid, true, false, 0),
+			TransactionStatus::Confirmed
+		);
+	}
+
+	#[tokio::test]
+	async fn broadcast_should_succeed_if_a_later_node_accepts() {
+		let txid = Txid::all_zeros();
+		let urls = vec![
+			"http://first:18443".parse().unwrap(),
+			"http://second:18443".parse().unwrap(),
+		];
+
+		let res = broadcast_to_any(urls, |url: url::Url| async move {
+			if url.host_str() == Some("first") {
+				Err(anyhow::anyhow!("connection refused"))
+			} else {
+				Ok(txid)
+			}
+		})
+		.await;
+
+		assert_eq!(res.unwrap(), txid);
+	}
+
+	#[tokio::test]
+	async fn broadcast_should_fail_if_no_node_accepts() {
+		let urls = vec!["http://first:18443".parse().unwrap()];
+
+		let res = broadcast_to_any(urls, |_| async {
+			Err(anyhow::anyhow!("connection refused"))
+		})
+		.await;
+
+		assert!(res.is_err());
+	}
+
+	// Serves Bitcoin node RPC responses, returning the credentialed node URL
+	async fn serve_rpc(
+		results: Vec<&'static str>,
+	) -> (url::Url, tokio::task::JoinHandle<Vec<String>>) {
+		let (url, node) = serve(
+			results
+				.into_iter()
+				.map(|result| {
+					(
+						"200 OK",
+						format!(
+							r#"{{"result":{},"error":null,"id":1}}"#,
+							result
+						),
+					)
+				})
+				.collect(),
+		)
+		.await;
+
+		(
+			url.replace("http://", "http://user:pass@").parse().unwrap(),
+			node,
+		)
+	}
+
+	#[tokio::test]
+	async fn smart_fee_should_be_converted_to_sats_per_vbyte() {
+		let (url, node) =
+			serve_rpc(vec![r#"{"feerate":0.00012,"blocks":2}"#]).await;
+
+		assert_eq!(Client::estimate_smart_fee_at(url, 2, 1).await.unwrap(), 12);
+		assert!(node.await.unwrap()[0].contains("estimatesmartfee"));
+	}
+
+	#[tokio::test]
+	async fn smart_fee_should_fall_back_to_the_floor_without_estimate() {
+		let (url, _) = serve_rpc(vec![
+			r#"{"errors":["Insufficient data or no feerate found"],"blocks":0}"#,
+		])
+		.await;
+
+		assert_eq!(Client::estimate_smart_fee_at(url, 6, 3).await.unwrap(), 3);
+	}
+
+	#[test]
+	fn fee_bump_should_pay_at_least_the_estimated_fee_rate() {
+		assert_eq!(bumped_fee_rate(10.0, 1), 15.0);
+		assert_eq!(bumped_fee_rate(1.0, 1), 2.0);
+		assert_eq!(bumped_fee_rate(10.0, 40), 40.0);
+	}
+
+	#[tokio::test]
+	async fn rpc_block_range_should_be_returned_in_ascending_order() {
+		let blocks: Vec<_> = (10..=12)
+			.map(|block_height| {
+				let mut block = genesis_block(BitcoinNetwork::Regtest);
+				block.header.nonce = block_height;
+				block
+			})
+			.collect();
+
+		let url = serve_with(move |request| {
+			let request: serde_json::Value =
+				serde_json::from_str(request).unwrap();
+			let params = &request["params"];
+
+			let result = match request["method"].as_str().unwrap() {
+				"getblockhash" => {
+					let block_height = params[0].as_u64().unwrap() as usize;
+					blocks[block_height - 10].block_hash().to_string()
+				}
+				"getblock" => {
+					let block = blocks
+						.iter()
+						.find(|block| {
+							block.block_hash().to_string() == params[0]
+						})
+						.unwrap();
+					serialize_hex(block)
+				}
+				method => panic!("Unexpected RPC method {}", method),
+			};
+
+			(
+				"200 OK",
+				serde_json::json!({
+					"result": result,
+					"error": null,
+					"id": request["id"],
+				})
+				.to_string(),
+			)
+		})
+		.await;
+		let url = url.replace("http://", "http://user:pass@").parse().unwrap();
+
+		let blocks =
+			Client::get_rpc_blocks_at(url, 10, 12, Duration::from_millis(10))
+				.await
+				.unwrap();
+
+		assert_eq!(
+			blocks
+				.iter()
+				.map(|(height, block)| (*height, block.header.nonce))
+				.collect::<Vec<_>>(),
+			vec![(10, 10), (11, 11), (12, 12)]
+		);
+	}
+
+	fn funded_wallet() -> BdkWallet<MemoryDatabase> {
+		let private_key = PrivateKey::new(
+			SecretKey::from_slice(&[1; 32]).unwrap(),
+			BitcoinNetwork::Testnet,
+		);
+
+		test_utils::funded_wallet(P2TR(private_key), BitcoinNetwork::Testnet)
+	}
+
+	#[test]
+	fn rbf_transaction_should_have_replaceable_inputs() {
+		let wallet = funded_wallet();
+		let outputs = vec![
+			(Script::new_op_return(&[1, 2, 3]), 0),
+			(
+				"tb1qwe9ddxp6v32uef2v66j00vx6wxax5zat223tms"
+					.parse::<BitcoinAddress>()
+					.unwrap()
+					.script_pubkey(),
+				10_000,
+			),
+		];
+
+		let tx = build_transaction(
+			&wallet,
+			outputs.clone(),
+			Some(FeeRate::from_sat_per_vb(2.0)),
+		)
+		.unwrap();
+
+		assert!(!tx.input.is_empty());
+		assert!(tx.input.iter().all(|input| input.sequence.is_rbf()));
+		for (output, (script, amount)) in tx.output.iter().zip(&outputs) {
+			assert_eq!(&output.script_pubkey, script);
+			assert_eq!(output.value, *amount);
+		}
+
+		let tx = build_transaction(&wallet, outputs, None).unwrap();
+
+		assert!(!tx.input.iter().any(|input| input.sequence.is_rbf()));
+	}
+
+	fn spending_tx(previous_output: OutPoint) -> Transaction {
+		Transaction {
+			version: 2,
+			lock_time: PackedLockTime(0),
+			input: vec![TxIn {
+				previous_output,
+				..Default::default()
+			}],
+			output: vec![TxOut {
+				value: 90_000,
+				script_pubkey: Script::new_op_return(&[1, 2, 3]),
+			}],
+		}
+	}
+
+	#[test]
+	fn signing_should_leave_foreign_inputs_untouched() {
+		let wallet = funded_wallet();
+		let mut tx = spending_tx(OutPoint::new(Txid::from_inner([1; 32]), 0));
+		tx.input[0].witness = Witness::from_vec(vec![vec![2; 64]]);
+
+		assert_eq!(sign_transaction(&wallet, tx.clone()).unwrap(), tx);
+	}
+
+	#[test]
+	fn signing_should_sign_wallet_inputs() {
+		let wallet = funded_wallet();
+		let utxo = wallet.list_unspent().unwrap().remove(0);
+		let tx = spending_tx(utxo.outpoint);
+
+		let signed_tx = sign_transaction(&wallet, tx.clone()).unwrap();
+
+		assert!(!signed_tx.input[0].witness.is_empty());
+		assert_eq!(signed_tx.txid(), tx.txid());
+	}
 }
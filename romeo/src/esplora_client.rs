@@ -0,0 +1,215 @@
+//! Esplora HTTP API client, an alternative to Bitcoin Core RPC for Bitcoin
+//! node reads and broadcasts
+
+use anyhow::anyhow;
+use bdk::bitcoin::{
+	consensus::{deserialize, serialize},
+	Block, BlockHash, Transaction, Txid,
+};
+use reqwest::{Response, StatusCode};
+use url::Url;
+
+/// Esplora HTTP API client
+#[derive(Debug, Clone)]
+pub struct EsploraClient {
+	url: Url,
+	http_client: reqwest::Client,
+}
+
+/// Where a transaction was last seen by the Esplora backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionPresence {
+	/// Whether the transaction is in a block
+	pub is_confirmed: bool,
+	/// Whether the transaction is waiting in the mempool
+	pub in_mempool: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct TransactionStatusResponse {
+	confirmed: bool,
+}
+
+impl EsploraClient {
+	/// Create a client of the Esplora API at the given base URL
+	pub fn new(url: Url) -> Self {
+		Self {
+			url,
+			http_client: reqwest::Client::new(),
+		}
+	}
+
+	/// Broadcast a transaction
+	pub async fn broadcast(&self, tx: &Transaction) -> anyhow::Result<Txid> {
+		let res = self
+			.http_client
+			.post(self.endpoint("tx"))
+			.body(hex::encode(serialize(tx)))
+			.send()
+			.await?;
+
+		Ok(Self::text(res).await?.trim().parse()?)
+	}
+
+	/// Whether a transaction is confirmed or in the mempool. Unknown
+	/// transactions are neither.
+	pub async fn get_tx_presence(
+		&self,
+		txid: Txid,
+	) -> anyhow::Result<TransactionPresence> {
+		let res = self
+			.http_client
+			.get(self.endpoint(&format!("tx/{}/status", txid)))
+			.send()
+			.await?;
+
+		if res.status() == StatusCode::NOT_FOUND {
+			return Ok(TransactionPresence {
+				is_confirmed: false,
+				in_mempool: false,
+			});
+		}
+
+		let status: TransactionStatusResponse =
+			serde_json::from_str(&Self::text(res).await?)?;
+
+		Ok(TransactionPresence {
+			is_confirmed: status.confirmed,
+			in_mempool: !status.confirmed,
+		})
+	}
+
+	/// Hash of the block at the given height, if it has been mined yet
+	pub async fn get_block_hash(
+		&self,
+		block_height: u32,
+	) -> anyhow::Result<Option<BlockHash>> {
+		let res = self
+			.http_client
+			.get(self.endpoint(&format!("block-height/{}", block_height)))
+			.send()
+			.await?;
+
+		if res.status() == StatusCode::NOT_FOUND {
+			return Ok(None);
+		}
+
+		Ok(Some(Self::text(res).await?.trim().parse()?))
+	}
+
+	/// Get a block by its hash
+	pub async fn get_block(
+		&self,
+		block_hash: BlockHash,
+	) -> anyhow::Result<Block> {
+		let bytes = self
+			.http_client
+			.get(self.endpoint(&format!("block/{}/raw", block_hash)))
+			.send()
+			.await?
+			.error_for_status()?
+			.bytes()
+			.await?;
+
+		Ok(deserialize(&bytes)?)
+	}
+
+	/// Get current block height
+	pub async fn get_height(&self) -> anyhow::Result<u32> {
+		let res = self
+			.http_client
+			.get(self.endpoint("blocks/tip/height"))
+			.send()
+			.await?;
+
+		Ok(Self::text(res).await?.trim().parse()?)
+	}
+
+	// Base URLs usually have a path, such as `/api`, which must be kept
+	fn endpoint(&self, path: &str) -> String {
+		format!("{}/{}", self.url.as_str().trim_end_matches('/'), path)
+	}
+
+	async fn text(res: Response) -> anyhow::Result<String> {
+		let status = res.status();
+		let body = res.text().await?;
+
+		if !status.is_success() {
+			return Err(anyhow!("Esplora responded with {}: {}", status, body));
+		}
+
+		Ok(body)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bdk::bitcoin::hashes::Hash;
+
+	use super::*;
+	use crate::{
+		bitcoin_client::MissingTransactions, event::TransactionStatus,
+		test_server::serve,
+	};
+
+	#[tokio::test]
+	async fn tx_status_should_map_to_transaction_status() {
+		let txid = Txid::from_inner([1; 32]);
+		let confirmed = r#"{
+			"confirmed": true,
+			"block_height": 2500000,
+			"block_hash": "000000000000001a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f708192",
+			"block_time": 1690000000
+		}"#;
+		let (url, node) = serve(vec![
+			("200 OK", confirmed.to_string()),
+			("200 OK", r#"{"confirmed": false}"#.to_string()),
+			("404 Not Found", "Transaction not found".to_string()),
+		])
+		.await;
+		let client =
+			EsploraClient::new(format!("{}/api/", url).parse().unwrap());
+
+		for expected_status in [
+			TransactionStatus::Confirmed,
+			TransactionStatus::Broadcasted,
+			TransactionStatus::Rejected,
+		] {
+			let presence = client.get_tx_presence(txid).await.unwrap();
+			let status = MissingTransactions::default().status(
+				txid,
+				presence.is_confirmed,
+				presence.in_mempool,
+				0,
+			);
+
+			assert_eq!(status, expected_status);
+		}
+
+		let requests = node.await.unwrap();
+		assert!(
+			requests[0].starts_with(&format!("GET /api/tx/{}/status ", txid))
+		);
+	}
+
+	#[tokio::test]
+	async fn server_errors_should_not_look_like_absent_transactions() {
+		let (url, _) =
+			serve(vec![("500 Internal Server Error", String::new())]).await;
+		let client = EsploraClient::new(url.parse().unwrap());
+
+		assert!(client
+			.get_tx_presence(Txid::from_inner([1; 32]))
+			.await
+			.is_err());
+	}
+
+	#[tokio::test]
+	async fn height_should_be_parsed_from_plain_text() {
+		let (url, node) = serve(vec![("200 OK", "2500000".to_string())]).await;
+		let client = EsploraClient::new(url.parse().unwrap());
+
+		assert_eq!(client.get_height().await.unwrap(), 2500000);
+		assert!(node.await.unwrap()[0].starts_with("GET /blocks/tip/height "));
+	}
+}
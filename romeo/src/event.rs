@@ -10,7 +10,11 @@ use crate::state::{DepositInfo, WithdrawalInfo};
 /// Events are spawned from tasks and used
 /// to update the system state.
 #[derive(
-	Clone, serde::Serialize, serde::Deserialize, derivative::Derivative,
+	Clone,
+	PartialEq,
+	serde::Serialize,
+	serde::Deserialize,
+	derivative::Derivative,
 )]
 #[derivative(Debug)]
 pub enum Event {
@@ -32,6 +36,18 @@ pub enum Event {
 	/// A stacks node has responded with an updated status regarding this txid
 	StacksTransactionUpdate(StacksTxId, TransactionStatus),
 
+	/// A stacks transaction has been replaced by one with a higher fee
+	StacksTransactionBumped(StacksTxId, StacksTxId),
+
+	/// The fee of a stacks transaction could not be bumped
+	StacksTransactionBumpFailed(StacksTxId),
+
+	/// A fulfill transaction has been replaced by one with a higher fee
+	FulfillmentBumped(WithdrawalInfo, BitcoinTxId),
+
+	/// The fee of a fulfill transaction could not be bumped
+	FulfillmentBumpFailed(WithdrawalInfo),
+
 	/// A bitcoin node has responded with an updated status regarding this txid
 	BitcoinTransactionUpdate(BitcoinTxId, TransactionStatus),
 
@@ -40,6 +56,13 @@ pub enum Event {
 
 	/// A wild bitcoin block has appeared
 	BitcoinBlock(u32, #[derivative(Debug = "ignore")] Block),
+
+	/// Consecutive bitcoin blocks have appeared, in ascending height order
+	BitcoinBlocks(#[derivative(Debug = "ignore")] Vec<(u32, Block)>),
+
+	/// The sBTC wallet UTXOs have been synced, which is not written to the
+	/// event log
+	SbtcWalletUtxosRefreshed,
 }
 
 /// Status of a broadcasted transaction, useful for implementing retry logic
@@ -52,4 +75,114 @@ pub enum TransactionStatus {
 	Confirmed,
 	/// There are indications that this transaction will never be mined
 	Rejected,
+	/// An output spent by this transaction was spent by another one, such as
+	/// a replacement, so this transaction will never be mined
+	Conflicted,
+}
+
+#[cfg(test)]
+mod tests {
+	use bdk::bitcoin::Address as BitcoinAddress;
+	use blockstack_lib::{
+		chainstate::stacks::{
+			TokenTransferMemo, TransactionAuth, TransactionPayload,
+			TransactionSpendingCondition, TransactionVersion,
+		},
+		types::chainstate::StacksPublicKey,
+		vm::types::PrincipalData,
+	};
+
+	use super::*;
+	use crate::test_util::testnet_block_100000;
+
+	fn stacks_transaction() -> StacksTransaction {
+		let public_key = StacksPublicKey::from_slice(
+			&hex::decode("03556902f83defc6c63a7eb56a2d8ee4baee109f2126aac41e4f9e3a0835f34bc5")
+				.unwrap(),
+		)
+		.unwrap();
+
+		let tx_auth = TransactionAuth::Standard(
+			TransactionSpendingCondition::new_singlesig_p2pkh(public_key)
+				.unwrap(),
+		);
+
+		let tx_payload = TransactionPayload::TokenTransfer(
+			PrincipalData::parse("ST000000000000000000002AMW42H").unwrap(),
+			1337,
+			TokenTransferMemo([0; 34]),
+		);
+
+		StacksTransaction::new(TransactionVersion::Testnet, tx_auth, tx_payload)
+	}
+
+	fn deposit_info() -> DepositInfo {
+		DepositInfo {
+			txid: testnet_block_100000().txdata[0].txid(),
+			amount: 1000,
+			recipient: PrincipalData::parse("ST000000000000000000002AMW42H")
+				.unwrap(),
+			block_height: 100000,
+			memo: None,
+		}
+	}
+
+	fn withdrawal_info() -> WithdrawalInfo {
+		WithdrawalInfo {
+			txid: testnet_block_100000().txdata[0].txid(),
+			amount: 1000,
+			source: PrincipalData::parse(
+				"ST000000000000000000002AMW42H.helloworld",
+			)
+			.unwrap(),
+			recipient: "tb1qwe9ddxp6v32uef2v66j00vx6wxax5zat223tms"
+				.parse::<BitcoinAddress>()
+				.unwrap(),
+			block_height: 100000,
+		}
+	}
+
+	#[test]
+	fn every_event_variant_should_round_trip_through_json() {
+		let stacks_txid = StacksTxId([1; 32]);
+		let bitcoin_txid = testnet_block_100000().txdata[0].txid();
+
+		let events = [
+			Event::ContractBlockHeight(1, 2),
+			Event::ContractPublicKeySetBroadcasted(stacks_txid),
+			Event::MintBroadcasted(deposit_info(), stacks_txid),
+			Event::BurnBroadcasted(withdrawal_info(), stacks_txid),
+			Event::FulfillBroadcasted(withdrawal_info(), bitcoin_txid),
+			Event::StacksTransactionUpdate(
+				stacks_txid,
+				TransactionStatus::Confirmed,
+			),
+			Event::StacksTransactionBumped(stacks_txid, StacksTxId([2; 32])),
+			Event::StacksTransactionBumpFailed(stacks_txid),
+			Event::FulfillmentBumped(withdrawal_info(), bitcoin_txid),
+			Event::FulfillmentBumpFailed(withdrawal_info()),
+			Event::BitcoinTransactionUpdate(
+				bitcoin_txid,
+				TransactionStatus::Rejected,
+			),
+			Event::StacksBlock(3, vec![stacks_transaction()]),
+			Event::BitcoinBlock(100000, testnet_block_100000()),
+			Event::BitcoinBlocks(vec![(100000, testnet_block_100000())]),
+			Event::SbtcWalletUtxosRefreshed,
+		];
+
+		for event in events {
+			let json = serde_json::to_string(&event).unwrap();
+			let deserialized: Event = serde_json::from_str(&json)
+				.unwrap_or_else(|err| {
+					panic!("Could not deserialize {:?}: {}", event, err)
+				});
+
+			assert_eq!(
+				deserialized, event,
+				"Event did not round-trip: {}",
+				json
+			);
+		}
+	}
 }
@@ -0,0 +1,250 @@
+//! Fault injection around Bitcoin and Stacks clients, to test how the system
+//! copes with slow and unreliable nodes
+
+use std::{
+	future::Future,
+	sync::{
+		atomic::{AtomicU32, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+use async_trait::async_trait;
+use bdk::{
+	bitcoin::{Block, Script, Txid as BitcoinTxId},
+	FeeRate,
+};
+use blockstack_lib::{
+	burnchains::Txid as StacksTxId,
+	chainstate::stacks::StacksTransaction,
+	vm::{ContractName, Value as ClarityValue},
+};
+use stacks_core::uint::Uint256;
+use tokio::time::sleep;
+
+use crate::{
+	bitcoin_client::BitcoinApi,
+	event::TransactionStatus,
+	stacks_client::{ContractDeployment, StacksApi},
+};
+
+/// Faults injected into the calls of a wrapped client
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Faults {
+	/// Delay added to every call
+	pub latency: Duration,
+
+	/// Every nth call fails without reaching the client
+	pub error_every: Option<u32>,
+
+	/// Every nth call reaches the client, but its response is lost
+	pub drop_every: Option<u32>,
+
+	/// Whether broadcasts are faulted as well. A lost broadcast may still have
+	/// gone through, so the system does not retry them.
+	pub broadcasts: bool,
+}
+
+/// Client wrapper injecting faults around the calls of a real or mock client
+#[derive(Debug, Clone)]
+pub struct FaultyClient<C> {
+	inner: C,
+	faults: Faults,
+	calls: Arc<AtomicU32>,
+	injected: Arc<AtomicU32>,
+}
+
+impl<C> FaultyClient<C> {
+	/// Wrap the client, injecting the given faults
+	pub fn new(inner: C, faults: Faults) -> Self {
+		Self {
+			inner,
+			faults,
+			calls: Default::default(),
+			injected: Default::default(),
+		}
+	}
+
+	/// Number of faults injected so far
+	pub fn injected(&self) -> u32 {
+		self.injected.load(Ordering::SeqCst)
+	}
+
+	async fn inject<T>(
+		&self,
+		call: impl Future<Output = anyhow::Result<T>>,
+	) -> anyhow::Result<T> {
+		sleep(self.faults.latency).await;
+
+		let call_number = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+		let is_nth = |every: Option<u32>| {
+			every.is_some_and(|every| call_number % every == 0)
+		};
+
+		if is_nth(self.faults.error_every) {
+			self.injected.fetch_add(1, Ordering::SeqCst);
+			anyhow::bail!("Injected error on call {}", call_number);
+		}
+
+		let result = call.await;
+
+		if is_nth(self.faults.drop_every) {
+			self.injected.fetch_add(1, Ordering::SeqCst);
+			anyhow::bail!("Injected dropped response on call {}", call_number);
+		}
+
+		result
+	}
+
+	async fn inject_broadcast<T>(
+		&self,
+		call: impl Future<Output = anyhow::Result<T>>,
+	) -> anyhow::Result<T> {
+		if self.faults.broadcasts {
+			self.inject(call).await
+		} else {
+			call.await
+		}
+	}
+}
+
+#[async_trait]
+impl<B: BitcoinApi> BitcoinApi for FaultyClient<B> {
+	async fn sign_and_broadcast(
+		&self,
+		outputs: Vec<(Script, u64)>,
+	) -> anyhow::Result<BitcoinTxId> {
+		self.inject_broadcast(self.inner.sign_and_broadcast(outputs))
+			.await
+	}
+
+	async fn sign_and_broadcast_rbf(
+		&self,
+		outputs: Vec<(Script, u64)>,
+		fee_rate: FeeRate,
+	) -> anyhow::Result<BitcoinTxId> {
+		self.inject_broadcast(
+			self.inner.sign_and_broadcast_rbf(outputs, fee_rate),
+		)
+		.await
+	}
+
+	async fn bump_fee(&self, txid: BitcoinTxId) -> anyhow::Result<BitcoinTxId> {
+		self.inject_broadcast(self.inner.bump_fee(txid)).await
+	}
+
+	async fn get_tx_status(
+		&self,
+		txid: BitcoinTxId,
+	) -> anyhow::Result<TransactionStatus> {
+		self.inject(self.inner.get_tx_status(txid)).await
+	}
+
+	async fn get_block(
+		&self,
+		block_height: u32,
+	) -> anyhow::Result<(u32, Block)> {
+		self.inject(self.inner.get_block(block_height)).await
+	}
+
+	async fn get_blocks(
+		&self,
+		from: u32,
+		to: u32,
+	) -> anyhow::Result<Vec<(u32, Block)>> {
+		self.inject(self.inner.get_blocks(from, to)).await
+	}
+
+	async fn get_height(&self) -> anyhow::Result<u32> {
+		self.inject(self.inner.get_height()).await
+	}
+
+	async fn refresh_wallet_utxos(&self) -> anyhow::Result<()> {
+		self.inject(self.inner.refresh_wallet_utxos()).await
+	}
+}
+
+#[async_trait]
+impl<S: StacksApi> StacksApi for FaultyClient<S> {
+	async fn chain_id(&self) -> anyhow::Result<u32> {
+		self.inject(self.inner.chain_id()).await
+	}
+
+	async fn sign_and_broadcast(
+		&self,
+		tx: StacksTransaction,
+	) -> anyhow::Result<StacksTxId> {
+		self.inject_broadcast(self.inner.sign_and_broadcast(tx))
+			.await
+	}
+
+	async fn bump_fee(&self, txid: StacksTxId) -> anyhow::Result<StacksTxId> {
+		self.inject_broadcast(self.inner.bump_fee(txid)).await
+	}
+
+	async fn get_transation_status(
+		&self,
+		txid: StacksTxId,
+	) -> anyhow::Result<TransactionStatus> {
+		self.inject(self.inner.get_transation_status(txid)).await
+	}
+
+	async fn get_contract_block_height(
+		&self,
+		name: ContractName,
+	) -> anyhow::Result<u32> {
+		self.inject(self.inner.get_contract_block_height(name))
+			.await
+	}
+
+	async fn get_stacks_tip_height(&self) -> anyhow::Result<u32> {
+		self.inject(self.inner.get_stacks_tip_height()).await
+	}
+
+	async fn get_burn_block_height(&self) -> anyhow::Result<u32> {
+		self.inject(self.inner.get_burn_block_height()).await
+	}
+
+	async fn get_contract_deployment(
+		&self,
+	) -> anyhow::Result<ContractDeployment> {
+		self.inject(self.inner.get_contract_deployment()).await
+	}
+
+	async fn find_contract_call(
+		&self,
+		function_name: &str,
+		args: &[ClarityValue],
+	) -> anyhow::Result<Option<StacksTxId>> {
+		self.inject(self.inner.find_contract_call(function_name, args))
+			.await
+	}
+
+	async fn is_contract_paused(&self) -> anyhow::Result<bool> {
+		self.inject(self.inner.is_contract_paused()).await
+	}
+
+	async fn get_bitcoin_block_height(
+		&self,
+		block_height: u32,
+	) -> anyhow::Result<u32> {
+		self.inject(self.inner.get_bitcoin_block_height(block_height))
+			.await
+	}
+
+	async fn get_block(
+		&self,
+		block_height: u32,
+	) -> anyhow::Result<Vec<StacksTransaction>> {
+		self.inject(self.inner.get_block(block_height)).await
+	}
+
+	async fn get_block_hash_from_bitcoin_height(
+		&self,
+		height: u32,
+	) -> anyhow::Result<Uint256> {
+		self.inject(self.inner.get_block_hash_from_bitcoin_height(height))
+			.await
+	}
+}
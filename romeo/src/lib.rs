@@ -8,9 +8,18 @@
 
 pub mod bitcoin_client;
 pub mod config;
+pub mod esplora_client;
 pub mod event;
+#[cfg(test)]
+mod fault_injection;
+pub mod metrics;
 pub mod proof_data;
+pub mod reconcile;
 pub mod stacks_client;
 pub mod state;
 pub mod system;
 pub mod task;
+#[cfg(test)]
+mod test_server;
+#[cfg(test)]
+mod test_util;
@@ -1,12 +1,28 @@
 use clap::Parser;
 use tracing_subscriber::{
-	filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt,
+	filter::LevelFilter, fmt::writer::BoxMakeWriter, layer::SubscriberExt,
+	util::SubscriberInitExt,
 };
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+	let args = romeo::config::Cli::parse();
+	let config = romeo::config::Config::from_path(args.config_file)?;
+
+	// Logs would otherwise be interleaved with the streamed events
+	let log_writer = if config.event_stream_stdout {
+		BoxMakeWriter::new(std::io::stderr)
+	} else {
+		BoxMakeWriter::new(std::io::stdout)
+	};
+
 	tracing_subscriber::registry()
-		.with(tracing_subscriber::fmt::layer().compact().with_ansi(false))
+		.with(
+			tracing_subscriber::fmt::layer()
+				.compact()
+				.with_ansi(false)
+				.with_writer(log_writer),
+		)
 		.with(
 			tracing_subscriber::EnvFilter::builder()
 				.with_default_directive(LevelFilter::INFO.into())
@@ -14,10 +30,25 @@ async fn main() -> anyhow::Result<()> {
 		)
 		.init();
 
-	let args = romeo::config::Cli::parse();
-	let config = romeo::config::Config::from_path(args.config_file)?;
+	match args.command {
+		Some(romeo::config::Command::Reconcile) => {
+			let diff = romeo::reconcile::reconcile(&config).await?;
+			serde_json::to_writer_pretty(std::io::stdout(), &diff)?;
+		}
+		Some(romeo::config::Command::DryUpdate { event }) => {
+			let event = serde_json::from_str(&event)?;
+			let (state, tasks) =
+				romeo::system::replay(&config)?.dry_update(event, &config)?;
+			let tasks: Vec<String> =
+				tasks.iter().map(|task| format!("{:?}", task)).collect();
 
-	romeo::system::run(config).await;
+			serde_json::to_writer_pretty(
+				std::io::stdout(),
+				&serde_json::json!({ "state": state, "tasks": tasks }),
+			)?;
+		}
+		None => romeo::system::run(config).await,
+	}
 
 	Ok(())
 }
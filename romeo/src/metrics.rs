@@ -0,0 +1,255 @@
+//! Prometheus metrics of the system
+
+use std::{
+	fmt::Write as _,
+	net::SocketAddr,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+};
+
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::TcpListener,
+};
+use tracing::{info, warn};
+
+use crate::{event::Event, state::State};
+
+/// Counters and gauges updated by the system as it runs
+#[derive(Debug, Default)]
+pub struct Metrics {
+	events_processed: AtomicU64,
+	tasks_spawned: AtomicU64,
+	mints_broadcasted: AtomicU64,
+	burns_broadcasted: AtomicU64,
+	fulfillments_broadcasted: AtomicU64,
+	stacks_block_height: AtomicU64,
+	bitcoin_block_height: AtomicU64,
+}
+
+impl Metrics {
+	/// Count a processed event
+	pub fn record_event(&self, event: &Event) {
+		self.events_processed.fetch_add(1, Ordering::Relaxed);
+
+		let broadcasted = match event {
+			Event::MintBroadcasted(..) => &self.mints_broadcasted,
+			Event::BurnBroadcasted(..) => &self.burns_broadcasted,
+			Event::FulfillBroadcasted(..) => &self.fulfillments_broadcasted,
+			_ => return,
+		};
+
+		broadcasted.fetch_add(1, Ordering::Relaxed);
+	}
+
+	/// Count spawned tasks
+	pub fn record_tasks_spawned(&self, count: usize) {
+		self.tasks_spawned
+			.fetch_add(count as u64, Ordering::Relaxed);
+	}
+
+	/// Update the block heights from the state
+	pub fn record_state(&self, state: &State) {
+		let (State::ContractDetected {
+			stacks_block_height,
+			bitcoin_block_height,
+		}
+		| State::ContractPublicKeySetup {
+			stacks_block_height,
+			bitcoin_block_height,
+			..
+		}
+		| State::Initialized {
+			stacks_block_height,
+			bitcoin_block_height,
+			..
+		}) = state
+		else {
+			return;
+		};
+
+		self.stacks_block_height
+			.store(*stacks_block_height as u64, Ordering::Relaxed);
+		self.bitcoin_block_height
+			.store(*bitcoin_block_height as u64, Ordering::Relaxed);
+	}
+
+	/// Render the metrics in the Prometheus text exposition format
+	pub fn render(&self) -> String {
+		let metrics = [
+			(
+				"romeo_events_processed_total",
+				"counter",
+				"Events processed by the system",
+				&self.events_processed,
+			),
+			(
+				"romeo_tasks_spawned_total",
+				"counter",
+				"Tasks spawned by the system",
+				&self.tasks_spawned,
+			),
+			(
+				"romeo_mints_broadcasted_total",
+				"counter",
+				"Mint transactions broadcasted",
+				&self.mints_broadcasted,
+			),
+			(
+				"romeo_burns_broadcasted_total",
+				"counter",
+				"Burn transactions broadcasted",
+				&self.burns_broadcasted,
+			),
+			(
+				"romeo_fulfillments_broadcasted_total",
+				"counter",
+				"Withdrawal fulfillment transactions broadcasted",
+				&self.fulfillments_broadcasted,
+			),
+			(
+				"romeo_stacks_block_height",
+				"gauge",
+				"Latest Stacks block height processed",
+				&self.stacks_block_height,
+			),
+			(
+				"romeo_bitcoin_block_height",
+				"gauge",
+				"Latest Bitcoin block height processed",
+				&self.bitcoin_block_height,
+			),
+		];
+
+		let mut rendered = String::new();
+
+		for (name, kind, help, value) in metrics {
+			// Writing to a string cannot fail
+			let _ = writeln!(
+				rendered,
+				"# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {}",
+				value.load(Ordering::Relaxed)
+			);
+		}
+
+		rendered
+	}
+}
+
+/// Serve the metrics over HTTP at the address, whatever the request path
+pub async fn serve(
+	addr: SocketAddr,
+	metrics: Arc<Metrics>,
+) -> anyhow::Result<()> {
+	let listener = TcpListener::bind(addr).await?;
+	info!("Serving metrics at {}", listener.local_addr()?);
+
+	loop {
+		let (mut stream, _) = listener.accept().await?;
+		let metrics = metrics.clone();
+
+		tokio::spawn(async move {
+			// The request is not needed to answer it
+			let mut request = [0; 4096];
+			if let Err(err) = stream.read(&mut request).await {
+				warn!("Could not read metrics request: {}", err);
+				return;
+			}
+
+			let body = metrics.render();
+			let response = format!(
+				"HTTP/1.1 200 OK\r\n\
+				 content-type: text/plain; version=0.0.4\r\n\
+				 connection: close\r\n\
+				 content-length: {}\r\n\r\n{}",
+				body.len(),
+				body
+			);
+
+			if let Err(err) = stream.write_all(response.as_bytes()).await {
+				warn!("Could not write metrics response: {}", err);
+			}
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{collections::BTreeMap, time::Duration};
+
+	use bdk::bitcoin::{hashes::Hash, Txid};
+	use blockstack_lib::{
+		burnchains::Txid as StacksTxId, vm::types::PrincipalData,
+	};
+	use tokio::net::TcpStream;
+
+	use super::*;
+	use crate::state::DepositInfo;
+
+	fn mint_broadcasted() -> Event {
+		Event::MintBroadcasted(
+			DepositInfo {
+				txid: Txid::from_inner([1; 32]),
+				amount: 1000,
+				recipient: PrincipalData::parse(
+					"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50",
+				)
+				.unwrap(),
+				block_height: 100000,
+				memo: None,
+			},
+			StacksTxId([2; 32]),
+		)
+	}
+
+	#[test]
+	fn mint_event_should_increment_the_mint_counter() {
+		let metrics = Metrics::default();
+
+		metrics.record_event(&mint_broadcasted());
+		metrics.record_event(&Event::SbtcWalletUtxosRefreshed);
+		metrics.record_state(&State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 100000,
+			deposits: vec![],
+			withdrawals: vec![],
+			bitcoin_block_hashes: BTreeMap::new(),
+		});
+
+		let rendered = metrics.render();
+		assert!(rendered.contains("\nromeo_events_processed_total 2\n"));
+		assert!(rendered.contains("\nromeo_mints_broadcasted_total 1\n"));
+		assert!(rendered.contains("\nromeo_burns_broadcasted_total 0\n"));
+		assert!(rendered.contains("\nromeo_stacks_block_height 5\n"));
+		assert!(rendered.contains("\nromeo_bitcoin_block_height 100000\n"));
+	}
+
+	#[tokio::test]
+	async fn metrics_should_be_served_over_http() {
+		let addr = std::net::TcpListener::bind("127.0.0.1:0")
+			.unwrap()
+			.local_addr()
+			.unwrap();
+		let metrics = Arc::new(Metrics::default());
+		metrics.record_event(&mint_broadcasted());
+		tokio::spawn(serve(addr, metrics));
+
+		let mut stream = loop {
+			match TcpStream::connect(addr).await {
+				Ok(stream) => break stream,
+				Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+			}
+		};
+		stream
+			.write_all(b"GET /metrics HTTP/1.1\r\n\r\n")
+			.await
+			.unwrap();
+		let mut response = String::new();
+		stream.read_to_string(&mut response).await.unwrap();
+
+		assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+		assert!(response.contains("\nromeo_mints_broadcasted_total 1\n"));
+	}
+}
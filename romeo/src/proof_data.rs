@@ -1,18 +1,21 @@
 //! Proof Data used in Clarity Contracts
+use anyhow::anyhow;
 use bdk::bitcoin::{Block, BlockHeader, Transaction, Txid as BitcoinTxId};
 use blockstack_lib::vm::types::{
 	ListData, ListTypeData, SequenceData, Value, BUFF_32,
 };
-use rs_merkle::Hasher;
 use stacks_core::crypto::{sha256::DoubleSha256Hasher, Hashing};
+
+/// Longest merkle path accepted by the asset contract's
+/// `(list 14 (buff 32))` arguments
+pub const MAX_MERKLE_PATH_LENGTH: usize = 14;
 /// The double sha256 algorithm used for bitcoin
 #[derive(Clone)]
 pub struct DoubleSha256Algorithm {}
 
-impl Hasher for DoubleSha256Algorithm {
-	type Hash = [u8; 32];
-
-	fn hash(data: &[u8]) -> [u8; 32] {
+impl DoubleSha256Algorithm {
+	/// Hashes the data twice with sha256, as Bitcoin merkle trees do
+	pub fn hash(data: &[u8]) -> [u8; 32] {
 		DoubleSha256Hasher::hash(data)
 			.as_bytes()
 			.try_into()
@@ -41,6 +44,18 @@ pub struct ProofData {
 	pub merkle_root: String,
 }
 
+/// Layout of the proof arguments expected by the sBTC asset contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProofFormatVersion {
+	/// `txid`, `block-height`, `merkle-path`, `tx-index`, `block-header`
+	#[default]
+	V1,
+	/// `txid`, `block-height`, `merkle-path`, `merkle-tree-depth`,
+	/// `tx-index`, `block-header`
+	V2,
+}
+
 /// Clarity values for the proof data
 pub struct ProofDataClarityValues {
 	/// The transaction id of the bitcoin transaction
@@ -53,6 +68,32 @@ pub struct ProofDataClarityValues {
 	pub block_header: Value,
 	/// The path of the bitcoin transaction in the merkle tree
 	pub merkle_path: Value,
+	/// The depth of the merkle tree, excluding the leaves
+	pub merkle_tree_depth: Value,
+}
+
+impl ProofDataClarityValues {
+	/// Converts the proof values into contract call arguments, ordered
+	/// according to the given proof format version
+	pub fn into_args(self, version: ProofFormatVersion) -> Vec<Value> {
+		match version {
+			ProofFormatVersion::V1 => vec![
+				self.txid,
+				self.block_height,
+				self.merkle_path,
+				self.tx_index,
+				self.block_header,
+			],
+			ProofFormatVersion::V2 => vec![
+				self.txid,
+				self.block_height,
+				self.merkle_path,
+				self.merkle_tree_depth,
+				self.tx_index,
+				self.block_header,
+			],
+		}
+	}
 }
 
 /// Merkle tree for Bitcoin block transactions
@@ -61,7 +102,8 @@ pub struct BitcoinMerkleTree {
 }
 
 impl BitcoinMerkleTree {
-	/// Make a new Merkle tree out of the given Bitcoin txids
+	/// Make a new Merkle tree out of the given Bitcoin txids. Block headers
+	/// commit to legacy txids, so these must not be witness txids.
 	pub fn new(txs: &[BitcoinTxId]) -> Self {
 		if txs.is_empty() {
 			return Self { data: vec![] };
@@ -76,6 +118,13 @@ impl BitcoinMerkleTree {
 			hash_slice.copy_from_slice(tx);
 			leaf_hashes.push(hash_slice);
 		}
+		// a lone transaction is its own root, like a coinbase-only block
+		if txs.len() == 1 {
+			return Self {
+				data: vec![leaf_hashes],
+			};
+		}
+
 		// must have an even number of hashes
 		if txs.len() % 2 == 1 {
 			let last_hash_slice = leaf_hashes
@@ -180,6 +229,42 @@ impl BitcoinMerkleTree {
 }
 
 impl ProofData {
+	/// Create a new proof for the transaction with the given legacy txid.
+	/// Fails if the block doesn't contain it, which is also the case for
+	/// witness txids of segwit transactions, or if the merkle path is longer
+	/// than the contract accepts.
+	pub fn from_block_and_txid(
+		block: &Block,
+		txid: BitcoinTxId,
+	) -> anyhow::Result<Self> {
+		let index = block
+			.txdata
+			.iter()
+			.position(|tx| tx.txid() == txid)
+			.ok_or_else(|| {
+				anyhow!(
+					"Transaction {} is not in block {}, note that witness \
+					 txids are not accepted",
+					txid,
+					block.block_hash()
+				)
+			})?;
+
+		let proof_data = Self::from_block_and_index(block, index);
+
+		if proof_data.merkle_path.len() > MAX_MERKLE_PATH_LENGTH {
+			return Err(anyhow!(
+				"Merkle path of transaction {} has {} hashes, the contract \
+				 accepts at most {}",
+				txid,
+				proof_data.merkle_path.len(),
+				MAX_MERKLE_PATH_LENGTH
+			));
+		}
+
+		Ok(proof_data)
+	}
+
 	/// Create a new proof from a bitcoin transaction and a block
 	pub fn from_block_and_index(block: &Block, index: usize) -> Self {
 		let tx: &Transaction =
@@ -192,7 +277,7 @@ impl ProofData {
 			.proof(index)
 			.expect("FATAL: index is out-of-bounds");
 
-		Self {
+		let proof = Self {
 			reversed_txid: tx.txid(),
 			tx_index: index as u32,
 			block_height: block
@@ -201,7 +286,38 @@ impl ProofData {
 			block_header: block.header,
 			merkle_path: merkle_path.into_iter().map(|h| h.to_vec()).collect(),
 			merkle_root: hex::encode(merkle_tree.root().unwrap()),
+		};
+
+		debug_assert!(
+			proof.verify(&block.header),
+			"Merkle proof does not match the block header"
+		);
+
+		proof
+	}
+
+	/// Checks that the merkle path leads from the transaction to the merkle
+	/// root of the given block header, the same way the contract does
+	pub fn verify(&self, block_header: &BlockHeader) -> bool {
+		let mut index = self.tx_index;
+		let mut hash: [u8; 32] =
+			self.reversed_txid.to_vec().try_into().unwrap();
+
+		for sibling in &self.merkle_path {
+			let mut preimage = [0u8; 64];
+			if index % 2 == 0 {
+				preimage[0..32].copy_from_slice(&hash);
+				preimage[32..64].copy_from_slice(sibling);
+			} else {
+				preimage[0..32].copy_from_slice(sibling);
+				preimage[32..64].copy_from_slice(&hash);
+			}
+
+			hash = DoubleSha256Algorithm::hash(&preimage);
+			index >>= 1;
 		}
+
+		hash.as_slice() == block_header.merkle_root.to_vec()
 	}
 
 	/// converts the proof data to a tuple of clarity values
@@ -229,9 +345,15 @@ impl ProofData {
 					.iter()
 					.map(|v| Value::buff_from(v.clone()).unwrap())
 					.collect(),
-				type_signature: ListTypeData::new_list(BUFF_32.clone(), 14)
-					.unwrap(),
+				// Blocks with many transactions have long paths, so the
+				// maximum length follows the path instead of a fixed size
+				type_signature: ListTypeData::new_list(
+					BUFF_32.clone(),
+					self.merkle_path.len() as u32,
+				)
+				.expect("Failed to build merkle path list type"),
 			})),
+			merkle_tree_depth: Value::UInt(self.merkle_path.len() as u128),
 		}
 	}
 }
@@ -243,13 +365,11 @@ mod tests {
 	use bdk::bitcoin::{consensus::deserialize, hashes::hex::FromHex, Block};
 
 	use super::*;
+	use crate::test_util::testnet_block_100000;
 
 	#[test]
 	fn should_create_correct_proof_data() {
-		// testnet block 100,000
-		let block_hex = "0200000035ab154183570282ce9afc0b494c9fc6a3cfea05aa8c1add2ecc56490000000038ba3d78e4500a5a7570dbe61960398add4410d278b21cd9708e6d9743f374d544fc055227f1001c29c1ea3b0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff3703a08601000427f1001c046a510100522cfabe6d6d0000000000000000000068692066726f6d20706f6f6c7365727665726aac1eeeed88ffffffff0100f2052a010000001976a914912e2b234f941f30b18afbb4fa46171214bf66c888ac00000000";
-		let block: Block =
-			deserialize(&Vec::<u8>::from_hex(block_hex).unwrap()).unwrap();
+		let block = testnet_block_100000();
 		let block_height = 100000;
 		let hash =
 			"00000000009e2958c15ff9290d571bf9459e93b19765c6801ddeccadbb160a1e";
@@ -268,10 +388,7 @@ mod tests {
 	#[test]
 	#[should_panic(expected = "Invalid tx index")]
 	fn should_throw_for_invalid_txindex() {
-		// testnet block 100,000
-		let block_hex = "0200000035ab154183570282ce9afc0b494c9fc6a3cfea05aa8c1add2ecc56490000000038ba3d78e4500a5a7570dbe61960398add4410d278b21cd9708e6d9743f374d544fc055227f1001c29c1ea3b0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff3703a08601000427f1001c046a510100522cfabe6d6d0000000000000000000068692066726f6d20706f6f6c7365727665726aac1eeeed88ffffffff0100f2052a010000001976a914912e2b234f941f30b18afbb4fa46171214bf66c888ac00000000";
-		let block: Block =
-			deserialize(&Vec::<u8>::from_hex(block_hex).unwrap()).unwrap();
+		let block = testnet_block_100000();
 		let txindex: usize = 1;
 
 		ProofData::from_block_and_index(&block, txindex);
@@ -293,20 +410,14 @@ mod tests {
 
 	#[test]
 	fn should_convert_to_clarity_values() {
-		// testnet block 100,000
-		let block_hex = "0200000035ab154183570282ce9afc0b494c9fc6a3cfea05aa8c1add2ecc56490000000038ba3d78e4500a5a7570dbe61960398add4410d278b21cd9708e6d9743f374d544fc055227f1001c29c1ea3b0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff3703a08601000427f1001c046a510100522cfabe6d6d0000000000000000000068692066726f6d20706f6f6c7365727665726aac1eeeed88ffffffff0100f2052a010000001976a914912e2b234f941f30b18afbb4fa46171214bf66c888ac00000000";
-		let block: Block =
-			deserialize(&Vec::<u8>::from_hex(block_hex).unwrap()).unwrap();
+		let block = testnet_block_100000();
 		let txindex: usize = 0;
 		let proof_data = ProofData::from_block_and_index(&block, txindex);
 		let values = proof_data.to_values();
 		assert_eq!(values.txid.to_string(), "0xd574f343976d8e70d91cb278d21044dd8a396019e6db70755a0a50e4783dba38");
 		assert_eq!(values.block_header.to_string(), "0x0200000035ab154183570282ce9afc0b494c9fc6a3cfea05aa8c1add2ecc56490000000038ba3d78e4500a5a7570dbe61960398add4410d278b21cd9708e6d9743f374d544fc055227f1001c29c1ea3b");
 		assert_eq!(values.block_height.to_string(), "u100000");
-		assert_eq!(
-            values.merkle_path.to_string(),
-            "(0x38ba3d78e4500a5a7570dbe61960398add4410d278b21cd9708e6d9743f374d5)"
-        );
+		assert_eq!(values.merkle_path.to_string(), "()");
 	}
 
 	// test from_block_and_index returns correct proof
@@ -347,6 +458,131 @@ mod tests {
 		assert_eq!(values.merkle_path.to_string(), "(0xa9db8b2c0b4de3ee6945db550541adcc18852acef9148dc59747a31c9fbf8327 0xde7c38d3e809bcb86fa94695de178e1b27d8d9b6d25a5683b598c36deca50580 0x02f0523e28df15bf268ab52b9a3826d7f933467ea2708c0d7e7d7cd5b2e44892 0x7f37d80a06a9c7d9db4cf14d63e826ecf136b59df3583cb2b94e0a438d3ae506)");
 	}
 
+	#[test]
+	fn should_order_args_according_to_proof_format_version() {
+		let block = testnet_block_100000();
+		let proof_data = ProofData::from_block_and_index(&block, 0);
+
+		let v1 = proof_data.to_values().into_args(ProofFormatVersion::V1);
+		let v1: Vec<String> = v1.iter().map(ToString::to_string).collect();
+		assert_eq!(
+			v1,
+			vec![
+				"0xd574f343976d8e70d91cb278d21044dd8a396019e6db70755a0a50e4783dba38",
+				"u100000",
+				"()",
+				"u0",
+				"0x0200000035ab154183570282ce9afc0b494c9fc6a3cfea05aa8c1add2ecc56490000000038ba3d78e4500a5a7570dbe61960398add4410d278b21cd9708e6d9743f374d544fc055227f1001c29c1ea3b",
+			]
+		);
+
+		let v2 = proof_data.to_values().into_args(ProofFormatVersion::V2);
+		let v2: Vec<String> = v2.iter().map(ToString::to_string).collect();
+		assert_eq!(
+			v2,
+			vec![
+				"0xd574f343976d8e70d91cb278d21044dd8a396019e6db70755a0a50e4783dba38",
+				"u100000",
+				"()",
+				"u0",
+				"u0",
+				"0x0200000035ab154183570282ce9afc0b494c9fc6a3cfea05aa8c1add2ecc56490000000038ba3d78e4500a5a7570dbe61960398add4410d278b21cd9708e6d9743f374d544fc055227f1001c29c1ea3b",
+			]
+		);
+	}
+
+	#[test]
+	fn should_convert_deep_merkle_paths_to_clarity_values() {
+		let mut block = testnet_block_100000();
+		let coinbase = block.txdata[0].clone();
+
+		// More than 2^14 transactions need a path of 15 hashes
+		block.txdata.extend((1..20_000).map(|lock_time| {
+			let mut tx = coinbase.clone();
+			tx.lock_time = bdk::bitcoin::PackedLockTime(lock_time);
+			tx
+		}));
+		block.header.merkle_root = block.compute_merkle_root().unwrap();
+
+		let proof_data = ProofData::from_block_and_index(&block, 12_345);
+		assert_eq!(proof_data.merkle_path.len(), 15);
+
+		let Value::Sequence(SequenceData::List(merkle_path)) =
+			proof_data.to_values().merkle_path
+		else {
+			panic!("Merkle path is not a list");
+		};
+		assert_eq!(merkle_path.data.len(), 15);
+		assert_eq!(merkle_path.type_signature.get_max_len(), 15);
+
+		let txid = block.txdata[12_345].txid();
+		let err = ProofData::from_block_and_txid(&block, txid).unwrap_err();
+		assert!(err.to_string().contains("accepts at most 14"));
+	}
+
+	// block taken from local regtest node
+	#[test]
+	fn should_verify_merkle_proof_against_block_header() {
+		let block_hex = "000000205214e3b1be1007826f4537f7d86d8f890104587beae37af2fb17e31195a62325bb8940196d4479391e3460fcc904963da6726ecbb99cb9dfc3705ad9ba748f2182270865ffff7f200000000003020000000001010000000000000000000000000000000000000000000000000000000000000000ffffffff0402d20d00ffffffff029f470000000000001976a914ee9369fb719c0ba43ddf4d94638a970b84775f4788ac0000000000000000266a24aa21a9ed2bec0280b5488f0dd3cca56932fdc3eb7b7fba766f6819a0de1cfeaf74c61ecb0120000000000000000000000000000000000000000000000000000000000000000000000000010000000131bde99ad6d1edb8a0f25b7f8458e605e6c1725217346757e7c9a4c4365ef634030000006b483045022100af3c5c67e972b3c744309e79476d71b8c408ce1e45891b3f4d0a9b8bb76c3a8f0220132e8850d7573747a83ccd0b969f12056b8e960a7e6556f389a87b9be218fc2301210239810ebf35e6f6c26062c99f3e183708d377720617c90a986859ec9c95d00be9fdffffff040000000000000000536a4c5069645b76f4413c41080e57ba4b01a485dc7d2465051bfbd2c97f419ddace3e993f88be7b621278694299a79abc623dd56d071f01245e8648e141bfec88d9ba3b1deef100000dd1000100000ab900014a10270000000000001976a914000000000000000000000000000000000000000088ac10270000000000001976a914000000000000000000000000000000000000000088ac82b0c524010000001976a914ee9369fb719c0ba43ddf4d94638a970b84775f4788ac0000000001000000000101010da73321be48f30562e44ff379ea981e204a4fa4bc859c6cd99418e705c7390000000000feffffff0300000000000000001b6a1969643c051a6d78de7b0625dfbfc16c3a8a5735f6dc3dc3f2cee8030000000000002251205e682db7c014ab76f2b4fdcbbdb76f9b8111468174cdb159df6e88fe9d078ce6ab040000000000001600148ae4a48cb0c3b7874460a6f5287d9dd512a182460247304402206387c555478eb821311ef4d3b125a8b4beb698be624e186ff6234f6cd1deb75702207cf063c9cd57dcd7c34b9477129a3a70403856a46be7b9e8942d79482b246379012103ab37f5b606931d7828855affe75199d952bc6174b4a23861b7ac94132210508cc10d0000";
+		let block: Block =
+			deserialize(&Vec::<u8>::from_hex(block_hex).unwrap()).unwrap();
+
+		for txindex in 0..block.txdata.len() {
+			let proof_data = ProofData::from_block_and_index(&block, txindex);
+			assert!(proof_data.verify(&block.header));
+		}
+
+		let mut proof_data = ProofData::from_block_and_index(&block, 2);
+		proof_data.merkle_path[1][0] ^= 1;
+		assert!(!proof_data.verify(&block.header));
+	}
+
+	#[test]
+	fn should_prove_coinbase_only_block_with_empty_path() {
+		// testnet block 100,000, which only has a coinbase transaction
+		let block = testnet_block_100000();
+		let proof_data = ProofData::from_block_and_index(&block, 0);
+
+		assert!(proof_data.merkle_path.is_empty());
+		assert!(proof_data.verify(&block.header));
+
+		let mut txid = block.txdata[0].txid().to_vec();
+		txid.reverse();
+		let values = proof_data.to_values();
+		assert_eq!(values.txid, Value::buff_from(txid).unwrap());
+
+		let Value::Sequence(SequenceData::List(merkle_path)) =
+			values.merkle_path
+		else {
+			panic!("Merkle path is not a list");
+		};
+		assert!(merkle_path.data.is_empty());
+		assert_eq!(merkle_path.type_signature.get_max_len(), 0);
+		assert_eq!(
+			*merkle_path.type_signature.get_list_item_type(),
+			BUFF_32.clone()
+		);
+	}
+
+	// block taken from local regtest node
+	#[test]
+	fn should_fail_to_prove_missing_or_witness_txid() {
+		let block_hex = "000000205214e3b1be1007826f4537f7d86d8f890104587beae37af2fb17e31195a62325bb8940196d4479391e3460fcc904963da6726ecbb99cb9dfc3705ad9ba748f2182270865ffff7f200000000003020000000001010000000000000000000000000000000000000000000000000000000000000000ffffffff0402d20d00ffffffff029f470000000000001976a914ee9369fb719c0ba43ddf4d94638a970b84775f4788ac0000000000000000266a24aa21a9ed2bec0280b5488f0dd3cca56932fdc3eb7b7fba766f6819a0de1cfeaf74c61ecb0120000000000000000000000000000000000000000000000000000000000000000000000000010000000131bde99ad6d1edb8a0f25b7f8458e605e6c1725217346757e7c9a4c4365ef634030000006b483045022100af3c5c67e972b3c744309e79476d71b8c408ce1e45891b3f4d0a9b8bb76c3a8f0220132e8850d7573747a83ccd0b969f12056b8e960a7e6556f389a87b9be218fc2301210239810ebf35e6f6c26062c99f3e183708d377720617c90a986859ec9c95d00be9fdffffff040000000000000000536a4c5069645b76f4413c41080e57ba4b01a485dc7d2465051bfbd2c97f419ddace3e993f88be7b621278694299a79abc623dd56d071f01245e8648e141bfec88d9ba3b1deef100000dd1000100000ab900014a10270000000000001976a914000000000000000000000000000000000000000088ac10270000000000001976a914000000000000000000000000000000000000000088ac82b0c524010000001976a914ee9369fb719c0ba43ddf4d94638a970b84775f4788ac0000000001000000000101010da73321be48f30562e44ff379ea981e204a4fa4bc859c6cd99418e705c7390000000000feffffff0300000000000000001b6a1969643c051a6d78de7b0625dfbfc16c3a8a5735f6dc3dc3f2cee8030000000000002251205e682db7c014ab76f2b4fdcbbdb76f9b8111468174cdb159df6e88fe9d078ce6ab040000000000001600148ae4a48cb0c3b7874460a6f5287d9dd512a182460247304402206387c555478eb821311ef4d3b125a8b4beb698be624e186ff6234f6cd1deb75702207cf063c9cd57dcd7c34b9477129a3a70403856a46be7b9e8942d79482b246379012103ab37f5b606931d7828855affe75199d952bc6174b4a23861b7ac94132210508cc10d0000";
+		let block: Block =
+			deserialize(&Vec::<u8>::from_hex(block_hex).unwrap()).unwrap();
+		let segwit_tx = &block.txdata[2];
+		assert_ne!(segwit_tx.txid().as_hash(), segwit_tx.wtxid().as_hash());
+
+		let proof_data =
+			ProofData::from_block_and_txid(&block, segwit_tx.txid()).unwrap();
+		assert_eq!(proof_data.tx_index, 2);
+
+		let witness_txid = BitcoinTxId::from_hash(segwit_tx.wtxid().as_hash());
+		let err =
+			ProofData::from_block_and_txid(&block, witness_txid).unwrap_err();
+		assert!(err.to_string().contains("is not in block"));
+	}
+
 	// test empty merkle tree
 	#[test]
 	fn should_create_merkle_trees_correctly() {
@@ -0,0 +1,263 @@
+//! Reconciliation of the Romeo state with the on-chain state of the asset
+//! contract
+
+use std::collections::HashMap;
+
+use anyhow::bail;
+use bdk::bitcoin::Txid as BitcoinTxId;
+use blockstack_lib::vm::types::{OptionalData, ResponseData, Value};
+
+use crate::{config::Config, stacks_client::StacksClient, system::replay};
+
+/// An sBTC operation as seen by Romeo
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Operation {
+	/// ID of the Bitcoin deposit or withdrawal request transaction
+	pub txid: BitcoinTxId,
+
+	/// Amount minted, or the negated amount burned
+	pub amount: i128,
+
+	/// Whether the mint or burn transaction is confirmed
+	pub confirmed: bool,
+}
+
+/// On-chain state of the asset contract relevant for reconciliation
+#[derive(Debug, Clone, Default)]
+pub struct ContractState {
+	/// Amounts the contract recorded for the Bitcoin transactions it processed
+	pub amounts: HashMap<BitcoinTxId, i128>,
+
+	/// Total supply of sBTC
+	pub total_supply: u128,
+}
+
+/// An operation recorded with different amounts
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct AmountMismatch {
+	/// ID of the Bitcoin deposit or withdrawal request transaction
+	pub txid: BitcoinTxId,
+
+	/// Amount according to Romeo
+	pub romeo_amount: i128,
+
+	/// Amount according to the contract
+	pub contract_amount: i128,
+}
+
+/// Differences between the Romeo state and the contract.
+///
+/// The contract can only be queried for Bitcoin transactions Romeo knows
+/// about, so operations entirely unknown to Romeo only show up as a total
+/// supply mismatch.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct Diff {
+	/// Operations Romeo considers done which the contract doesn't reflect
+	pub missing_on_chain: Vec<Operation>,
+
+	/// Operations the contract reflects which Romeo doesn't consider done
+	pub missing_in_romeo: Vec<Operation>,
+
+	/// Operations recorded with different amounts
+	pub amount_mismatches: Vec<AmountMismatch>,
+
+	/// Total supply implied by the operations Romeo considers done
+	pub expected_total_supply: i128,
+
+	/// Total supply reported by the contract
+	pub contract_total_supply: u128,
+}
+
+impl Diff {
+	/// Diff operations known to Romeo against the contract state
+	pub fn new(operations: &[Operation], contract: &ContractState) -> Self {
+		let mut diff = Self {
+			contract_total_supply: contract.total_supply,
+			..Default::default()
+		};
+
+		for operation in operations {
+			let contract_amount = contract.amounts.get(&operation.txid);
+
+			match (operation.confirmed, contract_amount) {
+				(true, None) => diff.missing_on_chain.push(operation.clone()),
+				(false, Some(_)) => {
+					diff.missing_in_romeo.push(operation.clone())
+				}
+				_ => {}
+			}
+
+			if let Some(&contract_amount) = contract_amount {
+				if contract_amount != operation.amount {
+					diff.amount_mismatches.push(AmountMismatch {
+						txid: operation.txid,
+						romeo_amount: operation.amount,
+						contract_amount,
+					});
+				}
+			}
+
+			if operation.confirmed {
+				diff.expected_total_supply += operation.amount;
+			}
+		}
+
+		diff
+	}
+
+	/// Whether Romeo and the contract agree
+	pub fn is_empty(&self) -> bool {
+		self.missing_on_chain.is_empty()
+			&& self.missing_in_romeo.is_empty()
+			&& self.amount_mismatches.is_empty()
+			&& self.expected_total_supply == self.contract_total_supply as i128
+	}
+}
+
+/// Replay the persisted events and diff the resulting state against the
+/// contract
+pub async fn reconcile(config: &Config) -> anyhow::Result<Diff> {
+	let operations = replay(config)?.operations();
+
+	let mut client = StacksClient::new(config.clone(), reqwest::Client::new());
+	let contract = get_contract_state(&mut client, &operations).await?;
+
+	Ok(Diff::new(&operations, &contract))
+}
+
+async fn get_contract_state(
+	client: &mut StacksClient,
+	operations: &[Operation],
+) -> anyhow::Result<ContractState> {
+	let mut amounts = HashMap::new();
+
+	for operation in operations {
+		// The contract keys amounts by the txid in big endian
+		let mut txid = operation.txid.to_vec();
+		txid.reverse();
+
+		let amount = client
+			.call_read_only(
+				"get-amount-by-btc-txid",
+				&[Value::buff_from(txid)
+					.expect("Failed to convert txid to buffer")],
+			)
+			.await?;
+
+		match amount {
+			Value::Optional(OptionalData { data: None }) => {}
+			Value::Optional(OptionalData { data: Some(amount) }) => {
+				let Value::Int(amount) = *amount else {
+					bail!(
+						"Unexpected amount for {}: {}",
+						operation.txid,
+						amount
+					);
+				};
+
+				amounts.insert(operation.txid, amount);
+			}
+			value => bail!("Unexpected amount response: {}", value),
+		}
+	}
+
+	let total_supply =
+		match client.call_read_only("get-total-supply", &[]).await? {
+			Value::Response(ResponseData {
+				committed: true,
+				data,
+			}) => match *data {
+				Value::UInt(total_supply) => total_supply,
+				value => bail!("Unexpected total supply: {}", value),
+			},
+			value => bail!("Unexpected total supply response: {}", value),
+		};
+
+	Ok(ContractState {
+		amounts,
+		total_supply,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn txid(n: u8) -> BitcoinTxId {
+		format!("{:064x}", n).parse().unwrap()
+	}
+
+	#[test]
+	fn diff_should_report_out_of_sync_contract() {
+		let operations = vec![
+			// Confirmed mint the contract agrees with
+			Operation {
+				txid: txid(1),
+				amount: 1000,
+				confirmed: true,
+			},
+			// Confirmed mint the contract doesn't know about
+			Operation {
+				txid: txid(2),
+				amount: 2000,
+				confirmed: true,
+			},
+			// Pending burn the contract already processed
+			Operation {
+				txid: txid(3),
+				amount: -500,
+				confirmed: false,
+			},
+			// Confirmed burn with a different amount on chain
+			Operation {
+				txid: txid(4),
+				amount: -300,
+				confirmed: true,
+			},
+		];
+
+		let contract = ContractState {
+			amounts: HashMap::from([
+				(txid(1), 1000),
+				(txid(3), -500),
+				(txid(4), -200),
+			]),
+			total_supply: 300,
+		};
+
+		let diff = Diff::new(&operations, &contract);
+
+		assert!(!diff.is_empty());
+		assert_eq!(diff.missing_on_chain, vec![operations[1].clone()]);
+		assert_eq!(diff.missing_in_romeo, vec![operations[2].clone()]);
+		assert_eq!(
+			diff.amount_mismatches,
+			vec![AmountMismatch {
+				txid: txid(4),
+				romeo_amount: -300,
+				contract_amount: -200,
+			}]
+		);
+		assert_eq!(diff.expected_total_supply, 2700);
+		assert_eq!(diff.contract_total_supply, 300);
+
+		let json = serde_json::to_value(&diff).unwrap();
+		assert_eq!(json["missing_on_chain"][0]["amount"], 2000);
+	}
+
+	#[test]
+	fn diff_should_be_empty_when_in_sync() {
+		let operations = vec![Operation {
+			txid: txid(1),
+			amount: 1000,
+			confirmed: true,
+		}];
+
+		let contract = ContractState {
+			amounts: HashMap::from([(txid(1), 1000)]),
+			total_supply: 1000,
+		};
+
+		assert!(Diff::new(&operations, &contract).is_empty());
+	}
+}
@@ -1,20 +1,29 @@
 //! Stacks client
 
-use std::{io::Cursor, sync::Arc, time::Duration};
+use std::{
+	collections::HashSet,
+	io::Cursor,
+	path::Path,
+	sync::Arc,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::{anyhow, Error};
+use async_trait::async_trait;
 use blockstack_lib::{
 	burnchains::Txid as StacksTxId,
 	chainstate::stacks::{
 		StacksTransaction, StacksTransactionSigner, TransactionAnchorMode,
-		TransactionPostConditionMode,
+		TransactionPayload, TransactionPostConditionMode,
+		TransactionSmartContract, TransactionVersion,
 	},
 	codec::StacksMessageCodec,
-	core::CHAIN_ID_TESTNET,
+	core::CHAIN_ID_MAINNET,
 	types::chainstate::StacksPrivateKey,
+	util_lib::strings::StacksString,
 	vm::{
 		types::{QualifiedContractIdentifier, StandardPrincipalData},
-		ContractName,
+		ContractName, Value as ClarityValue,
 	},
 };
 use futures::Future;
@@ -22,16 +31,30 @@ use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use reqwest::{Request, RequestBuilder, Response, StatusCode};
 use serde::de::DeserializeOwned;
 use serde_json::Value;
-use stacks_core::{codec::Codec, uint::Uint256};
+use stacks_core::{
+	codec::Codec,
+	crypto::{sha256::Sha256Hasher, Hashing},
+	uint::Uint256,
+	Network as StacksNetwork,
+};
 use tokio::{
+	fs::OpenOptions,
+	io::AsyncWriteExt,
 	sync::{Mutex, MutexGuard},
 	time::sleep,
 };
 use tracing::{debug, trace, warn};
 
-use crate::{config::Config, event::TransactionStatus};
+use crate::{
+	config::{ClarityVersion, Config, RetryPolicy},
+	event::TransactionStatus,
+};
+
+/// Percentage by which the fee of a stuck transaction is increased
+const FEE_BUMP_PERCENTAGE: u64 = 25;
 
-const BLOCK_POLLING_INTERVAL: Duration = Duration::from_secs(5);
+/// Number of transactions requested per page of the API transaction lists
+const TRANSACTIONS_PAGE_LIMIT: usize = 50;
 
 /// Wrapped Stacks Client which can be shared safely between threads.
 #[derive(Clone, Debug)]
@@ -50,6 +73,166 @@ impl From<StacksClient> for LockedClient {
 	}
 }
 
+/// Stacks operations performed by the system, abstracted so the system can
+/// run against other implementations than a Stacks node
+#[async_trait]
+pub trait StacksApi: Clone + Send + Sync + 'static {
+	/// Chain ID of the Stacks network
+	async fn chain_id(&self) -> anyhow::Result<u32>;
+
+	/// Sign and broadcast an unsigned stacks transaction
+	async fn sign_and_broadcast(
+		&self,
+		tx: StacksTransaction,
+	) -> anyhow::Result<StacksTxId>;
+
+	/// Rebroadcast a transaction with the same nonce and an increased fee,
+	/// returning the original txid if its fee is already at the cap
+	async fn bump_fee(&self, txid: StacksTxId) -> anyhow::Result<StacksTxId>;
+
+	/// Get transaction status for a given txid
+	async fn get_transation_status(
+		&self,
+		txid: StacksTxId,
+	) -> anyhow::Result<TransactionStatus>;
+
+	/// Get the block height of the contract
+	async fn get_contract_block_height(
+		&self,
+		name: ContractName,
+	) -> anyhow::Result<u32>;
+
+	/// Get the height of the current Stacks chain tip
+	async fn get_stacks_tip_height(&self) -> anyhow::Result<u32>;
+
+	/// Get the height of the Bitcoin chain tip seen by the Stacks node
+	async fn get_burn_block_height(&self) -> anyhow::Result<u32>;
+
+	/// Get the deployment of the asset contract
+	async fn get_contract_deployment(
+		&self,
+	) -> anyhow::Result<ContractDeployment>;
+
+	/// Find a pending or successful call to the asset contract
+	async fn find_contract_call(
+		&self,
+		function_name: &str,
+		args: &[ClarityValue],
+	) -> anyhow::Result<Option<StacksTxId>>;
+
+	/// Whether the asset contract is paused
+	async fn is_contract_paused(&self) -> anyhow::Result<bool>;
+
+	/// Get the Bitcoin block height for a Stacks block height
+	async fn get_bitcoin_block_height(
+		&self,
+		block_height: u32,
+	) -> anyhow::Result<u32>;
+
+	/// Get the block at height
+	async fn get_block(
+		&self,
+		block_height: u32,
+	) -> anyhow::Result<Vec<StacksTransaction>>;
+
+	/// Get the block hash for a given Bitcoin height
+	async fn get_block_hash_from_bitcoin_height(
+		&self,
+		height: u32,
+	) -> anyhow::Result<Uint256>;
+}
+
+#[async_trait]
+impl StacksApi for LockedClient {
+	async fn chain_id(&self) -> anyhow::Result<u32> {
+		self.lock().await.chain_id().await
+	}
+
+	async fn sign_and_broadcast(
+		&self,
+		tx: StacksTransaction,
+	) -> anyhow::Result<StacksTxId> {
+		self.lock().await.sign_and_broadcast(tx).await
+	}
+
+	async fn bump_fee(&self, txid: StacksTxId) -> anyhow::Result<StacksTxId> {
+		self.lock().await.bump_fee(txid).await
+	}
+
+	async fn get_transation_status(
+		&self,
+		txid: StacksTxId,
+	) -> anyhow::Result<TransactionStatus> {
+		self.lock().await.get_transation_status(txid).await
+	}
+
+	async fn get_contract_block_height(
+		&self,
+		name: ContractName,
+	) -> anyhow::Result<u32> {
+		self.lock().await.get_contract_block_height(name).await
+	}
+
+	async fn get_stacks_tip_height(&self) -> anyhow::Result<u32> {
+		self.lock().await.get_stacks_tip_height().await
+	}
+
+	async fn get_burn_block_height(&self) -> anyhow::Result<u32> {
+		self.lock().await.get_burn_block_height().await
+	}
+
+	async fn get_contract_deployment(
+		&self,
+	) -> anyhow::Result<ContractDeployment> {
+		let mut client = self.lock().await;
+		let id = client.contract_id();
+
+		client.get_contract_deployment(&id).await
+	}
+
+	async fn find_contract_call(
+		&self,
+		function_name: &str,
+		args: &[ClarityValue],
+	) -> anyhow::Result<Option<StacksTxId>> {
+		self.lock()
+			.await
+			.find_contract_call(function_name, args)
+			.await
+	}
+
+	async fn is_contract_paused(&self) -> anyhow::Result<bool> {
+		self.lock().await.is_contract_paused().await
+	}
+
+	async fn get_bitcoin_block_height(
+		&self,
+		block_height: u32,
+	) -> anyhow::Result<u32> {
+		self.lock()
+			.await
+			.get_bitcoin_block_height(block_height)
+			.await
+	}
+
+	async fn get_block(
+		&self,
+		block_height: u32,
+	) -> anyhow::Result<Vec<StacksTransaction>> {
+		self.lock().await.get_block(block_height).await
+	}
+
+	async fn get_block_hash_from_bitcoin_height(
+		&self,
+		height: u32,
+	) -> anyhow::Result<Uint256> {
+		self.lock()
+			.await
+			.get_block_hash_from_bitcoin_height(height)
+			.await
+	}
+}
+
 /// Stateful client for creating and broadcasting Stacks transactions
 ///
 /// This client keeps track of the last executed nonce for the given
@@ -58,17 +241,42 @@ impl From<StacksClient> for LockedClient {
 pub struct StacksClient {
 	config: Config,
 	http_client: reqwest::Client,
+	chain_id: Option<u32>,
+	next_nonce: Option<u64>,
+	capped_fees: HashSet<StacksTxId>,
 }
 
 impl StacksClient {
 	/// Create a new StacksClient
 	pub fn new(config: Config, http_client: reqwest::Client) -> Self {
 		Self {
+			chain_id: config.stacks_chain_id,
 			config,
 			http_client,
+			next_nonce: None,
+			capped_fees: HashSet::new(),
 		}
 	}
 
+	/// Chain ID of the Stacks network, as configured or otherwise reported by
+	/// the Stacks node. Detected once and cached afterwards. Fails if the
+	/// chain ID doesn't belong to the configured Stacks network.
+	pub async fn chain_id(&mut self) -> anyhow::Result<u32> {
+		let chain_id = match self.chain_id {
+			Some(chain_id) => chain_id,
+			None => {
+				let chain_id = self.get_node_info().await?.network_id;
+				debug!("Detected Stacks chain ID {:#x}", chain_id);
+				chain_id
+			}
+		};
+
+		check_chain_id(self.config.stacks_network, chain_id)?;
+		self.chain_id = Some(chain_id);
+
+		Ok(chain_id)
+	}
+
 	async fn send_request<B, T>(&self, request_builder: B) -> anyhow::Result<T>
 	where
 		B: Clone + Fn() -> Request,
@@ -76,7 +284,7 @@ impl StacksClient {
 	{
 		let request_url = request_builder().url().to_string();
 
-		let res = retry(|| {
+		let res = retry(&self.config.retry_policy, || {
 			self.http_client
 				.execute(self.add_stacks_api_key(request_builder()))
 		})
@@ -134,13 +342,60 @@ impl StacksClient {
 			sleep(Duration::from_secs(3)).await;
 		}
 
-		tx.set_origin_nonce(self.get_nonce_info().await?.possible_next_nonce);
+		let nonce = match self.next_nonce {
+			Some(nonce) => nonce,
+			None => self.get_nonce_info().await?.possible_next_nonce,
+		};
+
+		tx.set_origin_nonce(nonce);
 		tx.set_tx_fee(self.calculate_fee(tx.tx_len()).await?);
 
 		tx.anchor_mode = TransactionAnchorMode::Any;
 		tx.post_condition_mode = TransactionPostConditionMode::Allow;
-		tx.chain_id = CHAIN_ID_TESTNET;
+		set_chain_id(&mut tx, self.chain_id().await?);
+
+		// Transactions still in the mempool aren't reflected in the nonce
+		// reported by the node, so the next nonce is tracked locally. A
+		// failed broadcast or a rejected transaction may mean it is out of
+		// sync, in which case it is fetched from the node again.
+		let res = self.sign_and_send(tx).await;
+		self.next_nonce = res.as_ref().ok().map(|_| nonce + 1);
+
+		res
+	}
+
+	/// Rebroadcast a transaction with the same nonce and an increased fee,
+	/// replacing the original one in the mempool. A transaction whose fee is
+	/// already at the cap is left pending and its txid returned as is.
+	pub async fn bump_fee(
+		&mut self,
+		txid: StacksTxId,
+	) -> anyhow::Result<StacksTxId> {
+		let mut tx = self.get_transaction(txid).await?;
+
+		// Rebroadcasting the same fee would never replace the transaction
+		let Some(fee) = bumped_fee(tx.get_tx_fee(), self.config.max_stacks_fee)
+		else {
+			if self.capped_fees.insert(txid) {
+				warn!(
+					"Fee of Stacks transaction {} is already at the {} cap, leaving it pending",
+					txid, self.config.max_stacks_fee
+				);
+			}
 
+			return Ok(txid);
+		};
+
+		tx.set_tx_fee(fee);
+		tx.auth = tx.auth.into_initial_sighash_auth();
+
+		self.sign_and_send(tx).await
+	}
+
+	async fn sign_and_send(
+		&mut self,
+		mut tx: StacksTransaction,
+	) -> anyhow::Result<StacksTxId> {
 		let mut signer = StacksTransactionSigner::new(&tx);
 
 		signer
@@ -174,6 +429,14 @@ impl StacksClient {
 			})
 			.await?;
 
+		if let Some(record) = CallRecord::new(&tx, res) {
+			let path = self.config.state_directory.join("calls.ndjson");
+
+			if let Err(err) = record.append_to(&path).await {
+				warn!("Could not record contract call {}: {}", res, err);
+			}
+		}
+
 		Ok(res)
 	}
 
@@ -205,12 +468,25 @@ impl StacksClient {
 			err => panic!("Unknown transation status: {:?}", err),
 		};
 
-		Ok(match tx_status_str.as_str() {
+		let status = match tx_status_str.as_str() {
 			"pending" => TransactionStatus::Broadcasted,
 			"success" => TransactionStatus::Confirmed,
-			"abort_by_response" => TransactionStatus::Rejected,
+			"abort_by_response" | "abort_by_post_condition" => {
+				TransactionStatus::Rejected
+			}
+			status if status.starts_with("dropped_") => {
+				TransactionStatus::Rejected
+			}
 			status => panic!("Unknown transation status: {}", status),
-		})
+		};
+
+		// Later nonces may now be ahead of the node, or a dropped nonce left
+		// a gap, so the next broadcast gets its nonce from the node again
+		if status == TransactionStatus::Rejected {
+			self.next_nonce = None;
+		}
+
+		Ok(status)
 	}
 
 	async fn get_nonce_info(&mut self) -> anyhow::Result<NonceInfo> {
@@ -253,6 +529,159 @@ impl StacksClient {
 		}
 	}
 
+	/// Get the deployment transaction of a contract
+	pub async fn get_contract_deployment(
+		&mut self,
+		id: &QualifiedContractIdentifier,
+	) -> anyhow::Result<ContractDeployment> {
+		let res: Value = self
+			.send_request(|| {
+				self.http_client
+					.get(self.contract_info_url(id.to_string()))
+					.build()
+					.unwrap()
+			})
+			.await?;
+
+		ContractDeployment::from_contract_info(&res)
+	}
+
+	/// Get information about the Stacks node and its view of the chains
+	pub async fn get_node_info(&mut self) -> anyhow::Result<StacksNodeInfo> {
+		self.send_request(|| {
+			self.http_client
+				.get(self.cachebust(self.info_url()))
+				.build()
+				.unwrap()
+		})
+		.await
+	}
+
+	/// Get the height of the current Stacks chain tip
+	pub async fn get_stacks_tip_height(&mut self) -> anyhow::Result<u32> {
+		Ok(self.get_node_info().await?.stacks_tip_height)
+	}
+
+	/// Get the height of the Bitcoin chain tip seen by the Stacks node
+	pub async fn get_burn_block_height(&mut self) -> anyhow::Result<u32> {
+		Ok(self.get_node_info().await?.burn_block_height)
+	}
+
+	/// Call a read-only function of the asset contract
+	pub async fn call_read_only(
+		&mut self,
+		function_name: &str,
+		args: &[ClarityValue],
+	) -> anyhow::Result<ClarityValue> {
+		let contract_id = self.contract_id();
+
+		self.call_contract_read_only(&contract_id, function_name, args)
+			.await
+	}
+
+	/// Call a read-only function of any contract
+	pub async fn call_contract_read_only(
+		&mut self,
+		contract_id: &QualifiedContractIdentifier,
+		function_name: &str,
+		args: &[ClarityValue],
+	) -> anyhow::Result<ClarityValue> {
+		let arguments: Vec<String> =
+			args.iter().map(clarity_value_hex).collect();
+		let body = serde_json::json!({
+			"sender": self.config.stacks_credentials.address().to_string(),
+			"arguments": arguments,
+		});
+
+		let res: Value = self
+			.send_request(|| {
+				self.http_client
+					.post(self.read_only_url(contract_id, function_name))
+					.json(&body)
+					.build()
+					.unwrap()
+			})
+			.await?;
+
+		if !res["okay"].as_bool().unwrap_or_default() {
+			return Err(anyhow!(
+				"Read-only call to {} failed: {}",
+				function_name,
+				res["cause"]
+			));
+		}
+
+		let result = res["result"]
+			.as_str()
+			.ok_or_else(|| anyhow!("Could not get result from: {}", res))?;
+		let bytes = hex::decode(result.trim_start_matches("0x"))?;
+
+		Ok(ClarityValue::consensus_deserialize(&mut &bytes[..])?)
+	}
+
+	/// Whether the asset contract is paused
+	pub async fn is_contract_paused(&mut self) -> anyhow::Result<bool> {
+		match self.call_read_only("is-paused", &[]).await? {
+			ClarityValue::Bool(paused) => Ok(paused),
+			value => Err(anyhow!("Unexpected is-paused result: {:?}", value)),
+		}
+	}
+
+	/// Find a pending or successful transaction calling `function_name` on the
+	/// asset contract with the given arguments, so it doesn't need to be
+	/// broadcasted again. Every page of the mempool and account transactions
+	/// is searched.
+	pub async fn find_contract_call(
+		&mut self,
+		function_name: &str,
+		args: &[ClarityValue],
+	) -> anyhow::Result<Option<StacksTxId>> {
+		let contract_id = self.contract_id().to_string();
+		let args: Vec<String> = args.iter().map(clarity_value_hex).collect();
+
+		for url in [self.mempool_url(), self.address_transactions_url()] {
+			let mut offset = 0;
+
+			loop {
+				let mut page_url = url.clone();
+				page_url
+					.query_pairs_mut()
+					.append_pair("limit", &TRANSACTIONS_PAGE_LIMIT.to_string())
+					.append_pair("offset", &offset.to_string());
+
+				let res: Value = self
+					.send_request(|| {
+						self.http_client
+							.get(self.cachebust(page_url.clone()))
+							.build()
+							.unwrap()
+					})
+					.await?;
+
+				let txid = find_contract_call(
+					&res,
+					&contract_id,
+					function_name,
+					&args,
+				);
+
+				if txid.is_some() {
+					return Ok(txid);
+				}
+
+				let page_len = res["results"].as_array().map_or(0, Vec::len);
+				offset += page_len;
+
+				let total = res["total"].as_u64().unwrap_or_default();
+				if page_len == 0 || offset as u64 >= total {
+					break;
+				}
+			}
+		}
+
+		Ok(None)
+	}
+
 	/// Get the Bitcoin block height for a Stacks block height
 	pub async fn get_bitcoin_block_height(
 		&mut self,
@@ -293,7 +722,7 @@ impl StacksClient {
 			}
 
 			trace!("Stacks block not found, retrying...");
-			sleep(BLOCK_POLLING_INTERVAL).await;
+			sleep(self.config.block_polling_interval()).await;
 		};
 
 		let tx_ids: Vec<StacksTxId> = res["txs"]
@@ -377,8 +806,12 @@ impl StacksClient {
 			.json()
 			.await?;
 
-		// TODO: Figure out what's the right multiplier #98
-		Ok(fee_rate * tx_len * 100)
+		Ok(stacks_fee(
+			fee_rate,
+			tx_len,
+			self.config.stacks_fee_multiplier,
+			self.config.max_stacks_fee,
+		))
 	}
 
 	fn transaction_url(&self) -> reqwest::Url {
@@ -390,21 +823,21 @@ impl StacksClient {
 
 	fn get_raw_transaction_url(&self, txid: StacksTxId) -> reqwest::Url {
 		self.config
-			.stacks_node_url
+			.stacks_read_node_url
 			.join(&format!("/extended/v1/tx/{}/raw", txid))
 			.unwrap()
 	}
 
 	fn block_by_height_url(&self, height: u32) -> reqwest::Url {
 		self.config
-			.stacks_node_url
+			.stacks_read_node_url
 			.join(&format!("/extended/v1/block/by_height/{}", height))
 			.unwrap()
 	}
 
 	fn block_by_bitcoin_height_url(&self, height: u32) -> reqwest::Url {
 		self.config
-			.stacks_node_url
+			.stacks_read_node_url
 			.join(&format!(
 				"/extended/v1/block/by_burn_block_height/{}",
 				height
@@ -414,14 +847,58 @@ impl StacksClient {
 
 	fn contract_info_url(&self, id: impl AsRef<str>) -> reqwest::Url {
 		self.config
-			.stacks_node_url
+			.stacks_read_node_url
 			.join(&format!("/extended/v1/contract/{}", id.as_ref()))
 			.unwrap()
 	}
 
+	fn contract_id(&self) -> QualifiedContractIdentifier {
+		let addr = self.config.stacks_credentials.address();
+
+		QualifiedContractIdentifier::new(
+			StandardPrincipalData(
+				addr.version() as u8,
+				addr.hash().as_ref().try_into().unwrap(),
+			),
+			self.config.contract_name.clone(),
+		)
+	}
+
+	fn mempool_url(&self) -> reqwest::Url {
+		let path = format!(
+			"/extended/v1/tx/mempool?sender_address={}",
+			self.config.stacks_credentials.address(),
+		);
+
+		self.config.stacks_read_node_url.join(&path).unwrap()
+	}
+
+	fn address_transactions_url(&self) -> reqwest::Url {
+		let path = format!(
+			"/extended/v1/address/{}/transactions",
+			self.config.stacks_credentials.address(),
+		);
+
+		self.config.stacks_read_node_url.join(&path).unwrap()
+	}
+
+	fn read_only_url(
+		&self,
+		contract_id: &QualifiedContractIdentifier,
+		function_name: &str,
+	) -> reqwest::Url {
+		self.config
+			.stacks_read_node_url
+			.join(&format!(
+				"/v2/contracts/call-read/{}/{}/{}",
+				contract_id.issuer, contract_id.name, function_name
+			))
+			.unwrap()
+	}
+
 	fn get_transation_details_url(&self, txid: StacksTxId) -> reqwest::Url {
 		self.config
-			.stacks_node_url
+			.stacks_read_node_url
 			.join(&format!("/extended/v1/tx/{}", txid))
 			.unwrap()
 	}
@@ -446,6 +923,8 @@ impl StacksClient {
 		url
 	}
 
+	// A read replica may lag behind the node transactions are broadcast to,
+	// so nonces are always read from the latter
 	fn nonce_url(&self) -> reqwest::Url {
 		let path = format!(
 			"/extended/v1/address/{}/nonces",
@@ -455,6 +934,10 @@ impl StacksClient {
 		self.config.stacks_node_url.join(&path).unwrap()
 	}
 
+	fn info_url(&self) -> reqwest::Url {
+		self.config.stacks_read_node_url.join("/v2/info").unwrap()
+	}
+
 	fn fee_url(&self) -> reqwest::Url {
 		self.config
 			.stacks_node_url
@@ -463,12 +946,255 @@ impl StacksClient {
 	}
 }
 
+fn clarity_value_hex(value: &ClarityValue) -> String {
+	let mut bytes = vec![];
+	value.consensus_serialize(&mut bytes).unwrap();
+
+	format!("0x{}", hex::encode(bytes))
+}
+
+/// Finds a pending or successful contract call in a list of transactions
+/// returned by the API
+fn find_contract_call(
+	res: &Value,
+	contract_id: &str,
+	function_name: &str,
+	args: &[String],
+) -> Option<StacksTxId> {
+	res["results"].as_array()?.iter().find_map(|tx| {
+		let call = &tx["contract_call"];
+
+		let is_live = matches!(
+			tx["tx_status"].as_str(),
+			Some("pending") | Some("success")
+		);
+		let tx_args: Option<Vec<&str>> = call["function_args"]
+			.as_array()?
+			.iter()
+			.map(|arg| arg["hex"].as_str())
+			.collect();
+
+		let is_match = is_live
+			&& call["contract_id"].as_str() == Some(contract_id)
+			&& call["function_name"].as_str() == Some(function_name)
+			&& tx_args? == args;
+
+		is_match
+			.then(|| tx["tx_id"].as_str())
+			.flatten()
+			.and_then(|txid| {
+				StacksTxId::from_hex(txid.trim_start_matches("0x")).ok()
+			})
+	})
+}
+
+/// Transaction version matching a Stacks chain ID
+pub fn transaction_version(chain_id: u32) -> TransactionVersion {
+	match chain_id {
+		CHAIN_ID_MAINNET => TransactionVersion::Mainnet,
+		_ => TransactionVersion::Testnet,
+	}
+}
+
+/// Sets the chain ID of a transaction along with the matching version
+fn set_chain_id(tx: &mut StacksTransaction, chain_id: u32) {
+	tx.chain_id = chain_id;
+	tx.version = transaction_version(chain_id);
+}
+
+/// The Stacks node serves another network than the configured one. Retrying
+/// cannot fix this.
+#[derive(Debug, thiserror::Error)]
+#[error(
+	"Stacks chain ID {chain_id:#x} does not belong to the {network} network"
+)]
+pub struct ChainIdMismatch {
+	/// Chain ID reported by the node or configured
+	pub chain_id: u32,
+	/// Configured Stacks network
+	pub network: StacksNetwork,
+}
+
+/// Checks that a chain ID is the mainnet one exactly when the network is
+/// mainnet, so transactions are never signed for the wrong network
+fn check_chain_id(network: StacksNetwork, chain_id: u32) -> anyhow::Result<()> {
+	if (chain_id == CHAIN_ID_MAINNET) != (network == StacksNetwork::Mainnet) {
+		return Err(ChainIdMismatch { chain_id, network }.into());
+	}
+
+	Ok(())
+}
+
+/// Payload deploying a contract with the given Clarity version
+pub fn contract_deploy_payload(
+	contract_name: ContractName,
+	code: &str,
+	clarity_version: ClarityVersion,
+) -> anyhow::Result<TransactionPayload> {
+	let code_body = StacksString::from_str(code)
+		.ok_or_else(|| anyhow!("Contract code is not a valid Stacks string"))?;
+
+	Ok(TransactionPayload::SmartContract(
+		TransactionSmartContract {
+			name: contract_name,
+			code_body,
+		},
+		Some(clarity_version.into()),
+	))
+}
+
+/// Fee of a transaction of the given length, capped to the maximum fee
+fn stacks_fee(
+	fee_rate: u64,
+	tx_len: u64,
+	multiplier: u64,
+	max_fee: u64,
+) -> u64 {
+	fee_rate
+		.saturating_mul(tx_len)
+		.saturating_mul(multiplier)
+		.min(max_fee)
+}
+
+fn increased_fee(fee: u64) -> u64 {
+	fee.saturating_add((fee.saturating_mul(FEE_BUMP_PERCENTAGE) / 100).max(1))
+}
+
+/// Fee replacing a transaction with the given fee, if still below the cap
+fn bumped_fee(fee: u64, max_fee: u64) -> Option<u64> {
+	Some(increased_fee(fee).min(max_fee)).filter(|bumped_fee| *bumped_fee > fee)
+}
+
+/// Audit record of a contract call broadcasted by Romeo
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CallRecord {
+	/// Name of the called function
+	pub function_name: String,
+	/// Arguments of the call in their Clarity representation
+	pub args: Vec<String>,
+	/// Nonce of the transaction
+	pub nonce: u64,
+	/// Fee of the transaction
+	pub fee: u64,
+	/// ID of the transaction
+	pub txid: StacksTxId,
+	/// Seconds since the Unix epoch at which the call was broadcasted
+	pub timestamp: u64,
+}
+
+impl CallRecord {
+	/// Create a record of a broadcasted transaction, if it is a contract call
+	pub fn new(tx: &StacksTransaction, txid: StacksTxId) -> Option<Self> {
+		let TransactionPayload::ContractCall(call) = &tx.payload else {
+			return None;
+		};
+
+		Some(Self {
+			function_name: call.function_name.to_string(),
+			args: call.function_args.iter().map(ToString::to_string).collect(),
+			nonce: tx.get_origin_nonce(),
+			fee: tx.get_tx_fee(),
+			txid,
+			timestamp: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or_default()
+				.as_secs(),
+		})
+	}
+
+	/// Append the record as a line to the file at the given path
+	pub async fn append_to(&self, path: &Path) -> anyhow::Result<()> {
+		let mut file = OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(path)
+			.await?;
+
+		let mut line = serde_json::to_vec(self)?;
+		line.push(b'\n');
+		file.write_all(&line).await?;
+
+		Ok(())
+	}
+}
+
+/// Provenance of a deployed contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractDeployment {
+	/// ID of the deployment transaction
+	pub txid: StacksTxId,
+	/// Principal that deployed the contract
+	pub deployer: String,
+	/// Height of the Stacks block including the deployment
+	pub block_height: u32,
+	/// Hex encoded SHA256 hash of the contract source
+	pub source_hash: String,
+	/// Clarity version of the contract, if the node reports a known one
+	pub clarity_version: Option<ClarityVersion>,
+}
+
+impl ContractDeployment {
+	/// Parse a deployment from a contract info response
+	pub fn from_contract_info(res: &Value) -> anyhow::Result<Self> {
+		if let Some(err) = res["error"].as_str() {
+			return Err(Error::msg(err.to_string()));
+		}
+
+		let field = |name: &str| {
+			res[name]
+				.as_str()
+				.ok_or_else(|| anyhow!("Contract info is missing {}", name))
+		};
+
+		let txid =
+			StacksTxId::from_hex(field("tx_id")?.trim_start_matches("0x"))
+				.map_err(|err| anyhow!("Invalid deployment txid: {:?}", err))?;
+		let (deployer, _) = field("contract_id")?
+			.split_once('.')
+			.ok_or_else(|| anyhow!("Invalid contract ID"))?;
+		let block_height = res["block_height"]
+			.as_u64()
+			.ok_or_else(|| anyhow!("Contract info is missing block_height"))?;
+		let clarity_version = match res["clarity_version"].as_u64() {
+			Some(1) => Some(ClarityVersion::Clarity1),
+			Some(2) => Some(ClarityVersion::Clarity2),
+			_ => None,
+		};
+
+		Ok(Self {
+			txid,
+			deployer: deployer.to_string(),
+			block_height: block_height.try_into()?,
+			source_hash: Sha256Hasher::new(field("source_code")?).to_hex(),
+			clarity_version,
+		})
+	}
+}
+
+/// Information reported by a Stacks node on `/v2/info`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct StacksNodeInfo {
+	/// Network ID of the node, which doubles as the chain ID
+	pub network_id: u32,
+	/// Height of the Stacks chain tip
+	pub stacks_tip_height: u32,
+	/// Height of the Bitcoin chain tip seen by the node
+	pub burn_block_height: u32,
+	/// Version string of the node software
+	pub server_version: String,
+	/// Consensus hash of the current PoX fork
+	pub pox_consensus: String,
+}
+
 #[derive(serde::Deserialize)]
 struct NonceInfo {
 	possible_next_nonce: u64,
 }
 
-async fn retry<O, Fut>(operation: O) -> anyhow::Result<Response>
+async fn retry<O, Fut>(
+	policy: &RetryPolicy,
+	operation: O,
+) -> anyhow::Result<Response>
 where
 	O: Clone + Fn() -> Fut,
 	Fut: Future<Output = Result<Response, reqwest::Error>>,
@@ -487,8 +1213,9 @@ where
 						.status()
 						.unwrap_or(StatusCode::IM_A_TEAPOT)
 						.as_u16();
+					// Rate limits and unavailable or timed out upstreams
 					match status_code_number {
-						429 | 522 => {
+						429 | 502 | 503 | 504 | 522 => {
 							backoff::Error::transient(anyhow::anyhow!(err))
 						}
 						_ => backoff::Error::permanent(anyhow::anyhow!(err)),
@@ -503,18 +1230,49 @@ where
 		warn!("Retrying in {:?} after error: {:?}", duration, err);
 	};
 
-	backoff::future::retry_notify(
-		backoff::ExponentialBackoff::default(),
-		operation,
-		notify,
-	)
-	.await
+	backoff::future::retry_notify(policy.backoff(), operation, notify).await
 }
 
 #[cfg(test)]
 mod tests {
+	use bdk::bitcoin::secp256k1::Secp256k1;
+	use blockstack_lib::{
+		chainstate::stacks::{
+			AssetInfo, FungibleConditionCode, PostConditionPrincipal,
+			TokenTransferMemo, TransactionAuth, TransactionContractCall,
+			TransactionPostCondition, TransactionSpendingCondition,
+		},
+		core::CHAIN_ID_TESTNET,
+		types::chainstate::{StacksAddress, StacksPublicKey},
+		vm::{types::PrincipalData, ClarityName},
+	};
+	use stacks_core::{
+		address::{AddressVersion, StacksAddress as CoreStacksAddress},
+		clarity_name::ClarityName as CoreClarityName,
+		clarity_value::Value as CoreValue,
+		contract_name::ContractName as CoreContractName,
+		crypto::{hash160::Hash160Hasher, PrivateKey},
+		transaction::{
+			post_condition::{
+				AssetInfo as CoreAssetInfo,
+				FungibleConditionCode as CoreFungibleConditionCode,
+				PostCondition as CorePostCondition,
+				PostConditionPrincipal as CorePostConditionPrincipal,
+			},
+			signer::TransactionSigner,
+			AnchorMode, MessageSignature, PostConditionMode, PublicKeyEncoding,
+			SinglesigHashMode, SinglesigSpendingCondition,
+			Transaction as CoreTransaction,
+			TransactionAuth as CoreTransactionAuth,
+			TransactionPayload as CoreTransactionPayload,
+			TransactionSpendingCondition as CoreTransactionSpendingCondition,
+			TransactionVersion as CoreTransactionVersion,
+		},
+		utils::PrincipalData as CorePrincipalData,
+	};
+
 	use super::*;
-	use crate::config::Config;
+	use crate::{config::Config, test_server::serve, test_util::test_config};
 
 	// These integration tests are for exploration/experimentation but should be
 	// removed once we have more decent tests
@@ -542,4 +1300,825 @@ mod tests {
 
 		stacks_client.calculate_fee(123).await.unwrap();
 	}
+
+	#[test]
+	fn node_info_should_parse_from_response_body() {
+		let body = r#"{
+			"peer_version": 4207599113,
+			"pox_consensus": "17f76e597bab45646956f38dd39573085d72cbc0",
+			"burn_block_height": 2575799,
+			"stable_pox_consensus": "8e0561978fc5506b68a589c402dad97e862edb59",
+			"stable_burn_block_height": 2575792,
+			"server_version": "stacks-node 2.4.0.0.0",
+			"network_id": 2147483648,
+			"parent_network_id": 118034699,
+			"stacks_tip_height": 145004,
+			"stacks_tip": "0x0e9e8bf8c4d1b0c6b27ef3ae7eb6cb7f2a4fb3b4a2e8f83fbe5fea7f2e0b7f9c",
+			"stacks_tip_consensus_hash": "17f76e597bab45646956f38dd39573085d72cbc0",
+			"unanchored_tip": null,
+			"exit_at_block_height": null
+		}"#;
+
+		let info: StacksNodeInfo = serde_json::from_str(body).unwrap();
+
+		assert_eq!(
+			info,
+			StacksNodeInfo {
+				network_id: CHAIN_ID_TESTNET,
+				stacks_tip_height: 145004,
+				burn_block_height: 2575799,
+				server_version: "stacks-node 2.4.0.0.0".to_string(),
+				pox_consensus: "17f76e597bab45646956f38dd39573085d72cbc0"
+					.to_string(),
+			}
+		);
+	}
+
+	#[test]
+	fn mainnet_node_should_yield_mainnet_chain_id() {
+		let body = r#"{
+			"pox_consensus": "b3ba3e7a3c8d34e0a0e1b8e0ab6d9c1f0a4c8d2e",
+			"burn_block_height": 812345,
+			"server_version": "stacks-node 2.4.0.0.0",
+			"network_id": 1,
+			"parent_network_id": 3652501241,
+			"stacks_tip_height": 121234
+		}"#;
+
+		let info: StacksNodeInfo = serde_json::from_str(body).unwrap();
+
+		assert_eq!(info.network_id, CHAIN_ID_MAINNET);
+		assert_eq!(
+			transaction_version(info.network_id),
+			TransactionVersion::Mainnet
+		);
+		assert_eq!(
+			transaction_version(CHAIN_ID_TESTNET),
+			TransactionVersion::Testnet
+		);
+	}
+
+	#[tokio::test]
+	async fn request_should_succeed_after_service_unavailable() {
+		let body = r#"{
+			"pox_consensus": "b3ba3e7a3c8d34e0a0e1b8e0ab6d9c1f0a4c8d2e",
+			"burn_block_height": 812345,
+			"server_version": "stacks-node 2.4.0.0.0",
+			"network_id": 2147483648,
+			"parent_network_id": 118034699,
+			"stacks_tip_height": 121234
+		}"#;
+		let (url, node) = serve(vec![
+			("503 Service Unavailable", String::new()),
+			("200 OK", body.to_string()),
+		])
+		.await;
+
+		let mut config = config_with_read_node(Some(&url));
+		config.retry_policy.max_interval_ms = 10;
+		let mut client = StacksClient::new(config, reqwest::Client::new());
+
+		let info = client.get_node_info().await.unwrap();
+
+		assert_eq!(info.stacks_tip_height, 121234);
+		assert_eq!(node.await.unwrap().len(), 2);
+	}
+
+	#[tokio::test]
+	async fn read_only_call_should_return_the_result() {
+		let contract_id = QualifiedContractIdentifier::parse(
+			"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50.registry",
+		)
+		.unwrap();
+		let public_key = ClarityValue::buff_from(vec![2; 33]).unwrap();
+		let result =
+			clarity_value_hex(&ClarityValue::some(public_key.clone()).unwrap());
+		let (url, node) = serve(vec![(
+			"200 OK",
+			serde_json::json!({ "okay": true, "result": result }).to_string(),
+		)])
+		.await;
+
+		let mut client = StacksClient::new(
+			config_with_read_node(Some(&url)),
+			reqwest::Client::new(),
+		);
+
+		let value = client
+			.call_contract_read_only(
+				&contract_id,
+				"get-public-key",
+				&[ClarityValue::UInt(1)],
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(value, ClarityValue::some(public_key).unwrap());
+
+		let requests = node.await.unwrap();
+		assert!(requests[0].starts_with(
+			"POST /v2/contracts/call-read/ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50/registry/get-public-key "
+		));
+	}
+
+	#[tokio::test]
+	async fn block_polls_should_use_the_configured_interval() {
+		let pending_block = ("404 Not Found", String::new());
+		let (url, node) = serve(vec![
+			pending_block.clone(),
+			pending_block.clone(),
+			pending_block,
+			("200 OK", r#"{"txs": []}"#.to_string()),
+		])
+		.await;
+
+		let mut config = config_with_read_node(Some(&url));
+		config.block_polling_interval_ms = 10;
+		let mut client = StacksClient::new(config, reqwest::Client::new());
+
+		// The default interval would need 15 seconds for these polls
+		let txs =
+			tokio::time::timeout(Duration::from_secs(2), client.get_block(100))
+				.await
+				.expect("Polling did not use the configured interval")
+				.unwrap();
+
+		assert!(txs.is_empty());
+		assert_eq!(node.await.unwrap().len(), 4);
+	}
+
+	#[tokio::test]
+	async fn consecutive_broadcasts_should_use_consecutive_nonces() {
+		let txid = |byte: u8| StacksTxId([byte; 32]);
+		let (url, node) = serve(vec![
+			("200 OK", r#"{"possible_next_nonce": 7}"#.to_string()),
+			("200 OK", "1".to_string()),
+			("200 OK", serde_json::to_string(&txid(1)).unwrap()),
+			("200 OK", "1".to_string()),
+			("200 OK", serde_json::to_string(&txid(2)).unwrap()),
+		])
+		.await;
+
+		let mut config = config_with_read_node(Some(&url));
+		config.stacks_node_url = url.parse().unwrap();
+		config.stacks_chain_id = Some(CHAIN_ID_TESTNET);
+		let public_key = config.stacks_credentials.public_key();
+		let mut client = StacksClient::new(config, reqwest::Client::new());
+
+		for expected_txid in [txid(1), txid(2)] {
+			let tx = StacksTransaction::new(
+				TransactionVersion::Testnet,
+				TransactionAuth::Standard(
+					TransactionSpendingCondition::new_singlesig_p2pkh(
+						StacksPublicKey::from_slice(&public_key.serialize())
+							.unwrap(),
+					)
+					.unwrap(),
+				),
+				TransactionPayload::TokenTransfer(
+					StacksAddress::burn_address(false).into(),
+					1,
+					TokenTransferMemo([0; 34]),
+				),
+			);
+
+			assert_eq!(
+				client.sign_and_broadcast(tx).await.unwrap(),
+				expected_txid
+			);
+		}
+
+		// Only the first nonce comes from the node
+		assert_eq!(client.next_nonce, Some(9));
+		assert_eq!(
+			node.await
+				.unwrap()
+				.iter()
+				.filter(|request| request.contains("/nonces"))
+				.count(),
+			1
+		);
+	}
+
+	#[tokio::test]
+	async fn dropped_transaction_should_resync_the_nonce() {
+		let (url, _node) = serve(vec![(
+			"200 OK",
+			r#"{"tx_status": "dropped_stale_garbage_collect"}"#.to_string(),
+		)])
+		.await;
+
+		let mut client = StacksClient::new(
+			config_with_read_node(Some(&url)),
+			reqwest::Client::new(),
+		);
+		client.next_nonce = Some(9);
+
+		assert_eq!(
+			client
+				.get_transation_status(StacksTxId([1; 32]))
+				.await
+				.unwrap(),
+			TransactionStatus::Rejected
+		);
+		assert_eq!(client.next_nonce, None);
+	}
+
+	#[tokio::test]
+	async fn mainnet_client_should_sign_for_mainnet_chain_id() {
+		let mut config = config_with_read_node(None);
+		config.stacks_network = StacksNetwork::Mainnet;
+		config.stacks_chain_id = Some(CHAIN_ID_MAINNET);
+
+		let mut client = StacksClient::new(config, reqwest::Client::new());
+		let chain_id = client.chain_id().await.unwrap();
+
+		let public_key = StacksPublicKey::from_slice(
+			&hex::decode("03556902f83defc6c63a7eb56a2d8ee4baee109f2126aac41e4f9e3a0835f34bc5")
+				.unwrap(),
+		)
+		.unwrap();
+		let mut tx = StacksTransaction::new(
+			TransactionVersion::Testnet,
+			TransactionAuth::Standard(
+				TransactionSpendingCondition::new_singlesig_p2pkh(public_key)
+					.unwrap(),
+			),
+			TransactionPayload::ContractCall(TransactionContractCall {
+				address: StacksAddress::burn_address(true),
+				contract_name: ContractName::from("asset"),
+				function_name: ClarityName::from("mint"),
+				function_args: vec![ClarityValue::UInt(1337)],
+			}),
+		);
+		set_chain_id(&mut tx, chain_id);
+
+		assert_eq!(tx.chain_id, CHAIN_ID_MAINNET);
+		assert_eq!(tx.version, TransactionVersion::Mainnet);
+	}
+
+	#[tokio::test]
+	async fn chain_id_of_another_network_should_be_rejected() {
+		let mut config = config_with_read_node(None);
+		config.stacks_chain_id = Some(CHAIN_ID_MAINNET);
+
+		let mut client = StacksClient::new(config, reqwest::Client::new());
+
+		assert!(client.chain_id().await.is_err());
+		assert!(
+			check_chain_id(StacksNetwork::Testnet, CHAIN_ID_TESTNET).is_ok()
+		);
+		assert!(
+			check_chain_id(StacksNetwork::Mainnet, CHAIN_ID_TESTNET).is_err()
+		);
+	}
+
+	#[test]
+	fn pending_contract_call_should_be_found() {
+		let contract_id = "ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50.asset";
+		let public_key = ClarityValue::buff_from(vec![2; 33]).unwrap();
+		let args = vec![clarity_value_hex(&public_key)];
+
+		let res = serde_json::json!({
+			"results": [
+				{
+					"tx_id": "0x0101010101010101010101010101010101010101010101010101010101010101",
+					"tx_status": "abort_by_response",
+					"tx_type": "contract_call",
+					"contract_call": {
+						"contract_id": contract_id,
+						"function_name": "set-bitcoin-wallet-public-key",
+						"function_args": [{ "hex": args[0] }]
+					}
+				},
+				{
+					"tx_id": "0x0202020202020202020202020202020202020202020202020202020202020202",
+					"tx_status": "pending",
+					"tx_type": "contract_call",
+					"contract_call": {
+						"contract_id": contract_id,
+						"function_name": "set-bitcoin-wallet-public-key",
+						"function_args": [{ "hex": args[0] }]
+					}
+				}
+			]
+		});
+
+		assert_eq!(
+			find_contract_call(
+				&res,
+				contract_id,
+				"set-bitcoin-wallet-public-key",
+				&args
+			),
+			Some(StacksTxId([2; 32]))
+		);
+
+		let other_public_key = ClarityValue::buff_from(vec![3; 33]).unwrap();
+		assert_eq!(
+			find_contract_call(
+				&res,
+				contract_id,
+				"set-bitcoin-wallet-public-key",
+				&[clarity_value_hex(&other_public_key)]
+			),
+			None
+		);
+	}
+
+	/// Looks up the set public key call against pages of transactions,
+	/// returning the txid found and the requests made
+	async fn find_set_public_key_call(
+		pages: Vec<(Vec<(u8, &str)>, usize)>,
+	) -> (Option<StacksTxId>, Vec<String>) {
+		let public_key = ClarityValue::buff_from(vec![2; 33]).unwrap();
+		let contract_id =
+			StacksClient::new(test_config(), reqwest::Client::new())
+				.contract_id()
+				.to_string();
+
+		let responses = pages
+			.into_iter()
+			.map(|(txs, total)| {
+				let results: Vec<Value> = txs
+					.into_iter()
+					.map(|(byte, tx_status)| {
+						serde_json::json!({
+							"tx_id": format!("0x{}", hex::encode([byte; 32])),
+							"tx_status": tx_status,
+							"tx_type": "contract_call",
+							"contract_call": {
+								"contract_id": contract_id,
+								"function_name": "set-bitcoin-wallet-public-key",
+								"function_args": [{
+									"hex": clarity_value_hex(&public_key)
+								}]
+							}
+						})
+					})
+					.collect();
+
+				(
+					"200 OK",
+					serde_json::json!({ "total": total, "results": results })
+						.to_string(),
+				)
+			})
+			.collect();
+		let (url, node) = serve(responses).await;
+
+		let mut client = StacksClient::new(
+			config_with_read_node(Some(&url)),
+			reqwest::Client::new(),
+		);
+		let txid = client
+			.find_contract_call("set-bitcoin-wallet-public-key", &[public_key])
+			.await
+			.unwrap();
+
+		(txid, node.await.unwrap())
+	}
+
+	#[tokio::test]
+	async fn contract_call_past_the_first_page_should_be_reused() {
+		let (txid, requests) = find_set_public_key_call(vec![
+			(vec![], 0),
+			(vec![(1, "abort_by_response")], 2),
+			(vec![(2, "success")], 2),
+		])
+		.await;
+
+		assert_eq!(txid, Some(StacksTxId([2; 32])));
+		assert!(requests[2].contains("offset=1"));
+	}
+
+	#[tokio::test]
+	async fn failed_contract_call_should_not_be_reused() {
+		let (txid, requests) = find_set_public_key_call(vec![
+			(vec![], 0),
+			(vec![(1, "abort_by_response")], 1),
+		])
+		.await;
+
+		assert_eq!(txid, None);
+		assert_eq!(requests.len(), 2);
+	}
+
+	#[tokio::test]
+	async fn mint_should_produce_one_call_record() {
+		let txid = StacksTxId([1; 32]);
+		let (url, _node) = serve(vec![
+			("200 OK", r#"{"possible_next_nonce": 7}"#.to_string()),
+			("200 OK", "1".to_string()),
+			("200 OK", serde_json::to_string(&txid).unwrap()),
+		])
+		.await;
+
+		let state_directory = std::env::temp_dir()
+			.join(format!("romeo-calls-{}", std::process::id()));
+		let _ = tokio::fs::remove_dir_all(&state_directory).await;
+		tokio::fs::create_dir_all(&state_directory).await.unwrap();
+
+		let mut config = config_with_read_node(Some(&url));
+		config.stacks_node_url = url.parse().unwrap();
+		config.stacks_chain_id = Some(CHAIN_ID_TESTNET);
+		config.state_directory = state_directory.clone();
+		let public_key = config.stacks_credentials.public_key();
+		let mut client = StacksClient::new(config, reqwest::Client::new());
+
+		let tx = StacksTransaction::new(
+			TransactionVersion::Testnet,
+			TransactionAuth::Standard(
+				TransactionSpendingCondition::new_singlesig_p2pkh(
+					StacksPublicKey::from_slice(&public_key.serialize())
+						.unwrap(),
+				)
+				.unwrap(),
+			),
+			TransactionPayload::ContractCall(TransactionContractCall {
+				address: StacksAddress::burn_address(false),
+				contract_name: ContractName::from("asset"),
+				function_name: ClarityName::from("mint"),
+				function_args: vec![ClarityValue::UInt(1337)],
+			}),
+		);
+
+		assert_eq!(client.sign_and_broadcast(tx).await.unwrap(), txid);
+
+		let contents =
+			tokio::fs::read_to_string(state_directory.join("calls.ndjson"))
+				.await
+				.unwrap();
+		let records: Vec<CallRecord> = contents
+			.lines()
+			.map(|line| serde_json::from_str(line).unwrap())
+			.collect();
+
+		assert_eq!(records.len(), 1);
+		assert_eq!(records[0].function_name, "mint");
+		assert_eq!(records[0].args, vec!["u1337".to_string()]);
+		assert_eq!(records[0].nonce, 7);
+		assert!(records[0].fee > 0);
+		assert_eq!(records[0].txid, txid);
+		assert!(records[0].timestamp > 0);
+
+		tokio::fs::remove_dir_all(&state_directory).await.unwrap();
+	}
+
+	#[test]
+	fn deploy_transaction_should_encode_selected_clarity_version() {
+		for clarity_version in
+			[ClarityVersion::Clarity1, ClarityVersion::Clarity2]
+		{
+			let payload = contract_deploy_payload(
+				ContractName::from("asset"),
+				"(define-read-only (hello) u1)",
+				clarity_version,
+			)
+			.unwrap();
+
+			let tx = StacksTransaction::new(
+				TransactionVersion::Testnet,
+				TransactionAuth::Standard(
+					TransactionSpendingCondition::new_singlesig_p2pkh(
+						StacksPublicKey::from_slice(&hex::decode("03556902f83defc6c63a7eb56a2d8ee4baee109f2126aac41e4f9e3a0835f34bc5").unwrap())
+							.unwrap(),
+					)
+					.unwrap(),
+				),
+				payload,
+			);
+
+			let mut bytes = vec![];
+			tx.consensus_serialize(&mut bytes).unwrap();
+			let tx = StacksTransaction::consensus_deserialize(&mut &bytes[..])
+				.unwrap();
+
+			assert!(matches!(
+				tx.payload,
+				TransactionPayload::SmartContract(_, Some(version))
+					if version == clarity_version.into()
+			));
+		}
+	}
+
+	#[test]
+	fn stacks_fee_should_scale_with_multiplier() {
+		let low = stacks_fee(2, 300, 50, u64::MAX);
+		let high = stacks_fee(2, 300, 150, u64::MAX);
+
+		assert_eq!(low, 30_000);
+		assert_eq!(high, 3 * low);
+	}
+
+	#[test]
+	fn stacks_fee_should_be_capped() {
+		assert_eq!(stacks_fee(2, 300, 100, 50_000), 50_000);
+		assert_eq!(stacks_fee(u64::MAX, 300, 100, 50_000), 50_000);
+		assert_eq!(stacks_fee(2, 300, 100, 60_000), 60_000);
+		assert_eq!(stacks_fee(1, 300, 100, 60_000), 30_000);
+	}
+
+	#[test]
+	fn increased_fee_should_be_strictly_higher() {
+		assert_eq!(increased_fee(0), 1);
+		assert_eq!(increased_fee(3), 4);
+		assert_eq!(increased_fee(1000), 1250);
+		assert_eq!(increased_fee(u64::MAX), u64::MAX);
+	}
+
+	#[test]
+	fn bumped_fee_should_stop_at_the_cap() {
+		assert_eq!(bumped_fee(1000, 50_000), Some(1250));
+		assert_eq!(bumped_fee(49_000, 50_000), Some(50_000));
+		assert_eq!(bumped_fee(50_000, 50_000), None);
+		assert_eq!(bumped_fee(60_000, 50_000), None);
+	}
+
+	fn config_with_read_node(read_node_url: Option<&str>) -> Config {
+		let stacks_node_url: reqwest::Url =
+			"http://primary:3999".parse().unwrap();
+
+		Config {
+			stacks_read_node_url: read_node_url
+				.map(|url| url.parse().unwrap())
+				.unwrap_or_else(|| stacks_node_url.clone()),
+			stacks_node_url,
+			..test_config()
+		}
+	}
+
+	#[test]
+	fn core_signer_should_match_blockstack_signer() {
+		// Same inputs as the stacks-core signer test vector
+		let private_key_hex =
+			"edf9aee84d9b7abc145504dde6726c64f369d37ee34ded868fabd876c26570bc";
+		let recipient = "ST8H248H248H248H248H248H248H248H26RCPJ4T";
+		let (nonce, fee, amount) = (5, 180, 1000);
+
+		// The trailing byte flags a compressed public key
+		let private_key =
+			StacksPrivateKey::from_hex(&format!("{}01", private_key_hex))
+				.unwrap();
+		let mut tx = StacksTransaction::new(
+			TransactionVersion::Testnet,
+			TransactionAuth::Standard(
+				TransactionSpendingCondition::new_singlesig_p2pkh(
+					StacksPublicKey::from_private(&private_key),
+				)
+				.unwrap(),
+			),
+			TransactionPayload::TokenTransfer(
+				PrincipalData::parse(recipient).unwrap(),
+				amount,
+				TokenTransferMemo([0; 34]),
+			),
+		);
+		tx.chain_id = CHAIN_ID_TESTNET;
+		tx.anchor_mode = TransactionAnchorMode::Any;
+		tx.post_condition_mode = TransactionPostConditionMode::Deny;
+		tx.set_origin_nonce(nonce);
+		tx.set_tx_fee(fee);
+
+		let mut signer = StacksTransactionSigner::new(&tx);
+		signer.sign_origin(&private_key).unwrap();
+		let expected = signer.get_tx().unwrap();
+
+		let private_key =
+			PrivateKey::from_slice(&hex::decode(private_key_hex).unwrap())
+				.unwrap();
+		let public_key = private_key.public_key(&Secp256k1::new());
+		let mut signer = TransactionSigner::new(CoreTransaction {
+			version: CoreTransactionVersion::Testnet,
+			chain_id: stacks_core::transaction::CHAIN_ID_TESTNET,
+			auth: CoreTransactionAuth::Standard(
+				CoreTransactionSpendingCondition::Singlesig(
+					SinglesigSpendingCondition {
+						hash_mode: SinglesigHashMode::P2PKH,
+						signer: Hash160Hasher::new(public_key.serialize()),
+						nonce,
+						fee,
+						key_encoding: PublicKeyEncoding::Compressed,
+						signature: MessageSignature::empty(),
+					},
+				),
+			),
+			anchor_mode: AnchorMode::Any,
+			post_condition_mode: PostConditionMode::Deny,
+			post_conditions: vec![],
+			payload: CoreTransactionPayload::TokenTransfer(
+				CorePrincipalData::from(
+					CoreStacksAddress::try_from(recipient).unwrap(),
+				),
+				amount,
+				[0; 34],
+			),
+		});
+		signer.sign_origin(&private_key).unwrap();
+		let transaction = signer.into_transaction();
+
+		assert_eq!(transaction.serialize_to_vec(), expected.serialize_to_vec());
+		assert_eq!(transaction.txid().as_bytes(), expected.txid().as_bytes());
+	}
+
+	fn testnet_address(byte: u8) -> CoreStacksAddress {
+		CoreStacksAddress::new(
+			AddressVersion::TestnetSingleSig,
+			[byte; 20].into(),
+		)
+	}
+
+	#[test]
+	fn core_post_conditions_should_match_blockstack_serialization() {
+		// Same transaction as the stacks-core post condition test vector
+		let private_key_hex =
+			"edf9aee84d9b7abc145504dde6726c64f369d37ee34ded868fabd876c26570bc";
+		let (owner, contract, recipient) = (
+			testnet_address(0x22),
+			testnet_address(0x33),
+			testnet_address(0x11),
+		);
+
+		let private_key =
+			StacksPrivateKey::from_hex(&format!("{}01", private_key_hex))
+				.unwrap();
+		let mut tx = StacksTransaction::new(
+			TransactionVersion::Testnet,
+			TransactionAuth::Standard(
+				TransactionSpendingCondition::new_singlesig_p2pkh(
+					StacksPublicKey::from_private(&private_key),
+				)
+				.unwrap(),
+			),
+			TransactionPayload::TokenTransfer(
+				PrincipalData::parse(&recipient.to_string()).unwrap(),
+				1000,
+				TokenTransferMemo([0; 34]),
+			),
+		);
+		tx.chain_id = CHAIN_ID_TESTNET;
+		tx.anchor_mode = TransactionAnchorMode::Any;
+		tx.post_condition_mode = TransactionPostConditionMode::Deny;
+		tx.post_conditions = vec![
+			TransactionPostCondition::STX(
+				PostConditionPrincipal::Origin,
+				FungibleConditionCode::SentLe,
+				1000,
+			),
+			TransactionPostCondition::Fungible(
+				PostConditionPrincipal::Standard(
+					StacksAddress::from_string(&owner.to_string()).unwrap(),
+				),
+				AssetInfo {
+					contract_address: StacksAddress::from_string(
+						&contract.to_string(),
+					)
+					.unwrap(),
+					contract_name: ContractName::from("asset"),
+					asset_name: ClarityName::from("sbtc"),
+				},
+				FungibleConditionCode::SentEq,
+				5,
+			),
+		];
+		tx.set_origin_nonce(5);
+		tx.set_tx_fee(180);
+
+		let private_key =
+			PrivateKey::from_slice(&hex::decode(private_key_hex).unwrap())
+				.unwrap();
+		let public_key = private_key.public_key(&Secp256k1::new());
+		let transaction = CoreTransaction {
+			version: CoreTransactionVersion::Testnet,
+			chain_id: stacks_core::transaction::CHAIN_ID_TESTNET,
+			auth: CoreTransactionAuth::Standard(
+				CoreTransactionSpendingCondition::Singlesig(
+					SinglesigSpendingCondition {
+						hash_mode: SinglesigHashMode::P2PKH,
+						signer: Hash160Hasher::new(public_key.serialize()),
+						nonce: 5,
+						fee: 180,
+						key_encoding: PublicKeyEncoding::Compressed,
+						signature: MessageSignature::empty(),
+					},
+				),
+			),
+			anchor_mode: AnchorMode::Any,
+			post_condition_mode: PostConditionMode::Deny,
+			post_conditions: vec![
+				CorePostCondition::Stx(
+					CorePostConditionPrincipal::Origin,
+					CoreFungibleConditionCode::SentLe,
+					1000,
+				),
+				CorePostCondition::Fungible(
+					CorePostConditionPrincipal::Standard(owner),
+					CoreAssetInfo {
+						contract_address: contract,
+						contract_name: CoreContractName::new("asset").unwrap(),
+						asset_name: CoreClarityName::new("sbtc").unwrap(),
+					},
+					CoreFungibleConditionCode::SentEq,
+					5,
+				),
+			],
+			payload: CoreTransactionPayload::TokenTransfer(
+				CorePrincipalData::from(recipient),
+				1000,
+				[0; 34],
+			),
+		};
+
+		assert_eq!(transaction.serialize_to_vec(), tx.serialize_to_vec());
+	}
+
+	#[test]
+	fn core_contract_call_should_match_blockstack_serialization() {
+		// Same payload as the stacks-core contract call test vector
+		let (contract, recipient) =
+			(testnet_address(0x33), testnet_address(0x11));
+
+		let payload =
+			TransactionPayload::ContractCall(TransactionContractCall {
+				address: StacksAddress::from_string(&contract.to_string())
+					.unwrap(),
+				contract_name: ContractName::from("asset"),
+				function_name: ClarityName::from("mint"),
+				function_args: vec![
+					ClarityValue::UInt(1000),
+					ClarityValue::from(
+						PrincipalData::parse(&recipient.to_string()).unwrap(),
+					),
+				],
+			});
+
+		let core_payload = CoreTransactionPayload::contract_call(
+			contract,
+			"asset",
+			"mint",
+			[
+				CoreValue::UInt(1000),
+				CoreValue::from(CorePrincipalData::from(recipient)),
+			],
+		)
+		.unwrap();
+
+		assert_eq!(core_payload.serialize_to_vec(), payload.serialize_to_vec());
+	}
+
+	#[test]
+	fn reads_should_hit_the_read_node_and_writes_the_primary() {
+		let client = StacksClient::new(
+			config_with_read_node(Some("http://replica:3999")),
+			reqwest::Client::new(),
+		);
+
+		for url in [
+			client.block_by_height_url(1),
+			client.block_by_bitcoin_height_url(1),
+			client.get_transation_details_url(StacksTxId([1; 32])),
+			client.get_raw_transaction_url(StacksTxId([1; 32])),
+			client.read_only_url("is-protocol-paused"),
+			client.info_url(),
+		] {
+			assert_eq!(url.host_str(), Some("replica"), "{}", url);
+		}
+
+		for url in [client.transaction_url(), client.nonce_url()] {
+			assert_eq!(url.host_str(), Some("primary"), "{}", url);
+		}
+	}
+
+	#[test]
+	fn contract_deployment_should_parse_from_contract_info() {
+		let res = serde_json::json!({
+			"tx_id": "0x0101010101010101010101010101010101010101010101010101010101010101",
+			"canonical": true,
+			"contract_id": "ST3RBZ4TZ3EK22SZRKGFZYBCKD7WQ5B8FFRS57TT6.asset",
+			"block_height": 1234,
+			"clarity_version": 2,
+			"source_code": "(define-read-only (hello) u1)",
+			"abi": "{}"
+		});
+
+		let deployment = ContractDeployment::from_contract_info(&res).unwrap();
+
+		assert_eq!(
+			deployment,
+			ContractDeployment {
+				txid: StacksTxId([1; 32]),
+				deployer: "ST3RBZ4TZ3EK22SZRKGFZYBCKD7WQ5B8FFRS57TT6"
+					.to_string(),
+				block_height: 1234,
+				source_hash: Sha256Hasher::new("(define-read-only (hello) u1)")
+					.to_hex(),
+				clarity_version: Some(ClarityVersion::Clarity2),
+			}
+		);
+
+		assert!(ContractDeployment::from_contract_info(
+			&serde_json::json!({ "error": "cannot find contract" })
+		)
+		.is_err());
+	}
 }
@@ -1,8 +1,11 @@
 //! State
 
-use std::{io::Cursor, iter};
+use std::{collections::BTreeMap, io::Cursor, iter};
 
-use bdk::bitcoin::{Address as BitcoinAddress, Block, Txid as BitcoinTxId};
+use anyhow::anyhow;
+use bdk::bitcoin::{
+	Address as BitcoinAddress, Block, BlockHash, Txid as BitcoinTxId,
+};
 use blockstack_lib::{
 	burnchains::Txid as StacksTxId, chainstate::stacks::StacksTransaction,
 	codec::StacksMessageCodec, types::chainstate::StacksAddress,
@@ -12,20 +15,77 @@ use sbtc_core::operations::{
 	op_return, op_return::withdrawal_request::WithdrawalRequestData,
 };
 use stacks_core::codec::Codec;
-use tracing::{debug, info};
+use tracing::{debug, error, info, warn};
 
 use crate::{
 	config::Config,
 	event::{Event, TransactionStatus},
+	reconcile::Operation,
 	task::Task,
 };
 
-/// The delay in blocks between receiving a deposit request and creating
-/// the deposit transaction.
-const STX_TRANSACTION_DELAY_BLOCKS: u32 = 1;
+/// Number of processed Bitcoin block hashes kept to detect reorgs, bounding
+/// the depth of the reorgs that can be walked back
+const BITCOIN_REORG_DETECTION_DEPTH: u32 = 100;
+
+/// Error applying an event the state cannot account for
+#[derive(Debug, thiserror::Error)]
+pub enum StateError {
+	/// The event cannot be processed in the current state
+	#[error("{0}")]
+	UnexpectedEvent(&'static str),
+
+	/// A status update matched no transaction or several of them
+	#[error("Unexpected number of {0} statuses updated: {1}")]
+	UnexpectedStatusUpdates(&'static str, usize),
+
+	/// The event refers to an operation the state doesn't know
+	#[error("{0}")]
+	UnknownOperation(&'static str),
+
+	/// A status update for a transaction that is not acknowledged, under a
+	/// strict unknown transaction policy
+	#[error("{0}")]
+	UnknownTransaction(String),
+
+	/// A status update for a transaction without a pending task, under a
+	/// strict unexpected status policy
+	#[error("{0}")]
+	UnexpectedStatus(String),
+
+	/// A broadcasted transaction failed, under a strict broadcast failure
+	/// policy
+	#[error("{0}")]
+	BroadcastFailure(String),
+
+	/// A minted deposit is no longer in the Bitcoin chain, under a strict
+	/// reorged deposit policy
+	#[error("{0}")]
+	ReorgedDeposit(String),
+
+	/// A transaction was acknowledged twice, under a strict double broadcast
+	/// policy
+	#[error("{0}")]
+	DoubleBroadcast(&'static str),
+}
+
+impl StateError {
+	/// Whether the error was raised by a strict policy, in which case the
+	/// system halts
+	pub fn is_strict(&self) -> bool {
+		matches!(
+			self,
+			Self::UnknownTransaction(_)
+				| Self::UnexpectedStatus(_)
+				| Self::BroadcastFailure(_)
+				| Self::ReorgedDeposit(_)
+				| Self::DoubleBroadcast(_)
+		)
+	}
+}
 
 /// Romeo internal state
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum State {
 	/// Starting state without any data
 	Uninitialized,
@@ -58,6 +118,9 @@ pub enum State {
 		deposits: Vec<Deposit>,
 		/// Withdrawals
 		withdrawals: Vec<Withdrawal>,
+		/// Hashes of the latest processed Bitcoin blocks by height
+		#[serde(default)]
+		bitcoin_block_hashes: BTreeMap<u32, BlockHash>,
 	},
 }
 
@@ -68,7 +131,7 @@ impl State {
 	}
 
 	/// Spawn initial tasks given a recovered state
-	pub fn bootstrap(&mut self) -> Vec<Task> {
+	pub fn bootstrap(&mut self, config: &Config) -> Vec<Task> {
 		match self {
 			State::Uninitialized => vec![Task::GetContractBlockHeight],
 			State::ContractDetected { .. } => {
@@ -85,6 +148,7 @@ impl State {
 				bitcoin_block_height,
 				deposits,
 				withdrawals,
+				..
 			} => {
 				iter::empty()
 					.chain(
@@ -120,6 +184,13 @@ impl State {
 						}
 					});
 
+				// Processing blocks is idempotent, so rewinding lets us pick up
+				// operations a previous version may have missed
+				*stacks_block_height =
+					stacks_block_height.saturating_sub(config.rescan_depth);
+				*bitcoin_block_height =
+					bitcoin_block_height.saturating_sub(config.rescan_depth);
+
 				vec![
 					Task::FetchStacksBlock(*stacks_block_height + 1),
 					Task::FetchBitcoinBlock(*bitcoin_block_height + 1),
@@ -128,50 +199,120 @@ impl State {
 		}
 	}
 
-	/// Updates the state and return new tasks to be schedules
+	/// sBTC operations known to Romeo, with mints as positive and burns as
+	/// negative amounts
+	pub fn operations(&self) -> Vec<Operation> {
+		let State::Initialized {
+			deposits,
+			withdrawals,
+			..
+		} = self
+		else {
+			return vec![];
+		};
+
+		let is_confirmed = |req: &Option<TransactionRequest<StacksTxId>>| {
+			matches!(
+				req,
+				Some(TransactionRequest::Acknowledged {
+					status: TransactionStatus::Confirmed,
+					..
+				})
+			)
+		};
+
+		let mints = deposits.iter().map(|deposit| Operation {
+			txid: deposit.info.txid,
+			amount: i128::from(deposit.info.amount),
+			confirmed: is_confirmed(&deposit.mint),
+		});
+		let burns = withdrawals.iter().map(|withdrawal| Operation {
+			txid: withdrawal.info.txid,
+			amount: -i128::from(withdrawal.info.amount),
+			confirmed: is_confirmed(&withdrawal.burn),
+		});
+
+		mints.chain(burns).collect()
+	}
+
+	/// Applies an event to a copy of the state, returning the resulting state
+	/// and tasks while leaving this state untouched
+	pub fn dry_update(
+		&self,
+		event: Event,
+		config: &Config,
+	) -> Result<(State, Vec<Task>), StateError> {
+		let mut state = self.clone();
+		let tasks = state.update(event, config)?;
+
+		Ok((state, tasks))
+	}
+
+	/// Updates the state and return new tasks to be schedules. The state is
+	/// left untouched if the event cannot be applied, as every event is
+	/// validated before being applied.
 	#[tracing::instrument(skip(self, config))]
-	pub fn update(&mut self, event: Event, config: &Config) -> Vec<Task> {
+	pub fn update(
+		&mut self,
+		event: Event,
+		config: &Config,
+	) -> Result<Vec<Task>, StateError> {
 		info!("Processing");
 
 		match event {
 			Event::ContractBlockHeight(stacks_height, bitcoin_height) => self
-				.process_contract_block_height(stacks_height, bitcoin_height)
-				.into_iter()
-				.collect(),
+				.process_contract_block_height(stacks_height, bitcoin_height),
 			Event::ContractPublicKeySetBroadcasted(txid) => {
 				self.process_set_contract_public_key(txid)
 			}
-			Event::StacksTransactionUpdate(txid, status) => self
-				.process_stacks_transaction_update(txid, status, config)
-				.into_iter()
-				.collect(),
-			Event::BitcoinTransactionUpdate(txid, status) => self
-				.process_bitcoin_transaction_update(txid, status, config)
-				.into_iter()
-				.collect(),
+			Event::StacksTransactionUpdate(txid, status) => {
+				self.process_stacks_transaction_update(txid, status, config)
+			}
+			Event::StacksTransactionBumped(txid, bumped_txid) => {
+				self.process_stacks_transaction_bumped(txid, bumped_txid)?;
+				Ok(vec![])
+			}
+			Event::StacksTransactionBumpFailed(txid) => {
+				self.process_stacks_transaction_bump_failed(txid)?;
+				Ok(vec![])
+			}
+			Event::BitcoinTransactionUpdate(txid, status) => {
+				self.process_bitcoin_transaction_update(txid, status, config)
+			}
 			Event::StacksBlock(height, txs) => {
-				self.process_stacks_block(height, txs).into_iter().collect()
+				self.process_stacks_block(height, txs, config)
+			}
+			Event::BitcoinBlock(height, block) => {
+				self.process_bitcoin_block(config, height, block)
+			}
+			Event::BitcoinBlocks(blocks) => {
+				self.process_bitcoin_blocks(config, blocks)
 			}
-			Event::BitcoinBlock(height, block) => self
-				.process_bitcoin_block(config, height, block)
-				.into_iter()
-				.collect(),
 			Event::MintBroadcasted(deposit_info, txid) => {
-				self.process_mint_broadcasted(deposit_info, txid, config);
-				vec![]
+				self.process_mint_broadcasted(deposit_info, txid, config)?;
+				Ok(vec![])
 			}
 			Event::BurnBroadcasted(withdrawal_info, txid) => {
-				self.process_burn_broadcasted(withdrawal_info, txid, config);
-				vec![]
+				self.process_burn_broadcasted(withdrawal_info, txid, config)?;
+				Ok(vec![])
 			}
 			Event::FulfillBroadcasted(withdrawal_info, txid) => {
 				self.process_fulfillment_broadcasted(
 					withdrawal_info,
 					txid,
 					config,
-				);
-				vec![]
+				)?;
+				Ok(vec![])
 			}
+			Event::FulfillmentBumped(withdrawal_info, bumped_txid) => {
+				self.process_fulfillment_bumped(withdrawal_info, bumped_txid)?;
+				Ok(vec![])
+			}
+			Event::FulfillmentBumpFailed(withdrawal_info) => {
+				self.process_fulfillment_bump_failed(withdrawal_info)?;
+				Ok(vec![])
+			}
+			Event::SbtcWalletUtxosRefreshed => Ok(vec![]),
 		}
 	}
 
@@ -179,30 +320,33 @@ impl State {
 		&mut self,
 		contract_stacks_block_height: u32,
 		contract_bitcoin_block_height: u32,
-	) -> Vec<Task> {
-		assert!(
-			matches!(self, State::Uninitialized),
-			"Cannot process contract block height when state is initialized"
-		);
+	) -> Result<Vec<Task>, StateError> {
+		if !matches!(self, State::Uninitialized) {
+			return Err(StateError::UnexpectedEvent(
+				"Cannot process contract block height when state is initialized",
+			));
+		}
 
 		*self = State::ContractDetected {
 			stacks_block_height: contract_stacks_block_height,
 			bitcoin_block_height: contract_bitcoin_block_height,
 		};
 
-		vec![Task::UpdateContractPublicKey]
+		Ok(vec![Task::UpdateContractPublicKey])
 	}
 
 	fn process_set_contract_public_key(
 		&mut self,
 		txid: StacksTxId,
-	) -> Vec<Task> {
+	) -> Result<Vec<Task>, StateError> {
 		let State::ContractDetected {
 			stacks_block_height,
 			bitcoin_block_height,
 		} = self
 		else {
-			panic!("Cannot process contract public key when contract is not detected")
+			return Err(StateError::UnexpectedEvent(
+				"Cannot process contract public key when contract is not detected",
+			));
 		};
 
 		let stacks_block_height = *stacks_block_height;
@@ -215,10 +359,11 @@ impl State {
 				txid,
 				status: TransactionStatus::Broadcasted,
 				has_pending_task: false,
+				broadcasted_at: stacks_block_height,
 			},
 		};
 
-		vec![Task::FetchStacksBlock(stacks_block_height + 1)]
+		Ok(vec![Task::FetchStacksBlock(stacks_block_height + 1)])
 	}
 
 	fn process_stacks_transaction_update(
@@ -226,12 +371,36 @@ impl State {
 		txid: StacksTxId,
 		status: TransactionStatus,
 		config: &Config,
-	) -> Vec<Task> {
+	) -> Result<Vec<Task>, StateError> {
+		if let State::Initialized {
+			deposits,
+			withdrawals,
+			..
+		} = self
+		{
+			validate_status_update(
+				iter::empty()
+					.chain(
+						deposits
+							.iter()
+							.filter_map(|deposit| deposit.mint.as_ref()),
+					)
+					.chain(
+						withdrawals
+							.iter()
+							.filter_map(|withdrawal| withdrawal.burn.as_ref()),
+					),
+				"Stacks",
+				txid,
+				&status,
+				config,
+			)?;
+		}
+
 		let mut tasks = self.get_bitcoin_transactions();
 
-		let statuses_updated = match self {
-			State::Uninitialized => None,
-			State::ContractDetected { .. } => None,
+		match self {
+			State::Uninitialized | State::ContractDetected { .. } => {}
 			State::ContractPublicKeySetup {
 				stacks_block_height,
 				bitcoin_block_height,
@@ -241,33 +410,34 @@ impl State {
 					txid: current_txid,
 					status: current_status,
 					has_pending_task,
+					..
 				} = public_key_setup
 				else {
-					if config.strict {
-						panic!("Got an {:?} status update for a public key set Stacks transaction that is not acknowledged: {}", status, txid);
+					if config.strictness.unknown_transaction {
+						return Err(StateError::UnknownTransaction(format!("Got an {:?} status update for a public key set Stacks transaction that is not acknowledged: {}", status, txid)));
 					} else {
 						debug!("Ignoring a Stacks transaction update for a non acknowledged transaction");
-						return vec![];
+						return Ok(vec![]);
 					}
 				};
 
 				if txid != *current_txid {
-					if config.strict {
-						panic!("Got an {:?} status update for a Stacks transaction that is not public key set: {}", status, txid);
+					if config.strictness.unknown_transaction {
+						return Err(StateError::UnknownTransaction(format!("Got an {:?} status update for a Stacks transaction that is not public key set: {}", status, txid)));
 					} else {
 						debug!("Ignoring a Stacks transaction update for a non public key set transaction");
-						return vec![];
+						return Ok(vec![]);
 					}
 				}
 
 				if !*has_pending_task {
-					if config.strict {
-						panic!(
-				            "Got an {:?} status update for a public key set Stacks transaction that doesn't have a pending task: {}", status, txid
-				        );
+					if config.strictness.unexpected_status {
+						return Err(StateError::UnexpectedStatus(format!(
+							"Got an {:?} status update for a public key set Stacks transaction that doesn't have a pending task: {}", status, txid
+						)));
 					} else {
 						debug!("Ignoring a Stacks transaction update for a transaction that doesn't have a pending task");
-						return vec![];
+						return Ok(vec![]);
 					}
 				}
 
@@ -282,82 +452,47 @@ impl State {
 						bitcoin_block_height,
 						deposits: vec![],
 						withdrawals: vec![],
+						bitcoin_block_hashes: Default::default(),
 					};
 
 					tasks.push(Task::FetchBitcoinBlock(
 						bitcoin_block_height + 1,
 					));
 				}
-
-				Some(1)
 			}
 			State::Initialized {
 				deposits,
 				withdrawals,
 				..
 			} => {
-				let statuses_updated: usize = iter::empty()
-					.chain(
-						deposits
-							.iter_mut()
-							.filter_map(|deposit| deposit.mint.as_mut()),
-					)
-					.chain(
-						withdrawals
-							.iter_mut()
-							.filter_map(|withdrawal| withdrawal.burn.as_mut()),
-					)
-					.map(|req| {
-						let TransactionRequest::Acknowledged {
-							txid: current_txid,
-							status: current_status,
-							has_pending_task,
-						} = req
-						else {
-							if config.strict {
-								panic!("Got an {:?} status update for a Stacks transaction that is not acknowledged: {}", status, txid);
-							} else {
-								debug!("Ignoring {:?} status update for a Stacks transaction that is not acknowledged: {}", status, txid);
-								return false;
-							}
-						};
-
-						if txid != *current_txid {
-							return false;
-						}
-
-					    if !*has_pending_task {
-							if config.strict {
-								panic!(
-									"Got an {:?} status update for a Stacks transaction that doesn't have a pending task: {}", status, txid
-								);
-							} else {
-								debug!(
-									"Igno anring {:?} status update for a Stacks transaction that doesn't have a pending task: {}", status, txid
-								);
-							}
-					    }
-
-					    *current_status = status.clone();
-					    *has_pending_task = false;
-
-					    true
-					}).map(|updated| updated as usize).sum();
-
-				Some(statuses_updated)
-			}
-		};
-
-		if let Some(statuses_updated) = statuses_updated {
-			if statuses_updated != 1 {
-				panic!(
-					"Unexpected number of Stacks statuses updated: {}",
-					statuses_updated
+				apply_status_update(
+					iter::empty()
+						.chain(
+							deposits
+								.iter_mut()
+								.filter_map(|deposit| deposit.mint.as_mut()),
+						)
+						.chain(
+							withdrawals.iter_mut().filter_map(|withdrawal| {
+								withdrawal.burn.as_mut()
+							}),
+						),
+					txid,
+					&status,
 				);
+
+				if status == TransactionStatus::Rejected {
+					tasks.extend(retry_rejected_stacks_transactions(
+						deposits,
+						withdrawals,
+						txid,
+						config,
+					));
+				}
 			}
 		}
 
-		tasks
+		Ok(tasks)
 	}
 
 	fn process_bitcoin_transaction_update(
@@ -365,76 +500,94 @@ impl State {
 		txid: BitcoinTxId,
 		status: TransactionStatus,
 		config: &Config,
-	) -> impl IntoIterator<Item = Task> {
+	) -> Result<Vec<Task>, StateError> {
 		let State::Initialized { withdrawals, .. } = self else {
-			panic!("Cannot process Bitcoin transaction update when state is not initialized");
+			return Err(StateError::UnexpectedEvent(
+				"Cannot process Bitcoin transaction update when state is not initialized",
+			));
 		};
 
+		if let Some(withdrawal) = withdrawals
+			.iter_mut()
+			.find(|withdrawal| withdrawal.replaced_fulfillments.contains(&txid))
+		{
+			process_replaced_fulfillment_update(withdrawal, txid, status);
+
+			let mut tasks = self.get_stacks_transactions(config);
+			tasks.extend(self.get_bitcoin_transactions());
+
+			return Ok(tasks);
+		}
+
 		if status == TransactionStatus::Rejected {
-			if config.strict {
-				panic!("Bitcoin transaction failed: {}", txid);
+			if config.strictness.broadcast_failure {
+				return Err(StateError::BroadcastFailure(format!(
+					"Bitcoin transaction failed: {}",
+					txid
+				)));
 			} else {
 				debug!("Bitcoin transaction failed: {}", txid);
 			}
 		}
 
-		let statuses_updated: usize = withdrawals
-	        .iter_mut()
-	        .filter_map(|withdrawal| withdrawal.fulfillment.as_mut())
-			.map(|req| {
-				let TransactionRequest::Acknowledged {
-					txid: current_txid,
-					status: current_status,
-					has_pending_task,
-				} = req
-				else {
-					if config.strict {
-						panic!("Got an {:?} status update for a Bitcoin transaction that is not acknowledged: txid {} req {:?}", status, txid, req);
-					} else {
-						debug!("Ignoring {:?} status update for a Bitcoin transaction that is not acknowledged: txid {} req {:?}", status, txid, req);
-						return false;
-					};
-				};
-
-				if txid != *current_txid {
-					return false;
-				}
+		validate_status_update(
+			withdrawals
+				.iter()
+				.filter_map(|withdrawal| withdrawal.fulfillment.as_ref()),
+			"Bitcoin",
+			txid,
+			&status,
+			config,
+		)?;
+		apply_status_update(
+			withdrawals
+				.iter_mut()
+				.filter_map(|withdrawal| withdrawal.fulfillment.as_mut()),
+			txid,
+			&status,
+		);
 
-			    if !*has_pending_task {
-					if config.strict {
-			        panic!(
-			            "Got an {:?} status update for a Bitcoin transaction that doesn't have a pending task: {}", status, txid
-			        );
+		// Creating the fulfillment again could pay the withdrawal twice, as
+		// one of the transactions it replaced may have been mined instead
+		if status == TransactionStatus::Conflicted {
+			for withdrawal in withdrawals.iter().filter(|withdrawal| {
+				matches!(
+					withdrawal.fulfillment,
+					Some(TransactionRequest::Acknowledged {
+						txid: conflicted_txid,
+						..
+					}) if conflicted_txid == txid
+				)
+			}) {
+				if withdrawal.replaced_fulfillments.is_empty() {
+					error!(
+						"Fulfillment {} of withdrawal {} conflicts with another transaction and needs manual intervention",
+						txid, withdrawal.info.txid
+					);
 				} else {
-					debug!(
-			            "Ignoring {:?} status update for a Bitcoin transaction that doesn't have a pending task: {}", status, txid
-			        );
+					debug!("Checking the fulfillments replaced by {}", txid);
 				}
-			    }
-
-			    *current_status = status.clone();
-			    *has_pending_task = false;
-
-			    true
-			}).map(|updated| updated as usize).sum();
-
-		if statuses_updated != 1 {
-			panic!(
-				"Unexpected number of statuses updated: {}",
-				statuses_updated
-			);
+			}
 		}
 
-		self.get_stacks_transactions()
+		let mut tasks = self.get_stacks_transactions(config);
+		tasks.extend(self.get_bitcoin_transactions());
+
+		Ok(tasks)
 	}
 
 	fn process_stacks_block(
 		&mut self,
 		stacks_height: u32,
 		_txs: Vec<StacksTransaction>,
-	) -> Vec<Task> {
+		config: &Config,
+	) -> Result<Vec<Task>, StateError> {
 		let stacks_block_height = match self {
-			State::Uninitialized | State::ContractDetected { .. } => panic!("Cannot process Stacks block if uninitialized or contract detected"),
+			State::Uninitialized | State::ContractDetected { .. } => {
+				return Err(StateError::UnexpectedEvent(
+					"Cannot process Stacks block if uninitialized or contract detected",
+				))
+			}
 			State::ContractPublicKeySetup {
 				stacks_block_height,
 				..
@@ -449,10 +602,13 @@ impl State {
 
 		let mut tasks = vec![Task::FetchStacksBlock(stacks_height + 1)];
 
+		// Fee bumps take precedence, as a pending status check would otherwise
+		// block them until the next block
+		tasks.extend(self.get_stacks_fee_bumps(config));
 		tasks.extend(self.get_stacks_status_checks());
 		tasks.extend(self.get_bitcoin_transactions());
 
-		tasks
+		Ok(tasks)
 	}
 
 	fn process_bitcoin_block(
@@ -460,28 +616,212 @@ impl State {
 		config: &Config,
 		bitcoin_height: u32,
 		block: Block,
-	) -> Vec<Task> {
+	) -> Result<Vec<Task>, StateError> {
 		let State::Initialized {
 			bitcoin_block_height,
 			deposits,
 			withdrawals,
+			bitcoin_block_hashes,
 			..
 		} = self
 		else {
-			panic!("Cannot process Stacks block if not initialized")
+			return Err(StateError::UnexpectedEvent(
+				"Cannot process Bitcoin block if not initialized",
+			));
 		};
 
+		let parent_height = bitcoin_height.saturating_sub(1);
+
+		// A block not building on the one processed at the previous height
+		// means the chain reorged. Walk back until a common ancestor, which
+		// drops the operations of orphaned blocks as the replacing blocks are
+		// processed.
+		if bitcoin_block_hashes
+			.get(&parent_height)
+			.is_some_and(|hash| *hash != block.header.prev_blockhash)
+		{
+			warn!(
+				"Bitcoin block {} at height {} doesn't build on the processed chain, walking back",
+				block.block_hash(),
+				bitcoin_height
+			);
+
+			bitcoin_block_hashes.retain(|height, _| *height < parent_height);
+			*bitcoin_block_height = parent_height.saturating_sub(1);
+
+			return Ok(vec![Task::FetchBitcoinBlock(parent_height)]);
+		}
+
+		check_reorged_deposits(config, deposits, bitcoin_height, &block)?;
+
+		bitcoin_block_hashes.insert(bitcoin_height, block.block_hash());
+		bitcoin_block_hashes.retain(|height, _| {
+			height + BITCOIN_REORG_DETECTION_DEPTH > bitcoin_height
+		});
+
 		*bitcoin_block_height = bitcoin_height;
 
-		deposits.extend(parse_deposits(config, bitcoin_height, &block));
-		withdrawals.extend(parse_withdrawals(config, &block));
+		// A known deposit missing from the block at its height has been
+		// reorged out of the canonical chain
+		let block_txids: Vec<BitcoinTxId> =
+			block.txdata.iter().map(|tx| tx.txid()).collect();
+
+		let reorged_out = |deposit: &Deposit| {
+			deposit.info.block_height == bitcoin_height
+				&& !block_txids.contains(&deposit.info.txid)
+		};
+
+		deposits.retain(|deposit| {
+			if !reorged_out(deposit) {
+				return true;
+			}
+
+			// A created mint fails to prove the deposit, so it is never
+			// broadcasted
+			match deposit.mint {
+				None
+				| Some(
+					TransactionRequest::Scheduled { .. }
+					| TransactionRequest::Created,
+				) => {
+					warn!(
+						"Dropping deposit {} reorged out of block {}",
+						deposit.info.txid, bitcoin_height
+					);
+					false
+				}
+				_ => {
+					error!(
+						"CRITICAL: minted deposit {} is no longer in the Bitcoin chain at height {}",
+						deposit.info.txid, bitcoin_height
+					);
+					true
+				}
+			}
+		});
+
+		// Withdrawal requests submitted as contract calls are burned as soon as
+		// they are seen, so they are never dropped here
+		withdrawals.retain(|withdrawal| {
+			let orphaned = withdrawal.info.block_height == bitcoin_height
+				&& !block_txids.contains(&withdrawal.info.txid)
+				&& matches!(
+					withdrawal.burn,
+					None | Some(TransactionRequest::Scheduled { .. })
+				);
+
+			if orphaned {
+				warn!(
+					"Dropping withdrawal {} reorged out of block {}",
+					withdrawal.info.txid, bitcoin_height
+				);
+			}
+
+			!orphaned
+		});
+
+		// Blocks may be processed again when rescanning, so skip operations we
+		// already know about
+		let new_deposits: Vec<Deposit> =
+			parse_deposits(config, bitcoin_height, &block)
+				.into_iter()
+				.filter(|deposit| {
+					!deposits
+						.iter()
+						.any(|known| known.info.txid == deposit.info.txid)
+				})
+				.collect();
+		let new_withdrawals: Vec<Withdrawal> =
+			parse_withdrawals(config, &block)
+				.into_iter()
+				.filter(|withdrawal| {
+					!withdrawals
+						.iter()
+						.any(|known| known.info.txid == withdrawal.info.txid)
+				})
+				.collect();
+
+		deposits.extend(new_deposits);
+		withdrawals.extend(new_withdrawals);
 
-		let mut tasks = vec![Task::FetchBitcoinBlock(bitcoin_height + 1)];
+		let mut tasks = vec![
+			Task::FetchBitcoinBlock(bitcoin_height + 1),
+			Task::RefreshSbtcWalletUtxos,
+		];
 
+		// Fee bumps take precedence, as a pending status check would otherwise
+		// block them until the next block
+		tasks.extend(self.get_fulfillment_fee_bumps(config));
 		tasks.extend(self.get_bitcoin_status_checks());
-		tasks.extend(self.get_stacks_transactions());
+		tasks.extend(self.get_stacks_transactions(config));
 
-		tasks
+		Ok(tasks)
+	}
+
+	fn process_bitcoin_blocks(
+		&mut self,
+		config: &Config,
+		blocks: Vec<(u32, Block)>,
+	) -> Result<Vec<Task>, StateError> {
+		// Checked up front so that a failing block doesn't leave the ones
+		// before it applied. Deposits at the height of a later block are only
+		// dropped by that block, so the earlier ones don't change the outcome.
+		if let State::Initialized {
+			deposits,
+			bitcoin_block_hashes,
+			..
+		} = self
+		{
+			let mut last_hash = None;
+
+			for (bitcoin_height, block) in &blocks {
+				let parent_hash = last_hash.or_else(|| {
+					bitcoin_block_hashes
+						.get(&bitcoin_height.saturating_sub(1))
+						.copied()
+				});
+
+				// The rest of the batch is not applied
+				if parent_hash
+					.is_some_and(|hash| hash != block.header.prev_blockhash)
+				{
+					break;
+				}
+
+				check_reorged_deposits(
+					config,
+					deposits,
+					*bitcoin_height,
+					block,
+				)?;
+				last_hash = Some(block.block_hash());
+			}
+		}
+
+		let mut tasks = vec![];
+
+		for (bitcoin_height, block) in blocks {
+			let block_tasks =
+				self.process_bitcoin_block(config, bitcoin_height, block)?;
+
+			// Only the last processed block fetches the next one
+			tasks.retain(|task| {
+				!matches!(
+					task,
+					Task::FetchBitcoinBlock(_) | Task::RefreshSbtcWalletUtxos
+				)
+			});
+			let reorged = !block_tasks
+				.contains(&Task::FetchBitcoinBlock(bitcoin_height + 1));
+			tasks.extend(block_tasks);
+
+			// The rest of the batch builds on an orphaned block
+			if reorged {
+				break;
+			}
+		}
+
+		Ok(tasks)
 	}
 
 	fn get_bitcoin_transactions(&mut self) -> Vec<Task> {
@@ -489,7 +829,7 @@ impl State {
 			return vec![];
 		};
 
-		withdrawals
+		let mut tasks: Vec<Task> = withdrawals
 			.iter_mut()
 			.filter_map(|withdrawal| match withdrawal.burn {
 				Some(TransactionRequest::Acknowledged {
@@ -505,10 +845,14 @@ impl State {
 				},
 				_ => None,
 			})
-			.collect()
+			.collect();
+
+		tasks.sort_by_key(Task::order_key);
+
+		tasks
 	}
 
-	fn get_stacks_transactions(&mut self) -> Vec<Task> {
+	fn get_stacks_transactions(&mut self, config: &Config) -> Vec<Task> {
 		match self {
 			State::Uninitialized | State::ContractPublicKeySetup { .. } => {
 				vec![]
@@ -532,7 +876,7 @@ impl State {
 							// one we make ourselves resilient to mining delays
 							// without complex logic.
 							let scheduled_block_height = *stacks_block_height
-								+ STX_TRANSACTION_DELAY_BLOCKS;
+								+ config.stx_transaction_delay_blocks;
 
 							deposit.mint =
 								Some(TransactionRequest::Scheduled {
@@ -564,7 +908,7 @@ impl State {
 							None => {
 								let scheduled_block_height =
 									*stacks_block_height
-										+ STX_TRANSACTION_DELAY_BLOCKS;
+										+ config.stx_transaction_delay_blocks;
 
 								withdrawal.burn =
 									Some(TransactionRequest::Scheduled {
@@ -594,7 +938,14 @@ impl State {
 						}
 					});
 
-				deposit_tasks.chain(withdrawal_tasks).collect()
+				let mut tasks: Vec<Task> =
+					deposit_tasks.chain(withdrawal_tasks).collect();
+
+				// Operations are created in a deterministic order regardless
+				// of the order in which they were discovered
+				tasks.sort_by_key(Task::order_key);
+
+				tasks
 			}
 		}
 	}
@@ -627,6 +978,7 @@ impl State {
 					txid,
 					status: TransactionStatus::Broadcasted,
 					has_pending_task,
+					..
 				} if !*has_pending_task => {
 					*has_pending_task = true;
 					Some(Task::CheckStacksTransactionStatus(*txid))
@@ -636,21 +988,123 @@ impl State {
 			.collect()
 	}
 
+	fn get_stacks_fee_bumps(&mut self, config: &Config) -> Vec<Task> {
+		let State::Initialized {
+			stacks_block_height,
+			deposits,
+			withdrawals,
+			..
+		} = self
+		else {
+			return vec![];
+		};
+
+		let mint_reqs = deposits
+			.iter_mut()
+			.filter_map(|deposit| deposit.mint.as_mut());
+		let burn_reqs = withdrawals
+			.iter_mut()
+			.filter_map(|withdrawal| withdrawal.burn.as_mut());
+
+		mint_reqs
+			.chain(burn_reqs)
+			.filter_map(|req| match req {
+				TransactionRequest::Acknowledged {
+					txid,
+					status: TransactionStatus::Broadcasted,
+					has_pending_task,
+					broadcasted_at,
+				} if !*has_pending_task
+					&& *stacks_block_height
+						>= broadcasted_at
+							.saturating_add(config.stacks_fee_bump_blocks) =>
+				{
+					debug!(
+						"Bumping fee of {} broadcasted at stacks block height {}",
+						txid, broadcasted_at
+					);
+					*has_pending_task = true;
+					Some(Task::BumpStacksTransaction(*txid))
+				}
+				_ => None,
+			})
+			.collect()
+	}
+
+	fn get_fulfillment_fee_bumps(&mut self, config: &Config) -> Vec<Task> {
+		let State::Initialized {
+			bitcoin_block_height,
+			withdrawals,
+			..
+		} = self
+		else {
+			return vec![];
+		};
+
+		withdrawals
+			.iter_mut()
+			.filter_map(|withdrawal| match withdrawal.fulfillment.as_mut() {
+				Some(TransactionRequest::Acknowledged {
+					txid,
+					status: TransactionStatus::Broadcasted,
+					has_pending_task,
+					broadcasted_at,
+				}) if !*has_pending_task
+					&& *bitcoin_block_height
+						>= broadcasted_at
+							.saturating_add(config.bitcoin_fee_bump_blocks) =>
+				{
+					debug!(
+						"Bumping fee of {} broadcasted at bitcoin block height {}",
+						txid, broadcasted_at
+					);
+					*has_pending_task = true;
+					Some(Task::BumpFulfillment(withdrawal.info.clone(), *txid))
+				}
+				_ => None,
+			})
+			.collect()
+	}
+
 	fn get_bitcoin_status_checks(&mut self) -> Vec<Task> {
 		match self {
 			State::Initialized { withdrawals, .. } => withdrawals
 				.iter_mut()
-				.filter_map(|withdrawal| withdrawal.fulfillment.as_mut())
-				.filter_map(|req| match req {
-					TransactionRequest::Acknowledged {
+				.flat_map(|withdrawal| match withdrawal.fulfillment.as_mut() {
+					Some(TransactionRequest::Acknowledged {
 						txid,
-						status: TransactionStatus::Broadcasted,
+						status,
 						has_pending_task,
-					} if !*has_pending_task => {
+						..
+					}) if !*has_pending_task => {
+						let current_txid = match status {
+							TransactionStatus::Broadcasted => Some(*txid),
+							// Only the replaced transactions can still be mined
+							TransactionStatus::Conflicted
+								if !withdrawal
+									.replaced_fulfillments
+									.is_empty() =>
+							{
+								None
+							}
+							_ => return vec![],
+						};
+
 						*has_pending_task = true;
-						Some(Task::CheckBitcoinTransactionStatus(*txid))
+
+						// Any replaced transaction may be mined instead
+						current_txid
+							.into_iter()
+							.chain(
+								withdrawal
+									.replaced_fulfillments
+									.iter()
+									.copied(),
+							)
+							.map(Task::CheckBitcoinTransactionStatus)
+							.collect::<Vec<_>>()
 					}
-					_ => None,
+					_ => vec![],
 				})
 				.collect(),
 			_ => vec![],
@@ -662,29 +1116,42 @@ impl State {
 		deposit_info: DepositInfo,
 		txid: StacksTxId,
 		config: &Config,
-	) {
-		let State::Initialized { deposits, .. } = self else {
-			panic!("Cannot process broadcasted mint if uninitialized")
-		};
-
-		let deposit = deposits
+	) -> Result<(), StateError> {
+		let State::Initialized {
+			deposits,
+			stacks_block_height,
+			..
+		} = self
+		else {
+			return Err(StateError::UnexpectedEvent(
+				"Cannot process broadcasted mint if uninitialized",
+			));
+		};
+
+		let deposit = deposits
 			.iter_mut()
 			.find(|deposit| deposit.info == deposit_info)
-			.expect("Could not find a deposit for the mint");
+			.ok_or(StateError::UnknownOperation(
+				"Could not find a deposit for the mint",
+			))?;
 
 		debug!("Mint broadcasted: {:?}", deposit.mint);
-		if config.strict {
-			assert!(
-				matches!(deposit.mint, Some(TransactionRequest::Created)),
-				"Newly minted deposit already has mint acknowledged"
-			);
+		if config.strictness.double_broadcast
+			&& !matches!(deposit.mint, Some(TransactionRequest::Created))
+		{
+			return Err(StateError::DoubleBroadcast(
+				"Newly minted deposit already has mint acknowledged",
+			));
 		}
 
 		deposit.mint = Some(TransactionRequest::Acknowledged {
 			txid,
 			status: TransactionStatus::Broadcasted,
 			has_pending_task: false,
+			broadcasted_at: *stacks_block_height,
 		});
+
+		Ok(())
 	}
 
 	fn process_burn_broadcasted(
@@ -692,28 +1159,96 @@ impl State {
 		withdrawal_info: WithdrawalInfo,
 		txid: StacksTxId,
 		config: &Config,
-	) {
-		let State::Initialized { withdrawals, .. } = self else {
-			panic!("Cannot process broadcasted burn if uninitialized")
+	) -> Result<(), StateError> {
+		let State::Initialized {
+			withdrawals,
+			stacks_block_height,
+			..
+		} = self
+		else {
+			return Err(StateError::UnexpectedEvent(
+				"Cannot process broadcasted burn if uninitialized",
+			));
 		};
 
 		let withdrawal = withdrawals
 			.iter_mut()
 			.find(|withdrawal| withdrawal.info == withdrawal_info)
-			.expect("Could not find a withdrawal for the burn");
-
-		if config.strict {
-			assert!(
-				matches!(withdrawal.burn, Some(TransactionRequest::Created)),
-				"Newly burned withdrawal already has burn acknowledged"
-			);
+			.ok_or(StateError::UnknownOperation(
+				"Could not find a withdrawal for the burn",
+			))?;
+
+		if config.strictness.double_broadcast
+			&& !matches!(withdrawal.burn, Some(TransactionRequest::Created))
+		{
+			return Err(StateError::DoubleBroadcast(
+				"Newly burned withdrawal already has burn acknowledged",
+			));
 		}
 
 		withdrawal.burn = Some(TransactionRequest::Acknowledged {
 			txid,
 			status: TransactionStatus::Broadcasted,
 			has_pending_task: false,
+			broadcasted_at: *stacks_block_height,
 		});
+
+		Ok(())
+	}
+
+	fn process_stacks_transaction_bumped(
+		&mut self,
+		txid: StacksTxId,
+		bumped_txid: StacksTxId,
+	) -> Result<(), StateError> {
+		let State::Initialized {
+			stacks_block_height,
+			deposits,
+			withdrawals,
+			..
+		} = self
+		else {
+			return Err(StateError::UnexpectedEvent(
+				"Cannot process bumped Stacks transaction if uninitialized",
+			));
+		};
+
+		let req = find_stacks_request(deposits, withdrawals, txid)?;
+
+		*req = TransactionRequest::Acknowledged {
+			txid: bumped_txid,
+			status: TransactionStatus::Broadcasted,
+			has_pending_task: false,
+			broadcasted_at: *stacks_block_height,
+		};
+
+		Ok(())
+	}
+
+	fn process_stacks_transaction_bump_failed(
+		&mut self,
+		txid: StacksTxId,
+	) -> Result<(), StateError> {
+		let State::Initialized {
+			deposits,
+			withdrawals,
+			..
+		} = self
+		else {
+			return Err(StateError::UnexpectedEvent(
+				"Cannot process failed Stacks fee bump if uninitialized",
+			));
+		};
+
+		// The original transaction stays in place so the bump is retried
+		if let TransactionRequest::Acknowledged {
+			has_pending_task, ..
+		} = find_stacks_request(deposits, withdrawals, txid)?
+		{
+			*has_pending_task = false;
+		}
+
+		Ok(())
 	}
 
 	fn process_fulfillment_broadcasted(
@@ -721,28 +1256,115 @@ impl State {
 		withdrawal_info: WithdrawalInfo,
 		txid: BitcoinTxId,
 		config: &Config,
-	) {
-		let State::Initialized { withdrawals, .. } = self else {
-			panic!("Cannot process broadcasted fulfillment if uninitialized")
+	) -> Result<(), StateError> {
+		let State::Initialized {
+			withdrawals,
+			bitcoin_block_height,
+			..
+		} = self
+		else {
+			return Err(StateError::UnexpectedEvent(
+				"Cannot process broadcasted fulfillment if uninitialized",
+			));
 		};
 
 		let withdrawal = withdrawals
 			.iter_mut()
 			.find(|withdrawal| withdrawal.info == withdrawal_info)
-			.expect("Could not find a withdrawal for the fulfillment");
-
-		if config.strict {
-			assert!(
-			matches!(withdrawal.fulfillment, Some(TransactionRequest::Created)),
-			"Newly fulfilled withdrawal already has fulfillment acknowledged"
-		);
+			.ok_or(StateError::UnknownOperation(
+				"Could not find a withdrawal for the fulfillment",
+			))?;
+
+		if config.strictness.double_broadcast
+			&& !matches!(
+				withdrawal.fulfillment,
+				Some(TransactionRequest::Created)
+			) {
+			return Err(StateError::DoubleBroadcast(
+				"Newly fulfilled withdrawal already has fulfillment acknowledged",
+			));
 		}
 
 		withdrawal.fulfillment = Some(TransactionRequest::Acknowledged {
 			txid,
 			status: TransactionStatus::Broadcasted,
 			has_pending_task: false,
+			broadcasted_at: *bitcoin_block_height,
+		});
+
+		Ok(())
+	}
+
+	fn process_fulfillment_bumped(
+		&mut self,
+		withdrawal_info: WithdrawalInfo,
+		bumped_txid: BitcoinTxId,
+	) -> Result<(), StateError> {
+		let State::Initialized {
+			withdrawals,
+			bitcoin_block_height,
+			..
+		} = self
+		else {
+			return Err(StateError::UnexpectedEvent(
+				"Cannot process bumped fulfillment if uninitialized",
+			));
+		};
+
+		let withdrawal = withdrawals
+			.iter_mut()
+			.find(|withdrawal| withdrawal.info == withdrawal_info)
+			.ok_or(StateError::UnknownOperation(
+				"Could not find a withdrawal for the bumped fulfillment",
+			))?;
+
+		let Some(TransactionRequest::Acknowledged { txid, .. }) =
+			withdrawal.fulfillment
+		else {
+			return Err(StateError::UnexpectedEvent(
+				"Bumped fulfillment was never acknowledged",
+			));
+		};
+
+		// Kept in case the replaced transaction is mined after all
+		withdrawal.replaced_fulfillments.push(txid);
+
+		withdrawal.fulfillment = Some(TransactionRequest::Acknowledged {
+			txid: bumped_txid,
+			status: TransactionStatus::Broadcasted,
+			has_pending_task: false,
+			broadcasted_at: *bitcoin_block_height,
 		});
+
+		Ok(())
+	}
+
+	fn process_fulfillment_bump_failed(
+		&mut self,
+		withdrawal_info: WithdrawalInfo,
+	) -> Result<(), StateError> {
+		let State::Initialized { withdrawals, .. } = self else {
+			return Err(StateError::UnexpectedEvent(
+				"Cannot process failed fulfillment bump if uninitialized",
+			));
+		};
+
+		let withdrawal = withdrawals
+			.iter_mut()
+			.find(|withdrawal| withdrawal.info == withdrawal_info)
+			.ok_or(StateError::UnknownOperation(
+				"Could not find a withdrawal for the failed fulfillment bump",
+			))?;
+
+		// The original transaction stays in place so the bump is retried
+		if let Some(TransactionRequest::Acknowledged {
+			has_pending_task, ..
+		}) = withdrawal.fulfillment.as_mut()
+		{
+			*has_pending_task = false;
+		}
+
+		Ok(())
 	}
 }
 
@@ -752,6 +1374,268 @@ impl Default for State {
 	}
 }
 
+/// Find the mint or burn acknowledged with the given transaction
+fn find_stacks_request<'a>(
+	deposits: &'a mut [Deposit],
+	withdrawals: &'a mut [Withdrawal],
+	txid: StacksTxId,
+) -> Result<&'a mut TransactionRequest<StacksTxId>, StateError> {
+	deposits
+		.iter_mut()
+		.filter_map(|deposit| deposit.mint.as_mut())
+		.chain(
+			withdrawals
+				.iter_mut()
+				.filter_map(|withdrawal| withdrawal.burn.as_mut()),
+		)
+		.find(|req| {
+			matches!(
+				req,
+				TransactionRequest::Acknowledged { txid: current_txid, .. }
+					if *current_txid == txid
+			)
+		})
+		.ok_or(StateError::UnknownOperation(
+			"Could not find a Stacks transaction for the fee bump",
+		))
+}
+
+/// Fail under a strict reorged deposit policy if a minted deposit is missing
+/// from the block at its height
+fn check_reorged_deposits(
+	config: &Config,
+	deposits: &[Deposit],
+	bitcoin_height: u32,
+	block: &Block,
+) -> Result<(), StateError> {
+	if !config.strictness.reorged_deposit {
+		return Ok(());
+	}
+
+	let reorged_out_minted_deposit = deposits.iter().find(|deposit| {
+		deposit.info.block_height == bitcoin_height
+			&& !block.txdata.iter().any(|tx| tx.txid() == deposit.info.txid)
+			&& !matches!(
+				deposit.mint,
+				None | Some(
+					TransactionRequest::Scheduled { .. }
+						| TransactionRequest::Created
+				)
+			)
+	});
+
+	if let Some(deposit) = reorged_out_minted_deposit {
+		return Err(StateError::ReorgedDeposit(format!(
+			"Minted deposit {} is no longer in the Bitcoin chain at height {}",
+			deposit.info.txid, bitcoin_height
+		)));
+	}
+
+	Ok(())
+}
+
+/// Check that a status update matches exactly one acknowledged request, so
+/// that it can be applied without failing halfway
+fn validate_status_update<'a, T>(
+	requests: impl Iterator<Item = &'a TransactionRequest<T>>,
+	chain: &'static str,
+	txid: T,
+	status: &TransactionStatus,
+	config: &Config,
+) -> Result<(), StateError>
+where
+	T: Copy + PartialEq + std::fmt::Display + 'a,
+{
+	let mut statuses_updated = 0;
+
+	for req in requests {
+		let TransactionRequest::Acknowledged {
+			txid: current_txid,
+			has_pending_task,
+			..
+		} = req
+		else {
+			if config.strictness.unknown_transaction {
+				return Err(StateError::UnknownTransaction(format!("Got an {:?} status update for a {} transaction that is not acknowledged: {}", status, chain, txid)));
+			} else {
+				debug!("Ignoring {:?} status update for a {} transaction that is not acknowledged: {}", status, chain, txid);
+				continue;
+			}
+		};
+
+		if txid != *current_txid {
+			continue;
+		}
+
+		if !*has_pending_task {
+			if config.strictness.unexpected_status {
+				return Err(StateError::UnexpectedStatus(format!(
+					"Got an {:?} status update for a {} transaction that doesn't have a pending task: {}", status, chain, txid
+				)));
+			} else {
+				debug!(
+					"Ignoring {:?} status update for a {} transaction that doesn't have a pending task: {}", status, chain, txid
+				);
+			}
+		}
+
+		statuses_updated += 1;
+	}
+
+	if statuses_updated != 1 {
+		return Err(StateError::UnexpectedStatusUpdates(
+			chain,
+			statuses_updated,
+		));
+	}
+
+	Ok(())
+}
+
+/// Set the status of the request acknowledged with the transaction
+fn apply_status_update<'a, T>(
+	requests: impl Iterator<Item = &'a mut TransactionRequest<T>>,
+	txid: T,
+	status: &TransactionStatus,
+) where
+	T: PartialEq + 'a,
+{
+	for req in requests {
+		if let TransactionRequest::Acknowledged {
+			txid: current_txid,
+			status: current_status,
+			has_pending_task,
+			..
+		} = req
+		{
+			if txid == *current_txid {
+				*current_status = status.clone();
+				*has_pending_task = false;
+			}
+		}
+	}
+}
+
+/// Adopt a replaced fulfillment that got confirmed instead of its
+/// replacement, or that is still in the mempool once its replacement
+/// conflicts, and stop checking one that will never be mined
+fn process_replaced_fulfillment_update(
+	withdrawal: &mut Withdrawal,
+	txid: BitcoinTxId,
+	status: TransactionStatus,
+) {
+	let Some(TransactionRequest::Acknowledged {
+		txid: current_txid,
+		status: current_status,
+		has_pending_task,
+		broadcasted_at,
+	}) = &mut withdrawal.fulfillment
+	else {
+		return;
+	};
+
+	let is_conflicted = *current_status == TransactionStatus::Conflicted;
+	if is_conflicted {
+		*has_pending_task = false;
+	}
+	let (current_txid, broadcasted_at) = (*current_txid, *broadcasted_at);
+
+	match status {
+		// Still in the mempool, so it may be mined later
+		TransactionStatus::Broadcasted if !is_conflicted => {}
+		TransactionStatus::Broadcasted | TransactionStatus::Confirmed => {
+			debug!(
+				"Adopting replaced fulfillment {} of withdrawal {}",
+				txid, withdrawal.info.txid
+			);
+
+			withdrawal
+				.replaced_fulfillments
+				.retain(|replaced_txid| *replaced_txid != txid);
+			// Status updates of the replacement may still be in flight
+			if !is_conflicted {
+				withdrawal.replaced_fulfillments.push(current_txid);
+			}
+
+			withdrawal.fulfillment = Some(TransactionRequest::Acknowledged {
+				txid,
+				status,
+				has_pending_task: false,
+				broadcasted_at,
+			});
+		}
+		TransactionStatus::Rejected | TransactionStatus::Conflicted => {
+			withdrawal
+				.replaced_fulfillments
+				.retain(|replaced_txid| *replaced_txid != txid);
+
+			if is_conflicted && withdrawal.replaced_fulfillments.is_empty() {
+				error!(
+					"No fulfillment of withdrawal {} can be mined anymore and it needs manual intervention",
+					withdrawal.info.txid
+				);
+			}
+		}
+	}
+}
+
+/// Create again the mints and burns rejected as the given transaction, as
+/// long as they have retries left
+fn retry_rejected_stacks_transactions(
+	deposits: &mut [Deposit],
+	withdrawals: &mut [Withdrawal],
+	txid: StacksTxId,
+	config: &Config,
+) -> Vec<Task> {
+	let is_rejected = |req: &Option<TransactionRequest<StacksTxId>>| {
+		matches!(
+			req,
+			Some(TransactionRequest::Acknowledged { txid: rejected_txid, .. })
+				if *rejected_txid == txid
+		)
+	};
+
+	let mut tasks = vec![];
+
+	for deposit in deposits
+		.iter_mut()
+		.filter(|deposit| is_rejected(&deposit.mint))
+	{
+		if deposit.mint_retries >= config.stacks_rejection_retries {
+			error!(
+				"Giving up on deposit {} after {} rejected mints",
+				deposit.info.txid,
+				deposit.mint_retries + 1
+			);
+			continue;
+		}
+
+		deposit.mint_retries += 1;
+		deposit.mint = Some(TransactionRequest::Created);
+		tasks.push(Task::CreateMint(deposit.info.clone()));
+	}
+
+	for withdrawal in withdrawals
+		.iter_mut()
+		.filter(|withdrawal| is_rejected(&withdrawal.burn))
+	{
+		if withdrawal.burn_retries >= config.stacks_rejection_retries {
+			error!(
+				"Giving up on withdrawal {} after {} rejected burns",
+				withdrawal.info.txid,
+				withdrawal.burn_retries + 1
+			);
+			continue;
+		}
+
+		withdrawal.burn_retries += 1;
+		withdrawal.burn = Some(TransactionRequest::Created);
+		tasks.push(Task::CreateBurn(withdrawal.info.clone()));
+	}
+
+	tasks
+}
+
 fn parse_deposits(
 	config: &Config,
 	bitcoin_height: u32,
@@ -773,22 +1657,32 @@ fn parse_deposits(
 			.filter(|parsed_deposit| {
 				parsed_deposit.sbtc_wallet_address == sbtc_wallet_address
 			})
-			.map(|parsed_deposit| {
+			.and_then(|parsed_deposit| {
 				let bytes = parsed_deposit.recipient.serialize_to_vec();
 				let recipient = PrincipalData::consensus_deserialize(
 					&mut Cursor::new(bytes),
 				)
-				.unwrap();
+				.map_err(|err| debug!("Ignoring deposit {}: {}", txid, err))
+				.ok()?;
 
-				Deposit {
-					info: DepositInfo {
-						txid,
-						amount: parsed_deposit.amount,
-						recipient,
-						block_height: bitcoin_height,
-					},
+				let memo =
+					Some(parsed_deposit.memo).filter(|memo| !memo.is_empty());
+
+				let info = DepositInfo::new(
+					txid,
+					parsed_deposit.amount,
+					recipient,
+					bitcoin_height,
+					memo,
+				)
+				.map_err(|err| debug!("Ignoring deposit {}: {}", txid, err))
+				.ok()?;
+
+				Some(Deposit {
+					info,
 					mint: None,
-				}
+					mint_retries: 0,
+				})
 			})
 		})
 		.collect()
@@ -796,9 +1690,17 @@ fn parse_deposits(
 
 fn parse_withdrawals(config: &Config, block: &Block) -> Vec<Withdrawal> {
 	let sbtc_wallet_address = config.sbtc_wallet_address();
-	let block_height = block
-		.bip34_block_height()
-		.expect("Failed to get block height") as u32;
+
+	// An empty block or a block without a valid coinbase cannot contain any
+	// withdrawals, and we can't determine its height anyway
+	let Ok(block_height) = block.bip34_block_height() else {
+		debug!(
+			"Ignoring withdrawals in block {} without a BIP34 block height",
+			block.block_hash()
+		);
+		return vec![];
+	};
+	let block_height = block_height as u32;
 
 	block
 		.txdata
@@ -815,7 +1717,7 @@ fn parse_withdrawals(config: &Config, block: &Block) -> Vec<Withdrawal> {
 			.filter(|parsed_withdrawal| {
 				parsed_withdrawal.sbtc_wallet == sbtc_wallet_address
 			})
-			.map(
+			.and_then(
 				|WithdrawalRequestData {
 				     payee_bitcoin_address,
 				     drawee_stacks_address,
@@ -829,17 +1731,25 @@ fn parse_withdrawals(config: &Config, block: &Block) -> Vec<Withdrawal> {
 						.unwrap();
 					let source = PrincipalData::from(blockstack_lib_address);
 
-					Withdrawal {
-						info: WithdrawalInfo {
-							txid,
-							amount,
-							source,
-							recipient: payee_bitcoin_address,
-							block_height,
-						},
+					let info = WithdrawalInfo::new(
+						txid,
+						amount,
+						source,
+						payee_bitcoin_address,
+						block_height,
+					)
+					.map_err(|err| {
+						debug!("Ignoring withdrawal request {}: {}", txid, err)
+					})
+					.ok()?;
+
+					Some(Withdrawal {
+						info,
 						burn: None,
 						fulfillment: None,
-					}
+						burn_retries: 0,
+						replaced_fulfillments: vec![],
+					})
 				},
 			)
 		})
@@ -847,7 +1757,7 @@ fn parse_withdrawals(config: &Config, block: &Block) -> Vec<Withdrawal> {
 }
 
 /// A transaction request
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum TransactionRequest<T> {
 	/// Scheduled to be created at a given stacks block height.
 	Scheduled {
@@ -864,14 +1774,19 @@ pub enum TransactionRequest<T> {
 		status: TransactionStatus,
 		/// Whether the task has a pending request
 		has_pending_task: bool,
+		/// Height of the chain the transaction was broadcasted to when it was
+		/// acknowledged
+		broadcasted_at: u32,
 	},
 }
 
 /// A parsed deposit
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Deposit {
 	info: DepositInfo,
 	mint: Option<TransactionRequest<StacksTxId>>,
+	#[serde(default)]
+	mint_retries: u32,
 }
 
 /// Relevant information for processing deposits
@@ -888,20 +1803,53 @@ pub struct DepositInfo {
 
 	/// Height of the Bitcoin blockchain where this deposit transaction exists
 	pub block_height: u32,
+
+	/// Memo attached to the deposit, if any
+	#[serde(default)]
+	pub memo: Option<Vec<u8>>,
+}
+
+impl DepositInfo {
+	/// Creates deposit information, rejecting amounts that don't fit in a u64
+	pub fn new(
+		txid: BitcoinTxId,
+		amount: impl Into<u128>,
+		recipient: PrincipalData,
+		block_height: u32,
+		memo: Option<Vec<u8>>,
+	) -> anyhow::Result<Self> {
+		Ok(Self {
+			txid,
+			amount: checked_amount(amount.into())?,
+			recipient,
+			block_height,
+			memo,
+		})
+	}
+
+	/// Amount to deposit as a Clarity uint
+	pub fn clarity_amount(&self) -> u128 {
+		u128::from(self.amount)
+	}
 }
 
 /// A parsed withdrawal
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Withdrawal {
 	info: WithdrawalInfo,
 	burn: Option<TransactionRequest<StacksTxId>>,
 	fulfillment: Option<TransactionRequest<BitcoinTxId>>,
+	#[serde(default)]
+	burn_retries: u32,
+	#[serde(default)]
+	replaced_fulfillments: Vec<BitcoinTxId>,
 }
 
 /// Relevant information for processing withdrawals
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 pub struct WithdrawalInfo {
-	/// ID of the bitcoin withdrawal request transaction
+	/// ID of the bitcoin withdrawal request transaction, or of the Stacks
+	/// one for requests submitted as contract calls
 	pub txid: BitcoinTxId,
 
 	/// Amount to withdraw
@@ -917,3 +1865,1417 @@ pub struct WithdrawalInfo {
 	/// transaction exists
 	pub block_height: u32,
 }
+
+impl WithdrawalInfo {
+	/// Creates withdrawal information, rejecting amounts that don't fit in a
+	/// u64
+	pub fn new(
+		txid: BitcoinTxId,
+		amount: impl Into<u128>,
+		source: PrincipalData,
+		recipient: BitcoinAddress,
+		block_height: u32,
+	) -> anyhow::Result<Self> {
+		Ok(Self {
+			txid,
+			amount: checked_amount(amount.into())?,
+			source,
+			recipient,
+			block_height,
+		})
+	}
+
+	/// Amount to withdraw as a Clarity uint
+	pub fn clarity_amount(&self) -> u128 {
+		u128::from(self.amount)
+	}
+}
+
+/// Converts an amount to satoshis, failing instead of truncating amounts that
+/// don't fit in a u64
+fn checked_amount(amount: u128) -> anyhow::Result<u64> {
+	u64::try_from(amount)
+		.map_err(|_| anyhow!("Amount {} does not fit in a u64", amount))
+}
+
+#[cfg(test)]
+mod tests {
+	use bdk::bitcoin::{
+		consensus::deserialize,
+		hashes::{hex::FromHex, Hash},
+		Network as BitcoinNetwork, Transaction,
+	};
+	use blockstack_lib::vm::{
+		types::{QualifiedContractIdentifier, StandardPrincipalData},
+		ContractName,
+	};
+	use rand::Rng;
+	use stacks_core::{
+		address::{AddressVersion, StacksAddress as CoreStacksAddress},
+		contract_name::{
+			ContractName as CoreContractName, CONTRACT_MAX_NAME_LENGTH,
+		},
+		utils::{
+			PrincipalData as CorePrincipalData,
+			StandardPrincipalData as CoreStandardPrincipalData,
+		},
+	};
+
+	use super::*;
+	use crate::{
+		config::StrictnessPolicy,
+		test_util::{test_config, testnet_block_100000},
+	};
+
+	fn deposit_tx() -> Transaction {
+		let tx_hex = "010000000001019131d69f4616c2a17f3d2519a3dc697136a56846794e677982f565f79295e0370100000000feffffff0300000000000000001b6a1954323c051af0bf935f1ba62167f89c1fff2d9369f972ad0f7e6e0a020000000000225120b85fdda4ae0f69883280360a9b91555a2f23c5b9e34173fabec5d903416c2aaf7b850800000000001600147c969cfcab0d2ad171aa3f201c94b51b0e8eca6602473044022036663b723c79333f9c8b7d5d9db3b6cd301fc6bf82515e62303713eb69b4d18d0220548939af6e1d86fcf8a54da1f6942f25f36ed0488a0d3616c47daa49f59bc7b601210215bd6d522931e602fde924571eb472bc1db953484b29ba6542774ebbf083412329c62500";
+
+		deserialize(&Vec::<u8>::from_hex(tx_hex).unwrap()).unwrap()
+	}
+
+	#[test]
+	fn parse_deposits_should_use_overridden_sbtc_wallet_address() {
+		let deposit_tx = deposit_tx();
+		let deposit_address = BitcoinAddress::from_script(
+			&deposit_tx.output[1].script_pubkey,
+			BitcoinNetwork::Testnet,
+		)
+		.unwrap();
+
+		let mut block = testnet_block_100000();
+		block.txdata.push(deposit_tx);
+
+		let mut config = test_config();
+		assert_ne!(config.sbtc_wallet_address(), deposit_address);
+		assert!(parse_deposits(&config, 100000, &block).is_empty());
+
+		config.sbtc_wallet_address_override = Some(deposit_address);
+
+		let deposits = parse_deposits(&config, 100000, &block);
+		assert_eq!(deposits.len(), 1);
+		assert_eq!(deposits[0].info.amount, 133646);
+	}
+
+	#[test]
+	fn deposit_memo_should_survive_event_log_serialization() {
+		// Same deposit as `deposit_tx`, with a "sbtc memo" memo
+		let tx_hex = "010000000001019131d69f4616c2a17f3d2519a3dc697136a56846794e677982f565f79295e0370100000000feffffff030000000000000000246a2254323c051af0bf935f1ba62167f89c1fff2d9369f972ad0f7e6e73627463206d656d6f0a020000000000225120b85fdda4ae0f69883280360a9b91555a2f23c5b9e34173fabec5d903416c2aaf7b850800000000001600147c969cfcab0d2ad171aa3f201c94b51b0e8eca6602473044022036663b723c79333f9c8b7d5d9db3b6cd301fc6bf82515e62303713eb69b4d18d0220548939af6e1d86fcf8a54da1f6942f25f36ed0488a0d3616c47daa49f59bc7b601210215bd6d522931e602fde924571eb472bc1db953484b29ba6542774ebbf083412329c62500";
+		let deposit_tx: Transaction =
+			deserialize(&Vec::<u8>::from_hex(tx_hex).unwrap()).unwrap();
+
+		let mut config = test_config();
+		config.sbtc_wallet_address_override = Some(
+			BitcoinAddress::from_script(
+				&deposit_tx.output[1].script_pubkey,
+				BitcoinNetwork::Testnet,
+			)
+			.unwrap(),
+		);
+
+		let mut block = testnet_block_100000();
+		block.txdata.push(deposit_tx);
+
+		let deposits = parse_deposits(&config, 100000, &block);
+		assert_eq!(deposits.len(), 1);
+
+		let info = deposits[0].info.clone();
+		assert_eq!(info.memo.as_deref(), Some(&b"sbtc memo"[..]));
+
+		// Events are logged to log.ndjson one JSON line at a time
+		let event = Event::MintBroadcasted(info, StacksTxId([1; 32]));
+		let line = serde_json::to_string(&event).unwrap();
+		assert!(!line.contains('\n'));
+		assert_eq!(serde_json::from_str::<Event>(&line).unwrap(), event);
+
+		// Logs written before memos were parsed still load
+		let mut legacy_line = serde_json::to_value(&event).unwrap();
+		legacy_line["MintBroadcasted"][0]
+			.as_object_mut()
+			.unwrap()
+			.remove("memo");
+		let Event::MintBroadcasted(legacy_info, _) =
+			serde_json::from_value(legacy_line).unwrap()
+		else {
+			panic!("Expected a mint broadcasted event");
+		};
+		assert_eq!(legacy_info.memo, None);
+	}
+
+	#[test]
+	fn stale_fulfillment_should_be_bumped_and_replaced() {
+		let config = test_config();
+		let txid = BitcoinTxId::from_inner([1; 32]);
+		let bumped_txid = BitcoinTxId::from_inner([2; 32]);
+		let info = WithdrawalInfo {
+			txid: testnet_block_100000().txdata[0].txid(),
+			amount: 1000,
+			source: PrincipalData::parse(
+				"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50",
+			)
+			.unwrap(),
+			recipient: BitcoinAddress::from_script(
+				&deposit_tx().output[2].script_pubkey,
+				BitcoinNetwork::Testnet,
+			)
+			.unwrap(),
+			block_height: 99990,
+		};
+
+		let mut state = State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 100000,
+			deposits: vec![],
+			withdrawals: vec![Withdrawal {
+				info: info.clone(),
+				burn: Some(TransactionRequest::Acknowledged {
+					txid: StacksTxId([3; 32]),
+					status: TransactionStatus::Confirmed,
+					has_pending_task: false,
+					broadcasted_at: 4,
+				}),
+				fulfillment: Some(TransactionRequest::Acknowledged {
+					txid,
+					status: TransactionStatus::Broadcasted,
+					has_pending_task: false,
+					broadcasted_at: 100000,
+				}),
+				burn_retries: 0,
+				replaced_fulfillments: vec![],
+			}],
+			bitcoin_block_hashes: Default::default(),
+		};
+
+		let bump_height = 100000 + config.bitcoin_fee_bump_blocks;
+
+		let tasks = state
+			.update(
+				Event::BitcoinBlock(bump_height - 1, testnet_block_100000()),
+				&config,
+			)
+			.unwrap();
+		assert!(!tasks
+			.iter()
+			.any(|task| matches!(task, Task::BumpFulfillment(..))));
+
+		// The status check scheduled above has to finish first
+		state
+			.update(
+				Event::BitcoinTransactionUpdate(
+					txid,
+					TransactionStatus::Broadcasted,
+				),
+				&config,
+			)
+			.unwrap();
+
+		let mut next_block = testnet_block_100000();
+		next_block.header.prev_blockhash = testnet_block_100000().block_hash();
+
+		let tasks = state
+			.update(Event::BitcoinBlock(bump_height, next_block), &config)
+			.unwrap();
+		assert!(tasks.iter().any(|task| matches!(
+			task,
+			Task::BumpFulfillment(bumped_info, bumped)
+				if *bumped == txid && *bumped_info == info
+		)));
+		assert!(!tasks.iter().any(|task| matches!(
+			task,
+			Task::CheckBitcoinTransactionStatus(_)
+		)));
+
+		// A failed bump keeps the original transaction around
+		state
+			.update(Event::FulfillmentBumpFailed(info.clone()), &config)
+			.unwrap();
+		let State::Initialized { withdrawals, .. } = &state else {
+			panic!("State should be initialized");
+		};
+		assert!(matches!(
+			withdrawals[0].fulfillment,
+			Some(TransactionRequest::Acknowledged {
+				txid: current_txid,
+				has_pending_task: false,
+				broadcasted_at: 100000,
+				..
+			}) if current_txid == txid
+		));
+
+		state
+			.update(Event::FulfillmentBumped(info, bumped_txid), &config)
+			.unwrap();
+
+		let State::Initialized { withdrawals, .. } = &state else {
+			panic!("State should be initialized");
+		};
+		assert!(matches!(
+			withdrawals[0].fulfillment,
+			Some(TransactionRequest::Acknowledged {
+				txid,
+				status: TransactionStatus::Broadcasted,
+				has_pending_task: false,
+				broadcasted_at,
+			}) if txid == bumped_txid && broadcasted_at == bump_height
+		));
+		assert_eq!(withdrawals[0].replaced_fulfillments, vec![txid]);
+	}
+
+	#[test]
+	fn confirmed_replaced_fulfillment_should_be_adopted() {
+		let config = test_config();
+		let txid = BitcoinTxId::from_inner([1; 32]);
+		let bumped_txid = BitcoinTxId::from_inner([2; 32]);
+		let info = WithdrawalInfo {
+			txid: testnet_block_100000().txdata[0].txid(),
+			amount: 1000,
+			source: PrincipalData::parse(
+				"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50",
+			)
+			.unwrap(),
+			recipient: BitcoinAddress::from_script(
+				&deposit_tx().output[2].script_pubkey,
+				BitcoinNetwork::Testnet,
+			)
+			.unwrap(),
+			block_height: 99990,
+		};
+
+		let mut state = State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 100000,
+			deposits: vec![],
+			withdrawals: vec![Withdrawal {
+				info,
+				burn: Some(TransactionRequest::Acknowledged {
+					txid: StacksTxId([3; 32]),
+					status: TransactionStatus::Confirmed,
+					has_pending_task: false,
+					broadcasted_at: 4,
+				}),
+				fulfillment: Some(TransactionRequest::Acknowledged {
+					txid: bumped_txid,
+					status: TransactionStatus::Broadcasted,
+					has_pending_task: false,
+					broadcasted_at: 100000,
+				}),
+				burn_retries: 0,
+				replaced_fulfillments: vec![txid],
+			}],
+			bitcoin_block_hashes: Default::default(),
+		};
+
+		assert_eq!(
+			state.get_bitcoin_status_checks(),
+			vec![
+				Task::CheckBitcoinTransactionStatus(bumped_txid),
+				Task::CheckBitcoinTransactionStatus(txid),
+			]
+		);
+
+		state
+			.update(
+				Event::BitcoinTransactionUpdate(
+					txid,
+					TransactionStatus::Confirmed,
+				),
+				&config,
+			)
+			.unwrap();
+
+		// The replacement conflicts with the confirmed transaction
+		state
+			.update(
+				Event::BitcoinTransactionUpdate(
+					bumped_txid,
+					TransactionStatus::Conflicted,
+				),
+				&config,
+			)
+			.unwrap();
+
+		let State::Initialized { withdrawals, .. } = &state else {
+			panic!("State should be initialized");
+		};
+		assert_eq!(
+			withdrawals[0].fulfillment,
+			Some(TransactionRequest::Acknowledged {
+				txid,
+				status: TransactionStatus::Confirmed,
+				has_pending_task: false,
+				broadcasted_at: 100000,
+			})
+		);
+		assert!(withdrawals[0].replaced_fulfillments.is_empty());
+	}
+
+	#[test]
+	fn conflicted_fulfillment_should_not_be_created_again() {
+		let config = test_config();
+		let txid = BitcoinTxId::from_inner([1; 32]);
+		let bumped_txid = BitcoinTxId::from_inner([2; 32]);
+		let info = WithdrawalInfo {
+			txid: testnet_block_100000().txdata[0].txid(),
+			amount: 1000,
+			source: PrincipalData::parse(
+				"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50",
+			)
+			.unwrap(),
+			recipient: BitcoinAddress::from_script(
+				&deposit_tx().output[2].script_pubkey,
+				BitcoinNetwork::Testnet,
+			)
+			.unwrap(),
+			block_height: 99990,
+		};
+
+		let mut state = State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 100000,
+			deposits: vec![],
+			withdrawals: vec![Withdrawal {
+				info,
+				burn: Some(TransactionRequest::Acknowledged {
+					txid: StacksTxId([3; 32]),
+					status: TransactionStatus::Confirmed,
+					has_pending_task: false,
+					broadcasted_at: 4,
+				}),
+				fulfillment: Some(TransactionRequest::Acknowledged {
+					txid: bumped_txid,
+					status: TransactionStatus::Broadcasted,
+					has_pending_task: true,
+					broadcasted_at: 100000,
+				}),
+				burn_retries: 0,
+				replaced_fulfillments: vec![txid],
+			}],
+			bitcoin_block_hashes: Default::default(),
+		};
+
+		let tasks = state
+			.update(
+				Event::BitcoinTransactionUpdate(
+					bumped_txid,
+					TransactionStatus::Conflicted,
+				),
+				&config,
+			)
+			.unwrap();
+
+		assert!(tasks.is_empty());
+		assert_eq!(
+			state.get_bitcoin_status_checks(),
+			vec![Task::CheckBitcoinTransactionStatus(txid)]
+		);
+
+		// The replaced transaction is still in the mempool
+		state
+			.update(
+				Event::BitcoinTransactionUpdate(
+					txid,
+					TransactionStatus::Broadcasted,
+				),
+				&config,
+			)
+			.unwrap();
+
+		let State::Initialized { withdrawals, .. } = &state else {
+			panic!("State should be initialized");
+		};
+		assert_eq!(
+			withdrawals[0].fulfillment,
+			Some(TransactionRequest::Acknowledged {
+				txid,
+				status: TransactionStatus::Broadcasted,
+				has_pending_task: false,
+				broadcasted_at: 100000,
+			})
+		);
+		assert!(withdrawals[0].replaced_fulfillments.is_empty());
+	}
+
+	#[test]
+	fn conflicted_fulfillment_without_replaced_ones_should_be_terminal() {
+		let config = test_config();
+		let txid = BitcoinTxId::from_inner([1; 32]);
+		let info = WithdrawalInfo {
+			txid: testnet_block_100000().txdata[0].txid(),
+			amount: 1000,
+			source: PrincipalData::parse(
+				"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50",
+			)
+			.unwrap(),
+			recipient: BitcoinAddress::from_script(
+				&deposit_tx().output[2].script_pubkey,
+				BitcoinNetwork::Testnet,
+			)
+			.unwrap(),
+			block_height: 99990,
+		};
+
+		let mut state = State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 100000,
+			deposits: vec![],
+			withdrawals: vec![Withdrawal {
+				info,
+				burn: Some(TransactionRequest::Acknowledged {
+					txid: StacksTxId([3; 32]),
+					status: TransactionStatus::Confirmed,
+					has_pending_task: false,
+					broadcasted_at: 4,
+				}),
+				fulfillment: Some(TransactionRequest::Acknowledged {
+					txid,
+					status: TransactionStatus::Broadcasted,
+					has_pending_task: true,
+					broadcasted_at: 100000,
+				}),
+				burn_retries: 0,
+				replaced_fulfillments: vec![],
+			}],
+			bitcoin_block_hashes: Default::default(),
+		};
+
+		let tasks = state
+			.update(
+				Event::BitcoinTransactionUpdate(
+					txid,
+					TransactionStatus::Conflicted,
+				),
+				&config,
+			)
+			.unwrap();
+
+		assert!(tasks.is_empty());
+		assert!(state.get_bitcoin_status_checks().is_empty());
+		assert!(state.get_bitcoin_transactions().is_empty());
+	}
+
+	#[test]
+	fn stale_mint_should_be_bumped_and_replaced() {
+		let config = test_config();
+		let txid = StacksTxId([1; 32]);
+		let bumped_txid = StacksTxId([2; 32]);
+
+		let mut state = State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 100000,
+			deposits: vec![Deposit {
+				info: DepositInfo {
+					txid: testnet_block_100000().txdata[0].txid(),
+					amount: 1000,
+					recipient: PrincipalData::parse(
+						"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50",
+					)
+					.unwrap(),
+					block_height: 100000,
+					memo: None,
+				},
+				mint: Some(TransactionRequest::Acknowledged {
+					txid,
+					status: TransactionStatus::Broadcasted,
+					has_pending_task: false,
+					broadcasted_at: 5,
+				}),
+				mint_retries: 0,
+			}],
+			withdrawals: vec![],
+			bitcoin_block_hashes: Default::default(),
+		};
+
+		let tasks = state
+			.update(
+				Event::StacksBlock(4 + config.stacks_fee_bump_blocks, vec![]),
+				&config,
+			)
+			.unwrap();
+		assert!(!tasks
+			.iter()
+			.any(|task| matches!(task, Task::BumpStacksTransaction(_))));
+
+		// The status check scheduled above has to finish first
+		state
+			.update(
+				Event::StacksTransactionUpdate(
+					txid,
+					TransactionStatus::Broadcasted,
+				),
+				&config,
+			)
+			.unwrap();
+
+		let tasks = state
+			.update(
+				Event::StacksBlock(5 + config.stacks_fee_bump_blocks, vec![]),
+				&config,
+			)
+			.unwrap();
+		assert!(tasks.iter().any(|task| matches!(
+			task,
+			Task::BumpStacksTransaction(bumped) if *bumped == txid
+		)));
+		assert!(!tasks
+			.iter()
+			.any(|task| matches!(task, Task::CheckStacksTransactionStatus(_))));
+
+		// A failed bump leaves the original transaction to be bumped again
+		state
+			.update(Event::StacksTransactionBumpFailed(txid), &config)
+			.unwrap();
+		let tasks = state
+			.update(
+				Event::StacksBlock(6 + config.stacks_fee_bump_blocks, vec![]),
+				&config,
+			)
+			.unwrap();
+		assert!(tasks.iter().any(|task| matches!(
+			task,
+			Task::BumpStacksTransaction(bumped) if *bumped == txid
+		)));
+
+		state
+			.update(Event::StacksTransactionBumped(txid, bumped_txid), &config)
+			.unwrap();
+
+		let State::Initialized { deposits, .. } = &state else {
+			panic!("State should be initialized");
+		};
+		assert!(matches!(
+			deposits[0].mint,
+			Some(TransactionRequest::Acknowledged {
+				txid,
+				status: TransactionStatus::Broadcasted,
+				has_pending_task: false,
+				broadcasted_at,
+			}) if txid == bumped_txid
+				&& broadcasted_at == 6 + config.stacks_fee_bump_blocks
+		));
+	}
+
+	#[test]
+	fn rejected_mint_should_be_created_again_until_retries_run_out() {
+		let config = Config {
+			stacks_rejection_retries: 1,
+			..test_config()
+		};
+		let deposit_info = DepositInfo {
+			txid: testnet_block_100000().txdata[0].txid(),
+			amount: 1000,
+			recipient: PrincipalData::parse(
+				"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50",
+			)
+			.unwrap(),
+			block_height: 100000,
+			memo: None,
+		};
+		let pending_mint = |txid| {
+			Some(TransactionRequest::Acknowledged {
+				txid,
+				status: TransactionStatus::Broadcasted,
+				has_pending_task: true,
+				broadcasted_at: 5,
+			})
+		};
+		let mut state = State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 100000,
+			deposits: vec![Deposit {
+				info: deposit_info.clone(),
+				mint: pending_mint(StacksTxId([1; 32])),
+				mint_retries: 0,
+			}],
+			withdrawals: vec![],
+			bitcoin_block_hashes: Default::default(),
+		};
+
+		let tasks = state
+			.update(
+				Event::StacksTransactionUpdate(
+					StacksTxId([1; 32]),
+					TransactionStatus::Rejected,
+				),
+				&config,
+			)
+			.unwrap();
+		assert_eq!(tasks, vec![Task::CreateMint(deposit_info.clone())]);
+
+		state
+			.update(
+				Event::MintBroadcasted(deposit_info, StacksTxId([2; 32])),
+				&config,
+			)
+			.unwrap();
+		let State::Initialized { deposits, .. } = &mut state else {
+			panic!("State should be initialized");
+		};
+		deposits[0].mint = pending_mint(StacksTxId([2; 32]));
+
+		let tasks = state
+			.update(
+				Event::StacksTransactionUpdate(
+					StacksTxId([2; 32]),
+					TransactionStatus::Rejected,
+				),
+				&config,
+			)
+			.unwrap();
+		assert!(tasks.is_empty());
+	}
+
+	#[test]
+	fn mints_should_be_created_in_txid_order() {
+		let config = test_config();
+
+		// Txids sharing all but their last byte
+		let txids: Vec<BitcoinTxId> = [3, 1, 2]
+			.into_iter()
+			.map(|last| {
+				let mut bytes = [0xab; 32];
+				bytes[31] = last;
+				BitcoinTxId::from_inner(bytes)
+			})
+			.collect();
+
+		let state_with_deposits = |txids: &[BitcoinTxId]| State::Initialized {
+			stacks_block_height: 1,
+			bitcoin_block_height: 99999,
+			deposits: txids
+				.iter()
+				.map(|txid| Deposit {
+					info: DepositInfo {
+						txid: *txid,
+						amount: 1000,
+						recipient: PrincipalData::parse(
+							"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50",
+						)
+						.unwrap(),
+						block_height: 99999,
+						memo: None,
+					},
+					mint: Some(TransactionRequest::Scheduled {
+						block_height: 1,
+					}),
+					mint_retries: 0,
+				})
+				.collect(),
+			withdrawals: vec![],
+			bitcoin_block_hashes: Default::default(),
+		};
+
+		let mint_txids = |mut state: State| -> Vec<BitcoinTxId> {
+			state
+				.update(
+					Event::BitcoinBlock(100000, testnet_block_100000()),
+					&config,
+				)
+				.unwrap()
+				.into_iter()
+				.filter_map(|task| match task {
+					Task::CreateMint(info) => Some(info.txid),
+					_ => None,
+				})
+				.collect()
+		};
+
+		let mut reversed = txids.clone();
+		reversed.reverse();
+
+		let mut expected = txids.clone();
+		expected.sort_by_key(|txid| txid.into_inner());
+
+		assert_eq!(mint_txids(state_with_deposits(&txids)), expected);
+		assert_eq!(mint_txids(state_with_deposits(&reversed)), expected);
+	}
+
+	fn minted_deposit_state(
+		mint: Option<TransactionRequest<StacksTxId>>,
+	) -> State {
+		let deposit_tx = deposit_tx();
+
+		State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 100000,
+			deposits: vec![Deposit {
+				info: DepositInfo {
+					txid: deposit_tx.txid(),
+					amount: 133646,
+					recipient: PrincipalData::parse(
+						"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50",
+					)
+					.unwrap(),
+					block_height: 100000,
+					memo: None,
+				},
+				mint,
+				mint_retries: 0,
+			}],
+			withdrawals: vec![],
+			bitcoin_block_hashes: Default::default(),
+		}
+	}
+
+	#[test]
+	fn reorged_out_minted_deposit_should_halt_when_strict() {
+		let mut state =
+			minted_deposit_state(Some(TransactionRequest::Acknowledged {
+				txid: StacksTxId([1; 32]),
+				status: TransactionStatus::Confirmed,
+				has_pending_task: false,
+				broadcasted_at: 5,
+			}));
+		let expected_state = state.clone();
+
+		// The block at the deposit height no longer contains the deposit
+		let res = state.update(
+			Event::BitcoinBlock(100000, testnet_block_100000()),
+			&test_config(),
+		);
+
+		assert!(matches!(
+			res,
+			Err(StateError::ReorgedDeposit(message))
+				if message.contains("is no longer in the Bitcoin chain")
+		));
+		assert_eq!(state, expected_state);
+	}
+
+	#[test]
+	fn reorged_out_minted_deposit_should_be_kept_when_lenient() {
+		let config = Config {
+			strictness: StrictnessPolicy::uniform(false),
+			..test_config()
+		};
+		let mut state =
+			minted_deposit_state(Some(TransactionRequest::Acknowledged {
+				txid: StacksTxId([1; 32]),
+				status: TransactionStatus::Confirmed,
+				has_pending_task: false,
+				broadcasted_at: 5,
+			}));
+
+		state
+			.update(
+				Event::BitcoinBlock(100000, testnet_block_100000()),
+				&config,
+			)
+			.unwrap();
+
+		assert_eq!(state.operations().len(), 1);
+	}
+
+	#[test]
+	fn reorged_out_unminted_deposit_should_be_dropped() {
+		for mint in [None, Some(TransactionRequest::Created)] {
+			let mut state = minted_deposit_state(mint);
+
+			let tasks = state
+				.update(
+					Event::BitcoinBlock(100000, testnet_block_100000()),
+					&test_config(),
+				)
+				.unwrap();
+
+			assert!(state.operations().is_empty());
+			assert!(!tasks
+				.iter()
+				.any(|task| matches!(task, Task::CreateMint(_))));
+		}
+	}
+
+	#[test]
+	fn deposit_still_in_chain_should_not_be_flagged() {
+		let mut block = testnet_block_100000();
+		block.txdata.push(deposit_tx());
+		let mut state =
+			minted_deposit_state(Some(TransactionRequest::Acknowledged {
+				txid: StacksTxId([1; 32]),
+				status: TransactionStatus::Confirmed,
+				has_pending_task: false,
+				broadcasted_at: 5,
+			}));
+
+		state
+			.update(Event::BitcoinBlock(100000, block), &test_config())
+			.unwrap();
+
+		assert_eq!(state.operations().len(), 1);
+	}
+
+	#[test]
+	fn one_block_reorg_should_drop_orphaned_deposit() {
+		let deposit_tx = deposit_tx();
+		let config = Config {
+			sbtc_wallet_address_override: Some(
+				BitcoinAddress::from_script(
+					&deposit_tx.output[1].script_pubkey,
+					BitcoinNetwork::Testnet,
+				)
+				.unwrap(),
+			),
+			..test_config()
+		};
+		let deposits = |state: &State| {
+			let State::Initialized { deposits, .. } = state else {
+				panic!("State is not initialized");
+			};
+			deposits.len()
+		};
+
+		let mut orphaned_block = testnet_block_100000();
+		orphaned_block.txdata.push(deposit_tx);
+		let mut state = State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 99999,
+			deposits: vec![],
+			withdrawals: vec![],
+			bitcoin_block_hashes: BTreeMap::from([(
+				99999,
+				orphaned_block.header.prev_blockhash,
+			)]),
+		};
+
+		state
+			.update(Event::BitcoinBlock(100000, orphaned_block), &config)
+			.unwrap();
+		assert_eq!(deposits(&state), 1);
+
+		// A sibling block without the deposit replaced the one processed
+		let mut replacing_block = testnet_block_100000();
+		replacing_block.header.nonce += 1;
+		let mut next_block = testnet_block_100000();
+		next_block.header.prev_blockhash = replacing_block.block_hash();
+
+		let tasks = state
+			.update(Event::BitcoinBlock(100001, next_block), &config)
+			.unwrap();
+		assert_eq!(tasks, vec![Task::FetchBitcoinBlock(100000)]);
+		assert_eq!(deposits(&state), 1);
+
+		let tasks = state
+			.update(Event::BitcoinBlock(100000, replacing_block), &config)
+			.unwrap();
+		assert!(tasks.contains(&Task::FetchBitcoinBlock(100001)));
+		assert_eq!(deposits(&state), 0);
+	}
+
+	#[test]
+	fn mints_should_be_scheduled_after_the_configured_delay() {
+		let deposit_tx = deposit_tx();
+		let mut block = testnet_block_100000();
+		block.txdata.push(deposit_tx.clone());
+
+		for delay in [0, 3] {
+			let config = Config {
+				sbtc_wallet_address_override: Some(
+					BitcoinAddress::from_script(
+						&deposit_tx.output[1].script_pubkey,
+						BitcoinNetwork::Testnet,
+					)
+					.unwrap(),
+				),
+				stx_transaction_delay_blocks: delay,
+				..test_config()
+			};
+			let mut state = State::Initialized {
+				stacks_block_height: 5,
+				bitcoin_block_height: 99999,
+				deposits: vec![],
+				withdrawals: vec![],
+				bitcoin_block_hashes: Default::default(),
+			};
+
+			state
+				.update(Event::BitcoinBlock(100000, block.clone()), &config)
+				.unwrap();
+
+			let State::Initialized { deposits, .. } = &state else {
+				panic!("State should be initialized");
+			};
+			assert_eq!(
+				deposits[0].mint,
+				Some(TransactionRequest::Scheduled {
+					block_height: 5 + delay
+				})
+			);
+		}
+	}
+
+	#[test]
+	fn out_of_order_event_should_be_an_error() {
+		let mut state = State::new();
+
+		let res = state.update(
+			Event::BitcoinTransactionUpdate(
+				BitcoinTxId::all_zeros(),
+				TransactionStatus::Confirmed,
+			),
+			&mixed_policy_config(),
+		);
+
+		assert!(matches!(res, Err(StateError::UnexpectedEvent(_))));
+		assert_eq!(state, State::new());
+	}
+
+	#[test]
+	fn failed_update_should_leave_the_state_untouched() {
+		let mut state = minted_deposit_state(None);
+		let expected_state = state.clone();
+
+		let res = state.update(
+			Event::StacksTransactionBumped(
+				StacksTxId([1; 32]),
+				StacksTxId([2; 32]),
+			),
+			&test_config(),
+		);
+
+		assert!(matches!(res, Err(StateError::UnknownOperation(_))));
+		assert_eq!(state, expected_state);
+	}
+
+	#[test]
+	fn failed_block_batch_should_leave_the_state_untouched() {
+		let mut state =
+			minted_deposit_state(Some(TransactionRequest::Acknowledged {
+				txid: StacksTxId([1; 32]),
+				status: TransactionStatus::Confirmed,
+				has_pending_task: false,
+				broadcasted_at: 5,
+			}));
+		let expected_state = state.clone();
+
+		let mut parent = testnet_block_100000();
+		parent.header.time -= 600;
+		// The deposit is reorged out of the second block only
+		let mut block = testnet_block_100000();
+		block.header.prev_blockhash = parent.block_hash();
+
+		let res = state.update(
+			Event::BitcoinBlocks(vec![(99999, parent), (100000, block)]),
+			&test_config(),
+		);
+
+		assert!(matches!(res, Err(StateError::ReorgedDeposit(_))));
+		assert_eq!(state, expected_state);
+	}
+
+	fn mixed_policy_config() -> Config {
+		Config {
+			strictness: StrictnessPolicy {
+				double_broadcast: true,
+				unexpected_status: false,
+				unknown_transaction: true,
+				broadcast_failure: true,
+				reorged_deposit: true,
+				invalid_event: false,
+			},
+			..test_config()
+		}
+	}
+
+	fn public_key_setup_state(txid: StacksTxId) -> State {
+		State::ContractPublicKeySetup {
+			stacks_block_height: 1,
+			bitcoin_block_height: 100,
+			public_key_setup: TransactionRequest::Acknowledged {
+				txid,
+				status: TransactionStatus::Broadcasted,
+				has_pending_task: false,
+				broadcasted_at: 1,
+			},
+		}
+	}
+
+	#[test]
+	fn mixed_policy_should_ignore_unexpected_status_update() {
+		let txid = StacksTxId([1; 32]);
+		let mut state = public_key_setup_state(txid);
+
+		let tasks = state
+			.update(
+				Event::StacksTransactionUpdate(
+					txid,
+					TransactionStatus::Confirmed,
+				),
+				&mixed_policy_config(),
+			)
+			.unwrap();
+
+		assert!(tasks.is_empty());
+		assert!(matches!(state, State::ContractPublicKeySetup { .. }));
+	}
+
+	#[test]
+	fn mixed_policy_should_reject_unknown_transaction() {
+		let mut state = public_key_setup_state(StacksTxId([1; 32]));
+
+		let res = state.update(
+			Event::StacksTransactionUpdate(
+				StacksTxId([2; 32]),
+				TransactionStatus::Confirmed,
+			),
+			&mixed_policy_config(),
+		);
+
+		assert!(matches!(
+			res,
+			Err(StateError::UnknownTransaction(message))
+				if message.contains("not public key set")
+		));
+	}
+
+	#[test]
+	fn mixed_policy_should_reject_double_broadcast() {
+		let deposit_info = DepositInfo {
+			txid: testnet_block_100000().txdata[0].txid(),
+			amount: 1000,
+			recipient: PrincipalData::parse(
+				"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50",
+			)
+			.unwrap(),
+			block_height: 100000,
+			memo: None,
+		};
+
+		let mut state = State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 100000,
+			deposits: vec![Deposit {
+				info: deposit_info.clone(),
+				mint: Some(TransactionRequest::Acknowledged {
+					txid: StacksTxId([1; 32]),
+					status: TransactionStatus::Broadcasted,
+					has_pending_task: false,
+					broadcasted_at: 5,
+				}),
+				mint_retries: 0,
+			}],
+			withdrawals: vec![],
+			bitcoin_block_hashes: Default::default(),
+		};
+
+		let res = state.update(
+			Event::MintBroadcasted(deposit_info, StacksTxId([2; 32])),
+			&mixed_policy_config(),
+		);
+
+		assert!(matches!(res, Err(StateError::DoubleBroadcast(_))));
+	}
+
+	#[test]
+	fn dry_update_should_match_update_without_mutating() {
+		let config = Config {
+			sbtc_wallet_address_override: Some(
+				BitcoinAddress::from_script(
+					&deposit_tx().output[1].script_pubkey,
+					BitcoinNetwork::Testnet,
+				)
+				.unwrap(),
+			),
+			..test_config()
+		};
+
+		let mut block = testnet_block_100000();
+		block.txdata.push(deposit_tx());
+		let event = Event::BitcoinBlock(100001, block);
+
+		let mut state = State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 100000,
+			deposits: vec![],
+			withdrawals: vec![],
+			bitcoin_block_hashes: Default::default(),
+		};
+		let original = state.clone();
+
+		let (dry_state, dry_tasks) =
+			state.dry_update(event.clone(), &config).unwrap();
+		assert_eq!(state, original);
+
+		let tasks = state.update(event, &config).unwrap();
+		assert_eq!(dry_state, state);
+		assert_eq!(dry_tasks, tasks);
+		assert_ne!(state, original);
+	}
+
+	#[test]
+	fn bootstrap_should_rewind_by_rescan_depth() {
+		let config = Config {
+			rescan_depth: 5,
+			..test_config()
+		};
+
+		let mut state = State::Initialized {
+			stacks_block_height: 50,
+			bitcoin_block_height: 100,
+			deposits: vec![],
+			withdrawals: vec![],
+			bitcoin_block_hashes: Default::default(),
+		};
+
+		// The last 5 processed blocks are fetched again
+		assert_eq!(
+			state.bootstrap(&config),
+			vec![Task::FetchStacksBlock(46), Task::FetchBitcoinBlock(96)]
+		);
+	}
+
+	#[test]
+	fn rescanned_bitcoin_block_should_not_duplicate_deposits() {
+		let config = Config {
+			sbtc_wallet_address_override: Some(
+				BitcoinAddress::from_script(
+					&deposit_tx().output[1].script_pubkey,
+					BitcoinNetwork::Testnet,
+				)
+				.unwrap(),
+			),
+			..test_config()
+		};
+
+		let mut block = testnet_block_100000();
+		block.txdata.push(deposit_tx());
+
+		let mut state = State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 100000,
+			deposits: vec![],
+			withdrawals: vec![],
+			bitcoin_block_hashes: Default::default(),
+		};
+
+		state
+			.update(Event::BitcoinBlock(100001, block.clone()), &config)
+			.unwrap();
+		state
+			.update(Event::BitcoinBlock(100001, block), &config)
+			.unwrap();
+
+		assert_eq!(state.operations().len(), 1);
+	}
+
+	#[test]
+	fn parse_withdrawals_should_ignore_empty_block() {
+		let mut block = testnet_block_100000();
+		block.txdata.clear();
+
+		assert!(parse_withdrawals(&test_config(), &block).is_empty());
+	}
+
+	#[test]
+	fn parse_withdrawals_should_ignore_block_without_withdrawals() {
+		assert!(parse_withdrawals(&test_config(), &testnet_block_100000())
+			.is_empty());
+	}
+
+	#[test]
+	fn amounts_should_fit_in_u64_and_clarity_uint() {
+		let txid = BitcoinTxId::all_zeros();
+		let principal =
+			PrincipalData::parse("ST000000000000000000002AMW42H").unwrap();
+		let recipient = BitcoinAddress::from_script(
+			&deposit_tx().output[2].script_pubkey,
+			BitcoinNetwork::Testnet,
+		)
+		.unwrap();
+
+		let deposit =
+			DepositInfo::new(txid, u64::MAX, principal.clone(), 1, None)
+				.unwrap();
+		assert_eq!(deposit.amount, u64::MAX);
+		assert_eq!(deposit.clarity_amount(), u64::MAX as u128);
+
+		let withdrawal = WithdrawalInfo::new(
+			txid,
+			u64::MAX as u128,
+			principal.clone(),
+			recipient.clone(),
+			1,
+		)
+		.unwrap();
+		assert_eq!(withdrawal.amount, u64::MAX);
+		assert_eq!(withdrawal.clarity_amount(), u64::MAX as u128);
+
+		let too_large = u64::MAX as u128 + 1;
+		assert!(
+			DepositInfo::new(txid, too_large, principal.clone(), 1, None)
+				.is_err()
+		);
+		assert!(
+			WithdrawalInfo::new(txid, too_large, principal, recipient, 1)
+				.is_err()
+		);
+	}
+
+	#[test]
+	fn bitcoin_block_batch_should_fetch_the_block_after_the_last() {
+		let mut state = State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 99999,
+			deposits: vec![],
+			withdrawals: vec![],
+			bitcoin_block_hashes: Default::default(),
+		};
+		let mut next_block = testnet_block_100000();
+		next_block.header.prev_blockhash = testnet_block_100000().block_hash();
+
+		let tasks = state
+			.update(
+				Event::BitcoinBlocks(vec![
+					(100000, testnet_block_100000()),
+					(100001, next_block),
+				]),
+				&test_config(),
+			)
+			.unwrap();
+
+		assert_eq!(
+			tasks,
+			vec![
+				Task::FetchBitcoinBlock(100002),
+				Task::RefreshSbtcWalletUtxos
+			]
+		);
+		assert!(matches!(
+			state,
+			State::Initialized {
+				bitcoin_block_height: 100001,
+				..
+			}
+		));
+	}
+
+	#[test]
+	fn bitcoin_block_batch_should_stop_at_a_reorg() {
+		let mut state = State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 99999,
+			deposits: vec![],
+			withdrawals: vec![],
+			bitcoin_block_hashes: Default::default(),
+		};
+
+		// The second block doesn't build on the first one
+		let tasks = state
+			.update(
+				Event::BitcoinBlocks(vec![
+					(100000, testnet_block_100000()),
+					(100001, testnet_block_100000()),
+					(100002, testnet_block_100000()),
+				]),
+				&test_config(),
+			)
+			.unwrap();
+
+		assert_eq!(tasks, vec![Task::FetchBitcoinBlock(100000)]);
+		assert!(matches!(
+			state,
+			State::Initialized {
+				bitcoin_block_height: 99999,
+				..
+			}
+		));
+	}
+
+	#[test]
+	fn bitcoin_block_should_refresh_sbtc_wallet_utxos() {
+		let config = test_config();
+		let mut state = State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 99999,
+			deposits: vec![],
+			withdrawals: vec![],
+			bitcoin_block_hashes: Default::default(),
+		};
+
+		let mut next_block = testnet_block_100000();
+		next_block.header.prev_blockhash = testnet_block_100000().block_hash();
+
+		for (height, block) in
+			[(100000, testnet_block_100000()), (100001, next_block)]
+		{
+			let tasks = state
+				.update(Event::BitcoinBlock(height, block), &config)
+				.unwrap();
+
+			assert_eq!(
+				tasks
+					.iter()
+					.filter(|task| **task == Task::RefreshSbtcWalletUtxos)
+					.count(),
+				1
+			);
+		}
+
+		assert!(state
+			.update(Event::SbtcWalletUtxosRefreshed, &config)
+			.unwrap()
+			.is_empty());
+	}
+
+	#[test]
+	fn principal_serialization_should_match_clarity() {
+		const VERSIONS: [AddressVersion; 4] = [
+			AddressVersion::MainnetSingleSig,
+			AddressVersion::MainnetMultiSig,
+			AddressVersion::TestnetSingleSig,
+			AddressVersion::TestnetMultiSig,
+		];
+		const NAME_CHARS: &[u8] =
+			b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789-_";
+
+		let mut rng = rand::thread_rng();
+
+		for _ in 0..1000 {
+			let version = VERSIONS[rng.gen_range(0..VERSIONS.len())];
+			let hash: [u8; 20] = rng.gen();
+
+			// Contract names start with a letter
+			let name_length = rng.gen_range(1..=CONTRACT_MAX_NAME_LENGTH);
+			let name: String =
+				iter::once(NAME_CHARS[rng.gen_range(0..52)])
+					.chain((1..name_length).map(|_| {
+						NAME_CHARS[rng.gen_range(0..NAME_CHARS.len())]
+					}))
+					.map(char::from)
+					.collect();
+
+			let address = CoreStacksAddress::new(version, hash.into());
+			let standard = StandardPrincipalData(version as u8, hash);
+
+			for (principal, expected) in [
+				(
+					CorePrincipalData::from(address.clone()),
+					PrincipalData::Standard(standard.clone()),
+				),
+				(
+					CorePrincipalData::Contract(
+						CoreStandardPrincipalData::from(address),
+						CoreContractName::new(&name).unwrap(),
+					),
+					PrincipalData::Contract(QualifiedContractIdentifier::new(
+						standard,
+						ContractName::try_from(name).unwrap(),
+					)),
+				),
+			] {
+				let bytes = principal.serialize_to_vec();
+
+				assert_eq!(bytes, expected.serialize_to_vec(), "{}", expected);
+				assert_eq!(
+					PrincipalData::consensus_deserialize(&mut Cursor::new(
+						&bytes
+					))
+					.unwrap(),
+					expected
+				);
+				assert_eq!(
+					CorePrincipalData::deserialize(&mut bytes.as_slice())
+						.unwrap(),
+					principal
+				);
+			}
+		}
+	}
+}
@@ -1,8 +1,19 @@
 //! System
 
-use std::{fs::create_dir_all, io::Cursor};
+use std::{
+	fs::{self as std_fs, create_dir_all},
+	future::Future,
+	io::{BufRead, BufReader as StdBufReader, Cursor},
+	path::Path,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
 
-use bdk::bitcoin::Txid as BitcoinTxId;
+use anyhow::anyhow;
+use bdk::{
+	bitcoin::{Block, Txid as BitcoinTxId},
+	FeeRate,
+};
 use blockstack_lib::{
 	burnchains::Txid as StacksTxId,
 	chainstate::stacks::{
@@ -13,22 +24,28 @@ use blockstack_lib::{
 	types::chainstate::{StacksAddress, StacksPublicKey},
 	vm::{types::Value, ClarityName},
 };
+use futures::FutureExt;
 use sbtc_core::operations::op_return::withdrawal_fulfillment::create_outputs;
 use stacks_core::{codec::Codec, BlockId, Network as StacksNetwork};
 use tokio::{
-	fs::{File, OpenOptions},
-	io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter},
-	sync::mpsc,
-	task::JoinHandle,
+	fs::{rename, File, OpenOptions},
+	io::{
+		stdout, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+		BufWriter,
+	},
+	sync::{mpsc, Semaphore},
+	task::{AbortHandle, JoinSet},
+	time::sleep,
 };
-use tracing::{debug, info, trace};
+use tracing::{debug, error, info, trace, warn};
 
 use crate::{
-	bitcoin_client::Client as BitcoinClient,
-	config::Config,
+	bitcoin_client::{BitcoinApi, Client as BitcoinClient},
+	config::{Config, FlushStrategy},
 	event::Event,
+	metrics::{serve as serve_metrics, Metrics},
 	proof_data::{ProofData, ProofDataClarityValues},
-	stacks_client::{LockedClient, StacksClient},
+	stacks_client::{ChainIdMismatch, LockedClient, StacksApi, StacksClient},
 	state,
 	state::{DepositInfo, WithdrawalInfo},
 	task::Task,
@@ -39,18 +56,91 @@ const DUMMY_STACKS_ID: StacksTxId = StacksTxId([
 	0, 0, 0, 0, 0, 0, 0,
 ]);
 
-/// The main run loop of this system.
-/// This function feeds all events to the `state::update` function and spawns
-/// all tasks returned from this function.
-///
-/// The system is bootstrapped by emitting the CreateAssetContract task.
+const CONTRACT_CONFIRMATION_POLLING_INTERVAL: Duration = Duration::from_secs(5);
+
+const CONTRACT_PAUSE_POLLING_INTERVAL: Duration = Duration::from_secs(5);
+
+const STACKS_NODE_LAG_POLLING_INTERVAL: Duration = Duration::from_secs(5);
+
+const BITCOIN_BLOCK_BATCH_SIZE: u32 = 16;
+
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(60);
+
+tokio::task_local! {
+	static BROADCAST_STAGE: Arc<Mutex<BroadcastStage>>;
+}
+
+/// Runs the system against the Bitcoin and Stacks nodes in the config until
+/// Ctrl-C is pressed. See [`run_with_clients`].
 pub async fn run(config: Config) {
-	let (tx, mut rx) = mpsc::channel::<Event>(128); // TODO: Make capacity configurable
 	let bitcoin_client = BitcoinClient::new(config.clone())
 		.expect("Failed to instantiate bitcoin client");
 	let stacks_client: LockedClient =
 		StacksClient::new(config.clone(), reqwest::Client::new()).into();
 
+	let shutdown = async {
+		if let Err(err) = tokio::signal::ctrl_c().await {
+			error!("Could not listen for Ctrl-C: {}", err);
+			std::future::pending::<()>().await;
+		}
+
+		info!("Shutting down, press Ctrl-C again to exit right away");
+
+		tokio::spawn(async {
+			if tokio::signal::ctrl_c().await.is_ok() {
+				warn!("Exiting without waiting for broadcasts");
+				std::process::exit(130);
+			}
+		});
+	};
+
+	run_with_clients(config, bitcoin_client, stacks_client, shutdown).await
+}
+
+/// The main run loop of this system.
+/// This function feeds all events to the `state::update` function and spawns
+/// all tasks returned from this function, performing them with the given
+/// clients.
+///
+/// The system is bootstrapped by emitting the CreateAssetContract task.
+///
+/// Once `shutdown` completes, no new task is spawned. Tasks that started
+/// broadcasting transactions are awaited for up to a minute and their events
+/// recorded, other tasks are aborted as they are spawned again on the next
+/// start. The function returns once the event log is flushed.
+pub async fn run_with_clients<B: BitcoinApi, S: StacksApi>(
+	config: Config,
+	bitcoin_client: B,
+	stacks_client: S,
+	shutdown: impl Future<Output = ()>,
+) {
+	let (tx, mut rx) = mpsc::channel::<Event>(128); // TODO: Make capacity configurable
+
+	// Halts right away if the node serves another network
+	let chain_id = retry_read(&config, || stacks_client.chain_id())
+		.await
+		.expect("Could not determine the Stacks chain ID");
+	info!("Using Stacks chain ID {:#x}", chain_id);
+
+	match stacks_client.get_contract_deployment().await {
+		Ok(deployment) => {
+			info!("Asset contract deployment: {:?}", deployment);
+
+			if let Some(clarity_version) = deployment
+				.clarity_version
+				.filter(|version| *version != config.clarity_version)
+			{
+				warn!(
+					"The asset contract uses {:?} instead of the configured {:?}",
+					clarity_version, config.clarity_version
+				);
+			}
+		}
+		Err(err) => {
+			warn!("Could not fetch the asset contract deployment: {}", err)
+		}
+	}
+
 	info!("Starting replay of persisted events");
 
 	let (mut storage, mut state) =
@@ -58,11 +148,26 @@ pub async fn run(config: Config) {
 
 	info!("Replay finished with state: {:?}", state);
 
-	let bootstrap_tasks = state.bootstrap();
+	let metrics = Arc::new(Metrics::default());
+	metrics.record_state(&state);
+
+	if let Some(addr) = config.metrics_bind_addr {
+		let metrics = metrics.clone();
+
+		tokio::spawn(async move {
+			if let Err(err) = serve_metrics(addr, metrics).await {
+				error!("Metrics server failed: {}", err);
+			}
+		});
+	}
+
+	let bootstrap_tasks = state.bootstrap(&config);
+	metrics.record_tasks_spawned(bootstrap_tasks.len());
+	let mut running = RunningTasks::new(config.max_concurrent_broadcasts);
 
 	// Bootstrap
 	for task in bootstrap_tasks {
-		spawn(
+		running.spawn(
 			config.clone(),
 			bitcoin_client.clone(),
 			stacks_client.clone(),
@@ -71,14 +176,23 @@ pub async fn run(config: Config) {
 		);
 	}
 
-	while let Some(event) = rx.recv().await {
-		storage.record(&event).await;
+	tokio::pin!(shutdown);
+
+	loop {
+		let event = tokio::select! {
+			_ = &mut shutdown => break,
+			event = rx.recv() => event.expect("Event channel closed"),
+		};
 
-		let tasks = state.update(event, &config);
-		trace!("State: {}", serde_json::to_string(&state).unwrap());
+		let tasks =
+			process_event(&mut storage, &mut state, event, &config, &metrics)
+				.await;
+
+		running.reap();
+		metrics.record_tasks_spawned(tasks.len());
 
 		for task in tasks {
-			spawn(
+			running.spawn(
 				config.clone(),
 				bitcoin_client.clone(),
 				stacks_client.clone(),
@@ -87,66 +201,409 @@ pub async fn run(config: Config) {
 			);
 		}
 	}
+
+	running.reads.shutdown().await;
+	running.abort_unstarted_broadcasts();
+
+	let drain_timeout = sleep(SHUTDOWN_DRAIN_TIMEOUT);
+	tokio::pin!(drain_timeout);
+
+	loop {
+		tokio::select! {
+			Some(event) = rx.recv() => {
+				process_event(
+					&mut storage,
+					&mut state,
+					event,
+					&config,
+					&metrics,
+				)
+				.await;
+			}
+			res = running.broadcasts.join_next() => {
+				if res.is_none() {
+					break;
+				}
+			}
+			_ = &mut drain_timeout => {
+				warn!(
+					"Broadcasts still running after {:?}, aborting them",
+					SHUTDOWN_DRAIN_TIMEOUT
+				);
+				running.broadcasts.shutdown().await;
+				break;
+			}
+		}
+	}
+
+	while let Ok(event) = rx.try_recv() {
+		process_event(&mut storage, &mut state, event, &config, &metrics).await;
+	}
+
+	storage.flush().await;
+
+	info!("Shutdown complete");
+}
+
+/// Record an event and apply it to the state, returning the tasks to spawn
+async fn process_event(
+	storage: &mut Storage,
+	state: &mut state::State,
+	event: Event,
+	config: &Config,
+	metrics: &Metrics,
+) -> Vec<Task> {
+	storage.record(&event).await;
+	metrics.record_event(&event);
+
+	let tasks = apply_event(state, event, config);
+	metrics.record_state(state);
+	trace!("State: {}", serde_json::to_string(&state).unwrap());
+
+	tasks
+}
+
+/// Replay the persisted events into a fresh state, without opening the event
+/// log for writing
+pub fn replay(config: &Config) -> anyhow::Result<state::State> {
+	let file = std_fs::File::open(config.state_directory.join("log.ndjson"))?;
+	let mut state = state::State::new();
+
+	for line in StdBufReader::new(file).lines() {
+		replay_log_line(&mut state, &line?, config)?;
+	}
+
+	Ok(state)
+}
+
+/// A line of the event log
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum LogEntry {
+	/// State resulting from the events preceding it, written when compacting
+	Snapshot { snapshot: state::State },
+	/// Recorded event
+	Event(Event),
+}
+
+/// Apply a line of the event log to the state. Snapshots replace the state
+/// altogether, so replay effectively starts from the latest one.
+fn replay_log_line(
+	state: &mut state::State,
+	line: &str,
+	config: &Config,
+) -> anyhow::Result<()> {
+	match serde_json::from_str(line)? {
+		LogEntry::Snapshot { snapshot } => *state = snapshot,
+		LogEntry::Event(event) => {
+			apply_event(state, event, config);
+		}
+	}
+
+	Ok(())
+}
+
+/// Apply an event to the state, returning the tasks to spawn. Events the state
+/// cannot account for panic when strict, and are skipped otherwise. Events
+/// rejected by a strict policy always panic.
+fn apply_event(
+	state: &mut state::State,
+	event: Event,
+	config: &Config,
+) -> Vec<Task> {
+	match state.update(event, config) {
+		Ok(tasks) => tasks,
+		Err(err) if err.is_strict() || config.strictness.invalid_event => {
+			panic!("Could not apply event: {}", err)
+		}
+		Err(err) => {
+			error!("Skipping event that could not be applied: {}", err);
+			vec![]
+		}
+	}
 }
 
-struct Storage(BufWriter<File>);
+struct Storage {
+	writer: BufWriter<File>,
+	flush_strategy: FlushStrategy,
+	pending_events: usize,
+	last_flush: Instant,
+	event_stream: Option<Box<dyn AsyncWrite + Send + Unpin>>,
+}
 
 impl Storage {
+	fn new(file: File, flush_strategy: FlushStrategy) -> Self {
+		Self {
+			writer: BufWriter::new(file),
+			flush_strategy,
+			pending_events: 0,
+			last_flush: Instant::now(),
+			event_stream: None,
+		}
+	}
+
+	/// Mirror every recorded event to the stream as a JSON line
+	fn with_event_stream(
+		mut self,
+		stream: impl AsyncWrite + Send + Unpin + 'static,
+	) -> Self {
+		self.event_stream = Some(Box::new(stream));
+		self
+	}
+
 	async fn load_and_replay(
 		config: &Config,
 		mut state: state::State,
 	) -> (Self, state::State) {
 		create_dir_all(&config.state_directory).unwrap();
 
+		let path = config.state_directory.join("log.ndjson");
 		let mut file = OpenOptions::new()
 			.create(true)
 			.read(true)
 			.write(true)
 			.append(true)
-			.open(config.state_directory.join("log.ndjson"))
+			.open(&path)
 			.await
 			.unwrap();
 
 		let mut r = BufReader::new(&mut file).lines();
 
 		while let Some(line) = r.next_line().await.unwrap() {
-			let event: Event = serde_json::from_str(&line).unwrap();
+			replay_log_line(&mut state, &line, config).unwrap();
+		}
 
-			state.update(event, config);
+		let mut storage = Self::new(file, config.event_flush_strategy);
+
+		if config.compact_event_log {
+			storage.compact(&path, &state).await;
 		}
 
-		(Self(BufWriter::new(file)), state)
+		if config.event_stream_stdout {
+			(storage.with_event_stream(stdout()), state)
+		} else {
+			(storage, state)
+		}
 	}
 
 	async fn record(&mut self, event: &Event) {
+		// Refreshes happen every block and carry no state to replay
+		if matches!(event, Event::SbtcWalletUtxosRefreshed) {
+			return;
+		}
+
 		let bytes = serde_json::to_vec(event).unwrap();
-		self.0.write_all(&bytes).await.unwrap();
-		self.0.write_all(b"\n").await.unwrap();
-		self.0.flush().await.unwrap();
+		self.writer.write_all(&bytes).await.unwrap();
+		self.writer.write_all(b"\n").await.unwrap();
+
+		if let Some(stream) = self.event_stream.as_mut() {
+			// The stream only mirrors the log, so failing to write to it
+			// must not stop the system
+			let line = [bytes.as_slice(), b"\n"].concat();
+
+			if let Err(err) = async {
+				stream.write_all(&line).await?;
+				stream.flush().await
+			}
+			.await
+			{
+				warn!("Could not write event to the event stream: {}", err);
+			}
+		}
+
+		self.pending_events += 1;
+
+		if self
+			.flush_strategy
+			.should_flush(self.pending_events, self.last_flush.elapsed())
+		{
+			self.flush().await;
+		}
+	}
+
+	/// Replace the event log at the path with a snapshot of the state, which
+	/// must be the one the logged events result in
+	async fn compact(&mut self, path: &Path, state: &state::State) {
+		self.flush().await;
+
+		let compacted_path = path.with_extension("ndjson.compacting");
+		let mut line = serde_json::to_vec(&LogEntry::Snapshot {
+			snapshot: state.clone(),
+		})
+		.unwrap();
+		line.push(b'\n');
+
+		let mut compacted_file = File::create(&compacted_path).await.unwrap();
+		compacted_file.write_all(&line).await.unwrap();
+		compacted_file.sync_all().await.unwrap();
+
+		// Renaming is atomic, so a crash leaves either log intact
+		rename(&compacted_path, path).await.unwrap();
+
+		let file = OpenOptions::new().append(true).open(path).await.unwrap();
+		self.writer = BufWriter::new(file);
+	}
+
+	async fn flush(&mut self) {
+		self.writer.flush().await.unwrap();
+
+		// Batching trades durability for throughput, so make sure a batch
+		// actually reaches the disk once it is written out
+		if matches!(self.flush_strategy, FlushStrategy::Batched { .. }) {
+			self.writer.get_ref().sync_data().await.unwrap();
+		}
+
+		self.pending_events = 0;
+		self.last_flush = Instant::now();
 	}
 }
 
-#[tracing::instrument(skip(config, bitcoin_client, stacks_client, result))]
-fn spawn(
-	config: Config,
-	bitcoin_client: BitcoinClient,
-	stacks_client: LockedClient,
-	task: Task,
-	result: mpsc::Sender<Event>,
-) -> JoinHandle<()> {
-	info!("Spawning");
-
-	tokio::task::spawn(async move {
-		let event =
-			run_task(&config, bitcoin_client, stacks_client, task).await;
-		result.send(event).await.expect("Failed to return event");
-	})
+/// Spawned tasks, split by whether they can be aborted on shutdown
+struct RunningTasks {
+	reads: JoinSet<()>,
+	broadcasts: JoinSet<()>,
+	// Broadcasting tasks which may not have broadcasted yet
+	unstarted_broadcasts: Vec<(AbortHandle, Arc<Mutex<BroadcastStage>>)>,
+	// Only broadcasts are limited, as they are what loads the nodes and the
+	// nonce tracking. Reads mostly wait for the next block and would hold
+	// their permit all along, leaving none for the broadcasts.
+	broadcast_permits: Arc<Semaphore>,
+}
+
+impl RunningTasks {
+	fn new(max_concurrent_broadcasts: usize) -> Self {
+		Self {
+			reads: JoinSet::new(),
+			broadcasts: JoinSet::new(),
+			unstarted_broadcasts: vec![],
+			broadcast_permits: Arc::new(Semaphore::new(
+				max_concurrent_broadcasts,
+			)),
+		}
+	}
+
+	#[tracing::instrument(skip(
+		self,
+		config,
+		bitcoin_client,
+		stacks_client,
+		result
+	))]
+	fn spawn<B: BitcoinApi, S: StacksApi>(
+		&mut self,
+		config: Config,
+		bitcoin_client: B,
+		stacks_client: S,
+		task: Task,
+		result: mpsc::Sender<Event>,
+	) {
+		info!("Spawning");
+
+		let broadcasts = task.broadcasts();
+		let run = async move {
+			let event =
+				run_task(&config, bitcoin_client, stacks_client, task).await;
+			result.send(event).await.expect("Failed to return event");
+		};
+
+		if broadcasts {
+			self.spawn_broadcast(run);
+		} else {
+			self.reads.spawn(run);
+		}
+	}
+
+	fn spawn_broadcast<F>(&mut self, run: F)
+	where
+		F: Future<Output = ()> + Send + 'static,
+	{
+		let stage = Arc::new(Mutex::new(BroadcastStage::Preparing));
+		let handle = self.broadcasts.spawn(BROADCAST_STAGE.scope(
+			stage.clone(),
+			with_permit(self.broadcast_permits.clone(), run),
+		));
+
+		self.unstarted_broadcasts.push((handle, stage));
+	}
+
+	/// Drop finished tasks, which would otherwise be kept until joined
+	fn reap(&mut self) {
+		for tasks in [&mut self.reads, &mut self.broadcasts] {
+			while let Some(Some(res)) = tasks.join_next().now_or_never() {
+				if let Err(err) = res {
+					error!("Task failed: {}", err);
+				}
+			}
+		}
+
+		// Finished tasks drop their copy of the stage
+		self.unstarted_broadcasts.retain(|(_, stage)| {
+			Arc::strong_count(stage) > 1
+				&& *stage.lock().unwrap() == BroadcastStage::Preparing
+		});
+	}
+
+	/// Abort the broadcasting tasks which have not started broadcasting, as
+	/// they are spawned again on the next start
+	fn abort_unstarted_broadcasts(&mut self) {
+		for (handle, stage) in self.unstarted_broadcasts.drain(..) {
+			let mut stage = stage.lock().unwrap();
+
+			if *stage == BroadcastStage::Preparing {
+				*stage = BroadcastStage::Aborted;
+				handle.abort();
+			}
+		}
+	}
+}
+
+/// Progress of a broadcasting task, guarding against aborting it while it
+/// broadcasts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BroadcastStage {
+	Preparing,
+	Broadcasting,
+	Aborted,
+}
+
+/// Mark the current task as broadcasting so that shutdown awaits it. Never
+/// returns if the task was aborted in the meantime.
+async fn start_broadcast() {
+	let aborted = BROADCAST_STAGE
+		.try_with(|stage| {
+			let mut stage = stage.lock().unwrap();
+
+			if *stage == BroadcastStage::Aborted {
+				true
+			} else {
+				*stage = BroadcastStage::Broadcasting;
+				false
+			}
+		})
+		.unwrap_or(false);
+
+	if aborted {
+		std::future::pending::<()>().await;
+	}
+}
+
+/// Run the future once one of the permits is available
+async fn with_permit<F: Future>(
+	permits: Arc<Semaphore>,
+	future: F,
+) -> F::Output {
+	let _permit = permits
+		.acquire_owned()
+		.await
+		.expect("Task permits are never closed");
+
+	future.await
 }
 
-async fn run_task(
+async fn run_task<B: BitcoinApi, S: StacksApi>(
 	config: &Config,
-	bitcoin_client: BitcoinClient,
-	stacks_client: LockedClient,
+	bitcoin_client: B,
+	stacks_client: S,
 	task: Task,
 ) -> Event {
 	match task {
@@ -177,41 +634,218 @@ async fn run_task(
 			check_bitcoin_transaction_status(config, bitcoin_client, txid).await
 		}
 		Task::CheckStacksTransactionStatus(txid) => {
-			check_stacks_transaction_status(stacks_client, txid).await
+			check_stacks_transaction_status(config, stacks_client, txid).await
+		}
+		Task::BumpStacksTransaction(txid) => {
+			bump_stacks_transaction(config, stacks_client, txid).await
+		}
+		Task::BumpFulfillment(withdrawal_info, txid) => {
+			bump_fulfillment(config, bitcoin_client, withdrawal_info, txid)
+				.await
 		}
 		Task::FetchStacksBlock(block_height) => {
-			fetch_stacks_block(stacks_client, block_height).await
+			fetch_stacks_block(config, stacks_client, block_height).await
 		}
 		Task::FetchBitcoinBlock(block_height) => {
-			fetch_bitcoin_block(bitcoin_client, block_height).await
+			fetch_bitcoin_blocks(config, bitcoin_client, block_height).await
+		}
+		Task::RefreshSbtcWalletUtxos => {
+			refresh_sbtc_wallet_utxos(bitcoin_client).await
 		}
 	}
 }
 
-async fn get_contract_block_height(
+async fn get_contract_block_height<S: StacksApi>(
 	config: &Config,
-	client: LockedClient,
+	client: S,
 ) -> Event {
-	let block_height = client
-		.lock()
-		.await
-		.get_contract_block_height(config.contract_name.clone())
-		.await
-		.expect("Could not get block height. Binary needs to be restarted after contract deployment.");
+	let block_height = retry_read(config, || {
+		client.get_contract_block_height(config.contract_name.clone())
+	})
+	.await
+	.expect("Could not get block height. Binary needs to be restarted after contract deployment.");
+
+	wait_for_confirmations(
+		block_height,
+		config.contract_confirmation_depth,
+		CONTRACT_CONFIRMATION_POLLING_INTERVAL,
+		|| {
+			let client = client.clone();
+
+			async move {
+				retry_read(config, || client.get_stacks_tip_height())
+					.await
+					.expect("Could not get Stacks tip height")
+			}
+		},
+	)
+	.await;
 
-	let bitcoin_block_height = client
-		.lock()
-		.await
-		.get_bitcoin_block_height(block_height)
-		.await
-		.expect("Could not get burnchain block height. Binary needs to be restarted after bitcoin node is online again.");
+	let bitcoin_block_height =
+		retry_read(config, || client.get_bitcoin_block_height(block_height))
+			.await
+			.expect("Could not get burnchain block height. Binary needs to be restarted after bitcoin node is online again.");
 
 	Event::ContractBlockHeight(block_height, bitcoin_block_height)
 }
 
-async fn update_contract_public_key(
+/// Polls the Stacks chain tip until at least `depth` blocks have been built on
+/// top of `block_height`
+async fn wait_for_confirmations<F, Fut>(
+	block_height: u32,
+	depth: u32,
+	polling_interval: Duration,
+	mut get_tip_height: F,
+) where
+	F: FnMut() -> Fut,
+	Fut: Future<Output = u32>,
+{
+	loop {
+		let tip_height = get_tip_height().await;
+
+		if tip_height >= block_height.saturating_add(depth) {
+			break;
+		}
+
+		debug!(
+			"Block {} is not yet buried {} blocks deep, tip is at {}",
+			block_height, depth, tip_height
+		);
+		sleep(polling_interval).await;
+	}
+}
+
+/// Retries a read from a node with the configured backoff. Reads are safe to
+/// repeat, unlike broadcasts which may have gone through despite an error.
+/// A node serving another network is never retried.
+async fn retry_read<T, O, Fut>(
+	config: &Config,
+	operation: O,
+) -> anyhow::Result<T>
+where
+	O: Fn() -> Fut,
+	Fut: Future<Output = anyhow::Result<T>>,
+{
+	backoff::future::retry_notify(
+		config.retry_policy.backoff(),
+		|| {
+			let read = operation();
+
+			async move {
+				read.await.map_err(|err| {
+					if err.is::<ChainIdMismatch>() {
+						backoff::Error::permanent(err)
+					} else {
+						backoff::Error::transient(err)
+					}
+				})
+			}
+		},
+		|err, duration| {
+			warn!("Retrying read in {:?} after error: {:?}", duration, err);
+		},
+	)
+	.await
+}
+
+/// Holds an operation until the asset contract is no longer paused
+async fn wait_while_paused<F, Fut>(polling_interval: Duration, mut is_paused: F)
+where
+	F: FnMut() -> Fut,
+	Fut: Future<Output = bool>,
+{
+	while is_paused().await {
+		debug!("Asset contract is paused, deferring operation");
+		sleep(polling_interval).await;
+	}
+}
+
+async fn wait_until_unpaused<S: StacksApi>(config: &Config, stacks_client: &S) {
+	wait_while_paused(CONTRACT_PAUSE_POLLING_INTERVAL, || {
+		let stacks_client = stacks_client.clone();
+
+		async move {
+			match retry_read(config, || stacks_client.is_contract_paused())
+				.await
+			{
+				Ok(paused) => paused,
+				// Keeps deferring, as the contract may well be paused
+				Err(err) => {
+					warn!("Could not get the contract paused state: {:?}", err);
+					true
+				}
+			}
+		}
+	})
+	.await;
+}
+
+/// Holds an operation while the Stacks node lags more than `max_lag` blocks
+/// behind the Bitcoin tip. The heights are polled as the Bitcoin tip height
+/// and the Bitcoin height seen by the Stacks node, and polled again when they
+/// cannot be read.
+async fn wait_while_lagging<F, Fut>(
+	max_lag: u32,
+	polling_interval: Duration,
+	mut get_heights: F,
+) where
+	F: FnMut() -> Fut,
+	Fut: Future<Output = anyhow::Result<(u32, u32)>>,
+{
+	loop {
+		let (bitcoin_tip_height, burn_block_height) = match get_heights().await
+		{
+			Ok(heights) => heights,
+			Err(err) => {
+				warn!("Could not check the Stacks node lag: {}", err);
+				sleep(polling_interval).await;
+				continue;
+			}
+		};
+		let lag = bitcoin_tip_height.saturating_sub(burn_block_height);
+
+		if lag <= max_lag {
+			break;
+		}
+
+		debug!(
+			"Stacks node is {} Bitcoin blocks behind the tip, deferring operation",
+			lag
+		);
+		sleep(polling_interval).await;
+	}
+}
+
+async fn wait_until_stacks_node_caught_up<B: BitcoinApi, S: StacksApi>(
+	config: &Config,
+	bitcoin_client: &B,
+	stacks_client: &S,
+) {
+	wait_while_lagging(
+		config.max_stacks_burn_block_lag,
+		STACKS_NODE_LAG_POLLING_INTERVAL,
+		|| {
+			let bitcoin_client = bitcoin_client.clone();
+			let stacks_client = stacks_client.clone();
+
+			async move {
+				let bitcoin_tip_height =
+					retry_read(config, || bitcoin_client.get_height()).await?;
+				let burn_block_height = retry_read(config, || {
+					stacks_client.get_burn_block_height()
+				})
+				.await?;
+
+				Ok::<_, anyhow::Error>((bitcoin_tip_height, burn_block_height))
+			}
+		},
+	)
+	.await;
+}
+
+async fn update_contract_public_key<S: StacksApi>(
 	config: &Config,
-	stacks_client: LockedClient,
+	stacks_client: S,
 ) -> Event {
 	let public_key = StacksPublicKey::from_slice(
 		&config.stacks_credentials.public_key().serialize(),
@@ -232,6 +866,21 @@ async fn update_contract_public_key(
 	)
 	.expect("Cannot convert public key into a Clarity Value")];
 
+	// A previous run may have broadcasted the transaction without recording
+	// it, in which case we pick it up instead of creating a duplicate
+	if let Some(txid) = retry_read(config, || {
+		stacks_client.find_contract_call(
+			&config.contract_function_names.set_bitcoin_wallet_public_key,
+			&function_args,
+		)
+	})
+	.await
+	.expect("Could not look up existing set public key transactions")
+	{
+		info!("Found existing set public key transaction {}", txid);
+		return Event::ContractPublicKeySetBroadcasted(txid);
+	}
+
 	let addr = StacksAddress::consensus_deserialize(&mut Cursor::new(
 		config.stacks_credentials.address().serialize_to_vec(),
 	))
@@ -241,20 +890,24 @@ async fn update_contract_public_key(
 		TransactionPayload::ContractCall(TransactionContractCall {
 			address: addr,
 			contract_name: config.contract_name.clone(),
-			function_name: ClarityName::from("set-bitcoin-wallet-public-key"),
+			function_name: ClarityName::from(
+				config
+					.contract_function_names
+					.set_bitcoin_wallet_public_key
+					.as_str(),
+			),
 			function_args,
 		});
 
-	let tx_version = match config.stacks_network {
-		StacksNetwork::Mainnet => TransactionVersion::Mainnet,
-		StacksNetwork::Testnet => TransactionVersion::Testnet,
-	};
+	let tx = StacksTransaction::new(
+		network_transaction_version(config.stacks_network),
+		tx_auth,
+		tx_payload,
+	);
 
-	let tx = StacksTransaction::new(tx_version, tx_auth, tx_payload);
+	start_broadcast().await;
 
 	let txid = stacks_client
-		.lock()
-		.await
 		.sign_and_broadcast(tx)
 		.await
 		.expect("Unable to sign and broadcast the set public key transaction");
@@ -262,18 +915,37 @@ async fn update_contract_public_key(
 	Event::ContractPublicKeySetBroadcasted(txid)
 }
 
-async fn mint_asset(
+async fn mint_asset<B: BitcoinApi, S: StacksApi>(
 	config: &Config,
-	bitcoin_client: BitcoinClient,
-	stacks_client: LockedClient,
+	bitcoin_client: B,
+	stacks_client: S,
 	deposit_info: DepositInfo,
 ) -> Event {
-	let proof_data = get_tx_proof(
+	wait_until_unpaused(config, &stacks_client).await;
+	wait_until_stacks_node_caught_up(config, &bitcoin_client, &stacks_client)
+		.await;
+
+	let proof_data = match get_tx_proof(
+		config,
 		&bitcoin_client,
 		deposit_info.block_height,
 		deposit_info.txid,
 	)
-	.await;
+	.await
+	{
+		Ok(proof_data) => proof_data,
+		Err(err) => {
+			if config.strictness.broadcast_failure {
+				panic!("Unable to prove the mint transaction: {}", err);
+			} else {
+				debug!(
+					"Ignoring failure to prove the mint transaction: {}",
+					err
+				);
+				return Event::MintBroadcasted(deposit_info, DUMMY_STACKS_ID);
+			}
+		}
+	};
 
 	let public_key = StacksPublicKey::from_slice(
 		&config.stacks_credentials.public_key().serialize(),
@@ -284,15 +956,18 @@ async fn mint_asset(
 		TransactionSpendingCondition::new_singlesig_p2pkh(public_key).unwrap(),
 	);
 
-	let function_args = vec![
-		Value::UInt(deposit_info.amount as u128),
+	let mut function_args = vec![
+		Value::UInt(deposit_info.clarity_amount()),
 		Value::from(deposit_info.recipient.clone()),
-		proof_data.txid,
-		proof_data.block_height,
-		proof_data.merkle_path,
-		proof_data.tx_index,
-		proof_data.block_header,
 	];
+	function_args.extend(proof_data.into_args(config.proof_format_version));
+
+	if config.mint_memo {
+		function_args.push(
+			Value::buff_from(deposit_info.memo.clone().unwrap_or_default())
+				.expect("Deposit memos fit in a Clarity buffer"),
+		);
+	}
 
 	let addr = StacksAddress::consensus_deserialize(&mut Cursor::new(
 		config.stacks_credentials.address().serialize_to_vec(),
@@ -303,21 +978,24 @@ async fn mint_asset(
 		TransactionPayload::ContractCall(TransactionContractCall {
 			address: addr,
 			contract_name: config.contract_name.clone(),
-			function_name: ClarityName::from("mint"),
+			function_name: ClarityName::from(
+				config.contract_function_names.mint.as_str(),
+			),
 			function_args,
 		});
 
-	let tx_version = match config.stacks_network {
-		StacksNetwork::Mainnet => TransactionVersion::Mainnet,
-		StacksNetwork::Testnet => TransactionVersion::Testnet,
-	};
+	let tx = StacksTransaction::new(
+		network_transaction_version(config.stacks_network),
+		tx_auth,
+		tx_payload,
+	);
 
-	let tx = StacksTransaction::new(tx_version, tx_auth, tx_payload);
+	start_broadcast().await;
 
-	match stacks_client.lock().await.sign_and_broadcast(tx).await {
+	match stacks_client.sign_and_broadcast(tx).await {
 		Ok(txid) => Event::MintBroadcasted(deposit_info, txid),
 		Err(err) => {
-			if config.strict {
+			if config.strictness.broadcast_failure {
 				panic!(
 					"Unable to sign and broadcast the mint transaction: {}",
 					err
@@ -330,18 +1008,38 @@ async fn mint_asset(
 	}
 }
 
-async fn burn_asset(
+async fn burn_asset<B: BitcoinApi, S: StacksApi>(
 	config: &Config,
-	bitcoin_client: BitcoinClient,
-	stacks_client: LockedClient,
+	bitcoin_client: B,
+	stacks_client: S,
 	withdrawal_info: WithdrawalInfo,
 ) -> Event {
-	let proof_data = get_tx_proof(
+	wait_until_unpaused(config, &stacks_client).await;
+
+	let proof_data = match get_tx_proof(
+		config,
 		&bitcoin_client,
 		withdrawal_info.block_height,
 		withdrawal_info.txid,
 	)
-	.await;
+	.await
+	{
+		Ok(proof_data) => proof_data,
+		Err(err) => {
+			if config.strictness.broadcast_failure {
+				panic!("Unable to prove the burn transaction: {}", err);
+			} else {
+				debug!(
+					"Ignoring failure to prove the burn transaction: {}",
+					err
+				);
+				return Event::BurnBroadcasted(
+					withdrawal_info,
+					DUMMY_STACKS_ID,
+				);
+			}
+		}
+	};
 
 	let public_key = StacksPublicKey::from_slice(
 		&config.stacks_credentials.public_key().serialize(),
@@ -352,15 +1050,11 @@ async fn burn_asset(
 		TransactionSpendingCondition::new_singlesig_p2pkh(public_key).unwrap(),
 	);
 
-	let function_args = vec![
-		Value::UInt(withdrawal_info.amount as u128),
+	let mut function_args = vec![
+		Value::UInt(withdrawal_info.clarity_amount()),
 		Value::from(withdrawal_info.source.clone()),
-		proof_data.txid,
-		proof_data.block_height,
-		proof_data.merkle_path,
-		proof_data.tx_index,
-		proof_data.block_header,
 	];
+	function_args.extend(proof_data.into_args(config.proof_format_version));
 
 	let addr = StacksAddress::consensus_deserialize(&mut Cursor::new(
 		config.stacks_credentials.address().serialize_to_vec(),
@@ -371,21 +1065,24 @@ async fn burn_asset(
 		TransactionPayload::ContractCall(TransactionContractCall {
 			address: addr,
 			contract_name: config.contract_name.clone(),
-			function_name: ClarityName::from("burn"),
+			function_name: ClarityName::from(
+				config.contract_function_names.burn.as_str(),
+			),
 			function_args,
 		});
 
-	let tx_version = match config.stacks_network {
-		StacksNetwork::Mainnet => TransactionVersion::Mainnet,
-		StacksNetwork::Testnet => TransactionVersion::Testnet,
-	};
+	let tx = StacksTransaction::new(
+		network_transaction_version(config.stacks_network),
+		tx_auth,
+		tx_payload,
+	);
 
-	let tx = StacksTransaction::new(tx_version, tx_auth, tx_payload);
+	start_broadcast().await;
 
-	match stacks_client.lock().await.sign_and_broadcast(tx).await {
+	match stacks_client.sign_and_broadcast(tx).await {
 		Ok(txid) => Event::BurnBroadcasted(withdrawal_info, txid),
 		Err(err) => {
-			if config.strict {
+			if config.strictness.broadcast_failure {
 				panic!(
 					"Unable to sign and broadcast the burn transaction: {}",
 					err
@@ -398,29 +1095,35 @@ async fn burn_asset(
 	}
 }
 
-async fn fulfill_asset(
+async fn fulfill_asset<B: BitcoinApi, S: StacksApi>(
 	config: &Config,
-	bitcoin_client: BitcoinClient,
-	stacks_client: LockedClient,
+	bitcoin_client: B,
+	stacks_client: S,
 	withdrawal_info: WithdrawalInfo,
 ) -> Event {
-	let stacks_chain_tip = stacks_client
-		.lock()
-		.await
-		.get_block_hash_from_bitcoin_height(withdrawal_info.block_height)
-		.await
-		.expect("Unable to get stacks block hash");
+	let stacks_chain_tip = retry_read(config, || {
+		stacks_client
+			.get_block_hash_from_bitcoin_height(withdrawal_info.block_height)
+	})
+	.await
+	.expect("Unable to get stacks block hash");
 
 	let outputs = create_outputs(
 		BlockId::new(stacks_chain_tip),
 		config.bitcoin_network,
 		&withdrawal_info.recipient,
 		withdrawal_info.amount,
+		&config.operator_tag,
 	)
 	.expect("Could not create withdrawal fulfillment outputs");
 
+	start_broadcast().await;
+
 	let txid = bitcoin_client
-		.sign_and_broadcast(outputs.to_vec())
+		.sign_and_broadcast_rbf(
+			outputs.to_vec(),
+			FeeRate::from_sat_per_vb(config.fulfillment_fee_rate),
+		)
 		.await
 		.expect(
 		"Unable to sign and broadcast the withdrawal fulfillment transaction",
@@ -429,71 +1132,1040 @@ async fn fulfill_asset(
 	Event::FulfillBroadcasted(withdrawal_info, txid)
 }
 
-async fn get_tx_proof(
-	bitcoin_client: &BitcoinClient,
-	height: u32,
-	txid: BitcoinTxId,
-) -> ProofDataClarityValues {
-	let (_, block) = bitcoin_client
-		.get_block(height)
-		.await
-		.expect("Failed to fetch block");
-
-	let index = block
-		.txdata
-		.iter()
-		.position(|tx| tx.txid() == txid)
-		.expect("Failed to find transaction in block");
-
-	ProofData::from_block_and_index(&block, index).to_values()
-}
-
-async fn check_bitcoin_transaction_status(
-	_config: &Config,
-	client: BitcoinClient,
+async fn bump_fulfillment<B: BitcoinApi>(
+	config: &Config,
+	client: B,
+	withdrawal_info: WithdrawalInfo,
 	txid: BitcoinTxId,
 ) -> Event {
-	let status = client
-		.get_tx_status(txid)
-		.await
-		.expect("Could not get Bitcoin transaction status");
+	start_broadcast().await;
+
+	match client.bump_fee(txid).await {
+		Ok(bumped_txid) => {
+			Event::FulfillmentBumped(withdrawal_info, bumped_txid)
+		}
+		Err(err) => {
+			if config.strictness.broadcast_failure {
+				panic!("Unable to bump the fee of {}: {}", txid, err);
+			} else {
+				debug!("Ignoring failure to bump the fee of {}: {}", txid, err);
+				Event::FulfillmentBumpFailed(withdrawal_info)
+			}
+		}
+	}
+}
+
+fn network_transaction_version(network: StacksNetwork) -> TransactionVersion {
+	match network {
+		StacksNetwork::Mainnet => TransactionVersion::Mainnet,
+		StacksNetwork::Testnet => TransactionVersion::Testnet,
+	}
+}
+
+async fn get_tx_proof<B: BitcoinApi>(
+	config: &Config,
+	bitcoin_client: &B,
+	height: u32,
+	txid: BitcoinTxId,
+) -> anyhow::Result<ProofDataClarityValues> {
+	let (_, block) =
+		retry_read(config, || bitcoin_client.get_block(height)).await?;
+
+	let proof_data = ProofData::from_block_and_txid(&block, txid)?;
+
+	verify_tx_pays_wallet(config, &block, proof_data.tx_index as usize, txid)?;
+
+	Ok(proof_data.to_values())
+}
+
+/// Checks that the transaction at `index` in the block is `txid` and pays
+/// the sBTC wallet, since the block may have been replaced by a reorg after
+/// the transaction was parsed
+fn verify_tx_pays_wallet(
+	config: &Config,
+	block: &Block,
+	index: usize,
+	txid: BitcoinTxId,
+) -> anyhow::Result<()> {
+	let tx = block.txdata.get(index).ok_or_else(|| {
+		anyhow!(
+			"Block {} has no transaction at {}",
+			block.block_hash(),
+			index
+		)
+	})?;
+
+	if tx.txid() != txid {
+		anyhow::bail!(
+			"Transaction at {} in block {} is {} instead of {}",
+			index,
+			block.block_hash(),
+			tx.txid(),
+			txid
+		);
+	}
+
+	let wallet_script = config.sbtc_wallet_address().script_pubkey();
+
+	if !tx
+		.output
+		.iter()
+		.any(|output| output.script_pubkey == wallet_script)
+	{
+		anyhow::bail!("Transaction {} does not pay the sBTC wallet", txid);
+	}
+
+	Ok(())
+}
+
+async fn check_bitcoin_transaction_status<B: BitcoinApi>(
+	config: &Config,
+	client: B,
+	txid: BitcoinTxId,
+) -> Event {
+	let status = retry_read(config, || client.get_tx_status(txid))
+		.await
+		.expect("Could not get Bitcoin transaction status");
 
 	Event::BitcoinTransactionUpdate(txid, status)
 }
 
-async fn check_stacks_transaction_status(
-	client: LockedClient,
+async fn check_stacks_transaction_status<S: StacksApi>(
+	config: &Config,
+	client: S,
 	txid: StacksTxId,
 ) -> Event {
-	let status = client
-		.lock()
-		.await
-		.get_transation_status(txid)
+	let status = retry_read(config, || client.get_transation_status(txid))
 		.await
 		.expect("Could not get Stacks transaction status");
 
 	Event::StacksTransactionUpdate(txid, status)
 }
 
-async fn fetch_stacks_block(client: LockedClient, block_height: u32) -> Event {
-	let txs = client
-		.lock()
-		.await
-		.get_block(block_height)
+async fn bump_stacks_transaction<S: StacksApi>(
+	config: &Config,
+	client: S,
+	txid: StacksTxId,
+) -> Event {
+	start_broadcast().await;
+
+	match client.bump_fee(txid).await {
+		Ok(bumped_txid) => Event::StacksTransactionBumped(txid, bumped_txid),
+		Err(err) => {
+			if config.strictness.broadcast_failure {
+				panic!("Unable to bump the fee of {}: {}", txid, err);
+			} else {
+				debug!("Ignoring failure to bump the fee of {}: {}", txid, err);
+				Event::StacksTransactionBumpFailed(txid)
+			}
+		}
+	}
+}
+
+async fn fetch_stacks_block<S: StacksApi>(
+	config: &Config,
+	client: S,
+	block_height: u32,
+) -> Event {
+	let txs = retry_read(config, || client.get_block(block_height))
 		.await
 		.expect("Failed to get Stacks block");
 
 	Event::StacksBlock(block_height, txs)
 }
 
-async fn fetch_bitcoin_block(
-	client: BitcoinClient,
+async fn fetch_bitcoin_blocks<B: BitcoinApi>(
+	config: &Config,
+	client: B,
 	block_height: u32,
 ) -> Event {
-	let (height, block) = client
-		.get_block(block_height)
+	// Catching up fetches a batch of blocks, following the tip waits for the
+	// next one
+	let tip_height = retry_read(config, || client.get_height())
+		.await
+		.expect("Failed to fetch bitcoin block height");
+	let to = tip_height.clamp(
+		block_height,
+		block_height.saturating_add(BITCOIN_BLOCK_BATCH_SIZE - 1),
+	);
+
+	let blocks = retry_read(config, || client.get_blocks(block_height, to))
+		.await
+		.expect("Failed to fetch bitcoin blocks");
+
+	Event::BitcoinBlocks(blocks)
+}
+
+async fn refresh_sbtc_wallet_utxos<B: BitcoinApi>(client: B) -> Event {
+	// Fulfillments sync the wallet themselves if this fails, so it only costs
+	// time
+	if let Err(err) = client.refresh_wallet_utxos().await {
+		warn!("Could not refresh the sBTC wallet UTXOs: {}", err);
+	}
+
+	Event::SbtcWalletUtxosRefreshed
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		path::PathBuf,
+		str::FromStr,
+		sync::{
+			atomic::{AtomicUsize, Ordering},
+			Arc, Mutex,
+		},
+	};
+
+	use async_trait::async_trait;
+	use bdk::bitcoin::{
+		consensus::deserialize, hashes::hex::FromHex,
+		Address as BitcoinAddress, Block, Network as BitcoinNetwork, Script,
+		Transaction,
+	};
+	use blockstack_lib::{core::CHAIN_ID_TESTNET, vm::ContractName};
+	use serde_json::Value as JsonValue;
+	use stacks_core::uint::Uint256;
+	use tokio::{fs, time::timeout};
+
+	use super::*;
+	use crate::{
+		config::{RetryPolicy, StrictnessPolicy},
+		event::TransactionStatus,
+		fault_injection::{Faults, FaultyClient},
+		stacks_client::ContractDeployment,
+		test_util::{test_config, testnet_block_100000},
+	};
+
+	fn deposit_tx() -> Transaction {
+		let tx_hex = "010000000001019131d69f4616c2a17f3d2519a3dc697136a56846794e677982f565f79295e0370100000000feffffff0300000000000000001b6a1954323c051af0bf935f1ba62167f89c1fff2d9369f972ad0f7e6e0a020000000000225120b85fdda4ae0f69883280360a9b91555a2f23c5b9e34173fabec5d903416c2aaf7b850800000000001600147c969cfcab0d2ad171aa3f201c94b51b0e8eca6602473044022036663b723c79333f9c8b7d5d9db3b6cd301fc6bf82515e62303713eb69b4d18d0220548939af6e1d86fcf8a54da1f6942f25f36ed0488a0d3616c47daa49f59bc7b601210215bd6d522931e602fde924571eb472bc1db953484b29ba6542774ebbf083412329c62500";
+
+		deserialize(&Vec::<u8>::from_hex(tx_hex).unwrap()).unwrap()
+	}
+
+	fn config(state_directory: PathBuf) -> Config {
+		let sbtc_wallet_address = BitcoinAddress::from_script(
+			&deposit_tx().output[1].script_pubkey,
+			BitcoinNetwork::Testnet,
+		)
+		.unwrap();
+
+		Config {
+			state_directory,
+			sbtc_wallet_address_override: Some(sbtc_wallet_address),
+			stacks_fee_bump_blocks: 1000,
+			..test_config()
+		}
+	}
+
+	/// Bitcoin chain with the contract deployed at height 100 and a deposit
+	/// at height 101, followed by a steady stream of empty blocks
+	#[derive(Clone)]
+	struct MockBitcoinClient {
+		deposit_block: Block,
+	}
+
+	impl MockBitcoinClient {
+		/// Block at the height, building on the one at the previous height so
+		/// that no reorg is detected
+		fn block_at(&self, block_height: u32) -> Block {
+			let mut tip = self.deposit_block.clone();
+
+			for height in 102..=block_height {
+				let mut next = testnet_block_100000();
+				next.header.nonce = height;
+				next.header.prev_blockhash = tip.block_hash();
+				tip = next;
+			}
+
+			tip
+		}
+	}
+
+	#[async_trait]
+	impl BitcoinApi for MockBitcoinClient {
+		async fn sign_and_broadcast(
+			&self,
+			_outputs: Vec<(Script, u64)>,
+		) -> anyhow::Result<BitcoinTxId> {
+			unimplemented!("Deposits don't broadcast Bitcoin transactions")
+		}
+
+		async fn sign_and_broadcast_rbf(
+			&self,
+			_outputs: Vec<(Script, u64)>,
+			_fee_rate: FeeRate,
+		) -> anyhow::Result<BitcoinTxId> {
+			unimplemented!("Deposits don't broadcast Bitcoin transactions")
+		}
+
+		async fn bump_fee(
+			&self,
+			_txid: BitcoinTxId,
+		) -> anyhow::Result<BitcoinTxId> {
+			unimplemented!("Deposits don't broadcast Bitcoin transactions")
+		}
+
+		async fn get_tx_status(
+			&self,
+			_txid: BitcoinTxId,
+		) -> anyhow::Result<TransactionStatus> {
+			unimplemented!("Deposits don't broadcast Bitcoin transactions")
+		}
+
+		async fn get_block(
+			&self,
+			block_height: u32,
+		) -> anyhow::Result<(u32, Block)> {
+			if block_height > 101 {
+				sleep(Duration::from_millis(50)).await;
+			}
+
+			Ok((block_height, self.block_at(block_height)))
+		}
+
+		async fn get_blocks(
+			&self,
+			from: u32,
+			to: u32,
+		) -> anyhow::Result<Vec<(u32, Block)>> {
+			let mut blocks = vec![];
+
+			for block_height in from..=to {
+				blocks.push(self.get_block(block_height).await?);
+			}
+
+			Ok(blocks)
+		}
+
+		async fn get_height(&self) -> anyhow::Result<u32> {
+			Ok(101)
+		}
+
+		async fn refresh_wallet_utxos(&self) -> anyhow::Result<()> {
+			Ok(())
+		}
+	}
+
+	/// Stacks chain with the contract deployed at height 1, confirming every
+	/// transaction right away
+	#[derive(Clone, Default)]
+	struct MockStacksClient {
+		broadcasted: Arc<Mutex<Vec<StacksTransaction>>>,
+		existing_call: Option<StacksTxId>,
+		broadcast_delay: Duration,
+	}
+
+	#[async_trait]
+	impl StacksApi for MockStacksClient {
+		async fn chain_id(&self) -> anyhow::Result<u32> {
+			Ok(CHAIN_ID_TESTNET)
+		}
+
+		async fn sign_and_broadcast(
+			&self,
+			tx: StacksTransaction,
+		) -> anyhow::Result<StacksTxId> {
+			let txid = {
+				let mut broadcasted = self.broadcasted.lock().unwrap();
+				broadcasted.push(tx);
+
+				StacksTxId([broadcasted.len() as u8; 32])
+			};
+
+			sleep(self.broadcast_delay).await;
+
+			Ok(txid)
+		}
+
+		async fn bump_fee(
+			&self,
+			_txid: StacksTxId,
+		) -> anyhow::Result<StacksTxId> {
+			unimplemented!("Transactions are confirmed right away")
+		}
+
+		async fn get_transation_status(
+			&self,
+			_txid: StacksTxId,
+		) -> anyhow::Result<TransactionStatus> {
+			Ok(TransactionStatus::Confirmed)
+		}
+
+		async fn get_contract_block_height(
+			&self,
+			_name: ContractName,
+		) -> anyhow::Result<u32> {
+			Ok(1)
+		}
+
+		async fn get_stacks_tip_height(&self) -> anyhow::Result<u32> {
+			Ok(1)
+		}
+
+		async fn get_burn_block_height(&self) -> anyhow::Result<u32> {
+			Ok(101)
+		}
+
+		async fn get_contract_deployment(
+			&self,
+		) -> anyhow::Result<ContractDeployment> {
+			Ok(ContractDeployment {
+				txid: StacksTxId([1; 32]),
+				deployer: "ST000000000000000000002AMW42H".to_string(),
+				block_height: 1,
+				source_hash: String::new(),
+				clarity_version: None,
+			})
+		}
+
+		async fn find_contract_call(
+			&self,
+			_function_name: &str,
+			_args: &[Value],
+		) -> anyhow::Result<Option<StacksTxId>> {
+			Ok(self.existing_call)
+		}
+
+		async fn is_contract_paused(&self) -> anyhow::Result<bool> {
+			Ok(false)
+		}
+
+		async fn get_bitcoin_block_height(
+			&self,
+			_block_height: u32,
+		) -> anyhow::Result<u32> {
+			Ok(100)
+		}
+
+		async fn get_block(
+			&self,
+			_block_height: u32,
+		) -> anyhow::Result<Vec<StacksTransaction>> {
+			sleep(Duration::from_millis(10)).await;
+			Ok(vec![])
+		}
+
+		async fn get_block_hash_from_bitcoin_height(
+			&self,
+			_height: u32,
+		) -> anyhow::Result<Uint256> {
+			unimplemented!("Deposits don't need Stacks block hashes")
+		}
+	}
+
+	async fn read_events(config: &Config) -> Vec<Event> {
+		let log = fs::read_to_string(config.state_directory.join("log.ndjson"))
+			.await
+			.unwrap_or_default();
+
+		// The last line may still be in the process of being written
+		log.lines()
+			.filter_map(|line| serde_json::from_str(line).ok())
+			.collect()
+	}
+
+	fn mint_stage(state: &state::State) -> Option<String> {
+		let state = serde_json::to_value(state).unwrap();
+		let deposit = &state["Initialized"]["deposits"][0];
+
+		match &deposit["mint"] {
+			_ if deposit.is_null() => None,
+			JsonValue::Null => Some("Parsed".to_string()),
+			JsonValue::String(stage) => Some(stage.clone()),
+			JsonValue::Object(stage) if stage.contains_key("Acknowledged") => {
+				stage["Acknowledged"]["status"].as_str().map(str::to_string)
+			}
+			JsonValue::Object(stage) => stage.keys().next().cloned(),
+			stage => panic!("Unexpected mint stage: {}", stage),
+		}
+	}
+
+	/// Waits for the mint of the deposit to be confirmed and returns the
+	/// recorded events. Tests run it on paused time, which only advances
+	/// when every task is idle, so the deadline doesn't depend on how fast
+	/// the machine is.
+	async fn wait_until_minted(config: &Config) -> Vec<Event> {
+		timeout(Duration::from_secs(30), async {
+			loop {
+				let events = read_events(config).await;
+
+				let mint_txid = events.iter().find_map(|event| match event {
+					Event::MintBroadcasted(_, txid) => Some(*txid),
+					_ => None,
+				});
+				let mint_confirmed = events.iter().any(|event| {
+					matches!(
+						event,
+						Event::StacksTransactionUpdate(
+							txid,
+							TransactionStatus::Confirmed
+						) if Some(*txid) == mint_txid
+					)
+				});
+
+				if mint_confirmed {
+					break events;
+				}
+
+				sleep(Duration::from_millis(50)).await;
+			}
+		})
+		.await
+		.expect("The deposit was not minted in time")
+	}
+
+	#[test]
+	fn proof_should_require_transaction_paying_wallet() {
+		let config = config(PathBuf::new());
+		let txid = deposit_tx().txid();
+
+		let mut deposit_block = testnet_block_100000();
+		deposit_block.txdata.push(deposit_tx());
+
+		verify_tx_pays_wallet(&config, &deposit_block, 1, txid).unwrap();
+
+		// A reorg replaced the deposit with another transaction
+		let mut replaced_block = testnet_block_100000();
+		replaced_block
+			.txdata
+			.push(testnet_block_100000().txdata[0].clone());
+
+		assert!(
+			verify_tx_pays_wallet(&config, &replaced_block, 1, txid).is_err()
+		);
+		assert!(verify_tx_pays_wallet(
+			&config,
+			&testnet_block_100000(),
+			1,
+			txid
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn proof_should_reject_transaction_not_paying_wallet() {
+		let mut config = config(PathBuf::new());
+		config.sbtc_wallet_address_override = Some(
+			BitcoinAddress::from_str(
+				"tb1pte5zmd7qzj4hdu45lh9mmdm0nwq3z35pwnxmzkwld6y0a8g83nnq6ts2d4",
+			)
+			.unwrap(),
+		);
+
+		let mut deposit_block = testnet_block_100000();
+		deposit_block.txdata.push(deposit_tx());
+
+		assert!(verify_tx_pays_wallet(
+			&config,
+			&deposit_block,
+			1,
+			deposit_tx().txid()
+		)
+		.is_err());
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn deposit_should_be_minted_end_to_end() {
+		let state_directory = std::env::temp_dir()
+			.join(format!("romeo-deposit-lifecycle-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&state_directory).await;
+		let config = config(state_directory.clone());
+
+		let mut deposit_block = testnet_block_100000();
+		deposit_block.txdata.push(deposit_tx());
+
+		let bitcoin_client = MockBitcoinClient { deposit_block };
+		let stacks_client = MockStacksClient::default();
+
+		let system = tokio::spawn(run_with_clients(
+			config.clone(),
+			bitcoin_client,
+			stacks_client.clone(),
+			std::future::pending(),
+		));
+
+		let events = wait_until_minted(&config).await;
+
+		system.abort();
+
+		let deposit_info = events
+			.iter()
+			.find_map(|event| match event {
+				Event::MintBroadcasted(deposit_info, _) => Some(deposit_info),
+				_ => None,
+			})
+			.unwrap();
+		assert_eq!(deposit_info.txid, deposit_tx().txid());
+		assert_eq!(deposit_info.amount, 133646);
+		assert_eq!(deposit_info.block_height, 101);
+
+		// The set public key transaction is followed by the mint
+		let broadcasted = stacks_client.broadcasted.lock().unwrap();
+		assert_eq!(broadcasted.len(), 2);
+		let TransactionPayload::ContractCall(call) = &broadcasted[1].payload
+		else {
+			panic!("The mint should be a contract call");
+		};
+		assert_eq!(call.function_name, ClarityName::from("mint"));
+		assert_eq!(call.function_args[0], Value::UInt(133646));
+
+		// Replay the recorded events to follow the deposit through the state
+		let mut state = state::State::new();
+		let mut stages: Vec<String> = vec![];
+
+		for event in events {
+			state.update(event, &config).unwrap();
+
+			if let Some(stage) = mint_stage(&state) {
+				if stages.last() != Some(&stage) {
+					stages.push(stage);
+				}
+			}
+		}
+
+		assert_eq!(
+			stages,
+			["Scheduled", "Created", "Broadcasted", "Confirmed"]
+		);
+
+		fs::remove_dir_all(&state_directory).await.unwrap();
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn deposit_should_be_minted_despite_intermittent_failures() {
+		let state_directory = std::env::temp_dir()
+			.join(format!("romeo-deposit-faults-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&state_directory).await;
+		let mut config = config(state_directory.clone());
+		config.retry_policy = RetryPolicy {
+			max_interval_ms: 10,
+			max_elapsed_time_ms: None,
+		};
+
+		let mut deposit_block = testnet_block_100000();
+		deposit_block.txdata.push(deposit_tx());
+
+		let faults = Faults {
+			latency: Duration::from_millis(5),
+			error_every: Some(3),
+			drop_every: Some(5),
+			broadcasts: false,
+		};
+		let bitcoin_client =
+			FaultyClient::new(MockBitcoinClient { deposit_block }, faults);
+		let mock_stacks_client = MockStacksClient::default();
+		let stacks_client =
+			FaultyClient::new(mock_stacks_client.clone(), faults);
+
+		let system = tokio::spawn(run_with_clients(
+			config.clone(),
+			bitcoin_client.clone(),
+			stacks_client.clone(),
+			std::future::pending(),
+		));
+
+		let events = wait_until_minted(&config).await;
+
+		system.abort();
+
+		assert!(bitcoin_client.injected() > 0);
+		assert!(stacks_client.injected() > 0);
+
+		// Retried reads must not lead to duplicate broadcasts
+		assert_eq!(mock_stacks_client.broadcasted.lock().unwrap().len(), 2);
+		assert_eq!(
+			events
+				.iter()
+				.filter(|event| matches!(event, Event::MintBroadcasted(..)))
+				.count(),
+			1
+		);
+
+		fs::remove_dir_all(&state_directory).await.unwrap();
+	}
+
+	#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+	async fn shutdown_should_stop_the_system_and_flush_the_log() {
+		let state_directory = std::env::temp_dir()
+			.join(format!("romeo-shutdown-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&state_directory).await;
+		// Events are only flushed on shutdown
+		let config = Config {
+			event_flush_strategy: FlushStrategy::Batched {
+				max_events: usize::MAX,
+				max_delay_ms: u64::MAX,
+			},
+			..config(state_directory.clone())
+		};
+
+		let stacks_client = MockStacksClient::default();
+		let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+
+		let system = tokio::spawn(run_with_clients(
+			config.clone(),
+			MockBitcoinClient {
+				deposit_block: testnet_block_100000(),
+			},
+			stacks_client.clone(),
+			async move {
+				shutdown_rx.await.ok();
+			},
+		));
+
+		timeout(Duration::from_secs(30), async {
+			while stacks_client.broadcasted.lock().unwrap().is_empty() {
+				sleep(Duration::from_millis(10)).await;
+			}
+		})
 		.await
-		.expect("Failed to fetch bitcoin block");
+		.unwrap();
+		assert!(read_events(&config).await.is_empty());
+
+		shutdown_tx.send(()).unwrap();
+		timeout(Duration::from_secs(30), system)
+			.await
+			.expect("The system should stop on shutdown")
+			.unwrap();
+
+		let log = fs::read_to_string(state_directory.join("log.ndjson"))
+			.await
+			.unwrap();
+		assert!(log.ends_with('\n'));
+		assert!(log
+			.lines()
+			.all(|line| serde_json::from_str::<Event>(line).is_ok()));
+		// The public key setup broadcast was in flight or done, and recorded
+		assert!(read_events(&config).await.iter().any(|event| matches!(
+			event,
+			Event::ContractPublicKeySetBroadcasted(_)
+		)));
+
+		fs::remove_dir_all(&state_directory).await.unwrap();
+	}
+
+	#[tokio::test(start_paused = true)]
+	async fn single_permit_should_run_broadcasts_sequentially() {
+		let config = config(PathBuf::new());
+		let stacks_client = MockStacksClient {
+			broadcast_delay: Duration::from_secs(1),
+			..Default::default()
+		};
+		let (tx, mut rx) = mpsc::channel(2);
+		let mut running = RunningTasks::new(1);
+
+		for _ in 0..2 {
+			running.spawn(
+				config.clone(),
+				MockBitcoinClient {
+					deposit_block: testnet_block_100000(),
+				},
+				stacks_client.clone(),
+				Task::UpdateContractPublicKey,
+				tx.clone(),
+			);
+		}
 
-	Event::BitcoinBlock(height, block)
+		// The second broadcast waits for the permit of the first one
+		sleep(Duration::from_millis(500)).await;
+		assert_eq!(stacks_client.broadcasted.lock().unwrap().len(), 1);
+
+		for txid in [StacksTxId([1; 32]), StacksTxId([2; 32])] {
+			assert_eq!(
+				rx.recv().await,
+				Some(Event::ContractPublicKeySetBroadcasted(txid))
+			);
+		}
+	}
+
+	#[tokio::test]
+	async fn shutdown_should_only_abort_unstarted_broadcasts() {
+		let mut running = RunningTasks::new(2);
+		let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+		let (finish_tx, finish_rx) = tokio::sync::oneshot::channel::<()>();
+
+		// Stuck before broadcasting, e.g. while the contract is paused
+		running.spawn_broadcast(std::future::pending());
+		running.spawn_broadcast(async move {
+			start_broadcast().await;
+			started_tx.send(()).unwrap();
+			finish_rx.await.unwrap();
+		});
+
+		started_rx.await.unwrap();
+		running.abort_unstarted_broadcasts();
+		finish_tx.send(()).unwrap();
+
+		let mut results = vec![];
+		while let Some(res) = running.broadcasts.join_next().await {
+			results.push(res.map_err(|err| err.is_cancelled()));
+		}
+		results.sort();
+
+		assert_eq!(results, [Ok(()), Err(true)]);
+	}
+
+	#[tokio::test]
+	async fn batched_storage_should_flush_after_max_events() {
+		let path = std::env::temp_dir()
+			.join(format!("romeo-storage-{}.ndjson", std::process::id()));
+		let file = File::create(&path).await.unwrap();
+
+		let mut storage = Storage::new(
+			file,
+			FlushStrategy::Batched {
+				max_events: 3,
+				max_delay_ms: u64::MAX,
+			},
+		);
+
+		let event = Event::ContractBlockHeight(1, 2);
+
+		storage.record(&event).await;
+		storage.record(&event).await;
+
+		assert!(fs::read_to_string(&path).await.unwrap().is_empty());
+
+		storage.record(&event).await;
+
+		let contents = fs::read_to_string(&path).await.unwrap();
+		assert_eq!(contents.lines().count(), 3);
+		assert_eq!(storage.pending_events, 0);
+
+		fs::remove_file(&path).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn compacted_log_should_reload_the_same_state() {
+		let state_directory = std::env::temp_dir()
+			.join(format!("romeo-compaction-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&state_directory).await;
+		let config = Config {
+			compact_event_log: true,
+			..config(state_directory.clone())
+		};
+
+		// A log written before snapshots existed
+		fs::create_dir_all(&state_directory).await.unwrap();
+		let events = [
+			Event::ContractBlockHeight(1, 2),
+			Event::ContractPublicKeySetBroadcasted(StacksTxId([1; 32])),
+		];
+		let log: String = events
+			.iter()
+			.map(|event| serde_json::to_string(event).unwrap() + "\n")
+			.collect();
+		fs::write(state_directory.join("log.ndjson"), log)
+			.await
+			.unwrap();
+
+		let (mut storage, state) =
+			Storage::load_and_replay(&config, state::State::new()).await;
+		let log = fs::read_to_string(state_directory.join("log.ndjson"))
+			.await
+			.unwrap();
+		assert_eq!(log.lines().count(), 1);
+		assert!(log.starts_with(r#"{"snapshot":"#));
+
+		// Events recorded after compaction apply on top of the snapshot
+		let event = Event::StacksBlock(2, vec![]);
+		storage.record(&event).await;
+		drop(storage);
+
+		let (_, reloaded_state) =
+			Storage::load_and_replay(&config, state::State::new()).await;
+		let (expected_state, _) = state.dry_update(event, &config).unwrap();
+		assert_eq!(reloaded_state, expected_state);
+
+		fs::remove_dir_all(&state_directory).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn recorded_events_should_be_mirrored_to_event_stream() {
+		let path = std::env::temp_dir()
+			.join(format!("romeo-event-stream-{}.ndjson", std::process::id()));
+		let file = File::create(&path).await.unwrap();
+		let (stream, mut output) = tokio::io::duplex(1024);
+
+		let mut storage = Storage::new(file, FlushStrategy::EveryEvent)
+			.with_event_stream(stream);
+
+		let event = Event::ContractBlockHeight(1, 2);
+		storage.record(&event).await;
+
+		let mut line = String::new();
+		BufReader::new(&mut output)
+			.read_line(&mut line)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			line,
+			format!("{}\n", serde_json::to_string(&event).unwrap())
+		);
+		assert_eq!(fs::read_to_string(&path).await.unwrap(), line);
+
+		fs::remove_file(&path).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn wallet_refreshes_should_not_be_recorded() {
+		let path = std::env::temp_dir()
+			.join(format!("romeo-refreshes-{}.ndjson", std::process::id()));
+		let file = File::create(&path).await.unwrap();
+
+		let mut storage = Storage::new(file, FlushStrategy::EveryEvent);
+
+		let event = Event::ContractBlockHeight(1, 2);
+		storage.record(&Event::SbtcWalletUtxosRefreshed).await;
+		storage.record(&event).await;
+		storage.record(&Event::SbtcWalletUtxosRefreshed).await;
+		storage.flush().await;
+
+		assert_eq!(
+			fs::read_to_string(&path).await.unwrap(),
+			format!("{}\n", serde_json::to_string(&event).unwrap())
+		);
+
+		fs::remove_file(&path).await.unwrap();
+	}
+
+	#[test]
+	#[should_panic(expected = "Bitcoin transaction failed")]
+	fn strict_policy_should_halt_even_if_invalid_events_are_skipped() {
+		let mut config = config(std::env::temp_dir());
+		config.strictness = StrictnessPolicy {
+			broadcast_failure: true,
+			..StrictnessPolicy::uniform(false)
+		};
+		let mut state = state::State::Initialized {
+			stacks_block_height: 5,
+			bitcoin_block_height: 100000,
+			deposits: vec![],
+			withdrawals: vec![],
+			bitcoin_block_hashes: Default::default(),
+		};
+
+		// Bumps of unknown transactions are invalid events
+		assert!(apply_event(
+			&mut state,
+			Event::StacksTransactionBumpFailed(StacksTxId([1; 32])),
+			&config
+		)
+		.is_empty());
+
+		apply_event(
+			&mut state,
+			Event::BitcoinTransactionUpdate(
+				deposit_tx().txid(),
+				TransactionStatus::Rejected,
+			),
+			&config,
+		);
+	}
+
+	#[tokio::test]
+	async fn chain_id_mismatch_should_not_be_retried() {
+		let reads = AtomicUsize::new(0);
+
+		let res = retry_read(&config(std::env::temp_dir()), || {
+			reads.fetch_add(1, Ordering::SeqCst);
+
+			async {
+				Err::<u32, _>(
+					ChainIdMismatch {
+						chain_id: CHAIN_ID_TESTNET,
+						network: StacksNetwork::Mainnet,
+					}
+					.into(),
+				)
+			}
+		})
+		.await;
+
+		assert!(res.unwrap_err().is::<ChainIdMismatch>());
+		assert_eq!(reads.load(Ordering::SeqCst), 1);
+	}
+
+	#[tokio::test]
+	async fn contract_detection_should_wait_for_confirmation_depth() {
+		let mut tip_heights = vec![10, 11, 12, 13, 14].into_iter();
+		let mut polls = 0;
+
+		wait_for_confirmations(10, 3, Duration::ZERO, || {
+			polls += 1;
+			let tip_height = tip_heights.next().unwrap();
+
+			async move { tip_height }
+		})
+		.await;
+
+		assert_eq!(polls, 4);
+		assert_eq!(tip_heights.next(), Some(14));
+	}
+
+	#[tokio::test]
+	async fn existing_set_public_key_call_should_be_reused() {
+		let config = config(PathBuf::new());
+
+		let stacks_client = MockStacksClient {
+			existing_call: Some(StacksTxId([9; 32])),
+			..Default::default()
+		};
+		assert_eq!(
+			update_contract_public_key(&config, stacks_client.clone()).await,
+			Event::ContractPublicKeySetBroadcasted(StacksTxId([9; 32]))
+		);
+		assert!(stacks_client.broadcasted.lock().unwrap().is_empty());
+
+		let stacks_client = MockStacksClient::default();
+		assert_eq!(
+			update_contract_public_key(&config, stacks_client.clone()).await,
+			Event::ContractPublicKeySetBroadcasted(StacksTxId([1; 32]))
+		);
+		assert_eq!(stacks_client.broadcasted.lock().unwrap().len(), 1);
+	}
+
+	#[tokio::test]
+	async fn operations_should_be_held_while_contract_is_paused() {
+		let mut paused_states = vec![true, true, false, true].into_iter();
+		let mut polls = 0;
+
+		wait_while_paused(Duration::ZERO, || {
+			polls += 1;
+			let paused = paused_states.next().unwrap();
+
+			async move { paused }
+		})
+		.await;
+
+		assert_eq!(polls, 3);
+		assert_eq!(paused_states.next(), Some(true));
+	}
+
+	#[tokio::test]
+	async fn minting_should_be_deferred_while_stacks_node_lags() {
+		// The Stacks node catches up from 10 to 6 blocks behind the tip, with
+		// a failed read in between
+		let mut heights = vec![
+			Some((110, 100)),
+			None,
+			Some((111, 103)),
+			Some((111, 105)),
+			Some((112, 100)),
+		]
+		.into_iter();
+		let mut polls = 0;
+
+		wait_while_lagging(6, Duration::ZERO, || {
+			polls += 1;
+			let heights = heights.next().unwrap();
+
+			async move { heights.ok_or_else(|| anyhow!("Read failed")) }
+		})
+		.await;
+
+		assert_eq!(polls, 4);
+		assert_eq!(heights.next(), Some(Some((112, 100))));
+	}
 }
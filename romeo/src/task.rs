@@ -1,12 +1,12 @@
 //! Task
 
-use bdk::bitcoin::Txid as BitcoinTxId;
+use bdk::bitcoin::{hashes::Hash, Txid as BitcoinTxId};
 use blockstack_lib::burnchains::Txid as StacksTxId;
 
 use crate::state;
 
 /// Represents I/O operations performed by the system
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Task {
 	/// Get the block height of the contract deployment
 	GetContractBlockHeight,
@@ -29,9 +29,62 @@ pub enum Task {
 	/// Poll a stacks node for the status of a transaction
 	CheckStacksTransactionStatus(StacksTxId),
 
+	/// Rebroadcast a stacks transaction with the same nonce and a higher fee
+	BumpStacksTransaction(StacksTxId),
+
 	/// Fetch a Stacks block for the given block height
 	FetchStacksBlock(u32),
 
 	/// Fetch a Bitcoin block for the given block height
 	FetchBitcoinBlock(u32),
+
+	/// Sync the sBTC wallet UTXOs ahead of fulfillments
+	RefreshSbtcWalletUtxos,
+
+	/// Replace a fulfill bitcoin transaction with one paying a higher fee
+	BumpFulfillment(state::WithdrawalInfo, BitcoinTxId),
+}
+
+impl Task {
+	/// Whether the task broadcasts a transaction, in which case its outcome
+	/// must be recorded rather than interrupted
+	pub fn broadcasts(&self) -> bool {
+		matches!(
+			self,
+			Task::UpdateContractPublicKey
+				| Task::CreateMint(_)
+				| Task::CreateBurn(_)
+				| Task::CreateFulfillment(_)
+				| Task::BumpStacksTransaction(_)
+				| Task::BumpFulfillment(..)
+		)
+	}
+
+	/// Key defining a total order over tasks.
+	///
+	/// Tasks referring to a transaction are ordered by the full 32 bytes of
+	/// its txid, then by operation kind in declaration order. Tasks that
+	/// don't refer to a transaction sort first, by kind and block height.
+	pub fn order_key(&self) -> (Option<[u8; 32]>, u8, u32) {
+		match self {
+			Task::GetContractBlockHeight => (None, 0, 0),
+			Task::UpdateContractPublicKey => (None, 1, 0),
+			Task::CreateMint(info) => (Some(info.txid.into_inner()), 2, 0),
+			Task::CreateBurn(info) => (Some(info.txid.into_inner()), 3, 0),
+			Task::CreateFulfillment(info) => {
+				(Some(info.txid.into_inner()), 4, 0)
+			}
+			Task::CheckBitcoinTransactionStatus(txid) => {
+				(Some(txid.into_inner()), 5, 0)
+			}
+			Task::CheckStacksTransactionStatus(txid) => (Some(txid.0), 6, 0),
+			Task::BumpStacksTransaction(txid) => (Some(txid.0), 7, 0),
+			Task::FetchStacksBlock(height) => (None, 8, *height),
+			Task::FetchBitcoinBlock(height) => (None, 9, *height),
+			Task::RefreshSbtcWalletUtxos => (None, 10, 0),
+			Task::BumpFulfillment(info, _) => {
+				(Some(info.txid.into_inner()), 11, 0)
+			}
+		}
+	}
 }
@@ -0,0 +1,106 @@
+//! Local HTTP server standing in for Stacks and Bitcoin nodes in tests
+
+use std::sync::Arc;
+
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::{TcpListener, TcpStream},
+	task::JoinHandle,
+};
+
+/// Serves one response per connection on a local port, returning its URL and
+/// the requests received
+pub async fn serve(
+	responses: Vec<(&'static str, String)>,
+) -> (String, JoinHandle<Vec<String>>) {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let url = format!("http://{}", listener.local_addr().unwrap());
+
+	let node = tokio::spawn(async move {
+		let mut requests = vec![];
+
+		for (status, body) in responses {
+			let (mut stream, _) = listener.accept().await.unwrap();
+			let mut request = [0; 4096];
+			let read = stream.read(&mut request).await.unwrap();
+			requests.push(String::from_utf8_lossy(&request[..read]).into());
+
+			let response = format!(
+				"HTTP/1.1 {}\r\n\
+				 connection: close\r\n\
+				 content-length: {}\r\n\r\n{}",
+				status,
+				body.len(),
+				body
+			);
+			stream.write_all(response.as_bytes()).await.unwrap();
+		}
+
+		requests
+	});
+
+	(url, node)
+}
+
+/// Serves every connection concurrently with the status and body returned by
+/// the handler for the request body, returning the server URL
+pub async fn serve_with<F>(handler: F) -> String
+where
+	F: Fn(&str) -> (&'static str, String) + Send + Sync + 'static,
+{
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let url = format!("http://{}", listener.local_addr().unwrap());
+	let handler = Arc::new(handler);
+
+	tokio::spawn(async move {
+		loop {
+			let (mut stream, _) = listener.accept().await.unwrap();
+			let handler = handler.clone();
+
+			tokio::spawn(async move {
+				let body = read_body(&mut stream).await;
+				let (status, response_body) = handler(&body);
+
+				let response = format!(
+					"HTTP/1.1 {}\r\n\
+					 connection: close\r\n\
+					 content-length: {}\r\n\r\n{}",
+					status,
+					response_body.len(),
+					response_body
+				);
+				stream.write_all(response.as_bytes()).await.unwrap();
+			});
+		}
+	});
+
+	url
+}
+
+/// Reads a request until its whole body, as given by its content length, is
+/// received
+async fn read_body(stream: &mut TcpStream) -> String {
+	let mut request = vec![];
+
+	loop {
+		let mut chunk = [0; 4096];
+		let read = stream.read(&mut chunk).await.unwrap();
+		request.extend_from_slice(&chunk[..read]);
+
+		let request = String::from_utf8_lossy(&request);
+		if let Some((headers, body)) = request.split_once("\r\n\r\n") {
+			let content_length = headers
+				.lines()
+				.filter_map(|line| line.split_once(':'))
+				.find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+				.and_then(|(_, value)| value.trim().parse().ok())
+				.unwrap_or(0);
+
+			if body.len() >= content_length || read == 0 {
+				return body.to_string();
+			}
+		} else if read == 0 {
+			return String::new();
+		}
+	}
+}
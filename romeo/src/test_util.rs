@@ -0,0 +1,85 @@
+//! Fixtures shared by the unit tests
+
+use std::path::Path;
+
+use bdk::bitcoin::{
+	consensus::deserialize, hashes::hex::FromHex, Block,
+	Network as BitcoinNetwork,
+};
+use blockstack_lib::vm::ContractName;
+use stacks_core::{wallet::Wallet, Network as StacksNetwork};
+
+use crate::{
+	config::{
+		ClarityVersion, Config, ContractFunctionNames, FlushStrategy,
+		RetryPolicy, StrictnessPolicy,
+	},
+	proof_data::ProofFormatVersion,
+};
+
+/// Mnemonic of the wallet used by the test configs
+pub const TEST_MNEMONIC: &str = "twice kind fence tip hidden tilt action fragile skin nothing glory cousin green tomorrow spring wrist shed math olympic multiply hip blue scout claw";
+
+/// Testnet block 100,000
+pub const TESTNET_BLOCK_100000_HEX: &str = "0200000035ab154183570282ce9afc0b494c9fc6a3cfea05aa8c1add2ecc56490000000038ba3d78e4500a5a7570dbe61960398add4410d278b21cd9708e6d9743f374d544fc055227f1001c29c1ea3b0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff3703a08601000427f1001c046a510100522cfabe6d6d0000000000000000000068692066726f6d20706f6f6c7365727665726aac1eeeed88ffffffff0100f2052a010000001976a914912e2b234f941f30b18afbb4fa46171214bf66c888ac00000000";
+
+/// Deserialized testnet block 100,000
+pub fn testnet_block_100000() -> Block {
+	deserialize(&Vec::<u8>::from_hex(TESTNET_BLOCK_100000_HEX).unwrap())
+		.unwrap()
+}
+
+/// Testnet config pointing at local nodes, meant to be overridden with
+/// `..test_config()`
+pub fn test_config() -> Config {
+	let wallet = Wallet::new(TEST_MNEMONIC).unwrap();
+
+	let stacks_network = StacksNetwork::Testnet;
+	let stacks_credentials = wallet.credentials(stacks_network, 0).unwrap();
+	let bitcoin_credentials = wallet
+		.bitcoin_credentials(BitcoinNetwork::Testnet, 0)
+		.unwrap();
+
+	Config {
+		state_directory: Path::new("/tmp/romeo").to_path_buf(),
+		bitcoin_credentials,
+		bitcoin_node_url: "http://localhost:18443".parse().unwrap(),
+		electrum_node_url: "ssl://blockstream.info:993".parse().unwrap(),
+		bitcoin_network: BitcoinNetwork::Testnet,
+		contract_name: ContractName::from("asset"),
+		stacks_node_url: "http://localhost:20443".parse().unwrap(),
+		stacks_credentials,
+		stacks_network,
+		hiro_api_key: None,
+		strictness: StrictnessPolicy::uniform(true),
+		event_flush_strategy: FlushStrategy::EveryEvent,
+		proof_format_version: ProofFormatVersion::V1,
+		bitcoin_rejection_grace_polls: 0,
+		sbtc_wallet_address_override: None,
+		contract_confirmation_depth: 0,
+		stacks_fee_bump_blocks: 10,
+		stacks_chain_id: None,
+		rescan_depth: 0,
+		additional_bitcoin_node_urls: vec![],
+		clarity_version: ClarityVersion::default(),
+		operator_tag: vec![],
+		retry_policy: RetryPolicy::default(),
+		event_stream_stdout: false,
+		stacks_read_node_url: "http://localhost:20443".parse().unwrap(),
+		contract_function_names: ContractFunctionNames::default(),
+		max_stacks_burn_block_lag: 6,
+		mint_memo: false,
+		fulfillment_fee_rate: 1.0,
+		bitcoin_fee_bump_blocks: 6,
+		stacks_fee_multiplier: 100,
+		max_stacks_fee: 10_000_000,
+		bitcoin_backend: Default::default(),
+		block_polling_interval_ms: 5_000,
+		stacks_rejection_retries: 3,
+		stx_transaction_delay_blocks: 1,
+		compact_event_log: false,
+		max_concurrent_broadcasts: 8,
+		bitcoin_fee_rate_floor: 1,
+		metrics_bind_addr: None,
+	}
+}
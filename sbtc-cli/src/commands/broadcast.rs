@@ -1,5 +1,3 @@
-use std::io::stdout;
-
 use bdk::{
 	bitcoin::{psbt::serialize::Deserialize, Transaction},
 	electrum_client::ElectrumApi,
@@ -7,6 +5,8 @@ use bdk::{
 use clap::Parser;
 use url::Url;
 
+use crate::commands::utils::{to_stdout, OutputFormat};
+
 #[derive(Parser, Debug, Clone)]
 pub struct BroadcastArgs {
 	/// Where to broadcast the transaction
@@ -16,13 +16,16 @@ pub struct BroadcastArgs {
 	tx: String,
 }
 
-pub fn broadcast_tx(broadcast: &BroadcastArgs) -> anyhow::Result<()> {
+pub fn broadcast_tx(
+	broadcast: &BroadcastArgs,
+	format: OutputFormat,
+) -> anyhow::Result<()> {
 	let client =
 		bdk::electrum_client::Client::new(broadcast.node_url.as_str())?;
 	let tx = Transaction::deserialize(&hex::decode(&broadcast.tx)?)?;
 
 	client.transaction_broadcast(&tx)?;
-	serde_json::to_writer_pretty(stdout(), &tx.txid().to_string())?;
+	to_stdout(format, &tx.txid().to_string())?;
 
 	Ok(())
 }
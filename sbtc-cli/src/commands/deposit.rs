@@ -1,9 +1,11 @@
-use std::{io::stdout, str::FromStr};
+use std::{
+	io::{stdout, Write},
+	str::FromStr,
+};
 
 use bdk::{
 	bitcoin::{
-		psbt::serialize::Serialize, Address as BitcoinAddress,
-		Network as BitcoinNetwork, PrivateKey,
+		Address as BitcoinAddress, Network as BitcoinNetwork, PrivateKey,
 	},
 	blockchain::{
 		ConfigurableBlockchain, ElectrumBlockchain, ElectrumBlockchainConfig,
@@ -13,11 +15,16 @@ use bdk::{
 	SyncOptions, Wallet,
 };
 use clap::Parser;
-use sbtc_core::operations::op_return::deposit::build_deposit_transaction;
+use sbtc_core::operations::op_return::deposit::{
+	build_deposit_transaction, create_outputs, create_psbt,
+};
 use stacks_core::utils::PrincipalData;
 use url::Url;
 
-use crate::commands::utils;
+use crate::commands::{
+	estimate_fee::dry_run,
+	utils::{write_psbt, write_transaction, OutputFormat, TransactionOutput},
+};
 
 #[derive(Parser, Debug, Clone)]
 pub struct DepositArgs {
@@ -44,9 +51,26 @@ pub struct DepositArgs {
 	/// Bitcoin address of the sbtc wallet
 	#[clap(short, long)]
 	sbtc_wallet: String,
+
+	/// Optional memo attached to the deposit
+	#[clap(long)]
+	memo: Option<String>,
+
+	/// What to print: JSON with the signed transaction, only its hex, or the
+	/// unsigned transaction as a PSBT
+	#[clap(long, value_enum, default_value_t)]
+	output: TransactionOutput,
+
+	/// Print the fee, size and sBTC wallet dust limit of the transaction
+	/// instead of the transaction
+	#[clap(long)]
+	dry_run: bool,
 }
 
-pub fn build_deposit_tx(deposit: &DepositArgs) -> anyhow::Result<()> {
+pub fn build_deposit_tx(
+	deposit: &DepositArgs,
+	format: OutputFormat,
+) -> anyhow::Result<()> {
 	let private_key = PrivateKey::from_wif(&deposit.wif)?;
 
 	let blockchain =
@@ -68,24 +92,157 @@ pub fn build_deposit_tx(deposit: &DepositArgs) -> anyhow::Result<()> {
 
 	wallet.sync(&blockchain, SyncOptions::default())?;
 
+	write_deposit(stdout(), wallet, deposit, format)
+}
+
+/// Writes the deposit built from the synced wallet in the requested output
+fn write_deposit<W: Write>(
+	writer: W,
+	wallet: Wallet<MemoryDatabase>,
+	deposit: &DepositArgs,
+	format: OutputFormat,
+) -> anyhow::Result<()> {
 	let stx_recipient = PrincipalData::try_from(deposit.recipient.to_string())?;
 	let sbtc_wallet_address = BitcoinAddress::from_str(&deposit.sbtc_wallet)?;
 
+	let memo = deposit.memo.as_deref().unwrap_or_default().as_bytes();
+
+	if deposit.dry_run {
+		let outputs = create_outputs(
+			stx_recipient,
+			&sbtc_wallet_address,
+			deposit.amount,
+			deposit.network,
+			memo,
+		)?;
+
+		return Ok(format.write(
+			writer,
+			&dry_run(&wallet, &outputs, &sbtc_wallet_address)?,
+		)?);
+	}
+
+	if deposit.output == TransactionOutput::Psbt {
+		let psbt = create_psbt(
+			&wallet,
+			stx_recipient,
+			&sbtc_wallet_address,
+			deposit.amount,
+			deposit.network,
+			memo,
+		)?;
+
+		return write_psbt(writer, &psbt);
+	}
+
 	let tx = build_deposit_transaction(
 		wallet,
 		stx_recipient,
 		sbtc_wallet_address,
 		deposit.amount,
 		deposit.network,
+		memo,
 	)?;
 
-	serde_json::to_writer_pretty(
-		stdout(),
-		&utils::TransactionData {
-			id: tx.txid().to_string(),
-			hex: hex::encode(tx.serialize()),
-		},
-	)?;
+	write_transaction(writer, deposit.output, format, &tx)
+}
 
-	Ok(())
+#[cfg(test)]
+mod tests {
+	use bdk::{
+		bitcoin::{
+			consensus::deserialize, psbt::PartiallySignedTransaction,
+			Transaction,
+		},
+		wallet::AddressIndex,
+	};
+	use sbtc_core::test_utils;
+
+	use super::*;
+
+	const WIF: &str = "cN9spWsvaxA8taS7DFMxnk1yJD2gaF2PX1npuTpy3vuZFJdwavaw";
+
+	/// Wallet holding a single confirmed UTXO of 100,000 sats
+	fn funded_wallet() -> Wallet<MemoryDatabase> {
+		test_utils::funded_wallet(
+			P2Wpkh(PrivateKey::from_wif(WIF).unwrap()),
+			BitcoinNetwork::Testnet,
+		)
+	}
+
+	/// Runs the deposit command on the funded wallet
+	fn deposit(output: &str) -> String {
+		let args = DepositArgs::parse_from([
+			"deposit",
+			"--node-url",
+			"ssl://localhost:50002",
+			"--wif",
+			WIF,
+			"--network",
+			"testnet",
+			"--recipient",
+			"ST3RBZ4TZ3EK22SZRKGFZYBCKD7WQ5B8FFRS57TT6",
+			"--amount",
+			"10000",
+			"--sbtc-wallet",
+			"tb1qwe9ddxp6v32uef2v66j00vx6wxax5zat223tms",
+			"--output",
+			output,
+		]);
+
+		let mut written = vec![];
+		write_deposit(
+			&mut written,
+			funded_wallet(),
+			&args,
+			OutputFormat::Compact,
+		)
+		.unwrap();
+
+		String::from_utf8(written).unwrap()
+	}
+
+	fn deposit_psbt() -> PartiallySignedTransaction {
+		deposit("psbt").trim_end().parse().unwrap()
+	}
+
+	#[test]
+	fn psbt_output_data_should_follow_its_output() {
+		let psbt = deposit_psbt();
+		let change_script = funded_wallet()
+			.get_address(AddressIndex::Peek(0))
+			.unwrap()
+			.script_pubkey();
+
+		assert_eq!(psbt.outputs.len(), psbt.unsigned_tx.output.len());
+		assert_eq!(
+			psbt.unsigned_tx.output.last().unwrap().script_pubkey,
+			change_script
+		);
+
+		// Only the change belongs to the wallet and carries its key origin
+		for (txout, output) in psbt.unsigned_tx.output.iter().zip(&psbt.outputs)
+		{
+			assert_eq!(
+				output.bip32_derivation.is_empty(),
+				txout.script_pubkey != change_script
+			);
+		}
+	}
+
+	#[test]
+	fn deposit_should_be_written_in_each_output_mode() {
+		let unsigned_tx = deposit_psbt().unsigned_tx;
+
+		let hex = deposit("hex");
+		let hex = hex.trim_end();
+		let tx: Transaction = deserialize(&hex::decode(hex).unwrap()).unwrap();
+		assert_eq!(tx.output, unsigned_tx.output);
+		assert!(tx.input.iter().all(|input| !input.witness.is_empty()));
+
+		assert_eq!(
+			deposit("json"),
+			format!(r#"{{"id":"{}","hex":"{}"}}"#, tx.txid(), hex)
+		);
+	}
 }
@@ -0,0 +1,350 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use bdk::{
+	bitcoin::{
+		Address as BitcoinAddress, Network as BitcoinNetwork, PrivateKey,
+		Script,
+	},
+	blockchain::{
+		ConfigurableBlockchain, ElectrumBlockchain, ElectrumBlockchainConfig,
+	},
+	database::{BatchDatabase, MemoryDatabase},
+	template::P2Wpkh,
+	FeeRate, SignOptions, SyncOptions, Wallet,
+};
+use clap::{Parser, ValueEnum};
+use sbtc_core::operations::op_return::{deposit, withdrawal_request};
+use serde::Serialize;
+use stacks_core::utils::PrincipalData;
+use url::Url;
+
+use crate::commands::utils::{to_stdout, OutputFormat};
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum OperationKind {
+	Deposit,
+	Withdraw,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct EstimateFeeArgs {
+	/// Where to fetch the wallet UTXOs from
+	#[clap(short('u'), long)]
+	node_url: Url,
+
+	/// Bitcoin WIF of the P2wPKH address paying for the transaction
+	#[clap(short, long)]
+	wif: String,
+
+	/// Bitcoin network of the transaction
+	#[clap(short, long)]
+	network: BitcoinNetwork,
+
+	/// Kind of sBTC operation to estimate
+	#[clap(short, long)]
+	kind: OperationKind,
+
+	/// The amount of sats to deposit or withdraw
+	#[clap(short, long)]
+	amount: u64,
+
+	/// Fee rate in sats per virtual byte
+	#[clap(long)]
+	fee_rate: f32,
+
+	/// Bitcoin address of the sbtc wallet
+	#[clap(short, long)]
+	sbtc_wallet: String,
+
+	/// Stacks address that will receive sBTC, required for deposits
+	#[clap(short, long)]
+	recipient: Option<String>,
+
+	/// WIF of the Stacks address that owns sBTC to be withdrawn, required
+	/// for withdrawals
+	#[clap(short, long)]
+	drawee_wif: Option<String>,
+
+	/// Bitcoin address that will receive BTC, required for withdrawals
+	#[clap(short('b'), long)]
+	payee_address: Option<String>,
+
+	/// The amount of sats to send for the fulfillment fee, required for
+	/// withdrawals
+	#[clap(short, long)]
+	fulfillment_fee: Option<u64>,
+}
+
+/// Fee and size of a transaction that was built but not broadcasted
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeEstimate {
+	pub fee: u64,
+	pub vsize: usize,
+}
+
+/// What `--dry-run` reports instead of the transaction
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct DryRun {
+	pub fee: u64,
+	pub vsize: usize,
+	/// Sats per virtual byte actually paid
+	pub fee_rate: f32,
+	/// Smallest amount the sBTC wallet output can carry
+	pub sbtc_wallet_dust: u64,
+}
+
+pub fn estimate_fee(
+	args: &EstimateFeeArgs,
+	format: OutputFormat,
+) -> anyhow::Result<()> {
+	let private_key = PrivateKey::from_wif(&args.wif)?;
+
+	let blockchain =
+		ElectrumBlockchain::from_config(&ElectrumBlockchainConfig {
+			url: args.node_url.as_str().to_string(),
+			socks5: None,
+			retry: 3,
+			timeout: Some(10),
+			stop_gap: 10,
+			validate_domain: false,
+		})?;
+
+	let wallet = Wallet::new(
+		P2Wpkh(private_key),
+		Some(P2Wpkh(private_key)),
+		args.network,
+		MemoryDatabase::default(),
+	)?;
+
+	wallet.sync(&blockchain, SyncOptions::default())?;
+
+	let sbtc_wallet_address = BitcoinAddress::from_str(&args.sbtc_wallet)?;
+
+	let outputs: Vec<(Script, u64)> = match args.kind {
+		OperationKind::Deposit => {
+			let recipient = args
+				.recipient
+				.as_ref()
+				.ok_or(anyhow!("A recipient is required for deposits"))?;
+
+			deposit::create_outputs(
+				PrincipalData::try_from(recipient.to_string())?,
+				&sbtc_wallet_address,
+				args.amount,
+				args.network,
+				&[],
+			)?
+			.to_vec()
+		}
+		OperationKind::Withdraw => {
+			let drawee_wif = args
+				.drawee_wif
+				.as_ref()
+				.ok_or(anyhow!("A drawee WIF is required for withdrawals"))?;
+			let payee_address = args.payee_address.as_ref().ok_or(anyhow!(
+				"A payee address is required for withdrawals"
+			))?;
+			let fulfillment_fee = args.fulfillment_fee.ok_or(anyhow!(
+				"A fulfillment fee is required for withdrawals"
+			))?;
+
+			withdrawal_request::create_outputs(
+				&PrivateKey::from_wif(drawee_wif)?.inner,
+				&BitcoinAddress::from_str(payee_address)?,
+				&sbtc_wallet_address,
+				args.amount,
+				fulfillment_fee,
+				args.network,
+			)?
+			.to_vec()
+		}
+	};
+
+	let estimate = estimate_outputs_fee(
+		&wallet,
+		&outputs,
+		FeeRate::from_sat_per_vb(args.fee_rate),
+	)?;
+
+	to_stdout(format, &estimate)?;
+
+	Ok(())
+}
+
+/// Builds and signs a transaction paying to the outputs without broadcasting
+/// it, reporting its fee and virtual size
+pub fn estimate_outputs_fee<D: BatchDatabase>(
+	wallet: &Wallet<D>,
+	outputs: &[(Script, u64)],
+	fee_rate: FeeRate,
+) -> anyhow::Result<FeeEstimate> {
+	let mut tx_builder = wallet.build_tx();
+
+	for (script, amount) in outputs {
+		tx_builder.add_recipient(script.clone(), *amount);
+	}
+
+	tx_builder.fee_rate(fee_rate);
+
+	let (mut psbt, details) = tx_builder.finish()?;
+
+	wallet.sign(&mut psbt, SignOptions::default())?;
+
+	let fee = details
+		.fee
+		.ok_or(anyhow!("Could not compute the transaction fee"))?;
+
+	Ok(FeeEstimate {
+		fee,
+		vsize: psbt.extract_tx().vsize(),
+	})
+}
+
+/// Builds the transaction as the deposit and withdraw commands would, with
+/// the default fee rate, and reports its fee instead of the transaction
+pub fn dry_run<D: BatchDatabase>(
+	wallet: &Wallet<D>,
+	outputs: &[(Script, u64)],
+	sbtc_wallet_address: &BitcoinAddress,
+) -> anyhow::Result<DryRun> {
+	let FeeEstimate { fee, vsize } =
+		estimate_outputs_fee(wallet, outputs, FeeRate::default())?;
+
+	Ok(DryRun {
+		fee,
+		vsize,
+		fee_rate: fee as f32 / vsize as f32,
+		sbtc_wallet_dust: sbtc_wallet_address
+			.script_pubkey()
+			.dust_value()
+			.to_sat(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use sbtc_core::test_utils;
+
+	use super::*;
+
+	const WIF: &str = "cN9spWsvaxA8taS7DFMxnk1yJD2gaF2PX1npuTpy3vuZFJdwavaw";
+
+	/// Wallet holding a single confirmed UTXO of 100,000 sats
+	fn funded_wallet() -> Wallet<MemoryDatabase> {
+		test_utils::funded_wallet(
+			P2Wpkh(PrivateKey::from_wif(WIF).unwrap()),
+			BitcoinNetwork::Testnet,
+		)
+	}
+
+	#[test]
+	fn deposit_fee_should_match_fee_rate() {
+		let wallet = funded_wallet();
+		let outputs = deposit::create_outputs(
+			PrincipalData::try_from(
+				"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50".to_string(),
+			)
+			.unwrap(),
+			&BitcoinAddress::from_str(
+				"tb1pte5zmd7qzj4hdu45lh9mmdm0nwq3z35pwnxmzkwld6y0a8g83nnq6ts2d4",
+			)
+			.unwrap(),
+			10_000,
+			BitcoinNetwork::Testnet,
+			&[],
+		)
+		.unwrap();
+
+		let estimate = estimate_outputs_fee(
+			&wallet,
+			&outputs,
+			FeeRate::from_sat_per_vb(2.0),
+		)
+		.unwrap();
+
+		// The fee is computed on the worst case signature size, which may be
+		// a byte larger than the actual one
+		assert!(estimate.fee >= 2 * estimate.vsize as u64);
+		assert!(estimate.fee <= 2 * (estimate.vsize as u64 + 1));
+
+		// Estimating doesn't spend the wallet UTXOs
+		assert_eq!(
+			estimate_outputs_fee(
+				&wallet,
+				&outputs,
+				FeeRate::from_sat_per_vb(2.0)
+			)
+			.unwrap(),
+			estimate
+		);
+	}
+
+	#[test]
+	fn higher_fee_rate_should_increase_fee() {
+		let wallet = funded_wallet();
+		let outputs = deposit::create_outputs(
+			PrincipalData::try_from(
+				"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50".to_string(),
+			)
+			.unwrap(),
+			&BitcoinAddress::from_str(
+				"tb1pte5zmd7qzj4hdu45lh9mmdm0nwq3z35pwnxmzkwld6y0a8g83nnq6ts2d4",
+			)
+			.unwrap(),
+			10_000,
+			BitcoinNetwork::Testnet,
+			&[],
+		)
+		.unwrap();
+
+		let low = estimate_outputs_fee(
+			&wallet,
+			&outputs,
+			FeeRate::from_sat_per_vb(1.0),
+		)
+		.unwrap();
+		let high = estimate_outputs_fee(
+			&wallet,
+			&outputs,
+			FeeRate::from_sat_per_vb(10.0),
+		)
+		.unwrap();
+
+		assert_eq!(low.vsize, high.vsize);
+		assert!(high.fee > low.fee);
+	}
+
+	#[test]
+	fn dry_run_should_report_fee_and_taproot_dust() {
+		let wallet = funded_wallet();
+		let sbtc_wallet_address = BitcoinAddress::from_str(
+			"tb1pte5zmd7qzj4hdu45lh9mmdm0nwq3z35pwnxmzkwld6y0a8g83nnq6ts2d4",
+		)
+		.unwrap();
+		let outputs = deposit::create_outputs(
+			PrincipalData::try_from(
+				"ST2ST2H80NP5C9SPR4ENJ1Z9CDM9PKAJVPYWPQZ50".to_string(),
+			)
+			.unwrap(),
+			&sbtc_wallet_address,
+			10_000,
+			BitcoinNetwork::Testnet,
+			&[],
+		)
+		.unwrap();
+
+		let report = dry_run(&wallet, &outputs, &sbtc_wallet_address).unwrap();
+
+		assert!(report.fee > 0);
+		assert!(report.fee_rate >= 1.0);
+		// Dust limit of P2TR outputs at the default relay fee
+		assert_eq!(report.sbtc_wallet_dust, 330);
+
+		let mut output = vec![];
+		OutputFormat::Compact.write(&mut output, &report).unwrap();
+		assert!(String::from_utf8(output)
+			.unwrap()
+			.contains(r#""sbtc_wallet_dust":330"#));
+	}
+}
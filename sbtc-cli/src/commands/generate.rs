@@ -1,6 +1,4 @@
-use std::io::stdout;
-
-use bdk::bitcoin::Network as BitcoinNetwork;
+use bdk::{bitcoin::Network as BitcoinNetwork, keys::bip39::WordCount};
 use clap::Parser;
 use serde_json::{Map, Value};
 use stacks_core::{
@@ -8,6 +6,8 @@ use stacks_core::{
 	Network as StacksNetwork,
 };
 
+use crate::commands::utils::{to_stdout, OutputFormat};
+
 #[derive(Parser, Debug, Clone)]
 pub struct GenerateArgs {
 	/// Specify how to generate the credentials
@@ -33,23 +33,20 @@ enum GenerateSubcommand {
 	Mnemonic { mnemonic: String },
 }
 
-pub fn generate(generate_args: &GenerateArgs) -> anyhow::Result<()> {
+pub fn generate(
+	generate_args: &GenerateArgs,
+	format: OutputFormat,
+) -> anyhow::Result<()> {
 	match &generate_args.subcommand {
 		GenerateSubcommand::New => {
-			let wallet = Wallet::random()?;
+			let (wallet, _) = Wallet::random(WordCount::Words24)?;
 
-			serde_json::to_writer_pretty(
-				stdout(),
-				&value_from_wallet(&wallet, generate_args),
-			)?;
+			to_stdout(format, &value_from_wallet(&wallet, generate_args))?;
 		}
 		GenerateSubcommand::Mnemonic { mnemonic } => {
 			let wallet = Wallet::new(mnemonic)?;
 
-			serde_json::to_writer_pretty(
-				stdout(),
-				&value_from_wallet(&wallet, generate_args),
-			)?;
+			to_stdout(format, &value_from_wallet(&wallet, generate_args))?;
 		}
 	};
 
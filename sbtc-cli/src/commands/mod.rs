@@ -1,5 +1,7 @@
 pub mod broadcast;
 pub mod deposit;
+pub mod estimate_fee;
 pub mod generate;
+pub mod sign;
 pub mod utils;
 pub mod withdraw;
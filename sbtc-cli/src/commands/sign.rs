@@ -0,0 +1,179 @@
+use std::{io::stdout, path::Path};
+
+use bdk::{
+	bitcoin::{psbt::PartiallySignedTransaction, PrivateKey},
+	database::MemoryDatabase,
+	template::P2Wpkh,
+	wallet::AddressIndex,
+	SignOptions, Wallet,
+};
+use clap::Parser;
+
+use crate::commands::utils::{
+	write_psbt, write_transaction, OutputFormat, TransactionOutput,
+};
+
+#[derive(Parser, Debug, Clone)]
+pub struct SignArgs {
+	/// Bitcoin WIF of the P2WPKH address spending the inputs
+	#[clap(short, long)]
+	wif: String,
+
+	/// Base64 PSBT to sign, or the path of a file containing one
+	psbt: String,
+
+	/// What to print: JSON with the signed transaction, only its hex, or the
+	/// signed PSBT
+	#[clap(long, value_enum, default_value_t)]
+	output: TransactionOutput,
+}
+
+pub fn sign_tx(sign: &SignArgs, format: OutputFormat) -> anyhow::Result<()> {
+	let private_key = PrivateKey::from_wif(&sign.wif)?;
+	let psbt = sign_psbt(private_key, read_psbt(&sign.psbt)?)?;
+
+	match sign.output {
+		TransactionOutput::Psbt => write_psbt(stdout(), &psbt),
+		output => {
+			write_transaction(stdout(), output, format, &psbt.extract_tx())
+		}
+	}
+}
+
+fn read_psbt(psbt: &str) -> anyhow::Result<PartiallySignedTransaction> {
+	if Path::new(psbt).is_file() {
+		Ok(std::fs::read_to_string(psbt)?.trim().parse()?)
+	} else {
+		Ok(psbt.trim().parse()?)
+	}
+}
+
+/// Signs and finalizes the PSBT without any network access, so that
+/// transactions can be signed on an offline machine
+fn sign_psbt(
+	private_key: PrivateKey,
+	mut psbt: PartiallySignedTransaction,
+) -> anyhow::Result<PartiallySignedTransaction> {
+	let wallet = Wallet::new(
+		P2Wpkh(private_key),
+		Some(P2Wpkh(private_key)),
+		private_key.network,
+		MemoryDatabase::default(),
+	)?;
+
+	// Caches the wallet script, which a sync would otherwise do, so that the
+	// wallet recognizes its inputs
+	wallet.get_address(AddressIndex::New)?;
+
+	if !wallet.sign(&mut psbt, SignOptions::default())? {
+		anyhow::bail!("Could not sign every input of the transaction");
+	}
+
+	Ok(psbt)
+}
+
+#[cfg(test)]
+mod tests {
+	use bdk::bitcoin::{
+		psbt::serialize::{Deserialize, Serialize},
+		secp256k1::{Secp256k1, SecretKey},
+		Address, Network, OutPoint, PackedLockTime, Transaction, TxIn, TxOut,
+	};
+	use sbtc_core::operations::op_return::deposit::create_outputs;
+	use stacks_core::utils::PrincipalData;
+
+	use super::*;
+
+	// A deposit spending an output of the private key, as built by a wallet
+	// that knows the spent transaction
+	fn deposit_psbt(private_key: PrivateKey) -> PartiallySignedTransaction {
+		let address = Address::p2wpkh(
+			&private_key.public_key(&Secp256k1::new()),
+			Network::Testnet,
+		)
+		.unwrap();
+		let funding_tx = Transaction {
+			version: 2,
+			lock_time: PackedLockTime(0),
+			input: vec![TxIn::default()],
+			output: vec![TxOut {
+				value: 10_000,
+				script_pubkey: address.script_pubkey(),
+			}],
+		};
+
+		let sbtc_wallet: Address = "tb1qwe9ddxp6v32uef2v66j00vx6wxax5zat223tms"
+			.parse()
+			.unwrap();
+		let mut outputs: Vec<TxOut> = create_outputs(
+			PrincipalData::try_from(
+				"ST3RBZ4TZ3EK22SZRKGFZYBCKD7WQ5B8FFRS57TT6".to_string(),
+			)
+			.unwrap(),
+			&sbtc_wallet,
+			1000,
+			Network::Testnet,
+			&[],
+		)
+		.unwrap()
+		.into_iter()
+		.map(|(script_pubkey, value)| TxOut {
+			value,
+			script_pubkey,
+		})
+		.collect();
+		outputs.push(TxOut {
+			value: 8_000,
+			script_pubkey: address.script_pubkey(),
+		});
+
+		let mut psbt =
+			PartiallySignedTransaction::from_unsigned_tx(Transaction {
+				version: 2,
+				lock_time: PackedLockTime(0),
+				input: vec![TxIn {
+					previous_output: OutPoint::new(funding_tx.txid(), 0),
+					..Default::default()
+				}],
+				output: outputs,
+			})
+			.unwrap();
+		psbt.inputs[0].witness_utxo = Some(funding_tx.output[0].clone());
+		psbt.inputs[0].non_witness_utxo = Some(funding_tx);
+
+		psbt
+	}
+
+	#[test]
+	fn signed_deposit_should_be_broadcastable() {
+		let private_key = PrivateKey::new(
+			SecretKey::from_slice(&[1; 32]).unwrap(),
+			Network::Testnet,
+		);
+		let psbt = deposit_psbt(private_key);
+
+		let tx = sign_psbt(private_key, read_psbt(&psbt.to_string()).unwrap())
+			.unwrap()
+			.extract_tx();
+
+		// Signature and public key
+		assert_eq!(tx.input[0].witness.len(), 2);
+		assert_eq!(tx.txid(), psbt.unsigned_tx.txid());
+		// Parsed the same way as by the broadcast command
+		assert_eq!(Transaction::deserialize(&tx.serialize()).unwrap(), tx);
+	}
+
+	#[test]
+	fn foreign_inputs_should_fail_to_sign() {
+		let psbt = deposit_psbt(PrivateKey::new(
+			SecretKey::from_slice(&[1; 32]).unwrap(),
+			Network::Testnet,
+		));
+		let other_key = PrivateKey::new(
+			SecretKey::from_slice(&[2; 32]).unwrap(),
+			Network::Testnet,
+		);
+
+		assert!(sign_psbt(other_key, psbt).is_err());
+	}
+}
@@ -1,3 +1,10 @@
+use std::io::{stdout, Write};
+
+use bdk::bitcoin::{
+	psbt::{serialize::Serialize as _, PartiallySignedTransaction},
+	Transaction,
+};
+use clap::ValueEnum;
 use serde::Serialize;
 
 #[derive(Serialize)]
@@ -5,3 +12,119 @@ pub struct TransactionData {
 	pub id: String,
 	pub hex: String,
 }
+
+/// Formatting of the JSON printed by the commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+	/// Indented over multiple lines
+	#[default]
+	Pretty,
+	/// On a single line
+	Compact,
+}
+
+impl OutputFormat {
+	pub fn from_compact_flag(compact: bool) -> Self {
+		if compact {
+			Self::Compact
+		} else {
+			Self::Pretty
+		}
+	}
+
+	pub fn write<W: Write, T: Serialize + ?Sized>(
+		self,
+		writer: W,
+		value: &T,
+	) -> serde_json::Result<()> {
+		match self {
+			Self::Pretty => serde_json::to_writer_pretty(writer, value),
+			Self::Compact => serde_json::to_writer(writer, value),
+		}
+	}
+}
+
+/// What the transaction building commands print
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransactionOutput {
+	/// JSON with the txid and hex of the signed transaction
+	#[default]
+	Json,
+	/// Only the hex of the signed transaction
+	Hex,
+	/// The unsigned transaction as a base64 PSBT
+	Psbt,
+}
+
+/// Writes a signed transaction as JSON or, when requested, as plain hex
+pub fn write_transaction<W: Write>(
+	mut writer: W,
+	output: TransactionOutput,
+	format: OutputFormat,
+	tx: &Transaction,
+) -> anyhow::Result<()> {
+	let hex = hex::encode(tx.serialize());
+
+	match output {
+		TransactionOutput::Hex => writeln!(writer, "{}", hex)?,
+		_ => format.write(
+			writer,
+			&TransactionData {
+				id: tx.txid().to_string(),
+				hex,
+			},
+		)?,
+	}
+
+	Ok(())
+}
+
+/// Writes an unsigned transaction as a base64 PSBT
+pub fn write_psbt<W: Write>(
+	mut writer: W,
+	psbt: &PartiallySignedTransaction,
+) -> anyhow::Result<()> {
+	Ok(writeln!(writer, "{}", psbt)?)
+}
+
+pub fn to_stdout<T: Serialize + ?Sized>(
+	format: OutputFormat,
+	value: &T,
+) -> anyhow::Result<()> {
+	Ok(format.write(stdout(), value)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn transaction_data() -> TransactionData {
+		TransactionData {
+			id: "id".to_string(),
+			hex: "00".to_string(),
+		}
+	}
+
+	#[test]
+	fn compact_output_should_be_single_line() {
+		let mut output = vec![];
+		OutputFormat::Compact
+			.write(&mut output, &transaction_data())
+			.unwrap();
+
+		assert_eq!(
+			String::from_utf8(output).unwrap(),
+			r#"{"id":"id","hex":"00"}"#
+		);
+	}
+
+	#[test]
+	fn pretty_output_should_be_multi_line() {
+		let mut output = vec![];
+		OutputFormat::Pretty
+			.write(&mut output, &transaction_data())
+			.unwrap();
+
+		assert_eq!(String::from_utf8(output).unwrap().lines().count(), 4);
+	}
+}
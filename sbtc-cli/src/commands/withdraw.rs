@@ -2,8 +2,7 @@ use std::{io::stdout, str::FromStr};
 
 use bdk::{
 	bitcoin::{
-		psbt::serialize::Serialize, Address as BitcoinAddress,
-		Network as BitcoinNetwork, PrivateKey,
+		Address as BitcoinAddress, Network as BitcoinNetwork, PrivateKey,
 	},
 	blockchain::{
 		ConfigurableBlockchain, ElectrumBlockchain, ElectrumBlockchainConfig,
@@ -13,9 +12,19 @@ use bdk::{
 	SyncOptions, Wallet,
 };
 use clap::Parser;
+use sbtc_core::operations::op_return::withdrawal_request::{
+	build_withdrawal_tx as build_withdrawal_request_tx, create_outputs,
+	create_psbt,
+};
 use url::Url;
 
-use crate::commands::utils::TransactionData;
+use crate::commands::{
+	estimate_fee::dry_run,
+	utils::{
+		to_stdout, write_psbt, write_transaction, OutputFormat,
+		TransactionOutput,
+	},
+};
 
 #[derive(Parser, Debug, Clone)]
 pub struct WithdrawalArgs {
@@ -51,9 +60,22 @@ pub struct WithdrawalArgs {
 	/// Bitcoin address of the sbtc wallet
 	#[clap(short, long)]
 	sbtc_wallet: String,
+
+	/// What to print: JSON with the signed transaction, only its hex, or the
+	/// unsigned transaction as a PSBT
+	#[clap(long, value_enum, default_value_t)]
+	output: TransactionOutput,
+
+	/// Print the fee, size and sBTC wallet dust limit of the transaction
+	/// instead of the transaction
+	#[clap(long)]
+	dry_run: bool,
 }
 
-pub fn build_withdrawal_tx(withdrawal: &WithdrawalArgs) -> anyhow::Result<()> {
+pub fn build_withdrawal_tx(
+	withdrawal: &WithdrawalArgs,
+	format: OutputFormat,
+) -> anyhow::Result<()> {
 	let private_key = PrivateKey::from_wif(&withdrawal.wif)?;
 
 	let blockchain =
@@ -82,23 +104,45 @@ pub fn build_withdrawal_tx(withdrawal: &WithdrawalArgs) -> anyhow::Result<()> {
 	let sbtc_wallet_bitcoin_address =
 		BitcoinAddress::from_str(&withdrawal.sbtc_wallet)?;
 
-	let tx = sbtc_core::operations::op_return::withdrawal_request::build_withdrawal_tx(
-        &wallet,
-        withdrawal.network,
-        drawee_stacks_private_key,
-        payee_bitcoin_address,
-        sbtc_wallet_bitcoin_address,
-        withdrawal.amount,
-        withdrawal.fulfillment_fee,
-    )?;
-
-	serde_json::to_writer_pretty(
-		stdout(),
-		&TransactionData {
-			id: tx.txid().to_string(),
-			hex: hex::encode(tx.serialize()),
-		},
+	if withdrawal.dry_run {
+		let outputs = create_outputs(
+			&drawee_stacks_private_key,
+			&payee_bitcoin_address,
+			&sbtc_wallet_bitcoin_address,
+			withdrawal.amount,
+			withdrawal.fulfillment_fee,
+			withdrawal.network,
+		)?;
+
+		return to_stdout(
+			format,
+			&dry_run(&wallet, &outputs, &sbtc_wallet_bitcoin_address)?,
+		);
+	}
+
+	if withdrawal.output == TransactionOutput::Psbt {
+		let psbt = create_psbt(
+			&wallet,
+			&drawee_stacks_private_key,
+			&payee_bitcoin_address,
+			&sbtc_wallet_bitcoin_address,
+			withdrawal.amount,
+			withdrawal.fulfillment_fee,
+			withdrawal.network,
+		)?;
+
+		return write_psbt(stdout(), &psbt);
+	}
+
+	let tx = build_withdrawal_request_tx(
+		&wallet,
+		withdrawal.network,
+		drawee_stacks_private_key,
+		payee_bitcoin_address,
+		sbtc_wallet_bitcoin_address,
+		withdrawal.amount,
+		withdrawal.fulfillment_fee,
 	)?;
 
-	Ok(())
+	write_transaction(stdout(), withdrawal.output, format, &tx)
 }
@@ -11,7 +11,10 @@ use clap::{Parser, Subcommand};
 use crate::commands::{
 	broadcast::{broadcast_tx, BroadcastArgs},
 	deposit::{build_deposit_tx, DepositArgs},
+	estimate_fee::{estimate_fee, EstimateFeeArgs},
 	generate::{generate, GenerateArgs},
+	sign::{sign_tx, SignArgs},
+	utils::OutputFormat,
 	withdraw::{build_withdrawal_tx, WithdrawalArgs},
 };
 
@@ -21,6 +24,10 @@ mod commands;
 struct Cli {
 	#[command(subcommand)]
 	command: Command,
+
+	/// Print JSON output on a single line instead of indented
+	#[clap(long, global = true)]
+	compact: bool,
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -28,18 +35,31 @@ enum Command {
 	Deposit(DepositArgs),
 	Withdraw(WithdrawalArgs),
 	Broadcast(BroadcastArgs),
+	EstimateFee(EstimateFeeArgs),
 	GenerateFrom(GenerateArgs),
+	Sign(SignArgs),
 }
 
 fn main() -> Result<(), anyhow::Error> {
 	let args = Cli::parse();
+	let format = OutputFormat::from_compact_flag(args.compact);
 
 	match args.command {
-		Command::Deposit(deposit_args) => build_deposit_tx(&deposit_args),
+		Command::Deposit(deposit_args) => {
+			build_deposit_tx(&deposit_args, format)
+		}
 		Command::Withdraw(withdrawal_args) => {
-			build_withdrawal_tx(&withdrawal_args)
+			build_withdrawal_tx(&withdrawal_args, format)
+		}
+		Command::Broadcast(broadcast_args) => {
+			broadcast_tx(&broadcast_args, format)
+		}
+		Command::EstimateFee(estimate_fee_args) => {
+			estimate_fee(&estimate_fee_args, format)
+		}
+		Command::GenerateFrom(generate_args) => {
+			generate(&generate_args, format)
 		}
-		Command::Broadcast(broadcast_args) => broadcast_tx(&broadcast_args),
-		Command::GenerateFrom(generate_args) => generate(&generate_args),
+		Command::Sign(sign_args) => sign_tx(&sign_args, format),
 	}
 }
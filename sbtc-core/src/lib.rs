@@ -12,6 +12,10 @@ pub mod operations;
 /// Module for an sBTC signer
 pub mod signer;
 
+/// Fixtures for tests, enabled by the `testing` feature
+#[cfg(any(test, feature = "testing"))]
+pub mod test_utils;
+
 #[derive(Error, Debug)]
 /// sBTC error type
 pub enum SBTCError {
@@ -42,6 +46,15 @@ pub enum SBTCError {
 	/// Not an sBTC operation
 	#[error("Not an sBTC operation")]
 	NotSBTCOperation,
+	#[error("HTTP error: {0}: {1}")]
+	/// HTTP error
+	HTTPError(&'static str, reqwest::Error),
+	/// A node refused to broadcast a transaction
+	#[error("Broadcast rejected: {0}")]
+	BroadcastRejected(String),
+	#[error("Sighash error: {0}: {1}")]
+	/// Sighash error
+	SighashError(&'static str, bdk::bitcoin::util::sighash::Error),
 }
 
 /// A helper type for sBTC results
@@ -44,6 +44,7 @@ pub(crate) fn magic_bytes(network: Network) -> [u8; 2] {
 	match network {
 		Network::Bitcoin => [b'X', b'2'],
 		Network::Testnet => [b'T', b'2'],
-		_ => [b'i', b'd'],
+		Network::Signet => [b'S', b'2'],
+		Network::Regtest => [b'i', b'd'],
 	}
 }
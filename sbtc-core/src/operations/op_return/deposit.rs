@@ -19,10 +19,12 @@
 //! ```text
 //! 3                                                      25 >= N <= 66          80
 //! |------------------------------------------------------------------|-----------|
-//! principal data                              extra
+//! principal data                              memo
 //! bytes
 //! ```
 //!
+//! The memo is optional and takes up whatever space the principal data leaves.
+//!
 //! There are two types of principal data:
 //!
 //! - standard (includes only principal type, address version and address hash)
@@ -46,9 +48,9 @@ use bdk::{
 	bitcoin::{
 		blockdata::{opcodes::all::OP_RETURN, script::Instruction},
 		psbt::PartiallySignedTransaction,
-		Address as BitcoinAddress, Network, PrivateKey, Transaction,
+		Address as BitcoinAddress, Network, PrivateKey, Script, Transaction,
 	},
-	database::{BatchDatabase, MemoryDatabase},
+	database::BatchDatabase,
 	SignOptions, Wallet,
 };
 use stacks_core::{codec::Codec, utils::PrincipalData};
@@ -56,7 +58,9 @@ use stacks_core::{codec::Codec, utils::PrincipalData};
 use crate::{
 	operations::{
 		magic_bytes,
-		op_return::utils::{build_op_return_script, reorder_outputs},
+		op_return::utils::{
+			build_op_return_script, reorder_outputs, MAX_OP_RETURN_DATA_LENGTH,
+		},
 		utils::setup_wallet,
 		Opcode,
 	},
@@ -70,40 +74,52 @@ pub fn build_deposit_transaction<T: BatchDatabase>(
 	sbtc_address: BitcoinAddress,
 	amount: u64,
 	network: Network,
+	memo: &[u8],
 ) -> SBTCResult<Transaction> {
-	let mut tx_builder = wallet.build_tx();
+	let mut partial_tx =
+		create_psbt(&wallet, recipient, &sbtc_address, amount, network, memo)?;
 
-	let deposit_data =
-		DepositOutputData { network, recipient }.serialize_to_vec();
-	let op_return_script = build_op_return_script(&deposit_data);
+	wallet
+		.sign(&mut partial_tx, SignOptions::default())
+		.map_err(|err| {
+			SBTCError::BDKError("Could not sign the transaction", err)
+		})?;
 
-	let sbtc_wallet_script = sbtc_address.script_pubkey();
-	let dust_amount = sbtc_wallet_script.dust_value().to_sat();
+	Ok(partial_tx.extract_tx())
+}
 
-	if amount < dust_amount {
-		return Err(SBTCError::AmountInsufficient(amount, dust_amount));
+/// Generates the outputs for the deposit transaction, with an optional memo
+/// after the recipient
+pub fn create_outputs(
+	recipient: PrincipalData,
+	sbtc_address: &BitcoinAddress,
+	amount: u64,
+	network: Network,
+	memo: &[u8],
+) -> SBTCResult<[(Script, u64); 2]> {
+	let deposit_data = DepositOutputData {
+		network,
+		recipient,
+		memo: memo.to_vec(),
 	}
+	.serialize_to_vec();
 
-	let outputs = [(op_return_script, 0), (sbtc_wallet_script, amount)];
-
-	for (script, amount) in outputs.clone() {
-		tx_builder.add_recipient(script, amount);
+	if deposit_data.len() > MAX_OP_RETURN_DATA_LENGTH {
+		return Err(SBTCError::MalformedData(
+			"Deposit memo does not fit in the OP_RETURN output",
+		));
 	}
 
-	let (mut partial_tx, _) = tx_builder.finish().map_err(|err| {
-		SBTCError::BDKError("Could not finish the transaction", err)
-	})?;
+	let op_return_script = build_op_return_script(&deposit_data);
 
-	partial_tx.unsigned_tx.output =
-		reorder_outputs(partial_tx.unsigned_tx.output, outputs);
+	let sbtc_wallet_script = sbtc_address.script_pubkey();
+	let dust_amount = sbtc_wallet_script.dust_value().to_sat();
 
-	wallet
-		.sign(&mut partial_tx, SignOptions::default())
-		.map_err(|err| {
-			SBTCError::BDKError("Could not sign the transaction", err)
-		})?;
+	if amount < dust_amount {
+		return Err(SBTCError::AmountInsufficient(amount, dust_amount));
+	}
 
-	Ok(partial_tx.extract_tx())
+	Ok([(op_return_script, 0), (sbtc_wallet_script, amount)])
 }
 
 #[derive(Debug, Clone)]
@@ -117,6 +133,8 @@ pub struct Deposit {
 	pub sbtc_wallet_address: BitcoinAddress,
 	/// Network which the transaction is on
 	pub network: Network,
+	/// Memo attached to the deposit, empty if there is none
+	pub memo: Vec<u8>,
 }
 
 impl Deposit {
@@ -147,6 +165,10 @@ impl Deposit {
 		let deposit_data = DepositOutputData::codec_deserialize(&mut data)
 			.map_err(|_| DepositParseError::NotSbtcOp)?;
 
+		if deposit_data.network != network {
+			return Err(DepositParseError::NotSbtcOp);
+		}
+
 		let amount_output = output_iter
 			.next()
 			.ok_or(DepositParseError::InvalidOutputs)?;
@@ -160,6 +182,7 @@ impl Deposit {
 			recipient: deposit_data.recipient,
 			sbtc_wallet_address: address,
 			network,
+			memo: deposit_data.memo,
 		})
 	}
 }
@@ -187,13 +210,16 @@ pub struct DepositOutputData {
 	network: Network,
 	/// Recipient of the deposit
 	recipient: PrincipalData,
+	/// Memo following the recipient
+	memo: Vec<u8>,
 }
 
 impl Codec for DepositOutputData {
 	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
 		dest.write_all(&magic_bytes(self.network))?;
 		dest.write_all(&[Opcode::Deposit as u8])?;
-		self.recipient.codec_serialize(dest)
+		self.recipient.codec_serialize(dest)?;
+		dest.write_all(&self.memo)
 	}
 
 	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
@@ -233,30 +259,30 @@ impl Codec for DepositOutputData {
 
 		let recipient = PrincipalData::codec_deserialize(data)?;
 
-		Ok(Self { network, recipient })
+		let mut memo = vec![];
+		data.read_to_end(&mut memo)?;
+
+		Ok(Self {
+			network,
+			recipient,
+			memo,
+		})
 	}
 }
 
-fn create_partially_signed_deposit_transaction(
-	wallet: &Wallet<MemoryDatabase>,
+/// Construct a deposit partially signed transaction, before any signing
+pub fn create_psbt<D: BatchDatabase>(
+	wallet: &Wallet<D>,
 	recipient: PrincipalData,
 	sbtc_address: &BitcoinAddress,
 	amount: u64,
 	network: Network,
+	memo: &[u8],
 ) -> SBTCResult<PartiallySignedTransaction> {
 	let mut tx_builder = wallet.build_tx();
 
-	let deposit_data =
-		DepositOutputData { network, recipient }.serialize_to_vec();
-	let op_return_script = build_op_return_script(&deposit_data);
-	let sbtc_wallet_script = sbtc_address.script_pubkey();
-	let dust_amount = sbtc_wallet_script.dust_value().to_sat();
-
-	if amount < dust_amount {
-		return Err(SBTCError::AmountInsufficient(amount, dust_amount));
-	}
-
-	let outputs = [(op_return_script, 0), (sbtc_wallet_script, amount)];
+	let outputs =
+		create_outputs(recipient, sbtc_address, amount, network, memo)?;
 
 	for (script, amount) in outputs.clone() {
 		tx_builder.add_recipient(script, amount);
@@ -269,8 +295,7 @@ fn create_partially_signed_deposit_transaction(
 		)
 	})?;
 
-	partial_tx.unsigned_tx.output =
-		reorder_outputs(partial_tx.unsigned_tx.output, outputs);
+	reorder_outputs(&mut partial_tx, outputs);
 
 	Ok(partial_tx)
 }
@@ -281,15 +306,17 @@ pub fn deposit(
 	recipient: PrincipalData,
 	amount: u64,
 	sbtc_address: &BitcoinAddress,
+	memo: &[u8],
 ) -> SBTCResult<Transaction> {
 	let wallet = setup_wallet(depositor_private_key)?;
 
-	let mut psbt = create_partially_signed_deposit_transaction(
+	let mut psbt = create_psbt(
 		&wallet,
 		recipient,
 		sbtc_address,
 		amount,
 		depositor_private_key.network,
+		memo,
 	)?;
 
 	wallet
@@ -303,7 +330,9 @@ pub fn deposit(
 
 #[cfg(test)]
 mod tests {
-	use bdk::bitcoin::secp256k1::Secp256k1;
+	use std::str::FromStr;
+
+	use bdk::bitcoin::{secp256k1::Secp256k1, PackedLockTime, TxOut};
 	use rand::{distributions::Alphanumeric, rngs::StdRng, Rng, SeedableRng};
 	use stacks_core::{
 		address::{AddressVersion, StacksAddress},
@@ -387,6 +416,7 @@ mod tests {
 			let expected_data = DepositOutputData {
 				network: Network::Testnet,
 				recipient,
+				memo: vec![],
 			};
 
 			let serialized_data = expected_data.serialize_to_vec();
@@ -411,7 +441,14 @@ mod tests {
                 given_tx_hex: "010000000001019131d69f4616c2a17f3d2519a3dc697136a56846794e677982f565f79295e0370100000000feffffff0300000000000000001b6a1954323c051af0bf935f1ba62167f89c1fff2d9369f972ad0f7e6e0a020000000000225120b85fdda4ae0f69883280360a9b91555a2f23c5b9e34173fabec5d903416c2aaf7b850800000000001600147c969cfcab0d2ad171aa3f201c94b51b0e8eca6602473044022036663b723c79333f9c8b7d5d9db3b6cd301fc6bf82515e62303713eb69b4d18d0220548939af6e1d86fcf8a54da1f6942f25f36ed0488a0d3616c47daa49f59bc7b601210215bd6d522931e602fde924571eb472bc1db953484b29ba6542774ebbf083412329c62500",
                 expected_amount: 133742,
                 expected_recipient: recipient.clone(),
-            }
+                expected_memo: b"",
+            },
+            DepositParseScenario {
+                given_tx_hex: "010000000001019131d69f4616c2a17f3d2519a3dc697136a56846794e677982f565f79295e0370100000000feffffff030000000000000000246a2254323c051af0bf935f1ba62167f89c1fff2d9369f972ad0f7e6e73627463206d656d6f0a020000000000225120b85fdda4ae0f69883280360a9b91555a2f23c5b9e34173fabec5d903416c2aaf7b850800000000001600147c969cfcab0d2ad171aa3f201c94b51b0e8eca6602473044022036663b723c79333f9c8b7d5d9db3b6cd301fc6bf82515e62303713eb69b4d18d0220548939af6e1d86fcf8a54da1f6942f25f36ed0488a0d3616c47daa49f59bc7b601210215bd6d522931e602fde924571eb472bc1db953484b29ba6542774ebbf083412329c62500",
+                expected_amount: 133742,
+                expected_recipient: recipient.clone(),
+                expected_memo: b"sbtc memo",
+            },
         ];
 
 		for assertion in assertions {
@@ -419,10 +456,118 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn deposit_parse_should_expose_the_memo() {
+		let mut rng = test_rng();
+		let recipient = generate_contract_principal_data(&mut rng);
+		let sbtc_address = BitcoinAddress::from_str(
+			"tb1pte5zmd7qzj4hdu45lh9mmdm0nwq3z35pwnxmzkwld6y0a8g83nnq6ts2d4",
+		)
+		.unwrap();
+
+		let outputs = create_outputs(
+			recipient.clone(),
+			&sbtc_address,
+			10_000,
+			Network::Testnet,
+			b"invoice 42",
+		)
+		.unwrap();
+		let tx = Transaction {
+			version: 2,
+			lock_time: PackedLockTime::ZERO,
+			input: vec![],
+			output: outputs
+				.into_iter()
+				.map(|(script_pubkey, value)| TxOut {
+					value,
+					script_pubkey,
+				})
+				.collect(),
+		};
+
+		let deposit = Deposit::parse(Network::Testnet, tx).unwrap();
+
+		assert_eq!(deposit.amount, 10_000);
+		assert_eq!(deposit.recipient, recipient);
+		assert_eq!(deposit.memo, b"invoice 42");
+	}
+
+	#[test]
+	fn deposit_parse_should_reject_magic_bytes_of_another_network() {
+		let recipient = generate_standard_principal_data(&mut test_rng());
+		let sbtc_address = BitcoinAddress::from_str(
+			"tb1pte5zmd7qzj4hdu45lh9mmdm0nwq3z35pwnxmzkwld6y0a8g83nnq6ts2d4",
+		)
+		.unwrap();
+
+		let deposit_tx = |network| Transaction {
+			version: 2,
+			lock_time: PackedLockTime::ZERO,
+			input: vec![],
+			output: create_outputs(
+				recipient.clone(),
+				&sbtc_address,
+				10_000,
+				network,
+				&[],
+			)
+			.unwrap()
+			.into_iter()
+			.map(|(script_pubkey, value)| TxOut {
+				value,
+				script_pubkey,
+			})
+			.collect(),
+		};
+
+		assert_eq!(
+			Deposit::parse(Network::Bitcoin, deposit_tx(Network::Regtest))
+				.unwrap_err(),
+			DepositParseError::NotSbtcOp
+		);
+		assert_eq!(
+			Deposit::parse(Network::Signet, deposit_tx(Network::Regtest))
+				.unwrap_err(),
+			DepositParseError::NotSbtcOp
+		);
+		assert!(
+			Deposit::parse(Network::Regtest, deposit_tx(Network::Regtest))
+				.is_ok()
+		);
+	}
+
+	#[test]
+	fn should_reject_memo_overflowing_op_return() {
+		let recipient = generate_standard_principal_data(&mut test_rng());
+		let sbtc_address = BitcoinAddress::from_str(
+			"tb1pte5zmd7qzj4hdu45lh9mmdm0nwq3z35pwnxmzkwld6y0a8g83nnq6ts2d4",
+		)
+		.unwrap();
+
+		// Magic bytes, opcode and a standard principal take 25 bytes
+		let create_outputs_with_memo_length = |length| {
+			create_outputs(
+				recipient.clone(),
+				&sbtc_address,
+				10_000,
+				Network::Testnet,
+				&vec![0; length],
+			)
+		};
+
+		assert!(create_outputs_with_memo_length(55).is_ok());
+		assert!(matches!(
+			create_outputs_with_memo_length(56),
+			Err(SBTCError::MalformedData(_))
+		));
+	}
+
 	struct DepositParseScenario {
 		given_tx_hex: &'static str,
 		expected_amount: u64,
 		expected_recipient: PrincipalData,
+		expected_memo: &'static [u8],
 	}
 
 	impl DepositParseScenario {
@@ -435,6 +580,7 @@ mod tests {
 
 			assert_eq!(deposit.amount, self.expected_amount);
 			assert_eq!(deposit.recipient, self.expected_recipient);
+			assert_eq!(deposit.memo, self.expected_memo);
 		}
 	}
 }
@@ -1,5 +1,6 @@
 //! Primitives for sBTC OP_RETURN transactions
 pub mod deposit;
 pub mod utils;
+pub mod wallet_handoff;
 pub mod withdrawal_fulfillment;
 pub mod withdrawal_request;
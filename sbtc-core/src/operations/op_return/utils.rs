@@ -1,12 +1,16 @@
 //! Utilities for sBTC OP_RETURN transactions
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::HashMap;
 
 use bdk::bitcoin::{
 	blockdata::{opcodes::all::OP_RETURN, script::Builder},
+	psbt::{Output, PartiallySignedTransaction},
 	Script, TxOut,
 };
 
+/// Maximum length of the data pushed by an OP_RETURN output to be relayed
+pub const MAX_OP_RETURN_DATA_LENGTH: usize = 80;
+
 /// Builds an OP_RETURN script from the provided data
 pub(crate) fn build_op_return_script(data: &[u8]) -> Script {
 	Builder::new()
@@ -15,28 +19,31 @@ pub(crate) fn build_op_return_script(data: &[u8]) -> Script {
 		.into_script()
 }
 
-/// Reorders outputs according to the provided order
+/// Reorders the outputs of a partially signed transaction according to the
+/// provided order, keeping the PSBT data of each output with it. Outputs that
+/// are not in the order, like the change, go last.
 pub fn reorder_outputs(
-	outputs: impl IntoIterator<Item = TxOut>,
+	psbt: &mut PartiallySignedTransaction,
 	order: impl IntoIterator<Item = (Script, u64)>,
-) -> Vec<TxOut> {
+) {
 	let indices: HashMap<(Script, u64), usize> = order
 		.into_iter()
 		.enumerate()
 		.map(|(idx, val)| (val, idx))
 		.collect();
 
-	let outputs_ordered: BTreeMap<usize, TxOut> = outputs
-		.into_iter()
-		.map(|txout| {
-			(
-				*indices
-					.get(&(txout.script_pubkey.clone(), txout.value))
-					.unwrap_or(&usize::MAX), // Change amount
-				txout,
-			)
-		})
+	let mut outputs: Vec<(TxOut, Output)> = psbt
+		.unsigned_tx
+		.output
+		.drain(..)
+		.zip(psbt.outputs.drain(..))
 		.collect();
 
-	outputs_ordered.into_values().collect()
+	outputs.sort_by_key(|(txout, _)| {
+		*indices
+			.get(&(txout.script_pubkey.clone(), txout.value))
+			.unwrap_or(&usize::MAX) // Change amount
+	});
+
+	(psbt.unsigned_tx.output, psbt.outputs) = outputs.into_iter().unzip();
 }
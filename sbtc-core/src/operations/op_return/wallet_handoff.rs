@@ -0,0 +1,418 @@
+//! Tools for the construction and parsing of the sBTC OP_RETURN wallet handoff
+//! transactions.
+//!
+//! Wallet handoff is a Bitcoin transaction moving the funds of the current
+//! sBTC wallet to a new one, with the output structure as below:
+//!
+//! 1. data output
+//! 2. payment to the new sbtc wallet address
+//!
+//! The data output should contain data in the following byte format:
+//!
+//! ```text
+//! 0     2  3                                  35
+//! |-----|--|----------------------------------|
+//! magic op      new wallet output key
+//! ```
+//!
+//! Where the new wallet output key is the 32 byte x-only taproot output key of
+//! the new sBTC wallet address.
+use std::{collections::HashMap, io};
+
+use bdk::{
+	bitcoin::{
+		blockdata::{opcodes::all::OP_RETURN, script::Instruction},
+		schnorr::TweakedPublicKey,
+		secp256k1::XOnlyPublicKey,
+		Address as BitcoinAddress, Network, Script, Transaction,
+	},
+	database::BatchDatabase,
+	SignOptions, Wallet,
+};
+use stacks_core::codec::Codec;
+
+use crate::{
+	operations::{
+		magic_bytes,
+		op_return::utils::{build_op_return_script, reorder_outputs},
+		Opcode,
+	},
+	SBTCError, SBTCResult,
+};
+
+/// Builds a complete wallet handoff transaction, moving all the funds of the
+/// wallet to the new sBTC wallet address
+pub fn build_wallet_handoff_transaction<T: BatchDatabase>(
+	wallet: &Wallet<T>,
+	new_sbtc_address: &BitcoinAddress,
+	network: Network,
+) -> SBTCResult<Transaction> {
+	let data_output = (
+		build_op_return_script(
+			&WalletHandoffOutputData::new(network, new_sbtc_address)?
+				.serialize_to_vec(),
+		),
+		0,
+	);
+
+	let mut tx_builder = wallet.build_tx();
+
+	tx_builder
+		.add_recipient(data_output.0.clone(), data_output.1)
+		.drain_wallet()
+		.drain_to(new_sbtc_address.script_pubkey());
+
+	let (mut partial_tx, _) = tx_builder.finish().map_err(|err| {
+		SBTCError::BDKError("Could not finish the transaction", err)
+	})?;
+
+	// The drained payment has no known amount and goes after the data output
+	reorder_outputs(&mut partial_tx, [data_output]);
+
+	wallet
+		.sign(&mut partial_tx, SignOptions::default())
+		.map_err(|err| {
+			SBTCError::BDKError("Could not sign the transaction", err)
+		})?;
+
+	Ok(partial_tx.extract_tx())
+}
+
+/// Generates the outputs for the wallet handoff transaction
+pub fn create_outputs(
+	new_sbtc_address: &BitcoinAddress,
+	amount: u64,
+	network: Network,
+) -> SBTCResult<[(Script, u64); 2]> {
+	let handoff_data = WalletHandoffOutputData::new(network, new_sbtc_address)?
+		.serialize_to_vec();
+	let op_return_script = build_op_return_script(&handoff_data);
+
+	let sbtc_wallet_script = new_sbtc_address.script_pubkey();
+	let dust_amount = sbtc_wallet_script.dust_value().to_sat();
+
+	if amount < dust_amount {
+		return Err(SBTCError::AmountInsufficient(amount, dust_amount));
+	}
+
+	Ok([(op_return_script, 0), (sbtc_wallet_script, amount)])
+}
+
+#[derive(Debug, Clone)]
+/// The funds handed off to a new sBTC wallet
+pub struct WalletHandoff {
+	/// Amount of BTC moved to the new wallet
+	pub amount: u64,
+	/// The address of the new sBTC wallet
+	pub new_sbtc_wallet_address: BitcoinAddress,
+	/// Network which the transaction is on
+	pub network: Network,
+}
+
+impl WalletHandoff {
+	/// Parse a wallet handoff from a transaction
+	pub fn parse(
+		network: Network,
+		tx: Transaction,
+	) -> Result<Self, WalletHandoffParseError> {
+		let mut output_iter = tx.output.into_iter();
+
+		let data_output = output_iter
+			.next()
+			.ok_or(WalletHandoffParseError::InvalidOutputs)?;
+
+		let mut instructions_iter = data_output.script_pubkey.instructions();
+
+		let Some(Ok(Instruction::Op(OP_RETURN))) = instructions_iter.next()
+		else {
+			return Err(WalletHandoffParseError::NotSbtcOp);
+		};
+
+		let Some(Ok(Instruction::PushBytes(mut data))) =
+			instructions_iter.next()
+		else {
+			return Err(WalletHandoffParseError::NotSbtcOp);
+		};
+
+		let handoff_data =
+			WalletHandoffOutputData::codec_deserialize(&mut data)
+				.map_err(|_| WalletHandoffParseError::NotSbtcOp)?;
+
+		if handoff_data.network != network {
+			return Err(WalletHandoffParseError::NotSbtcOp);
+		}
+
+		let new_sbtc_wallet_address =
+			handoff_data.new_sbtc_wallet_address(network);
+
+		let amount_output = output_iter
+			.next()
+			.ok_or(WalletHandoffParseError::InvalidOutputs)?;
+
+		if amount_output.script_pubkey
+			!= new_sbtc_wallet_address.script_pubkey()
+		{
+			return Err(WalletHandoffParseError::PaymentMismatch);
+		}
+
+		Ok(Self {
+			amount: amount_output.value,
+			new_sbtc_wallet_address,
+			network,
+		})
+	}
+}
+
+#[derive(thiserror::Error, Clone, Debug, Eq, PartialEq)]
+/// Errors occuring when parsing wallet handoffs
+pub enum WalletHandoffParseError {
+	/// Missing expected output
+	#[error("Missing an expected output")]
+	InvalidOutputs,
+
+	/// Doesn't contain an OP_RETURN with the right opcode
+	#[error("Not an sBTC operation")]
+	NotSbtcOp,
+
+	/// The funds are not paid to the new wallet in the data output
+	#[error("The payment does not go to the new sBTC wallet")]
+	PaymentMismatch,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+/// Data for the sBTC OP_RETURN wallet handoff transaction output
+pub struct WalletHandoffOutputData {
+	/// Network to be used for the transaction
+	network: Network,
+	/// Taproot output key of the new sBTC wallet
+	new_wallet_output_key: XOnlyPublicKey,
+}
+
+impl WalletHandoffOutputData {
+	/// Creates the data for a handoff to the taproot address of the new wallet
+	pub fn new(
+		network: Network,
+		new_sbtc_address: &BitcoinAddress,
+	) -> SBTCResult<Self> {
+		let script = new_sbtc_address.script_pubkey();
+
+		if !script.is_v1_p2tr() {
+			return Err(SBTCError::MalformedData(
+				"The new sBTC wallet address should be a taproot address",
+			));
+		}
+
+		// The script is the witness version and length followed by the key
+		let new_wallet_output_key =
+			XOnlyPublicKey::from_slice(&script.as_bytes()[2..]).map_err(
+				|err| SBTCError::SECPError("Invalid taproot output key", err),
+			)?;
+
+		Ok(Self {
+			network,
+			new_wallet_output_key,
+		})
+	}
+
+	/// Address of the new sBTC wallet on the given network
+	pub fn new_sbtc_wallet_address(&self, network: Network) -> BitcoinAddress {
+		BitcoinAddress::p2tr_tweaked(
+			TweakedPublicKey::dangerous_assume_tweaked(
+				self.new_wallet_output_key,
+			),
+			network,
+		)
+	}
+}
+
+impl Codec for WalletHandoffOutputData {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		dest.write_all(&magic_bytes(self.network))?;
+		dest.write_all(&[Opcode::WalletHandoff as u8])?;
+		dest.write_all(&self.new_wallet_output_key.serialize())
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		let mut magic_bytes_buffer = [0; 2];
+		data.read_exact(&mut magic_bytes_buffer)?;
+
+		let network_magic_bytes = [
+			Network::Bitcoin,
+			Network::Testnet,
+			Network::Signet,
+			Network::Regtest,
+		]
+		.into_iter()
+		.map(|network| (magic_bytes(network), network))
+		.collect::<HashMap<[u8; 2], Network>>();
+
+		let network = network_magic_bytes
+			.get(&magic_bytes_buffer)
+			.cloned()
+			.ok_or(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("Unknown magic bytes: {:?}", magic_bytes_buffer),
+			))?;
+
+		let opcode = Opcode::codec_deserialize(data)
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+		if !matches!(opcode, Opcode::WalletHandoff) {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!(
+					"Invalid opcode, expected wallet handoff: {:?}",
+					opcode
+				),
+			));
+		}
+
+		let mut key_buffer = [0; 32];
+		data.read_exact(&mut key_buffer)?;
+
+		let new_wallet_output_key = XOnlyPublicKey::from_slice(&key_buffer)
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+		Ok(Self {
+			network,
+			new_wallet_output_key,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+
+	use bdk::bitcoin::{
+		secp256k1::Secp256k1, PackedLockTime, PublicKey, TxOut,
+	};
+	use rand::{rngs::StdRng, SeedableRng};
+
+	use super::*;
+
+	fn test_rng() -> StdRng {
+		StdRng::seed_from_u64(0)
+	}
+
+	fn generate_output_key(rng: &mut StdRng) -> XOnlyPublicKey {
+		Secp256k1::new()
+			.generate_keypair(rng)
+			.1
+			.x_only_public_key()
+			.0
+	}
+
+	fn transaction(
+		outputs: impl IntoIterator<Item = (Script, u64)>,
+	) -> Transaction {
+		Transaction {
+			version: 2,
+			lock_time: PackedLockTime::ZERO,
+			input: vec![],
+			output: outputs
+				.into_iter()
+				.map(|(script_pubkey, value)| TxOut {
+					value,
+					script_pubkey,
+				})
+				.collect(),
+		}
+	}
+
+	#[test]
+	fn should_serialize_and_deserialize_wallet_handoff_output_data() {
+		let mut rng = test_rng();
+
+		for _ in 0..1000 {
+			let expected_data = WalletHandoffOutputData {
+				network: Network::Testnet,
+				new_wallet_output_key: generate_output_key(&mut rng),
+			};
+
+			let serialized_data = expected_data.serialize_to_vec();
+			let deserialized_data = WalletHandoffOutputData::deserialize(
+				&mut serialized_data.as_slice(),
+			)
+			.unwrap();
+
+			assert_eq!(serialized_data.len(), 35);
+			assert_eq!(deserialized_data, expected_data);
+		}
+	}
+
+	#[test]
+	fn wallet_handoff_parse_should_succeed_given_valid_outputs() {
+		let new_sbtc_address = BitcoinAddress::from_str(
+			"tb1pte5zmd7qzj4hdu45lh9mmdm0nwq3z35pwnxmzkwld6y0a8g83nnq6ts2d4",
+		)
+		.unwrap();
+
+		let outputs =
+			create_outputs(&new_sbtc_address, 100_000, Network::Testnet)
+				.unwrap();
+		let handoff =
+			WalletHandoff::parse(Network::Testnet, transaction(outputs))
+				.unwrap();
+
+		assert_eq!(handoff.amount, 100_000);
+		assert_eq!(handoff.new_sbtc_wallet_address, new_sbtc_address);
+	}
+
+	#[test]
+	fn wallet_handoff_parse_should_validate_magic_bytes_and_payment() {
+		let mut rng = test_rng();
+		let new_sbtc_address = WalletHandoffOutputData {
+			network: Network::Bitcoin,
+			new_wallet_output_key: generate_output_key(&mut rng),
+		}
+		.new_sbtc_wallet_address(Network::Bitcoin);
+
+		let outputs =
+			create_outputs(&new_sbtc_address, 100_000, Network::Bitcoin)
+				.unwrap();
+
+		assert_eq!(
+			WalletHandoff::parse(
+				Network::Testnet,
+				transaction(outputs.clone())
+			)
+			.unwrap_err(),
+			WalletHandoffParseError::NotSbtcOp
+		);
+
+		let other_address = WalletHandoffOutputData {
+			network: Network::Bitcoin,
+			new_wallet_output_key: generate_output_key(&mut rng),
+		}
+		.new_sbtc_wallet_address(Network::Bitcoin);
+
+		assert_eq!(
+			WalletHandoff::parse(
+				Network::Bitcoin,
+				transaction([
+					outputs[0].clone(),
+					(other_address.script_pubkey(), 100_000)
+				])
+			)
+			.unwrap_err(),
+			WalletHandoffParseError::PaymentMismatch
+		);
+	}
+
+	#[test]
+	fn should_reject_non_taproot_new_wallet_address() {
+		let public_key = Secp256k1::new().generate_keypair(&mut test_rng()).1;
+		let new_sbtc_address = BitcoinAddress::p2wpkh(
+			&PublicKey::new(public_key),
+			Network::Testnet,
+		)
+		.unwrap();
+
+		assert!(create_outputs(&new_sbtc_address, 100_000, Network::Testnet)
+			.is_err());
+	}
+}
@@ -15,9 +15,13 @@
 //! Where withdrawal fulfillment data should be in the following format:
 //!
 //! ```text
-//! 0                                                                             32
-//! |------------------------------------------------------------------------------|
-//! chain tip
+//! 0                                     32                              32 + N <= 77
+//! |--------------------------------------|-------------------------------|
+//! chain tip                               operator tag
+//! ```
+//!
+//! The operator tag is optional and lets operators attribute fulfillment
+//! transactions to themselves.
 
 use std::{collections::HashMap, io};
 
@@ -39,6 +43,10 @@ use crate::{
 	SBTCError, SBTCResult,
 };
 
+/// Maximum length of the operator tag, which has to fit in the OP_RETURN
+/// output next to the magic bytes, opcode and chain tip
+pub const MAX_OPERATOR_TAG_LENGTH: usize = 80 - 2 - 1 - 32;
+
 /// Construct a withdrawal fulfillment transaction
 pub fn build_withdrawal_fulfillment_tx(
 	wallet: &Wallet<impl BatchDatabase>,
@@ -46,6 +54,7 @@ pub fn build_withdrawal_fulfillment_tx(
 	bitcoin_network: BitcoinNetwork,
 	recipient_bitcoin_address: &BitcoinAddress,
 	amount: u64,
+	operator_tag: &[u8],
 ) -> SBTCResult<Transaction> {
 	let mut psbt = create_psbt(
 		wallet,
@@ -53,6 +62,7 @@ pub fn build_withdrawal_fulfillment_tx(
 		bitcoin_network,
 		recipient_bitcoin_address,
 		amount,
+		operator_tag,
 	)?;
 
 	wallet
@@ -71,12 +81,14 @@ pub fn create_psbt<D: BatchDatabase>(
 	bitcoin_network: BitcoinNetwork,
 	recipient_bitcoin_address: &BitcoinAddress,
 	amount: u64,
+	operator_tag: &[u8],
 ) -> SBTCResult<PartiallySignedTransaction> {
 	let outputs = create_outputs(
 		stacks_chain_tip,
 		bitcoin_network,
 		recipient_bitcoin_address,
 		amount,
+		operator_tag,
 	)?;
 
 	let mut tx_builder = wallet.build_tx();
@@ -92,8 +104,7 @@ pub fn create_psbt<D: BatchDatabase>(
         )
     })?;
 
-	partial_tx.unsigned_tx.output =
-		reorder_outputs(partial_tx.unsigned_tx.output, outputs);
+	reorder_outputs(&mut partial_tx, outputs);
 
 	Ok(partial_tx)
 }
@@ -104,10 +115,16 @@ pub fn create_outputs(
 	bitcoin_network: BitcoinNetwork,
 	recipient_bitcoin_address: &BitcoinAddress,
 	amount: u64,
+	operator_tag: &[u8],
 ) -> SBTCResult<[(Script, u64); 2]> {
+	if operator_tag.len() > MAX_OPERATOR_TAG_LENGTH {
+		return Err(SBTCError::MalformedData("Operator tag is too long"));
+	}
+
 	let data = ParsedWithdrawalFulfillmentData {
 		network: bitcoin_network,
 		chain_tip: stacks_chain_tip,
+		operator_tag: operator_tag.to_vec(),
 	};
 
 	let data_script = build_op_return_script(&data.serialize_to_vec());
@@ -123,13 +140,17 @@ pub struct ParsedWithdrawalFulfillmentData {
 
 	/// The chain tip block ID
 	pub chain_tip: BlockId,
+
+	/// Tag identifying the operator who broadcasted the fulfillment
+	pub operator_tag: Vec<u8>,
 }
 
 impl Codec for ParsedWithdrawalFulfillmentData {
 	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
 		dest.write_all(&magic_bytes(self.network))?;
 		dest.write_all(&[Opcode::WithdrawalFulfillment as u8])?;
-		self.chain_tip.codec_serialize(dest)
+		self.chain_tip.codec_serialize(dest)?;
+		dest.write_all(&self.operator_tag)
 	}
 
 	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
@@ -172,6 +193,88 @@ impl Codec for ParsedWithdrawalFulfillmentData {
 
 		let chain_tip = BlockId::codec_deserialize(data)?;
 
-		Ok(Self { network, chain_tip })
+		let mut operator_tag = vec![];
+		data.read_to_end(&mut operator_tag)?;
+
+		if operator_tag.len() > MAX_OPERATOR_TAG_LENGTH {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!("Operator tag is too long: {}", operator_tag.len()),
+			));
+		}
+
+		Ok(Self {
+			network,
+			chain_tip,
+			operator_tag,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::str::FromStr;
+
+	use stacks_core::uint::Uint256;
+
+	use super::*;
+
+	fn data(operator_tag: &[u8]) -> ParsedWithdrawalFulfillmentData {
+		ParsedWithdrawalFulfillmentData {
+			network: BitcoinNetwork::Testnet,
+			chain_tip: BlockId::new(Uint256::from(1337u64)),
+			operator_tag: operator_tag.to_vec(),
+		}
+	}
+
+	#[test]
+	fn should_round_trip_operator_tag() {
+		let bytes = data(b"signer-1").serialize_to_vec();
+		let parsed =
+			ParsedWithdrawalFulfillmentData::deserialize(&mut &bytes[..])
+				.unwrap();
+
+		assert_eq!(parsed.network, BitcoinNetwork::Testnet);
+		assert_eq!(
+			parsed.chain_tip.serialize_to_vec(),
+			data(b"").chain_tip.serialize_to_vec()
+		);
+		assert_eq!(parsed.operator_tag, b"signer-1");
+	}
+
+	#[test]
+	fn should_parse_fulfillment_without_operator_tag() {
+		let bytes = data(b"").serialize_to_vec();
+		let parsed =
+			ParsedWithdrawalFulfillmentData::deserialize(&mut &bytes[..])
+				.unwrap();
+
+		assert!(parsed.operator_tag.is_empty());
+	}
+
+	#[test]
+	fn should_reject_too_long_operator_tag() {
+		let recipient = BitcoinAddress::from_str(
+			"tb1pte5zmd7qzj4hdu45lh9mmdm0nwq3z35pwnxmzkwld6y0a8g83nnq6ts2d4",
+		)
+		.unwrap();
+		let chain_tip = || BlockId::new(Uint256::from(1337u64));
+
+		assert!(create_outputs(
+			chain_tip(),
+			BitcoinNetwork::Testnet,
+			&recipient,
+			1000,
+			&[0; MAX_OPERATOR_TAG_LENGTH],
+		)
+		.is_ok());
+		assert!(create_outputs(
+			chain_tip(),
+			BitcoinNetwork::Testnet,
+			&recipient,
+			1000,
+			&[0; MAX_OPERATOR_TAG_LENGTH + 1],
+		)
+		.is_err());
 	}
 }
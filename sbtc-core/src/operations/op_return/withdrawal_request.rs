@@ -124,6 +124,10 @@ pub fn try_parse_withdrawal_request(
 		WithdrawalRequestDataOutputData::codec_deserialize(&mut data)
 			.map_err(|_| SBTCError::NotSBTCOperation)?;
 
+	if withdrawal_data.network() != network {
+		return Err(SBTCError::NotSBTCOperation);
+	}
+
 	let recipient_pubkey_output =
 		output_iter.next().ok_or(SBTCError::NotSBTCOperation)?;
 
@@ -211,6 +215,27 @@ pub fn build_withdrawal_tx(
 	Ok(psbt.extract_tx())
 }
 
+/// Construct a withdrawal request transaction funded by the synced wallet
+/// of the payer, who broadcasts it, on the network of the wallet
+pub fn build(
+	payer_wallet: &Wallet<impl BatchDatabase>,
+	drawee_stacks_private_key: StacksPrivateKey,
+	payee_bitcoin_address: BitcoinAddress,
+	amount: u64,
+	fulfillment_fee: u64,
+	sbtc_wallet_bitcoin_address: BitcoinAddress,
+) -> SBTCResult<Transaction> {
+	build_withdrawal_tx(
+		payer_wallet,
+		payer_wallet.network(),
+		drawee_stacks_private_key,
+		payee_bitcoin_address,
+		sbtc_wallet_bitcoin_address,
+		amount,
+		fulfillment_fee,
+	)
+}
+
 /// Construct a withdrawal request partially signed transaction
 pub fn create_psbt<D: BatchDatabase>(
 	wallet: &Wallet<D>,
@@ -243,8 +268,7 @@ pub fn create_psbt<D: BatchDatabase>(
 		)
 	})?;
 
-	partial_tx.unsigned_tx.output =
-		reorder_outputs(partial_tx.unsigned_tx.output, outputs);
+	reorder_outputs(&mut partial_tx, outputs);
 
 	Ok(partial_tx)
 }
@@ -471,7 +495,125 @@ pub fn create_signing_message(data: impl AsRef<[u8]>) -> Message {
 // test that create signing message returns correct hash
 #[cfg(test)]
 mod tests {
+	use bdk::{bitcoin::PrivateKey, template::P2Wpkh};
+
 	use super::*;
+	use crate::test_utils::funded_wallet;
+
+	const PAYER_WIF: &str =
+		"cN9spWsvaxA8taS7DFMxnk1yJD2gaF2PX1npuTpy3vuZFJdwavaw";
+
+	#[test]
+	fn built_withdrawal_request_should_parse_back() {
+		let payer_private_key = PrivateKey::from_wif(PAYER_WIF).unwrap();
+		let drawee_private_key =
+			StacksPrivateKey::from_slice(&[1; 32]).unwrap();
+		let payee: BitcoinAddress =
+			"tb1qwe9ddxp6v32uef2v66j00vx6wxax5zat223tms"
+				.parse()
+				.unwrap();
+		let sbtc_wallet: BitcoinAddress =
+			"tb1pte5zmd7qzj4hdu45lh9mmdm0nwq3z35pwnxmzkwld6y0a8g83nnq6ts2d4"
+				.parse()
+				.unwrap();
+
+		let tx = build(
+			&funded_wallet(
+				P2Wpkh(payer_private_key),
+				payer_private_key.network,
+			),
+			drawee_private_key,
+			payee.clone(),
+			1000,
+			500,
+			sbtc_wallet.clone(),
+		)
+		.unwrap();
+
+		let request =
+			try_parse_withdrawal_request(BitcoinNetwork::Testnet, tx).unwrap();
+
+		assert_eq!(request.payee_bitcoin_address, payee);
+		assert_eq!(request.sbtc_wallet, sbtc_wallet);
+		assert_eq!(request.amount, 1000);
+		assert_eq!(request.fulfillment_amount, 500);
+		assert_eq!(
+			request.drawee_stacks_address,
+			StacksAddress::from_public_key(
+				StacksAddressVersion::TestnetSingleSig,
+				&StacksPublicKey::from_secret_key(
+					&Secp256k1::new(),
+					&drawee_private_key
+				),
+			)
+		);
+	}
+
+	#[test]
+	fn signature_should_recover_the_drawee_key() {
+		let drawee_private_key =
+			StacksPrivateKey::from_slice(&[1; 32]).unwrap();
+		let payee: BitcoinAddress =
+			"tb1qwe9ddxp6v32uef2v66j00vx6wxax5zat223tms"
+				.parse()
+				.unwrap();
+
+		let signature = create_signature(&drawee_private_key, &payee, 1000);
+
+		assert_eq!(
+			recover_signature(1000, &payee, &signature).unwrap(),
+			StacksPublicKey::from_secret_key(
+				&Secp256k1::new(),
+				&drawee_private_key
+			)
+		);
+	}
+
+	#[test]
+	fn tampered_withdrawal_request_should_not_recover_the_drawee() {
+		let drawee_private_key =
+			StacksPrivateKey::from_slice(&[1; 32]).unwrap();
+		let drawee_address = StacksAddress::from_public_key(
+			StacksAddressVersion::TestnetSingleSig,
+			&StacksPublicKey::from_secret_key(
+				&Secp256k1::new(),
+				&drawee_private_key,
+			),
+		);
+		let payee: BitcoinAddress =
+			"tb1qwe9ddxp6v32uef2v66j00vx6wxax5zat223tms"
+				.parse()
+				.unwrap();
+		let sbtc_wallet: BitcoinAddress =
+			"tb1pte5zmd7qzj4hdu45lh9mmdm0nwq3z35pwnxmzkwld6y0a8g83nnq6ts2d4"
+				.parse()
+				.unwrap();
+
+		let payer_private_key = PrivateKey::from_wif(PAYER_WIF).unwrap();
+
+		let mut tx = build_withdrawal_tx(
+			&funded_wallet(
+				P2Wpkh(payer_private_key),
+				payer_private_key.network,
+			),
+			BitcoinNetwork::Testnet,
+			drawee_private_key,
+			payee,
+			sbtc_wallet.clone(),
+			1000,
+			500,
+		)
+		.unwrap();
+
+		// Redirects the withdrawn BTC without the drawee signing it
+		tx.output[1].script_pubkey = sbtc_wallet.script_pubkey();
+
+		let request =
+			try_parse_withdrawal_request(BitcoinNetwork::Testnet, tx).unwrap();
+
+		assert_eq!(request.payee_bitcoin_address, sbtc_wallet);
+		assert_ne!(request.drawee_stacks_address, drawee_address);
+	}
 
 	#[test]
 	fn test_create_signing_message() {
@@ -0,0 +1,243 @@
+use bdk::bitcoin::{
+	consensus::serialize, Transaction as BitcoinTransaction, Txid,
+};
+use reqwest::blocking::{Client, Response};
+use serde_json::{json, Value};
+use stacks_core::{
+	codec::Codec,
+	crypto::{sha512::Sha512_256Hasher, Hashing},
+	transaction::Transaction as StacksTransaction,
+};
+use url::Url;
+
+use crate::{SBTCError, SBTCResult};
+
+/// Broadcast a Bitcoin transaction through the `sendrawtransaction` RPC of
+/// the Bitcoin node, returning the txid it reports
+pub fn broadcast_transaction_bitcoin(
+	bitcoin_node_rpc_url: &Url,
+	tx: &BitcoinTransaction,
+) -> SBTCResult<Txid> {
+	let mut request =
+		Client::new()
+			.post(bitcoin_node_rpc_url.clone())
+			.json(&json!({
+				"jsonrpc": "1.0",
+				"id": "sbtc",
+				"method": "sendrawtransaction",
+				"params": [hex::encode(serialize(tx))],
+			}));
+
+	if !bitcoin_node_rpc_url.username().is_empty() {
+		request = request.basic_auth(
+			bitcoin_node_rpc_url.username(),
+			bitcoin_node_rpc_url.password(),
+		);
+	}
+
+	let res = request
+		.send()
+		.map_err(|err| SBTCError::HTTPError("Could not reach node", err))?;
+
+	// The node reports RPC errors in the body of non-successful responses
+	let body: Value = res.json().map_err(|err| {
+		SBTCError::HTTPError("Could not parse node response", err)
+	})?;
+
+	if !body["error"].is_null() {
+		return Err(SBTCError::BroadcastRejected(body["error"].to_string()));
+	}
+
+	body["result"]
+		.as_str()
+		.and_then(|txid| txid.parse().ok())
+		.ok_or(SBTCError::MalformedData("Node did not return a txid"))
+}
+
+/// Broadcast a Stacks transaction to the `/v2/transactions` endpoint of the
+/// Stacks node, returning the txid it reports
+pub fn broadcast_transaction_stacks(
+	stacks_node_rpc_url: &Url,
+	tx: &StacksTransaction,
+) -> SBTCResult<Sha512_256Hasher> {
+	let url = stacks_node_rpc_url
+		.join("/v2/transactions")
+		.map_err(|_| SBTCError::MalformedData("Invalid Stacks node URL"))?;
+
+	let res = Client::new()
+		.post(url)
+		.header("Content-type", "application/octet-stream")
+		.body(tx.serialize_to_vec())
+		.send()
+		.map_err(|err| SBTCError::HTTPError("Could not reach node", err))?;
+
+	let txid: String = successful(res)?.json().map_err(|err| {
+		SBTCError::HTTPError("Could not parse node response", err)
+	})?;
+
+	Sha512_256Hasher::from_hex(txid.trim_start_matches("0x"))
+		.map_err(|_| SBTCError::MalformedData("Node did not return a txid"))
+}
+
+fn successful(res: Response) -> SBTCResult<Response> {
+	if res.status().is_success() {
+		return Ok(res);
+	}
+
+	let status = res.status();
+	let body = res.text().unwrap_or_default();
+
+	Err(SBTCError::BroadcastRejected(format!(
+		"{}: {}",
+		status, body
+	)))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		io::{Read, Write},
+		net::TcpListener,
+		thread::{self, JoinHandle},
+	};
+
+	use bdk::bitcoin::{hashes::Hash, PackedLockTime};
+	use stacks_core::{
+		address::{AddressVersion, StacksAddress},
+		crypto::hash160::Hash160Hasher,
+		transaction::{
+			AnchorMode, MessageSignature, PostConditionMode, PublicKeyEncoding,
+			SinglesigHashMode, SinglesigSpendingCondition, TransactionAuth,
+			TransactionPayload, TransactionSpendingCondition,
+			TransactionVersion,
+		},
+	};
+
+	use super::*;
+
+	// Answers a single request, returning the request it received
+	fn serve(status: &'static str, body: String) -> (Url, JoinHandle<String>) {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let url = format!("http://{}", listener.local_addr().unwrap());
+
+		let node = thread::spawn(move || {
+			let (mut stream, _) = listener.accept().unwrap();
+			let mut request = [0; 4096];
+			let read = stream.read(&mut request).unwrap();
+
+			write!(
+				stream,
+				"HTTP/1.1 {}\r\nconnection: close\r\ncontent-length: {}\r\n\r\n{}",
+				status,
+				body.len(),
+				body
+			)
+			.unwrap();
+
+			String::from_utf8_lossy(&request[..read]).into()
+		});
+
+		(url.parse().unwrap(), node)
+	}
+
+	fn bitcoin_transaction() -> BitcoinTransaction {
+		BitcoinTransaction {
+			version: 2,
+			lock_time: PackedLockTime(0),
+			input: vec![],
+			output: vec![],
+		}
+	}
+
+	fn stacks_transaction() -> StacksTransaction {
+		StacksTransaction {
+			version: TransactionVersion::Testnet,
+			chain_id: 0x80000000,
+			auth: TransactionAuth::Standard(
+				TransactionSpendingCondition::Singlesig(
+					SinglesigSpendingCondition {
+						hash_mode: SinglesigHashMode::P2PKH,
+						signer: Hash160Hasher::new([0; 33]),
+						nonce: 0,
+						fee: 0,
+						key_encoding: PublicKeyEncoding::Compressed,
+						signature: MessageSignature::empty(),
+					},
+				),
+			),
+			anchor_mode: AnchorMode::Any,
+			post_condition_mode: PostConditionMode::Allow,
+			post_conditions: vec![],
+			payload: TransactionPayload::TokenTransfer(
+				StacksAddress::new(
+					AddressVersion::TestnetSingleSig,
+					Hash160Hasher::new([1; 33]),
+				)
+				.into(),
+				1,
+				[0; 34],
+			),
+		}
+	}
+
+	#[test]
+	fn bitcoin_broadcast_should_return_the_node_txid() {
+		let txid = Txid::from_inner([1; 32]);
+		let (url, node) = serve(
+			"200 OK",
+			json!({ "result": txid.to_string(), "error": null, "id": "sbtc" })
+				.to_string(),
+		);
+
+		assert_eq!(
+			broadcast_transaction_bitcoin(&url, &bitcoin_transaction())
+				.unwrap(),
+			txid
+		);
+		assert!(node.join().unwrap().contains("sendrawtransaction"));
+	}
+
+	#[test]
+	fn bitcoin_rpc_errors_should_be_surfaced() {
+		let (url, _) = serve(
+			"500 Internal Server Error",
+			json!({
+				"result": null,
+				"error": { "code": -26, "message": "bad-txns-vin-empty" },
+				"id": "sbtc",
+			})
+			.to_string(),
+		);
+
+		let err = broadcast_transaction_bitcoin(&url, &bitcoin_transaction())
+			.unwrap_err();
+
+		assert!(matches!(err, SBTCError::BroadcastRejected(message)
+			if message.contains("bad-txns-vin-empty")));
+	}
+
+	#[test]
+	fn stacks_broadcast_should_return_the_node_txid() {
+		let tx = stacks_transaction();
+		let (url, node) =
+			serve("200 OK", json!(tx.txid().to_hex()).to_string());
+
+		assert_eq!(broadcast_transaction_stacks(&url, &tx).unwrap(), tx.txid());
+		assert!(node.join().unwrap().starts_with("POST /v2/transactions "));
+	}
+
+	#[test]
+	fn stacks_rejections_should_be_surfaced() {
+		let (url, _) = serve(
+			"400 Bad Request",
+			json!({ "error": "transaction rejected", "reason": "BadNonce" })
+				.to_string(),
+		);
+
+		let err = broadcast_transaction_stacks(&url, &stacks_transaction())
+			.unwrap_err();
+
+		assert!(matches!(err, SBTCError::BroadcastRejected(message)
+			if message.contains("BadNonce")));
+	}
+}
@@ -1,2 +1,10 @@
 // TODO: FIRE coordination logic
 // https://github.com/Trust-Machines/stacks-sbtc/issues/667
+//
+// Out of scope until signers can exchange DKG messages with the coordinator:
+// - a DKG round implementing `Coordinate::generate_sbtc_wallet_public_key`
+// - a signing round implementing `Coordinate::run_signing_round`, over the
+//   message given by `taproot_key_spend_sighash`
+//
+// Running every participant in-process would make the coordinator hold all
+// the secret polynomials, so neither is implemented that way.
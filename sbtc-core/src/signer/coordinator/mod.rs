@@ -6,14 +6,17 @@ pub mod roast;
 use std::collections::HashMap;
 
 use bdk::bitcoin::{
-	util::taproot::TaprootSpendInfo, PublicKey,
-	Transaction as BitcoinTransaction,
+	util::{
+		sighash::{Prevouts, SchnorrSighashType, SighashCache},
+		taproot::{TapSighashHash, TaprootSpendInfo},
+	},
+	PublicKey, Transaction as BitcoinTransaction, TxOut,
 };
 use p256k1::ecdsa;
 use wsts::{bip340::SchnorrProof, common::Signature};
 
 use super::StacksTransaction;
-use crate::SBTCResult;
+use crate::{SBTCError, SBTCResult};
 
 #[derive(Default, Clone, Debug)]
 /// Signers' public keys required for weighted distributed signing
@@ -66,15 +69,97 @@ pub trait Reveal {
 pub trait Coordinate {
 	/// Retrieve sBTC transactions from the blockchain
 	fn sbtc_transactions(&self) -> SBTCResult<Vec<SBTCTransaction>>;
-	/// Generate the sBTC wallet public key
+	/// Generate the sBTC wallet public key. No coordinator implements the
+	/// DKG round yet, see the `fire` module.
 	fn generate_sbtc_wallet_public_key(
 		&self,
 		public_keys: &PublicKeys,
 	) -> SBTCResult<PublicKey>;
-	/// Run the signing round for the transaction
+	/// Run the signing round for the transaction. No coordinator implements
+	/// the signing round yet, see the `fire` module.
 	fn run_signing_round(
 		&self,
 		public_keys: &PublicKeys,
 		tx: &BitcoinTransaction,
 	) -> SBTCResult<(Signature, SchnorrProof)>;
 }
+
+/// Sighash signed to spend the input of the transaction through the taproot
+/// key path, given the outputs spent by every input
+pub fn taproot_key_spend_sighash(
+	tx: &BitcoinTransaction,
+	input_index: usize,
+	prevouts: &[TxOut],
+) -> SBTCResult<TapSighashHash> {
+	SighashCache::new(tx)
+		.taproot_key_spend_signature_hash(
+			input_index,
+			&Prevouts::All(prevouts),
+			SchnorrSighashType::Default,
+		)
+		.map_err(|err| {
+			SBTCError::SighashError(
+				"Could not compute the taproot sighash",
+				err,
+			)
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use bdk::bitcoin::{
+		secp256k1::{KeyPair, Secp256k1},
+		PackedLockTime, Script, TxIn,
+	};
+
+	use super::*;
+
+	fn spend(prevout: &TxOut) -> BitcoinTransaction {
+		BitcoinTransaction {
+			version: 2,
+			lock_time: PackedLockTime::ZERO,
+			input: vec![TxIn::default()],
+			output: vec![TxOut {
+				value: prevout.value - 1000,
+				script_pubkey: prevout.script_pubkey.clone(),
+			}],
+		}
+	}
+
+	fn prevout(value: u64) -> TxOut {
+		let secp = Secp256k1::new();
+		let keypair = KeyPair::from_seckey_slice(&secp, &[1; 32]).unwrap();
+		let (internal_key, _) = keypair.x_only_public_key();
+
+		TxOut {
+			value,
+			script_pubkey: Script::new_v1_p2tr(&secp, internal_key, None),
+		}
+	}
+
+	#[test]
+	fn taproot_sighash_should_commit_to_the_spent_amount() {
+		let tx = spend(&prevout(10_000));
+
+		let sighash =
+			taproot_key_spend_sighash(&tx, 0, &[prevout(10_000)]).unwrap();
+		let other_sighash =
+			taproot_key_spend_sighash(&tx, 0, &[prevout(20_000)]).unwrap();
+
+		assert_ne!(sighash, other_sighash);
+	}
+
+	#[test]
+	fn taproot_sighash_should_require_every_prevout() {
+		let tx = spend(&prevout(10_000));
+
+		assert!(matches!(
+			taproot_key_spend_sighash(&tx, 0, &[]),
+			Err(SBTCError::SighashError(..))
+		));
+		assert!(matches!(
+			taproot_key_spend_sighash(&tx, 1, &[prevout(10_000)]),
+			Err(SBTCError::SighashError(..))
+		));
+	}
+}
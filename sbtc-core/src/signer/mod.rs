@@ -1,15 +1,22 @@
+/// sBTC transaction broadcasting module
+pub mod broadcast;
 /// sBTC signer configuration module
 pub mod config;
 /// sBTC coordinator module
 pub mod coordinator;
 
 use bdk::bitcoin::{
-	Address, Network, PrivateKey, PublicKey, Transaction as BitcoinTransaction,
+	hashes::Hash, secp256k1::Secp256k1, Address, Network, PrivateKey,
+	PublicKey, Transaction as BitcoinTransaction, Txid,
 };
 use p256k1::ecdsa;
+use stacks_core::crypto::sha512::Sha512_256Hasher;
+/// A Stacks transaction
+pub use stacks_core::transaction::Transaction as StacksTransaction;
 use url::Url;
 
 use crate::{
+	operations::op_return::withdrawal_request::WithdrawalRequestData,
 	signer::{
 		config::Config,
 		coordinator::{Coordinate, PublicKeys, Reveal},
@@ -17,21 +24,41 @@ use crate::{
 	SBTCError, SBTCResult,
 };
 
-/// A Stacks transaction
-/// TODO: replace with the core library's StacksTransaction
-pub struct StacksTransaction {}
-
 /// An Bitcoin transaction needing to be SIGNED by the signer
 /// TODO: update with https://github.com/Trust-Machines/stacks-sbtc/pull/595
 pub enum SignableTransaction {
 	/// A reveal transaction
 	Reveal(BitcoinTransaction),
-	/// A withdrawal fulfillment Bitcoin transaction
-	WithdrawalFulfillment(BitcoinTransaction),
+	/// A withdrawal fulfillment Bitcoin transaction and the request it
+	/// fulfills
+	WithdrawalFulfillment(BitcoinTransaction, WithdrawalRequestData),
 	/// A Bitcoin sBTC wallet handoff transaction
 	Handoff(BitcoinTransaction),
 }
 
+/// A signer's decision on a Bitcoin transaction, signed with its key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vote {
+	/// The transaction voted on
+	pub txid: Txid,
+	/// Whether the transaction is approved or denied
+	pub approved: bool,
+	/// Public key of the voting signer
+	pub public_key: PublicKey,
+	/// Signature over the vote message
+	pub signature: Vec<u8>,
+}
+
+impl Vote {
+	/// The signed message: the txid followed by 1 for an approval or 0 for
+	/// a denial
+	pub fn message(txid: Txid, approved: bool) -> Vec<u8> {
+		let mut message = txid.into_inner().to_vec();
+		message.push(approved as u8);
+		message
+	}
+}
+
 /// sBTC Keys trait for retrieving signer IDs, vote IDs, and public keys
 trait Keys {
 	/// Retrieve the current public keys for the signers and their vote ids
@@ -108,17 +135,45 @@ impl<S: Sign + Coordinate + Reveal> Signer<S> {
 	}
 
 	/// Sign approve the given transaction
-	pub fn approve(&self, _tx: &BitcoinTransaction) -> SBTCResult<()> {
-		todo!()
+	pub fn approve(&self, tx: &BitcoinTransaction) -> SBTCResult<Vote> {
+		self.vote(tx, true)
 	}
 
 	/// Sign deny the given transaction
-	pub fn deny(&self, _tx: &BitcoinTransaction) -> Result<(), SBTCError> {
-		todo!()
+	pub fn deny(&self, tx: &BitcoinTransaction) -> Result<Vote, SBTCError> {
+		self.vote(tx, false)
 	}
 
 	// Private methods
 
+	/// Sign a vote on the transaction, identified by its txid
+	fn vote(
+		&self,
+		tx: &BitcoinTransaction,
+		approved: bool,
+	) -> SBTCResult<Vote> {
+		// WIFs only tell mainnet keys apart, so a regtest or signet key is
+		// read as a testnet one
+		if (self.private_key.network == Network::Bitcoin)
+			!= (self.network == Network::Bitcoin)
+		{
+			return Err(SBTCError::MalformedData(
+				"Signer private key is for another network",
+			));
+		}
+
+		let txid = tx.txid();
+		let signature =
+			self.signer.sign_message(&Vote::message(txid, approved))?;
+
+		Ok(Vote {
+			txid,
+			approved,
+			public_key: self.private_key.public_key(&Secp256k1::new()),
+			signature,
+		})
+	}
+
 	/// Fulfill the withdrawal request using the provided address
 	fn _fulfill_withdrawal_request(
 		&self,
@@ -131,17 +186,20 @@ impl<S: Sign + Coordinate + Reveal> Signer<S> {
 	/// Broadcast the transaction to the bitcoin network
 	fn _broadcast_transaction_bitcoin(
 		&self,
-		_tx: BitcoinTransaction,
-	) -> SBTCResult<()> {
-		todo!()
+		tx: BitcoinTransaction,
+	) -> SBTCResult<Txid> {
+		broadcast::broadcast_transaction_bitcoin(
+			&self.bitcoin_node_rpc_url,
+			&tx,
+		)
 	}
 
 	/// Broadcast the transaction to the stacks network
 	fn _broadcast_transaction_stacks(
 		&self,
-		_tx: StacksTransaction,
-	) -> SBTCResult<()> {
-		todo!()
+		tx: StacksTransaction,
+	) -> SBTCResult<Sha512_256Hasher> {
+		broadcast::broadcast_transaction_stacks(&self.stacks_node_rpc_url, &tx)
 	}
 }
 
@@ -167,19 +225,285 @@ impl<S> Validator for Signer<S> {
 		&self,
 		tx: &SignableTransaction,
 	) -> SBTCResult<bool> {
-		// TODO: check all addresses involved in each transaction
+		// TODO: check the addresses of the inputs as well
+		let (SignableTransaction::Reveal(bitcoin_tx)
+		| SignableTransaction::WithdrawalFulfillment(bitcoin_tx, _)
+		| SignableTransaction::Handoff(bitcoin_tx)) = tx;
+
+		if self.pays_auto_deny_address(bitcoin_tx) {
+			return Ok(false);
+		}
+
+		// Transactions that cannot be fully checked yet are never approved
 		match tx {
-			SignableTransaction::Reveal(_tx) => {
-				// TODO: retrieve the initiator from the originator transaction
-				// to verify it is not an auto deny address
-				todo!()
-			}
-			SignableTransaction::WithdrawalFulfillment(_tx) => {
-				todo!()
-			}
-			SignableTransaction::Handoff(_tx) => {
-				todo!()
+			// TODO: retrieve the initiator from the originator transaction
+			// to verify it is not an auto deny address
+			SignableTransaction::Reveal(_) => Ok(false),
+			SignableTransaction::WithdrawalFulfillment(tx, request) => {
+				// The payment follows the data output
+				Ok(tx.output.get(1).is_some_and(|output| {
+					output.script_pubkey
+						== request.payee_bitcoin_address.script_pubkey()
+						&& output.value == request.amount
+				}))
 			}
+			// TODO: check the handoff pays the next sBTC wallet
+			SignableTransaction::Handoff(_) => Ok(false),
+		}
+	}
+}
+
+impl<S> Signer<S> {
+	fn pays_auto_deny_address(&self, tx: &BitcoinTransaction) -> bool {
+		tx.output.iter().any(|output| {
+			self.config
+				.auto_deny_addresses_btc
+				.iter()
+				.any(|address| address.script_pubkey() == output.script_pubkey)
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bdk::bitcoin::{
+		secp256k1::SecretKey, util::taproot::TaprootSpendInfo, PackedLockTime,
+		Script, TxOut,
+	};
+	use stacks_core::{
+		address::{AddressVersion, StacksAddress},
+		crypto::{
+			hash160::Hash160Hasher, Hashing, PrivateKey as StacksPrivateKey,
+		},
+	};
+	use wsts::{bip340::SchnorrProof, common::Signature};
+
+	use super::*;
+	use crate::{
+		operations::op_return::withdrawal_request::create_signature,
+		signer::coordinator::SBTCTransaction,
+	};
+
+	// Signs by prefixing the message, which is enough to check what was signed
+	struct TestSigner;
+
+	impl Sign for TestSigner {
+		fn sign_message(&self, message: &[u8]) -> SBTCResult<Vec<u8>> {
+			Ok([b"signed:".as_slice(), message].concat())
+		}
+
+		fn verify_message(
+			&self,
+			_public_key: &ecdsa::PublicKey,
+			_message: &[u8],
+		) -> SBTCResult<bool> {
+			unimplemented!()
+		}
+	}
+
+	impl Coordinate for TestSigner {
+		fn sbtc_transactions(&self) -> SBTCResult<Vec<SBTCTransaction>> {
+			unimplemented!()
+		}
+
+		fn generate_sbtc_wallet_public_key(
+			&self,
+			_public_keys: &PublicKeys,
+		) -> SBTCResult<PublicKey> {
+			unimplemented!()
+		}
+
+		fn run_signing_round(
+			&self,
+			_public_keys: &PublicKeys,
+			_tx: &BitcoinTransaction,
+		) -> SBTCResult<(Signature, SchnorrProof)> {
+			unimplemented!()
+		}
+	}
+
+	impl Reveal for TestSigner {
+		fn commit_transactions(
+			&self,
+		) -> SBTCResult<Vec<(TaprootSpendInfo, BitcoinTransaction)>> {
+			unimplemented!()
+		}
+
+		fn validate_commit_transaction(
+			&self,
+			_spend_info: TaprootSpendInfo,
+			_tx: &BitcoinTransaction,
+		) -> SBTCResult<bool> {
+			unimplemented!()
+		}
+
+		fn reveal_transaction(
+			&self,
+			_spend_info: TaprootSpendInfo,
+			_tx: &BitcoinTransaction,
+		) -> SBTCResult<BitcoinTransaction> {
+			unimplemented!()
+		}
+	}
+
+	fn signer(network: Network) -> Signer<TestSigner> {
+		let private_key = PrivateKey::new(
+			SecretKey::from_slice(&[1; 32]).unwrap(),
+			Network::Testnet,
+		);
+		let url: Url = "http://localhost".parse().unwrap();
+
+		Signer::new(
+			Config {
+				auto_approve_max_amount: 0,
+				delegate_public_key: private_key
+					.public_key(&Secp256k1::new())
+					.inner,
+				auto_deny_addresses_btc: vec![],
+				auto_deny_addresses_stx: vec![],
+			},
+			private_key,
+			network,
+			url.clone(),
+			url.clone(),
+			url,
+			TestSigner,
+		)
+	}
+
+	// Spends nothing the signer owns
+	fn transaction() -> BitcoinTransaction {
+		BitcoinTransaction {
+			version: 2,
+			lock_time: PackedLockTime(0),
+			input: vec![],
+			output: vec![],
+		}
+	}
+
+	#[test]
+	fn approve_should_sign_an_approval_of_the_transaction() {
+		let signer = signer(Network::Testnet);
+		let tx = transaction();
+
+		let vote = signer.approve(&tx).unwrap();
+
+		assert_eq!(vote.txid, tx.txid());
+		assert!(vote.approved);
+		assert_eq!(
+			vote.public_key,
+			signer.private_key.public_key(&Secp256k1::new())
+		);
+		assert_eq!(
+			vote.signature,
+			[b"signed:".as_slice(), &Vote::message(tx.txid(), true)].concat()
+		);
+	}
+
+	#[test]
+	fn deny_should_sign_a_denial_of_the_transaction() {
+		let tx = transaction();
+
+		let vote = signer(Network::Testnet).deny(&tx).unwrap();
+
+		assert!(!vote.approved);
+		assert_eq!(
+			vote.signature,
+			[b"signed:".as_slice(), &Vote::message(tx.txid(), false)].concat()
+		);
+	}
+
+	#[test]
+	fn votes_should_be_refused_for_keys_of_another_network() {
+		assert!(signer(Network::Bitcoin).approve(&transaction()).is_err());
+	}
+
+	#[test]
+	fn testnet_keys_should_vote_on_regtest_and_signet() {
+		for network in [Network::Regtest, Network::Signet] {
+			assert!(signer(network).approve(&transaction()).is_ok());
+		}
+	}
+
+	fn fulfillment(
+		request: WithdrawalRequestData,
+		amount: u64,
+	) -> SignableTransaction {
+		let mut tx = transaction();
+		tx.output = vec![
+			TxOut {
+				value: 0,
+				script_pubkey: Script::new_op_return(&[]),
+			},
+			TxOut {
+				value: amount,
+				script_pubkey: request.payee_bitcoin_address.script_pubkey(),
+			},
+		];
+
+		SignableTransaction::WithdrawalFulfillment(tx, request)
+	}
+
+	fn withdrawal_request() -> WithdrawalRequestData {
+		let payee_bitcoin_address: Address =
+			"tb1qwe9ddxp6v32uef2v66j00vx6wxax5zat223tms"
+				.parse()
+				.unwrap();
+		let drawee_private_key =
+			StacksPrivateKey::from_slice(&[2; 32]).unwrap();
+
+		WithdrawalRequestData {
+			drawee_stacks_address: StacksAddress::new(
+				AddressVersion::TestnetSingleSig,
+				Hash160Hasher::new([2; 33]),
+			),
+			amount: 1000,
+			fulfillment_amount: 546,
+			sbtc_wallet: payee_bitcoin_address.clone(),
+			signature: create_signature(
+				&drawee_private_key,
+				&payee_bitcoin_address,
+				1000,
+			),
+			payee_bitcoin_address,
+		}
+	}
+
+	#[test]
+	fn fulfillment_paying_the_request_should_be_valid() {
+		let tx = fulfillment(withdrawal_request(), 1000);
+
+		assert!(signer(Network::Testnet).validate_transaction(&tx).unwrap());
+	}
+
+	#[test]
+	fn fulfillment_paying_the_wrong_amount_should_be_invalid() {
+		let tx = fulfillment(withdrawal_request(), 999);
+
+		assert!(!signer(Network::Testnet).validate_transaction(&tx).unwrap());
+	}
+
+	#[test]
+	fn fulfillment_paying_an_auto_deny_address_should_be_invalid() {
+		let request = withdrawal_request();
+		let mut signer = signer(Network::Testnet);
+		signer.config.auto_deny_addresses_btc =
+			vec![request.payee_bitcoin_address.clone()];
+
+		let tx = fulfillment(request, 1000);
+
+		assert!(!signer.validate_transaction(&tx).unwrap());
+	}
+
+	#[test]
+	fn unchecked_transactions_should_be_invalid() {
+		let signer = signer(Network::Testnet);
+
+		for tx in [
+			SignableTransaction::Reveal(transaction()),
+			SignableTransaction::Handoff(transaction()),
+		] {
+			assert!(!signer.validate_transaction(&tx).unwrap());
 		}
 	}
 }
@@ -0,0 +1,74 @@
+//! Fixtures shared by the tests of the sBTC crates
+
+use bdk::{
+	bitcoin::{Network, OutPoint, PackedLockTime, Transaction, TxIn, TxOut},
+	database::{BatchOperations, MemoryDatabase},
+	descriptor::IntoWalletDescriptor,
+	wallet::AddressIndex,
+	BlockTime, KeychainKind, LocalUtxo, TransactionDetails, Wallet,
+};
+
+/// Wallet holding a single confirmed UTXO of 100,000 sats on its first
+/// address, so that transactions can be built without syncing
+pub fn funded_wallet<E>(
+	descriptor: E,
+	network: Network,
+) -> Wallet<MemoryDatabase>
+where
+	E: IntoWalletDescriptor + Clone,
+{
+	let new_wallet = |database| {
+		Wallet::new(
+			descriptor.clone(),
+			Some(descriptor.clone()),
+			network,
+			database,
+		)
+		.unwrap()
+	};
+
+	let address = new_wallet(MemoryDatabase::default())
+		.get_address(AddressIndex::Peek(0))
+		.unwrap();
+	let txout = TxOut {
+		value: 100_000,
+		script_pubkey: address.script_pubkey(),
+	};
+	let funding_tx = Transaction {
+		version: 2,
+		lock_time: PackedLockTime(0),
+		input: vec![TxIn::default()],
+		output: vec![txout.clone()],
+	};
+
+	let mut database = MemoryDatabase::default();
+	database
+		.set_utxo(&LocalUtxo {
+			outpoint: OutPoint::new(funding_tx.txid(), 0),
+			txout,
+			keychain: KeychainKind::External,
+			is_spent: false,
+		})
+		.unwrap();
+	database
+		.set_tx(&TransactionDetails {
+			txid: funding_tx.txid(),
+			transaction: Some(funding_tx),
+			received: 100_000,
+			sent: 0,
+			fee: None,
+			confirmation_time: Some(BlockTime {
+				height: 1,
+				timestamp: 0,
+			}),
+		})
+		.unwrap();
+
+	let wallet = new_wallet(database);
+
+	// Caches the wallet script, which a sync would otherwise do, so that the
+	// UTXO can be spent
+	wallet.get_address(AddressIndex::New).unwrap();
+
+	wallet
+}
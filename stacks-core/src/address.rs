@@ -1,6 +1,7 @@
 use std::{
 	fmt,
 	io::{self, Read, Write},
+	str::FromStr,
 };
 
 use bdk::bitcoin::blockdata::{
@@ -17,12 +18,14 @@ use crate::{
 		sha256::Sha256Hasher,
 		Hashing, PublicKey,
 	},
-	StacksError, StacksResult,
+	Network, StacksError, StacksResult,
 };
 
 /// Supported stacks address versions
 #[repr(u8)]
-#[derive(FromRepr, EnumIter, PartialEq, Eq, Copy, Clone, Debug)]
+#[derive(
+	FromRepr, EnumIter, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Debug,
+)]
 pub enum AddressVersion {
 	/// Mainnet single sig address version
 	MainnetSingleSig = 22,
@@ -43,8 +46,17 @@ impl TryFrom<u8> for AddressVersion {
 	}
 }
 
-/// A Stacks address
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// Hash modes supported for multisig Stacks addresses
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum MultisigHashMode {
+	/// Pay-2-script-hash
+	P2SH,
+	/// Pay-2-witness-script-hash
+	P2WSH,
+}
+
+/// A Stacks address, ordered by version and then by hash bytes
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
 #[serde(into = "String")]
 pub struct StacksAddress {
 	version: AddressVersion,
@@ -95,11 +107,54 @@ impl StacksAddress {
 		Self::new(version, hash_p2wsh(keys, signature_threshold))
 	}
 
+	/// Check whether the public keys and signature threshold produce this
+	/// pay-2-script-hash address
+	pub fn matches_p2sh<'a>(
+		&self,
+		keys: impl IntoIterator<Item = &'a PublicKey>,
+		signature_threshold: usize,
+	) -> bool {
+		hash_p2sh(keys, signature_threshold) == self.hash
+	}
+
+	/// Check whether the public keys and signature threshold produce this
+	/// pay-2-witness-script-hash address
+	pub fn matches_p2wsh<'a>(
+		&self,
+		keys: impl IntoIterator<Item = &'a PublicKey>,
+		signature_threshold: usize,
+	) -> bool {
+		hash_p2wsh(keys, signature_threshold) == self.hash
+	}
+
 	/// Create a Stacks address from the public key. This is always a P2PKH
 	/// address, by convention.
 	pub fn from_public_key(version: AddressVersion, key: &PublicKey) -> Self {
 		Self::p2pkh(version, key)
 	}
+
+	/// Create a multisig Stacks address from the public keys, picking the
+	/// multisig address version matching the network
+	pub fn from_public_keys<'a>(
+		network: Network,
+		keys: impl IntoIterator<Item = &'a PublicKey>,
+		signature_threshold: usize,
+		hash_mode: MultisigHashMode,
+	) -> Self {
+		let version = match network {
+			Network::Mainnet => AddressVersion::MainnetMultiSig,
+			Network::Testnet => AddressVersion::TestnetMultiSig,
+		};
+
+		match hash_mode {
+			MultisigHashMode::P2SH => {
+				Self::p2sh(version, keys, signature_threshold)
+			}
+			MultisigHashMode::P2WSH => {
+				Self::p2wsh(version, keys, signature_threshold)
+			}
+		}
+	}
 }
 
 impl Codec for StacksAddress {
@@ -112,7 +167,8 @@ impl Codec for StacksAddress {
 		let mut version_buffer = [0; 1];
 		data.read_exact(&mut version_buffer)?;
 
-		let version = AddressVersion::from_repr(version_buffer[0]).unwrap();
+		let version = AddressVersion::try_from(version_buffer[0])
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
 
 		let mut hash_buffer = [0; HASH160_LENGTH];
 		data.read_exact(&mut hash_buffer)?;
@@ -129,12 +185,11 @@ impl From<StacksAddress> for String {
 	}
 }
 
-impl TryFrom<&str> for StacksAddress {
-	type Error = StacksError;
+impl FromStr for StacksAddress {
+	type Err = StacksError;
 
-	fn try_from(address: &str) -> Result<Self, Self::Error> {
-		let (version, hash_bytes) = decode_address(address)
-			.map_err::<StacksError, _>(|err| err.into())?;
+	fn from_str(address: &str) -> Result<Self, Self::Err> {
+		let (version, hash_bytes) = decode_address(address)?;
 
 		if hash_bytes.len() != HASH160_LENGTH {
 			return Err(StacksError::InvalidArguments(
@@ -149,13 +204,21 @@ impl TryFrom<&str> for StacksAddress {
 	}
 }
 
+impl TryFrom<&str> for StacksAddress {
+	type Error = StacksError;
+
+	fn try_from(address: &str) -> Result<Self, Self::Error> {
+		address.parse()
+	}
+}
+
 impl fmt::Display for StacksAddress {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		write!(f, "{}", encode_address(self.version, self.hash.as_ref()))
 	}
 }
 
-fn hash_p2pkh(key: &PublicKey) -> Hash160Hasher {
+pub(crate) fn hash_p2pkh(key: &PublicKey) -> Hash160Hasher {
 	Hash160Hasher::new(key.serialize())
 }
 
@@ -182,7 +245,7 @@ fn hash_p2sh<'a>(
 	script_hash
 }
 
-fn hash_p2wpkh(key: &PublicKey) -> Hash160Hasher {
+pub(crate) fn hash_p2wpkh(key: &PublicKey) -> Hash160Hasher {
 	let key_hash_hasher = Hash160Hasher::new(key.serialize());
 	let key_hash = key_hash_hasher.as_ref();
 	let key_hash_len = key_hash.len();
@@ -228,8 +291,10 @@ fn hash_p2wsh<'a>(
 
 #[cfg(test)]
 mod tests {
+	use std::collections::BTreeSet;
+
 	use super::*;
-	use crate::crypto::hash160::Hash160Hasher;
+	use crate::{c32::C32Error, crypto::hash160::Hash160Hasher};
 
 	/// Sample data computed with these commands on MacOS:
 	///
@@ -342,6 +407,44 @@ mod tests {
 		assert_eq!(hash_p2wpkh(&pk).as_ref(), expected_hash.as_ref());
 	}
 
+	#[test]
+	fn should_create_2_of_3_multisig_address_for_both_networks() {
+		let keys: Vec<PublicKey> = [
+			"0325a1b9799db9852ee1c99280b20695b1889eff7ec0352d634912818d02f91f84",
+			"0279d7abd36d41d51e225efbbc8376a257051cecdf8b47eaffeb49b77547bc3bff",
+			"028cac21ac93bf697dc31da79e11aad8d285b2e2e81bcfc8de982179c6d468d339",
+		]
+		.into_iter()
+		.map(|pk_hex| {
+			PublicKey::from_slice(&hex::decode(pk_hex).unwrap()).unwrap()
+		})
+		.collect();
+
+		for (network, expected_version) in [
+			(Network::Mainnet, AddressVersion::MainnetMultiSig),
+			(Network::Testnet, AddressVersion::TestnetMultiSig),
+		] {
+			let p2sh_addr = StacksAddress::from_public_keys(
+				network,
+				&keys,
+				2,
+				MultisigHashMode::P2SH,
+			);
+			let p2wsh_addr = StacksAddress::from_public_keys(
+				network,
+				&keys,
+				2,
+				MultisigHashMode::P2WSH,
+			);
+
+			assert_eq!(p2sh_addr.version(), expected_version);
+			assert_eq!(p2sh_addr.hash(), &hash_p2sh(&keys, 2));
+			assert_eq!(p2wsh_addr.version(), expected_version);
+			assert_eq!(p2wsh_addr.hash(), &hash_p2wsh(&keys, 2));
+			assert_ne!(p2sh_addr, p2wsh_addr);
+		}
+	}
+
 	/// Data generated with `stx make_keychain`
 	#[test]
 	fn should_create_correct_address_from_public_key() {
@@ -370,4 +473,121 @@ mod tests {
 
 		assert_eq!(addr.hash(), &expected_hash);
 	}
+
+	#[test]
+	fn should_parse_address_from_str() {
+		let address_str = "SPR4FMGJCD78NF4FRGPM621CW1KHNFEG0HSRDSPK";
+		let address = address_str.parse::<StacksAddress>().unwrap();
+
+		assert_eq!(address.version(), AddressVersion::MainnetSingleSig);
+		assert_eq!(address.to_string(), address_str);
+	}
+
+	#[test]
+	fn should_not_parse_truncated_address() {
+		let err = "SPR4FMGJCD78NF4FRGPM621CW1KHNFEG"
+			.parse::<StacksAddress>()
+			.unwrap_err();
+
+		assert!(matches!(
+			err,
+			StacksError::C32Error(C32Error::InvalidChecksum(..))
+		));
+		assert!(err.to_string().contains("Invalid C32 checksum"));
+	}
+
+	#[test]
+	fn should_sort_addresses_by_version_then_hash() {
+		let mainnet_high = StacksAddress::new(
+			AddressVersion::MainnetSingleSig,
+			[2; 20].into(),
+		);
+		let mainnet_low = StacksAddress::new(
+			AddressVersion::MainnetSingleSig,
+			[1; 20].into(),
+		);
+		let mainnet_multisig =
+			StacksAddress::new(AddressVersion::MainnetMultiSig, [3; 20].into());
+		let testnet = StacksAddress::new(
+			AddressVersion::TestnetSingleSig,
+			[0; 20].into(),
+		);
+		let testnet_multisig =
+			StacksAddress::new(AddressVersion::TestnetMultiSig, [9; 20].into());
+
+		let addresses: BTreeSet<StacksAddress> = [
+			testnet.clone(),
+			mainnet_high.clone(),
+			testnet_multisig.clone(),
+			mainnet_low.clone(),
+			mainnet_multisig.clone(),
+			mainnet_low.clone(),
+		]
+		.into_iter()
+		.collect();
+
+		assert_eq!(
+			addresses.into_iter().collect::<Vec<_>>(),
+			vec![
+				mainnet_multisig,
+				testnet_multisig,
+				mainnet_low,
+				mainnet_high,
+				testnet
+			]
+		);
+	}
+
+	#[test]
+	fn should_match_p2sh_keys_and_threshold() {
+		let pk1_hex = "0325a1b9799db9852ee1c99280b20695b1889eff7ec0352d634912818d02f91f84";
+		let pk2_hex = "0279d7abd36d41d51e225efbbc8376a257051cecdf8b47eaffeb49b77547bc3bff";
+
+		let pk1 =
+			PublicKey::from_slice(&hex::decode(pk1_hex).unwrap()).unwrap();
+		let pk2 =
+			PublicKey::from_slice(&hex::decode(pk2_hex).unwrap()).unwrap();
+		let address = StacksAddress::new(
+			AddressVersion::MainnetMultiSig,
+			Hash160Hasher::from_hex("073503b6e6ef916e4ab40f31abc83217c271d917")
+				.unwrap(),
+		);
+
+		assert!(address.matches_p2sh(&[pk1, pk2], 2));
+		assert!(!address.matches_p2sh(&[pk1, pk2], 1));
+		assert!(!address.matches_p2sh(&[pk2, pk1], 2));
+		assert!(!address.matches_p2sh(&[pk1], 1));
+		assert!(!address.matches_p2wsh(&[pk1, pk2], 2));
+	}
+
+	#[test]
+	fn should_match_p2wsh_keys_and_threshold() {
+		let pk1_hex = "037c6e4c27b3d39ab73c2cd2fdd2ea34cec3d9b6881a2a4a17e42fcafb6b64c3aa";
+		let pk2_hex = "03a544a1d3fb4238d5841647100c53e371a1d72f027857899256f0c754cf266491";
+
+		let pk1 =
+			PublicKey::from_slice(&hex::decode(pk1_hex).unwrap()).unwrap();
+		let pk2 =
+			PublicKey::from_slice(&hex::decode(pk2_hex).unwrap()).unwrap();
+		let address = StacksAddress::new(
+			AddressVersion::TestnetMultiSig,
+			Hash160Hasher::from_hex("d5f3ddac2358f61088d951aead20c270a045d46d")
+				.unwrap(),
+		);
+
+		assert!(address.matches_p2wsh(&[pk1, pk2], 2));
+		assert!(!address.matches_p2wsh(&[pk1, pk2], 1));
+		assert!(!address.matches_p2sh(&[pk1, pk2], 2));
+	}
+
+	#[test]
+	fn deserialize_should_reject_unknown_version() {
+		let mut bytes = vec![0xFF];
+		bytes.extend([0; HASH160_LENGTH]);
+
+		let err = StacksAddress::codec_deserialize(&mut bytes.as_slice())
+			.unwrap_err();
+
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+	}
 }
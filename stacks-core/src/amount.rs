@@ -0,0 +1,147 @@
+//! STX amount type and parsing
+use std::{
+	fmt::{Display, Formatter},
+	io,
+};
+
+use crate::{codec::Codec, StacksError, StacksResult};
+
+/// Number of decimal places of an STX amount
+pub const STX_DECIMALS: usize = 6;
+/// Number of micro-STX in one STX
+pub const MICRO_STX_PER_STX: u64 = 1_000_000;
+
+/// Amount of STX, held in micro-STX
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StxAmount(pub u64);
+
+impl StxAmount {
+	/// Parses a decimal STX amount such as `12.5` into micro-STX, rejecting
+	/// amounts with more than six decimal places
+	pub fn from_stx_str(amount: &str) -> StacksResult<Self> {
+		let invalid = || {
+			StacksError::InvalidData(format!("Invalid STX amount: {amount}"))
+		};
+		let is_digits = |digits: &str| {
+			!digits.is_empty()
+				&& digits.bytes().all(|byte| byte.is_ascii_digit())
+		};
+
+		let (whole, fraction) = amount.split_once('.').unwrap_or((amount, "0"));
+
+		if !is_digits(whole) || !is_digits(fraction) {
+			return Err(invalid());
+		}
+
+		if fraction.len() > STX_DECIMALS {
+			return Err(StacksError::InvalidData(format!(
+				"STX amount {amount} has more than {STX_DECIMALS} decimal places"
+			)));
+		}
+
+		let fraction = format!("{fraction:0<STX_DECIMALS$}")
+			.parse::<u64>()
+			.map_err(|_| invalid())?;
+
+		whole
+			.parse::<u64>()
+			.ok()
+			.and_then(|whole| whole.checked_mul(MICRO_STX_PER_STX))
+			.and_then(|micro_stx| micro_stx.checked_add(fraction))
+			.map(Self)
+			.ok_or_else(invalid)
+	}
+
+	/// Formats the amount in STX, without trailing zeroes
+	pub fn to_stx_string(&self) -> String {
+		let whole = self.0 / MICRO_STX_PER_STX;
+		let fraction = self.0 % MICRO_STX_PER_STX;
+
+		if fraction == 0 {
+			whole.to_string()
+		} else {
+			let fraction = format!("{fraction:0>STX_DECIMALS$}");
+
+			format!("{whole}.{}", fraction.trim_end_matches('0'))
+		}
+	}
+}
+
+impl From<u64> for StxAmount {
+	fn from(micro_stx: u64) -> Self {
+		Self(micro_stx)
+	}
+}
+
+impl Display for StxAmount {
+	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+		write!(f, "{} STX", self.to_stx_string())
+	}
+}
+
+impl Codec for StxAmount {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		self.0.codec_serialize(dest)
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		Ok(Self(u64::codec_deserialize(data)?))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_parse_stx_amounts() {
+		for (amount, micro_stx, formatted) in [
+			("0.000001", 1, "0.000001"),
+			("12", 12_000_000, "12"),
+			("12.5", 12_500_000, "12.5"),
+			("12.500000", 12_500_000, "12.5"),
+			("0", 0, "0"),
+		] {
+			let amount = StxAmount::from_stx_str(amount).unwrap();
+
+			assert_eq!(amount, StxAmount(micro_stx));
+			assert_eq!(amount.to_stx_string(), formatted);
+		}
+	}
+
+	#[test]
+	fn should_reject_invalid_stx_amounts() {
+		for amount in [
+			"1.0000001",
+			"",
+			".5",
+			"5.",
+			"-1",
+			"+1",
+			"1 000",
+			"1e6",
+			"1.2.3",
+		] {
+			assert!(StxAmount::from_stx_str(amount).is_err(), "{}", amount);
+		}
+
+		assert!(
+			StxAmount::from_stx_str(&(u64::MAX / 1000).to_string()).is_err()
+		);
+	}
+
+	#[test]
+	fn should_serialize_big_endian_micro_stx() {
+		let amount = StxAmount(0x0102030405060708);
+		let bytes = amount.serialize_to_vec();
+
+		assert_eq!(bytes, [1, 2, 3, 4, 5, 6, 7, 8]);
+		assert_eq!(
+			StxAmount::deserialize(&mut bytes.as_slice()).unwrap(),
+			amount
+		);
+	}
+}
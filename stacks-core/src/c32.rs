@@ -314,6 +314,26 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn test_c32_leading_zeros_roundtrip() {
+		let mut rng = thread_rng();
+
+		assert_eq!(encode(b""), "");
+		assert_eq!(super::decode("").unwrap(), Vec::<u8>::new());
+		assert_eq!(encode([0u8; 4]), "0000");
+		assert_eq!(super::decode("0000").unwrap(), vec![0; 4]);
+
+		for _ in 0..1000 {
+			let zeros = rng.gen_range(0..=4);
+			let mut input = vec![0u8; zeros + rng.gen_range(0..=32)];
+			rng.fill_bytes(&mut input[zeros..]);
+
+			let decoded = super::decode(encode(&input)).unwrap();
+
+			assert_eq!(decoded, input);
+		}
+	}
+
 	#[test]
 	fn test_c32_check_randomized_input() {
 		let mut rng = thread_rng();
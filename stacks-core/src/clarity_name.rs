@@ -0,0 +1,155 @@
+//! Clarity name type and parsing
+use std::{
+	borrow::Borrow,
+	fmt::{Display, Formatter},
+	io::{self, Read},
+	ops::Deref,
+};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::codec::Codec;
+
+/// Maximum Clarity name length
+pub const CLARITY_MAX_NAME_LENGTH: usize = 128;
+
+/// Regex for Clarity names, such as function and asset names
+pub static CLARITY_NAME_REGEX: Lazy<Regex> = Lazy::new(|| {
+	regex::Regex::new(
+		"^[a-zA-Z]([a-zA-Z0-9]|[-_!?+<>=/*])*$|^[-+=/*]$|^[<>]=?$",
+	)
+	.unwrap()
+});
+
+#[derive(Error, Debug)]
+/// Error type for Clarity name parsing
+pub enum ClarityNameError {
+	#[error("Length should be at most {}", CLARITY_MAX_NAME_LENGTH)]
+	/// Invalid length
+	InvalidLength,
+	#[error("Format should follow the Clarity name specification")]
+	/// Invalid format
+	InvalidFormat,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+/// Clarity name type
+pub struct ClarityName(String);
+
+impl ClarityName {
+	/// Create a new Clarity name from the given string
+	pub fn new(name: &str) -> Result<Self, ClarityNameError> {
+		if name.len() > CLARITY_MAX_NAME_LENGTH {
+			Err(ClarityNameError::InvalidLength)
+		} else if CLARITY_NAME_REGEX.is_match(name) {
+			Ok(Self(name.to_string()))
+		} else {
+			Err(ClarityNameError::InvalidFormat)
+		}
+	}
+}
+
+impl Codec for ClarityName {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		dest.write_all(&[self.len() as u8])?;
+		dest.write_all(self.as_bytes())
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		let mut length_buffer = [0u8; 1];
+		data.read_exact(&mut length_buffer)?;
+		let name_length = length_buffer[0] as usize;
+
+		let mut name_buffer = Vec::with_capacity(name_length);
+		data.take(name_length as u64)
+			.read_to_end(&mut name_buffer)?;
+
+		if name_buffer.len() != name_length {
+			return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+		}
+
+		let name = String::from_utf8(name_buffer)
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+		Self::new(&name)
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+	}
+}
+
+impl TryFrom<&str> for ClarityName {
+	type Error = ClarityNameError;
+
+	fn try_from(value: &str) -> Result<Self, Self::Error> {
+		ClarityName::new(value)
+	}
+}
+
+impl AsRef<str> for ClarityName {
+	fn as_ref(&self) -> &str {
+		self.0.as_ref()
+	}
+}
+
+impl Deref for ClarityName {
+	type Target = str;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl Borrow<str> for ClarityName {
+	fn borrow(&self) -> &str {
+		self.as_ref()
+	}
+}
+
+// From conversion is fallible for this type
+#[allow(clippy::from_over_into)]
+impl Into<String> for ClarityName {
+	fn into(self) -> String {
+		self.0
+	}
+}
+
+impl Display for ClarityName {
+	fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+		self.0.fmt(f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_accept_clarity_names() {
+		for name in ["mint", "get-balance?", "set-owner!", "+", "<=", "a/b"] {
+			let clarity_name = ClarityName::new(name).unwrap();
+
+			assert_eq!(
+				ClarityName::deserialize(
+					&mut clarity_name.serialize_to_vec().as_slice()
+				)
+				.unwrap(),
+				clarity_name
+			);
+		}
+	}
+
+	#[test]
+	fn should_reject_invalid_clarity_names() {
+		for name in ["", "1mint", "mint sbtc", "mint.sbtc"] {
+			assert!(ClarityName::new(name).is_err(), "{}", name);
+		}
+
+		assert!(
+			ClarityName::new(&"a".repeat(CLARITY_MAX_NAME_LENGTH + 1)).is_err()
+		);
+	}
+}
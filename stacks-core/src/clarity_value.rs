@@ -0,0 +1,407 @@
+//! Clarity values and their consensus serialization
+use std::io::{self, Read};
+
+use strum::FromRepr;
+
+use crate::{
+	clarity_name::ClarityName, codec::Codec, utils::PrincipalData, StacksError,
+	StacksResult,
+};
+
+/// Maximum nesting depth of a deserialized value
+pub const MAX_VALUE_DEPTH: usize = 32;
+
+#[repr(u8)]
+#[derive(FromRepr, Debug, Clone, Copy)]
+enum ValueTypeID {
+	Int = 0x00,
+	UInt = 0x01,
+	Buffer = 0x02,
+	BoolTrue = 0x03,
+	BoolFalse = 0x04,
+	PrincipalStandard = 0x05,
+	PrincipalContract = 0x06,
+	ResponseOk = 0x07,
+	ResponseErr = 0x08,
+	OptionalNone = 0x09,
+	OptionalSome = 0x0a,
+	List = 0x0b,
+	Tuple = 0x0c,
+	StringASCII = 0x0d,
+	StringUTF8 = 0x0e,
+}
+
+/// Clarity value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+	/// Signed integer
+	Int(i128),
+	/// Unsigned integer
+	UInt(u128),
+	/// Boolean
+	Bool(bool),
+	/// Byte buffer
+	Buffer(Vec<u8>),
+	/// ASCII string
+	StringASCII(String),
+	/// Standard or contract principal
+	Principal(PrincipalData),
+	/// Optional value, `none` when empty
+	Optional(Option<Box<Value>>),
+	/// Response, `ok` when successful and `err` otherwise
+	Response(Result<Box<Value>, Box<Value>>),
+	/// List of values
+	List(Vec<Value>),
+	/// Tuple, with its fields sorted by name
+	Tuple(Vec<(ClarityName, Value)>),
+}
+
+impl Value {
+	/// Creates a buffer value
+	pub fn buff(bytes: impl Into<Vec<u8>>) -> Self {
+		Self::Buffer(bytes.into())
+	}
+
+	/// Creates an ASCII string value
+	pub fn string_ascii(string: impl Into<String>) -> StacksResult<Self> {
+		let string = string.into();
+
+		if !string.is_ascii() {
+			return Err(StacksError::InvalidData(format!(
+				"{string} is not an ASCII string"
+			)));
+		}
+
+		Ok(Self::StringASCII(string))
+	}
+
+	/// Creates a `some` optional value
+	pub fn some(value: Value) -> Self {
+		Self::Optional(Some(Box::new(value)))
+	}
+
+	/// Creates a `none` optional value
+	pub fn none() -> Self {
+		Self::Optional(None)
+	}
+
+	/// Creates an `ok` response value
+	pub fn okay(value: Value) -> Self {
+		Self::Response(Ok(Box::new(value)))
+	}
+
+	/// Creates an `err` response value
+	pub fn error(value: Value) -> Self {
+		Self::Response(Err(Box::new(value)))
+	}
+
+	/// Creates a tuple value, sorting its fields by name
+	pub fn tuple(
+		fields: impl IntoIterator<Item = (ClarityName, Value)>,
+	) -> Self {
+		let mut fields: Vec<_> = fields.into_iter().collect();
+		fields.sort_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+
+		Self::Tuple(fields)
+	}
+
+	fn deserialize_with_depth<R: io::Read>(
+		data: &mut R,
+		depth: usize,
+	) -> io::Result<Self> {
+		if depth > MAX_VALUE_DEPTH {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"Clarity value is nested too deeply",
+			));
+		}
+
+		let mut type_buffer = [0; 1];
+		data.read_exact(&mut type_buffer)?;
+
+		let type_id =
+			ValueTypeID::from_repr(type_buffer[0]).ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::InvalidData,
+					format!("Invalid Clarity value type: {}", type_buffer[0]),
+				)
+			})?;
+
+		let value = match type_id {
+			ValueTypeID::Int => {
+				let mut buffer = [0; 16];
+				data.read_exact(&mut buffer)?;
+
+				Self::Int(i128::from_be_bytes(buffer))
+			}
+			ValueTypeID::UInt => {
+				let mut buffer = [0; 16];
+				data.read_exact(&mut buffer)?;
+
+				Self::UInt(u128::from_be_bytes(buffer))
+			}
+			ValueTypeID::Buffer => Self::Buffer(read_bytes(data)?),
+			ValueTypeID::BoolTrue => Self::Bool(true),
+			ValueTypeID::BoolFalse => Self::Bool(false),
+			ValueTypeID::PrincipalStandard | ValueTypeID::PrincipalContract => {
+				// Principals share their type byte with the value type
+				let mut principal_data = (&type_buffer[..]).chain(data);
+
+				Self::Principal(PrincipalData::codec_deserialize(
+					&mut principal_data,
+				)?)
+			}
+			ValueTypeID::ResponseOk => {
+				Self::okay(Self::deserialize_with_depth(data, depth + 1)?)
+			}
+			ValueTypeID::ResponseErr => {
+				Self::error(Self::deserialize_with_depth(data, depth + 1)?)
+			}
+			ValueTypeID::OptionalNone => Self::none(),
+			ValueTypeID::OptionalSome => {
+				Self::some(Self::deserialize_with_depth(data, depth + 1)?)
+			}
+			ValueTypeID::List => Self::List(
+				(0..read_length(data)?)
+					.map(|_| Self::deserialize_with_depth(data, depth + 1))
+					.collect::<io::Result<_>>()?,
+			),
+			ValueTypeID::Tuple => Self::Tuple(
+				(0..read_length(data)?)
+					.map(|_| {
+						Ok((
+							ClarityName::codec_deserialize(data)?,
+							Self::deserialize_with_depth(data, depth + 1)?,
+						))
+					})
+					.collect::<io::Result<_>>()?,
+			),
+			ValueTypeID::StringASCII => {
+				let string = String::from_utf8(read_bytes(data)?)
+					.ok()
+					.filter(|string| string.is_ascii())
+					.ok_or_else(|| {
+						io::Error::new(
+							io::ErrorKind::InvalidData,
+							"Invalid ASCII string",
+						)
+					})?;
+
+				Self::StringASCII(string)
+			}
+			ValueTypeID::StringUTF8 => {
+				return Err(io::Error::new(
+					io::ErrorKind::InvalidData,
+					"UTF-8 strings are not supported",
+				))
+			}
+		};
+
+		Ok(value)
+	}
+}
+
+impl Codec for Value {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		match self {
+			Self::Int(value) => {
+				dest.write_all(&[ValueTypeID::Int as u8])?;
+				dest.write_all(&value.to_be_bytes())
+			}
+			Self::UInt(value) => {
+				dest.write_all(&[ValueTypeID::UInt as u8])?;
+				dest.write_all(&value.to_be_bytes())
+			}
+			Self::Bool(true) => dest.write_all(&[ValueTypeID::BoolTrue as u8]),
+			Self::Bool(false) => {
+				dest.write_all(&[ValueTypeID::BoolFalse as u8])
+			}
+			Self::Buffer(bytes) => {
+				dest.write_all(&[ValueTypeID::Buffer as u8])?;
+				write_bytes(dest, bytes)
+			}
+			Self::StringASCII(string) => {
+				dest.write_all(&[ValueTypeID::StringASCII as u8])?;
+				write_bytes(dest, string.as_bytes())
+			}
+			// Principals share their type byte with the value type
+			Self::Principal(principal) => principal.codec_serialize(dest),
+			Self::Optional(None) => {
+				dest.write_all(&[ValueTypeID::OptionalNone as u8])
+			}
+			Self::Optional(Some(value)) => {
+				dest.write_all(&[ValueTypeID::OptionalSome as u8])?;
+				value.codec_serialize(dest)
+			}
+			Self::Response(Ok(value)) => {
+				dest.write_all(&[ValueTypeID::ResponseOk as u8])?;
+				value.codec_serialize(dest)
+			}
+			Self::Response(Err(value)) => {
+				dest.write_all(&[ValueTypeID::ResponseErr as u8])?;
+				value.codec_serialize(dest)
+			}
+			Self::List(values) => {
+				dest.write_all(&[ValueTypeID::List as u8])?;
+				write_length(dest, values.len())?;
+
+				values
+					.iter()
+					.try_for_each(|value| value.codec_serialize(dest))
+			}
+			Self::Tuple(fields) => {
+				dest.write_all(&[ValueTypeID::Tuple as u8])?;
+				write_length(dest, fields.len())?;
+
+				fields.iter().try_for_each(|(name, value)| {
+					name.codec_serialize(dest)?;
+					value.codec_serialize(dest)
+				})
+			}
+		}
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		Self::deserialize_with_depth(data, 0)
+	}
+}
+
+impl From<PrincipalData> for Value {
+	fn from(principal: PrincipalData) -> Self {
+		Self::Principal(principal)
+	}
+}
+
+/// Writes a length as the 4 byte big endian prefix used by Stacks encodings
+pub(crate) fn write_length<W: io::Write>(
+	dest: &mut W,
+	length: usize,
+) -> io::Result<()> {
+	let length = u32::try_from(length)
+		.map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+	dest.write_all(&length.to_be_bytes())
+}
+
+/// Reads a 4 byte big endian length prefix
+pub(crate) fn read_length<R: io::Read>(data: &mut R) -> io::Result<u32> {
+	let mut buffer = [0; 4];
+	data.read_exact(&mut buffer)?;
+
+	Ok(u32::from_be_bytes(buffer))
+}
+
+fn write_bytes<W: io::Write>(dest: &mut W, bytes: &[u8]) -> io::Result<()> {
+	write_length(dest, bytes.len())?;
+	dest.write_all(bytes)
+}
+
+fn read_bytes<R: io::Read>(data: &mut R) -> io::Result<Vec<u8>> {
+	let length = read_length(data)? as usize;
+
+	let mut bytes = Vec::new();
+	data.take(length as u64).read_to_end(&mut bytes)?;
+
+	if bytes.len() != length {
+		return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+	}
+
+	Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		address::{AddressVersion, StacksAddress},
+		contract_name::ContractName,
+		utils::StandardPrincipalData,
+	};
+
+	fn name(name: &str) -> ClarityName {
+		ClarityName::new(name).unwrap()
+	}
+
+	#[test]
+	fn should_serialize_like_clarity() {
+		let principal = PrincipalData::from(StacksAddress::new(
+			AddressVersion::TestnetSingleSig,
+			[0x11; 20].into(),
+		));
+
+		for (value, expected_hex) in [
+			(Value::Int(-1), "00ffffffffffffffffffffffffffffffff"),
+			(Value::UInt(1000), "01000000000000000000000000000003e8"),
+			(Value::buff([0xde, 0xad]), "0200000002dead"),
+			(Value::Bool(true), "03"),
+			(Value::Bool(false), "04"),
+			(
+				Value::from(principal),
+				"051a1111111111111111111111111111111111111111",
+			),
+			(Value::okay(Value::Bool(true)), "0703"),
+			(
+				Value::error(Value::UInt(1)),
+				"080100000000000000000000000000000001",
+			),
+			(Value::none(), "09"),
+			(Value::some(Value::Bool(false)), "0a04"),
+			(
+				Value::List(vec![Value::Bool(true), Value::Bool(false)]),
+				"0b000000020304",
+			),
+			(
+				Value::tuple([
+					(name("b"), Value::Bool(false)),
+					(name("a"), Value::Bool(true)),
+				]),
+				"0c00000002016103016204",
+			),
+			(Value::string_ascii("hi").unwrap(), "0d000000026869"),
+		] {
+			let bytes = value.serialize_to_vec();
+
+			assert_eq!(hex::encode(&bytes), expected_hex, "{:?}", value);
+			assert_eq!(
+				Value::deserialize(&mut bytes.as_slice()).unwrap(),
+				value
+			);
+		}
+	}
+
+	#[test]
+	fn should_round_trip_contract_principal() {
+		let value = Value::from(PrincipalData::Contract(
+			StandardPrincipalData::from(StacksAddress::new(
+				AddressVersion::TestnetSingleSig,
+				[0x11; 20].into(),
+			)),
+			ContractName::new("asset").unwrap(),
+		));
+
+		let bytes = value.serialize_to_vec();
+
+		assert_eq!(bytes[0], 0x06);
+		assert_eq!(Value::deserialize(&mut bytes.as_slice()).unwrap(), value);
+	}
+
+	#[test]
+	fn should_reject_deeply_nested_values() {
+		let mut bytes = vec![0x0a; MAX_VALUE_DEPTH + 1];
+		bytes.push(0x03);
+
+		assert!(Value::deserialize(&mut bytes.as_slice()).is_err());
+		assert!(Value::deserialize(&mut &bytes[1..]).is_ok());
+	}
+
+	#[test]
+	fn should_reject_truncated_buffers() {
+		assert!(
+			Value::deserialize(&mut [0x02, 0, 0, 0, 3, 1].as_slice()).is_err()
+		);
+		assert!(Value::string_ascii("é").is_err());
+	}
+}
@@ -44,6 +44,19 @@ pub trait Codec {
 			.map_err(|err| CodecError::IoError(err).into())
 	}
 
+	/// Deserialize from a reader and return the number of bytes consumed
+	fn deserialize_counting<R: io::Read>(
+		data: &mut R,
+	) -> StacksResult<(Self, usize)>
+	where
+		Self: Sized,
+	{
+		let mut reader = CountingReader::new(data);
+		let value = Self::deserialize(&mut reader)?;
+
+		Ok((value, reader.count()))
+	}
+
 	/// Serialize to a vector
 	fn serialize_to_vec(&self) -> Vec<u8> {
 		let mut buffer = vec![];
@@ -54,6 +67,76 @@ pub trait Codec {
 	}
 }
 
+/// Reader adapter counting the number of bytes read through it
+#[derive(Debug)]
+pub struct CountingReader<R> {
+	inner: R,
+	count: usize,
+}
+
+impl<R: io::Read> CountingReader<R> {
+	/// Wrap a reader
+	pub fn new(inner: R) -> Self {
+		Self { inner, count: 0 }
+	}
+
+	/// Number of bytes read so far
+	pub fn count(&self) -> usize {
+		self.count
+	}
+
+	/// Unwrap the inner reader
+	pub fn into_inner(self) -> R {
+		self.inner
+	}
+}
+
+impl<R: io::Read> io::Read for CountingReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let read = self.inner.read(buf)?;
+		self.count += read;
+
+		Ok(read)
+	}
+}
+
+/// Writer adapter counting the number of bytes written through it
+#[derive(Debug)]
+pub struct CountingWriter<W> {
+	inner: W,
+	count: usize,
+}
+
+impl<W: io::Write> CountingWriter<W> {
+	/// Wrap a writer
+	pub fn new(inner: W) -> Self {
+		Self { inner, count: 0 }
+	}
+
+	/// Number of bytes written so far
+	pub fn count(&self) -> usize {
+		self.count
+	}
+
+	/// Unwrap the inner writer
+	pub fn into_inner(self) -> W {
+		self.inner
+	}
+}
+
+impl<W: io::Write> io::Write for CountingWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let written = self.inner.write(buf)?;
+		self.count += written;
+
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
 impl Codec for Amount {
 	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
 		dest.write_all(&self.to_sat().to_be_bytes())
@@ -129,3 +212,64 @@ impl Codec for Script {
 		Ok(Self::from(buffer))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::io::Write;
+
+	use bdk::bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
+
+	use super::*;
+
+	fn signature() -> RecoverableSignature {
+		let secp = Secp256k1::new();
+		let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+		let message = Message::from_slice(&[2; 32]).unwrap();
+
+		secp.sign_ecdsa_recoverable(&message, &secret_key)
+	}
+
+	#[test]
+	fn counting_writer_should_count_serialized_bytes() {
+		let mut writer = CountingWriter::new(vec![]);
+
+		42u64.serialize(&mut writer).unwrap();
+		assert_eq!(writer.count(), 8);
+
+		signature().serialize(&mut writer).unwrap();
+		assert_eq!(writer.count(), 8 + 65);
+
+		writer.flush().unwrap();
+		assert_eq!(writer.into_inner().len(), 8 + 65);
+	}
+
+	#[test]
+	fn deserialize_counting_should_count_consumed_bytes() {
+		let mut bytes = Amount::from_sat(1337).serialize_to_vec();
+		bytes.extend(signature().serialize_to_vec());
+		bytes.extend([0xff; 4]);
+
+		let mut data = bytes.as_slice();
+
+		let (amount, count) = Amount::deserialize_counting(&mut data).unwrap();
+		assert_eq!(amount, Amount::from_sat(1337));
+		assert_eq!(count, 8);
+
+		let (recovered, count) =
+			RecoverableSignature::deserialize_counting(&mut data).unwrap();
+		assert_eq!(recovered, signature());
+		assert_eq!(count, 65);
+
+		assert_eq!(data, [0xff; 4]);
+	}
+
+	#[test]
+	fn deserialize_counting_should_count_reads_to_end() {
+		let bytes = [0x51, 0x52, 0x53];
+
+		let (script, count) =
+			Script::deserialize_counting(&mut bytes.as_slice()).unwrap();
+		assert_eq!(script.as_bytes(), bytes);
+		assert_eq!(count, 3);
+	}
+}
@@ -58,7 +58,7 @@ impl ContractName {
 	/// Create a new contract name from the given string
 	pub fn new(contract_name: &str) -> Result<Self, ContractNameError> {
 		if contract_name.len() < CONTRACT_MIN_NAME_LENGTH
-			&& contract_name.len() > CONTRACT_MAX_NAME_LENGTH
+			|| contract_name.len() > CONTRACT_MAX_NAME_LENGTH
 		{
 			Err(ContractNameError::InvalidLength)
 		} else if CONTRACT_NAME_REGEX.is_match(contract_name) {
@@ -87,6 +87,10 @@ impl Codec for ContractName {
 		data.take(contract_name_length as u64)
 			.read_to_end(&mut name_buffer)?;
 
+		if name_buffer.len() != contract_name_length {
+			return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+		}
+
 		let contract_name_string = String::from_utf8(name_buffer)
 			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
 
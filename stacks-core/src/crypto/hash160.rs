@@ -1,7 +1,7 @@
 use ripemd::{Digest, Ripemd160};
 use serde::{Deserialize, Serialize};
 
-use super::sha256::Sha256Hasher;
+use super::sha256::{Sha256Hasher, Sha256Stream};
 use crate::{
 	crypto::{Hasher, Hashing, Hex},
 	StacksError, StacksResult,
@@ -10,7 +10,16 @@ use crate::{
 pub(crate) const HASH160_LENGTH: usize = 20;
 
 #[derive(
-	Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+	Serialize,
+	Deserialize,
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	PartialOrd,
+	Ord,
+	Hash,
 )]
 #[serde(try_from = "Hex")]
 #[serde(into = "Hex")]
@@ -50,6 +59,30 @@ impl TryFrom<Hex> for Hash160Hashing {
 /// Hash160 hasher type
 pub type Hash160Hasher = Hasher<Hash160Hashing, HASH160_LENGTH>;
 
+/// Incremental Hash160 hashing of data fed in chunks
+#[derive(Clone, Default)]
+pub struct Hash160Stream(Sha256Stream);
+
+impl Hash160Stream {
+	/// Create an empty stream
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Feed data to the stream
+	pub fn update(&mut self, data: impl AsRef<[u8]>) {
+		self.0.update(data);
+	}
+
+	/// Hash of all the data fed to the stream
+	pub fn finalize(self) -> Hash160Hasher {
+		let bytes: [u8; HASH160_LENGTH] =
+			Ripemd160::digest(self.0.finalize()).into();
+
+		bytes.into()
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -64,4 +97,14 @@ mod tests {
 			expected_hash_hex
 		);
 	}
+
+	#[test]
+	fn streamed_hash_should_match_one_shot_hash() {
+		let mut stream = Hash160Stream::new();
+		stream.update("Hello");
+		stream.update(" ");
+		stream.update("world");
+
+		assert_eq!(stream.finalize(), Hash160Hasher::hash(b"Hello world"));
+	}
 }
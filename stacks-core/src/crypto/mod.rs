@@ -7,6 +7,8 @@ use crate::{StacksError, StacksResult};
 pub mod hash160;
 /// Module for sha256 hashing
 pub mod sha256;
+/// Module for sha512/256 hashing
+pub mod sha512;
 pub mod wif;
 
 const CHECKSUM_LENGTH: usize = 4;
@@ -50,7 +52,16 @@ pub trait Hashing<const LENGTH: usize>: Clone + Sized {
 }
 
 #[derive(
-	Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+	Serialize,
+	Deserialize,
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	PartialOrd,
+	Ord,
+	Hash,
 )]
 #[serde(try_from = "Hex")]
 #[serde(into = "Hex")]
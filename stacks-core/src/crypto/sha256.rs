@@ -10,7 +10,16 @@ use crate::{
 pub(crate) const SHA256_LENGTH: usize = 32;
 
 #[derive(
-	Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+	Serialize,
+	Deserialize,
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	PartialOrd,
+	Ord,
+	Hash,
 )]
 #[serde(try_from = "Hex")]
 #[serde(into = "Hex")]
@@ -47,7 +56,7 @@ impl TryFrom<Hex> for Sha256Hashing {
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 /// The DoubleSha256 hashing type
 pub struct DoubleSha256Hashing(Sha256Hashing);
 
@@ -86,6 +95,53 @@ pub type Sha256Hasher = Hasher<Sha256Hashing, SHA256_LENGTH>;
 /// The DoubleSha256 hasher type
 pub type DoubleSha256Hasher = Hasher<DoubleSha256Hashing, SHA256_LENGTH>;
 
+/// Incremental Sha256 hashing of data fed in chunks
+#[derive(Clone, Default)]
+pub struct Sha256Stream(Sha256);
+
+impl Sha256Stream {
+	/// Create an empty stream
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Feed data to the stream
+	pub fn update(&mut self, data: impl AsRef<[u8]>) {
+		self.0.update(data);
+	}
+
+	/// Hash of all the data fed to the stream
+	pub fn finalize(self) -> Sha256Hasher {
+		let bytes: [u8; SHA256_LENGTH] = self.0.finalize().into();
+
+		bytes.into()
+	}
+}
+
+/// Incremental DoubleSha256 hashing of data fed in chunks
+#[derive(Clone, Default)]
+pub struct DoubleSha256Stream(Sha256Stream);
+
+impl DoubleSha256Stream {
+	/// Create an empty stream
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Feed data to the stream
+	pub fn update(&mut self, data: impl AsRef<[u8]>) {
+		self.0.update(data);
+	}
+
+	/// Hash of all the data fed to the stream
+	pub fn finalize(self) -> DoubleSha256Hasher {
+		DoubleSha256Hasher::from_bytes(
+			Sha256Hasher::new(self.0.finalize()).as_bytes(),
+		)
+		.unwrap()
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -157,4 +213,21 @@ mod tests {
 			Uint256::from_le_bytes(hash.as_bytes()).unwrap()
 		);
 	}
+
+	#[test]
+	fn streamed_hash_should_match_one_shot_hash() {
+		// Header of testnet block 100,000
+		let header = hex::decode("0200000035ab154183570282ce9afc0b494c9fc6a3cfea05aa8c1add2ecc56490000000038ba3d78e4500a5a7570dbe61960398add4410d278b21cd9708e6d9743f374d544fc055227f1001c29c1ea3b").unwrap();
+
+		let mut stream = Sha256Stream::new();
+		let mut double_stream = DoubleSha256Stream::new();
+
+		for chunk in [&header[..4], &header[4..68], &header[68..]] {
+			stream.update(chunk);
+			double_stream.update(chunk);
+		}
+
+		assert_eq!(stream.finalize(), Sha256Hasher::hash(&header));
+		assert_eq!(double_stream.finalize(), DoubleSha256Hasher::hash(&header));
+	}
 }
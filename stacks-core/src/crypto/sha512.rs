@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512_256};
+
+use crate::{
+	crypto::{Hasher, Hashing, Hex},
+	StacksError, StacksResult,
+};
+
+pub(crate) const SHA512_256_LENGTH: usize = 32;
+
+#[derive(
+	Serialize,
+	Deserialize,
+	Debug,
+	Clone,
+	Copy,
+	PartialEq,
+	Eq,
+	PartialOrd,
+	Ord,
+	Hash,
+)]
+#[serde(try_from = "Hex")]
+#[serde(into = "Hex")]
+/// The Sha512/256 hashing type
+pub struct Sha512_256Hashing([u8; SHA512_256_LENGTH]);
+
+impl Hashing<SHA512_256_LENGTH> for Sha512_256Hashing {
+	fn hash(data: &[u8]) -> Self {
+		Self(Sha512_256::digest(data).into())
+	}
+
+	fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+
+	fn from_bytes(bytes: &[u8]) -> StacksResult<Self> {
+		Ok(Self(bytes.try_into()?))
+	}
+}
+
+// From conversion is fallible for this type
+#[allow(clippy::from_over_into)]
+impl Into<Hex> for Sha512_256Hashing {
+	fn into(self) -> Hex {
+		Hex(hex::encode(self.as_bytes()))
+	}
+}
+
+impl TryFrom<Hex> for Sha512_256Hashing {
+	type Error = StacksError;
+
+	fn try_from(value: Hex) -> Result<Self, Self::Error> {
+		Self::from_bytes(&hex::decode(value.0)?)
+	}
+}
+
+/// The Sha512/256 hasher type
+pub type Sha512_256Hasher = Hasher<Sha512_256Hashing, SHA512_256_LENGTH>;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_sha512_256_hash_correctly() {
+		let plaintext = "Hello world";
+		let expected_hash_hex =
+			"f7b55872d4aefe68143bd2ebd928b87f769e15362fcd5a1af8da184bbfcb5fa8";
+
+		assert_eq!(
+			hex::encode(Sha512_256Hasher::hash(plaintext.as_bytes())),
+			expected_hash_hex
+		);
+	}
+}
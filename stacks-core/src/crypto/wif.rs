@@ -1,6 +1,6 @@
 //! WIF parsing and construction of Stacks private keys.
 
-use bdk::bitcoin::util::base58;
+use bdk::bitcoin::{util::base58, Network as BitcoinNetwork};
 use strum::{Display, EnumIter, FromRepr, IntoEnumIterator};
 
 use super::Hashing;
@@ -99,6 +99,33 @@ impl From<Network> for WIFPrefix {
 	}
 }
 
+/// Decodes a WIF into its private key, Bitcoin network and whether the public
+/// key is compressed. Testnet, signet and regtest share a prefix, so they are
+/// all reported as testnet.
+pub fn decode(wif: &str) -> StacksResult<(PrivateKey, BitcoinNetwork, bool)> {
+	let bytes = base58::from_check(wif)?;
+
+	let compressed = match bytes.len() {
+		33 => false,
+		34 if bytes[33] == 0x01 => true,
+		_ => {
+			return Err(StacksError::InvalidData(
+				"WIF has an invalid length or compression byte".into(),
+			))
+		}
+	};
+
+	let network = match WIFPrefix::from_repr(bytes[0]) {
+		Some(WIFPrefix::Mainnet) => BitcoinNetwork::Bitcoin,
+		Some(WIFPrefix::Testnet) => BitcoinNetwork::Testnet,
+		None => {
+			return Err(StacksError::InvalidData("Unknown network byte".into()))
+		}
+	};
+
+	Ok((PrivateKey::from_slice(&bytes[1..33])?, network, compressed))
+}
+
 impl TryFrom<String> for WIF {
 	type Error = StacksError;
 
@@ -142,4 +169,44 @@ mod tests {
 			assert_eq!(bitcoin_pk.network, network.into());
 		}
 	}
+
+	#[test]
+	fn decode_should_detect_network_and_compression() {
+		let (pk, network, compressed) =
+			decode("cN9spWsvaxA8taS7DFMxnk1yJD2gaF2PX1npuTpy3vuZFJdwavaw")
+				.unwrap();
+
+		assert_eq!(network, BitcoinNetwork::Testnet);
+		assert!(compressed);
+		assert_eq!(
+			WIF::new(Network::Testnet, pk).to_string(),
+			"cN9spWsvaxA8taS7DFMxnk1yJD2gaF2PX1npuTpy3vuZFJdwavaw"
+		);
+
+		let pk = Secp256k1::new().generate_keypair(&mut thread_rng()).0;
+
+		for network in [BitcoinNetwork::Bitcoin, BitcoinNetwork::Testnet] {
+			for compressed in [true, false] {
+				let wif = bdk::bitcoin::PrivateKey {
+					compressed,
+					network,
+					inner: pk,
+				}
+				.to_wif();
+
+				assert_eq!(decode(&wif).unwrap(), (pk, network, compressed));
+			}
+		}
+	}
+
+	#[test]
+	fn decode_should_reject_invalid_wif() {
+		let mut wif =
+			"cN9spWsvaxA8taS7DFMxnk1yJD2gaF2PX1npuTpy3vuZFJdwavaw".to_string();
+		wif.pop();
+		wif.push('b');
+
+		assert!(decode(&wif).is_err());
+		assert!(decode("").is_err());
+	}
 }
@@ -11,14 +11,19 @@ use strum::{Display, EnumIter, EnumString, FromRepr};
 use thiserror::Error;
 use uint::Uint256;
 
+pub mod amount;
 /// Module for interacting with stacks addresses
 pub mod address;
 /// Module for c32 encoding and decoding
 pub mod c32;
+pub mod clarity_name;
+pub mod clarity_value;
 pub mod codec;
 pub mod contract_name;
 /// Module for crypto functions
 pub mod crypto;
+/// Module for building, signing and serializing Stacks transactions
+pub mod transaction;
 /// Module for creating large integers and performing basic arithmetic
 pub mod uint;
 /// Module for utility functions
@@ -46,9 +51,15 @@ pub enum StacksError {
 	#[error("Could not create Uint from {0} bytes")]
 	/// Invalid Uint bytes
 	InvalidUintBytes(usize),
+	#[error("Could not parse Uint from decimal string: {0}")]
+	/// Invalid Uint decimal string
+	InvalidUintDecimal(String),
 	#[error("Codec error: {0}")]
 	/// Codec error
 	CodecError(#[from] CodecError),
+	#[error("Invalid signature: {0}")]
+	/// Invalid signature
+	InvalidSignature(&'static str),
 	#[error("Invalid data: {0}")]
 	/// Invalid data
 	InvalidData(String),
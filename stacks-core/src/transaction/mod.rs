@@ -0,0 +1,729 @@
+use std::io::{self, Read};
+
+use bdk::bitcoin::secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use strum::FromRepr;
+
+use self::post_condition::PostCondition;
+use crate::{
+	address::StacksAddress,
+	clarity_name::ClarityName,
+	clarity_value::{read_length, write_length, Value},
+	codec::Codec,
+	contract_name::ContractName,
+	crypto::{
+		hash160::{Hash160Hasher, HASH160_LENGTH},
+		sha512::Sha512_256Hasher,
+		Hashing,
+	},
+	utils::PrincipalData,
+	StacksError, StacksResult,
+};
+
+/// Module for transaction post conditions
+pub mod post_condition;
+/// Module for signing and verifying transactions
+pub mod signer;
+
+/// Length of a recoverable message signature
+pub const MESSAGE_SIGNATURE_LENGTH: usize = 65;
+/// Length of a token transfer memo
+pub const TOKEN_TRANSFER_MEMO_LENGTH: usize = 34;
+
+/// Chain ID of the Stacks mainnet
+pub const CHAIN_ID_MAINNET: u32 = 0x00000001;
+/// Chain ID of the Stacks testnet
+pub const CHAIN_ID_TESTNET: u32 = 0x80000000;
+
+/// Transaction version, identifying the network the transaction is for
+#[repr(u8)]
+#[derive(FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionVersion {
+	/// Mainnet
+	Mainnet = 0x00,
+	/// Testnet
+	Testnet = 0x80,
+}
+
+/// Whether the transaction must be included in an anchored block, a
+/// microblock, or either
+#[repr(u8)]
+#[derive(FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnchorMode {
+	/// Anchored blocks only
+	OnChainOnly = 0x01,
+	/// Microblocks only
+	OffChainOnly = 0x02,
+	/// Either
+	Any = 0x03,
+}
+
+/// Whether asset transfers not covered by post conditions are allowed
+#[repr(u8)]
+#[derive(FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostConditionMode {
+	/// Allow uncovered transfers
+	Allow = 0x01,
+	/// Deny uncovered transfers
+	Deny = 0x02,
+}
+
+/// Authorization flag of a transaction
+#[repr(u8)]
+#[derive(FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionAuthFlag {
+	/// Origin pays the fee
+	Standard = 0x04,
+	/// Sponsor pays the fee
+	Sponsored = 0x05,
+}
+
+/// Hash mode of a single signature spending condition
+#[repr(u8)]
+#[derive(FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinglesigHashMode {
+	/// Pay-to-public-key-hash
+	P2PKH = 0x00,
+	/// Pay-to-witness-public-key-hash
+	P2WPKH = 0x02,
+}
+
+/// Encoding of the public key recovered from a signature
+#[repr(u8)]
+#[derive(FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublicKeyEncoding {
+	/// Compressed public key
+	Compressed = 0x00,
+	/// Uncompressed public key
+	Uncompressed = 0x01,
+}
+
+/// Recoverable signature laid out as the recovery ID followed by the compact
+/// signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageSignature(pub [u8; MESSAGE_SIGNATURE_LENGTH]);
+
+impl MessageSignature {
+	/// Signature made of zeroes, used for unsigned transactions
+	pub fn empty() -> Self {
+		Self([0; MESSAGE_SIGNATURE_LENGTH])
+	}
+}
+
+impl Default for MessageSignature {
+	fn default() -> Self {
+		Self::empty()
+	}
+}
+
+impl From<RecoverableSignature> for MessageSignature {
+	fn from(signature: RecoverableSignature) -> Self {
+		let (id, compact) = signature.serialize_compact();
+
+		let mut bytes = [0; MESSAGE_SIGNATURE_LENGTH];
+		bytes[0] = id.to_i32() as u8;
+		bytes[1..].copy_from_slice(&compact);
+
+		Self(bytes)
+	}
+}
+
+impl TryFrom<&MessageSignature> for RecoverableSignature {
+	type Error = StacksError;
+
+	fn try_from(signature: &MessageSignature) -> Result<Self, Self::Error> {
+		let id = RecoveryId::from_i32(signature.0[0] as i32)?;
+
+		Ok(Self::from_compact(&signature.0[1..], id)?)
+	}
+}
+
+impl Codec for MessageSignature {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		dest.write_all(&self.0)
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		let mut buffer = [0; MESSAGE_SIGNATURE_LENGTH];
+		data.read_exact(&mut buffer)?;
+
+		Ok(Self(buffer))
+	}
+}
+
+/// Spending condition authorized by a single signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinglesigSpendingCondition {
+	/// Hash mode of the signer
+	pub hash_mode: SinglesigHashMode,
+	/// Hash160 of the signer public key
+	pub signer: Hash160Hasher,
+	/// Account nonce
+	pub nonce: u64,
+	/// Fee in micro-STX
+	pub fee: u64,
+	/// Encoding of the signer public key
+	pub key_encoding: PublicKeyEncoding,
+	/// Signature over the transaction
+	pub signature: MessageSignature,
+}
+
+impl SinglesigSpendingCondition {
+	/// Clears the nonce, fee and signature, as done when computing the
+	/// initial sighash
+	pub fn clear(&mut self) {
+		self.nonce = 0;
+		self.fee = 0;
+		self.signature = MessageSignature::empty();
+	}
+}
+
+impl Codec for SinglesigSpendingCondition {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		dest.write_all(&[self.hash_mode as u8])?;
+		dest.write_all(self.signer.as_bytes())?;
+		self.nonce.codec_serialize(dest)?;
+		self.fee.codec_serialize(dest)?;
+		dest.write_all(&[self.key_encoding as u8])?;
+		self.signature.codec_serialize(dest)
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		let hash_mode = read_repr(data, SinglesigHashMode::from_repr)?;
+
+		let mut signer_buffer = [0; HASH160_LENGTH];
+		data.read_exact(&mut signer_buffer)?;
+
+		let nonce = u64::codec_deserialize(data)?;
+		let fee = u64::codec_deserialize(data)?;
+		let key_encoding = read_repr(data, PublicKeyEncoding::from_repr)?;
+		let signature = MessageSignature::codec_deserialize(data)?;
+
+		Ok(Self {
+			hash_mode,
+			signer: signer_buffer.into(),
+			nonce,
+			fee,
+			key_encoding,
+			signature,
+		})
+	}
+}
+
+/// Condition that must be met to spend from an account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionSpendingCondition {
+	/// Single signature
+	Singlesig(SinglesigSpendingCondition),
+}
+
+impl TransactionSpendingCondition {
+	/// Clears the nonce, fee and signatures of the condition
+	pub fn clear(&mut self) {
+		match self {
+			Self::Singlesig(condition) => condition.clear(),
+		}
+	}
+}
+
+impl Codec for TransactionSpendingCondition {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		match self {
+			Self::Singlesig(condition) => condition.codec_serialize(dest),
+		}
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		Ok(Self::Singlesig(
+			SinglesigSpendingCondition::codec_deserialize(data)?,
+		))
+	}
+}
+
+/// Transaction authorization
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionAuth {
+	/// The origin account authorizes and pays for the transaction
+	Standard(TransactionSpendingCondition),
+}
+
+impl TransactionAuth {
+	/// Authorization flag of the transaction
+	pub fn flag(&self) -> TransactionAuthFlag {
+		match self {
+			Self::Standard(_) => TransactionAuthFlag::Standard,
+		}
+	}
+
+	/// Spending condition of the origin account
+	pub fn origin(&self) -> &TransactionSpendingCondition {
+		match self {
+			Self::Standard(origin) => origin,
+		}
+	}
+
+	/// Mutable spending condition of the origin account
+	pub fn origin_mut(&mut self) -> &mut TransactionSpendingCondition {
+		match self {
+			Self::Standard(origin) => origin,
+		}
+	}
+}
+
+impl Codec for TransactionAuth {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		dest.write_all(&[self.flag() as u8])?;
+
+		match self {
+			Self::Standard(origin) => origin.codec_serialize(dest),
+		}
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		match read_repr(data, TransactionAuthFlag::from_repr)? {
+			TransactionAuthFlag::Standard => Ok(Self::Standard(
+				TransactionSpendingCondition::codec_deserialize(data)?,
+			)),
+			TransactionAuthFlag::Sponsored => Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"Sponsored transactions are not supported",
+			)),
+		}
+	}
+}
+
+#[repr(u8)]
+#[derive(FromRepr, Debug, Clone, Copy)]
+enum TransactionPayloadID {
+	TokenTransfer = 0x00,
+	SmartContract = 0x01,
+	ContractCall = 0x02,
+}
+
+/// Call of a public function of a deployed contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionContractCall {
+	/// Address of the contract deployer
+	pub address: StacksAddress,
+	/// Name of the contract
+	pub contract_name: ContractName,
+	/// Name of the called function
+	pub function_name: ClarityName,
+	/// Arguments of the called function
+	pub function_args: Vec<Value>,
+}
+
+impl Codec for TransactionContractCall {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		self.address.codec_serialize(dest)?;
+		self.contract_name.codec_serialize(dest)?;
+		self.function_name.codec_serialize(dest)?;
+		write_length(dest, self.function_args.len())?;
+
+		self.function_args
+			.iter()
+			.try_for_each(|arg| arg.codec_serialize(dest))
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		let address = StacksAddress::codec_deserialize(data)?;
+		let contract_name = ContractName::codec_deserialize(data)?;
+		let function_name = ClarityName::codec_deserialize(data)?;
+		let function_args = (0..read_length(data)?)
+			.map(|_| Value::codec_deserialize(data))
+			.collect::<io::Result<_>>()?;
+
+		Ok(Self {
+			address,
+			contract_name,
+			function_name,
+			function_args,
+		})
+	}
+}
+
+/// Deployment of a new contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionSmartContract {
+	/// Name of the contract
+	pub name: ContractName,
+	/// Clarity source code of the contract
+	pub code_body: String,
+}
+
+impl Codec for TransactionSmartContract {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		self.name.codec_serialize(dest)?;
+		write_length(dest, self.code_body.len())?;
+		dest.write_all(self.code_body.as_bytes())
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		let name = ContractName::codec_deserialize(data)?;
+		let code_length = read_length(data)? as usize;
+
+		let mut code_buffer = Vec::new();
+		data.take(code_length as u64)
+			.read_to_end(&mut code_buffer)?;
+
+		if code_buffer.len() != code_length {
+			return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+		}
+
+		let code_body = String::from_utf8(code_buffer)
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+		Ok(Self { name, code_body })
+	}
+}
+
+/// Payload of a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionPayload {
+	/// Transfer of STX to a recipient, with an amount in micro-STX and a memo
+	TokenTransfer(PrincipalData, u64, [u8; TOKEN_TRANSFER_MEMO_LENGTH]),
+	/// Contract deployment
+	SmartContract(TransactionSmartContract),
+	/// Contract call
+	ContractCall(TransactionContractCall),
+}
+
+impl TransactionPayload {
+	/// Creates a call of the given contract function
+	pub fn contract_call(
+		address: StacksAddress,
+		contract_name: &str,
+		function_name: &str,
+		function_args: impl IntoIterator<Item = Value>,
+	) -> StacksResult<Self> {
+		Ok(Self::ContractCall(TransactionContractCall {
+			address,
+			contract_name: ContractName::new(contract_name)
+				.map_err(|err| StacksError::InvalidData(err.to_string()))?,
+			function_name: ClarityName::new(function_name)
+				.map_err(|err| StacksError::InvalidData(err.to_string()))?,
+			function_args: function_args.into_iter().collect(),
+		}))
+	}
+
+	/// Creates a deployment of the given contract source code
+	pub fn smart_contract(
+		name: &str,
+		code_body: impl Into<String>,
+	) -> StacksResult<Self> {
+		Ok(Self::SmartContract(TransactionSmartContract {
+			name: ContractName::new(name)
+				.map_err(|err| StacksError::InvalidData(err.to_string()))?,
+			code_body: code_body.into(),
+		}))
+	}
+}
+
+impl Codec for TransactionPayload {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		match self {
+			Self::TokenTransfer(recipient, amount, memo) => {
+				dest.write_all(&[TransactionPayloadID::TokenTransfer as u8])?;
+				recipient.codec_serialize(dest)?;
+				amount.codec_serialize(dest)?;
+				dest.write_all(memo)
+			}
+			Self::SmartContract(smart_contract) => {
+				dest.write_all(&[TransactionPayloadID::SmartContract as u8])?;
+				smart_contract.codec_serialize(dest)
+			}
+			Self::ContractCall(contract_call) => {
+				dest.write_all(&[TransactionPayloadID::ContractCall as u8])?;
+				contract_call.codec_serialize(dest)
+			}
+		}
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		match read_repr(data, TransactionPayloadID::from_repr)? {
+			TransactionPayloadID::TokenTransfer => {
+				let recipient = PrincipalData::codec_deserialize(data)?;
+				let amount = u64::codec_deserialize(data)?;
+
+				let mut memo = [0; TOKEN_TRANSFER_MEMO_LENGTH];
+				data.read_exact(&mut memo)?;
+
+				Ok(Self::TokenTransfer(recipient, amount, memo))
+			}
+			TransactionPayloadID::SmartContract => Ok(Self::SmartContract(
+				TransactionSmartContract::codec_deserialize(data)?,
+			)),
+			TransactionPayloadID::ContractCall => Ok(Self::ContractCall(
+				TransactionContractCall::codec_deserialize(data)?,
+			)),
+		}
+	}
+}
+
+/// A Stacks transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+	/// Transaction version
+	pub version: TransactionVersion,
+	/// Chain ID
+	pub chain_id: u32,
+	/// Authorization
+	pub auth: TransactionAuth,
+	/// Anchor mode
+	pub anchor_mode: AnchorMode,
+	/// Post condition mode
+	pub post_condition_mode: PostConditionMode,
+	/// Post conditions
+	pub post_conditions: Vec<PostCondition>,
+	/// Payload
+	pub payload: TransactionPayload,
+}
+
+impl Transaction {
+	/// Transaction ID, the Sha512/256 hash of the serialized transaction
+	pub fn txid(&self) -> Sha512_256Hasher {
+		Sha512_256Hasher::new(self.serialize_to_vec())
+	}
+}
+
+impl Codec for Transaction {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		dest.write_all(&[self.version as u8])?;
+		dest.write_all(&self.chain_id.to_be_bytes())?;
+		self.auth.codec_serialize(dest)?;
+		dest.write_all(&[self.anchor_mode as u8])?;
+		dest.write_all(&[self.post_condition_mode as u8])?;
+		dest.write_all(&(self.post_conditions.len() as u32).to_be_bytes())?;
+		for post_condition in &self.post_conditions {
+			post_condition.codec_serialize(dest)?;
+		}
+		self.payload.codec_serialize(dest)
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		let version = read_repr(data, TransactionVersion::from_repr)?;
+
+		let mut chain_id_buffer = [0; 4];
+		data.read_exact(&mut chain_id_buffer)?;
+
+		let auth = TransactionAuth::codec_deserialize(data)?;
+		let anchor_mode = read_repr(data, AnchorMode::from_repr)?;
+		let post_condition_mode =
+			read_repr(data, PostConditionMode::from_repr)?;
+
+		let mut post_conditions_length_buffer = [0; 4];
+		data.read_exact(&mut post_conditions_length_buffer)?;
+
+		let post_conditions =
+			(0..u32::from_be_bytes(post_conditions_length_buffer))
+				.map(|_| PostCondition::codec_deserialize(data))
+				.collect::<io::Result<_>>()?;
+
+		let payload = TransactionPayload::codec_deserialize(data)?;
+
+		Ok(Self {
+			version,
+			chain_id: u32::from_be_bytes(chain_id_buffer),
+			auth,
+			anchor_mode,
+			post_condition_mode,
+			post_conditions,
+			payload,
+		})
+	}
+}
+
+fn read_repr<R: io::Read, T>(
+	data: &mut R,
+	from_repr: impl FnOnce(u8) -> Option<T>,
+) -> io::Result<T> {
+	let mut buffer = [0; 1];
+	data.read_exact(&mut buffer)?;
+
+	from_repr(buffer[0]).ok_or_else(|| {
+		io::Error::new(
+			io::ErrorKind::InvalidData,
+			format!("Invalid byte: {}", buffer[0]),
+		)
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		address::AddressVersion,
+		transaction::post_condition::{
+			AssetInfo, FungibleConditionCode, PostConditionPrincipal,
+		},
+	};
+
+	// Unsigned testnet token transfer with an STX and a fungible token post
+	// condition. Must match blockstack_lib's serialization, which the romeo
+	// stacks client tests check by serializing the same transaction with both
+	const TX_WITH_POST_CONDITIONS_HEX: &str = "8080000000040015c31b8c1c11c515e244b75806bac48d1399c775000000000000000500000000000000b400000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000003020000000200010500000000000003e801021a22222222222222222222222222222222222222221a3333333333333333333333333333333333333333056173736574047362746301000000000000000500051a111111111111111111111111111111111111111100000000000003e800000000000000000000000000000000000000000000000000000000000000000000";
+
+	// Call of `asset.mint` with an amount and a recipient. Must match
+	// blockstack_lib's serialization, which the romeo stacks client tests
+	// check by serializing the same payload with both
+	const CONTRACT_CALL_PAYLOAD_HEX: &str = "021a3333333333333333333333333333333333333333056173736574046d696e740000000201000000000000000000000000000003e8051a1111111111111111111111111111111111111111";
+
+	fn address(byte: u8) -> StacksAddress {
+		StacksAddress::new(AddressVersion::TestnetSingleSig, [byte; 20].into())
+	}
+
+	fn transaction_with_post_conditions() -> Transaction {
+		Transaction {
+			version: TransactionVersion::Testnet,
+			chain_id: CHAIN_ID_TESTNET,
+			auth: TransactionAuth::Standard(
+				TransactionSpendingCondition::Singlesig(
+					SinglesigSpendingCondition {
+						hash_mode: SinglesigHashMode::P2PKH,
+						signer: Hash160Hasher::from_hex(
+							"15c31b8c1c11c515e244b75806bac48d1399c775",
+						)
+						.unwrap(),
+						nonce: 5,
+						fee: 180,
+						key_encoding: PublicKeyEncoding::Compressed,
+						signature: MessageSignature::empty(),
+					},
+				),
+			),
+			anchor_mode: AnchorMode::Any,
+			post_condition_mode: PostConditionMode::Deny,
+			post_conditions: vec![
+				PostCondition::Stx(
+					PostConditionPrincipal::Origin,
+					FungibleConditionCode::SentLe,
+					1000,
+				),
+				PostCondition::Fungible(
+					PostConditionPrincipal::Standard(address(0x22)),
+					AssetInfo {
+						contract_address: address(0x33),
+						contract_name: ContractName::new("asset").unwrap(),
+						asset_name: ClarityName::new("sbtc").unwrap(),
+					},
+					FungibleConditionCode::SentEq,
+					5,
+				),
+			],
+			payload: TransactionPayload::TokenTransfer(
+				PrincipalData::from(address(0x11)),
+				1000,
+				[0; TOKEN_TRANSFER_MEMO_LENGTH],
+			),
+		}
+	}
+
+	#[test]
+	fn should_serialize_post_conditions() {
+		assert_eq!(
+			hex::encode(transaction_with_post_conditions().serialize_to_vec()),
+			TX_WITH_POST_CONDITIONS_HEX
+		);
+	}
+
+	#[test]
+	fn should_round_trip_post_conditions() {
+		let bytes = hex::decode(TX_WITH_POST_CONDITIONS_HEX).unwrap();
+		let transaction =
+			Transaction::deserialize(&mut bytes.as_slice()).unwrap();
+
+		assert_eq!(transaction, transaction_with_post_conditions());
+		assert_eq!(transaction.serialize_to_vec(), bytes);
+	}
+
+	#[test]
+	fn should_reject_nonfungible_post_conditions() {
+		let mut bytes = hex::decode(TX_WITH_POST_CONDITIONS_HEX).unwrap();
+		// First post condition, right after its count
+		let asset_info_id_index =
+			1 + 4 + 1 + 1 + 20 + 8 + 8 + 1 + 65 + 1 + 1 + 4;
+		bytes[asset_info_id_index] = 0x02;
+
+		assert!(Transaction::deserialize(&mut bytes.as_slice()).is_err());
+	}
+
+	#[test]
+	fn should_serialize_contract_call() {
+		let payload = TransactionPayload::contract_call(
+			address(0x33),
+			"asset",
+			"mint",
+			[
+				Value::UInt(1000),
+				Value::from(PrincipalData::from(address(0x11))),
+			],
+		)
+		.unwrap();
+
+		let bytes = payload.serialize_to_vec();
+
+		assert_eq!(hex::encode(&bytes), CONTRACT_CALL_PAYLOAD_HEX);
+		assert_eq!(
+			TransactionPayload::deserialize(&mut bytes.as_slice()).unwrap(),
+			payload
+		);
+	}
+
+	#[test]
+	fn should_reject_invalid_contract_call_names() {
+		assert!(TransactionPayload::contract_call(
+			address(0x33),
+			"asset.sbtc",
+			"mint",
+			[]
+		)
+		.is_err());
+		assert!(TransactionPayload::contract_call(
+			address(0x33),
+			"asset",
+			"mint sbtc",
+			[]
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn should_round_trip_smart_contract() {
+		let payload = TransactionPayload::smart_contract(
+			"asset",
+			"(define-fungible-token sbtc)",
+		)
+		.unwrap();
+
+		let bytes = payload.serialize_to_vec();
+
+		assert_eq!(&bytes[..8], [0x01, 0x05, b'a', b's', b's', b'e', b't', 0]);
+		assert_eq!(
+			TransactionPayload::deserialize(&mut bytes.as_slice()).unwrap(),
+			payload
+		);
+	}
+}
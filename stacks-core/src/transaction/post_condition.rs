@@ -0,0 +1,176 @@
+use std::io;
+
+use strum::FromRepr;
+
+use super::read_repr;
+use crate::{
+	address::StacksAddress, clarity_name::ClarityName, codec::Codec,
+	contract_name::ContractName,
+};
+
+#[repr(u8)]
+#[derive(FromRepr, Debug, Clone, Copy)]
+enum PostConditionPrincipalID {
+	Origin = 0x01,
+	Standard = 0x02,
+	Contract = 0x03,
+}
+
+/// Principal whose assets a post condition is about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PostConditionPrincipal {
+	/// Origin account of the transaction
+	Origin,
+	/// Standard account
+	Standard(StacksAddress),
+	/// Contract account
+	Contract(StacksAddress, ContractName),
+}
+
+impl Codec for PostConditionPrincipal {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		match self {
+			Self::Origin => {
+				dest.write_all(&[PostConditionPrincipalID::Origin as u8])
+			}
+			Self::Standard(address) => {
+				dest.write_all(&[PostConditionPrincipalID::Standard as u8])?;
+				address.codec_serialize(dest)
+			}
+			Self::Contract(address, contract_name) => {
+				dest.write_all(&[PostConditionPrincipalID::Contract as u8])?;
+				address.codec_serialize(dest)?;
+				contract_name.codec_serialize(dest)
+			}
+		}
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		match read_repr(data, PostConditionPrincipalID::from_repr)? {
+			PostConditionPrincipalID::Origin => Ok(Self::Origin),
+			PostConditionPrincipalID::Standard => {
+				Ok(Self::Standard(StacksAddress::codec_deserialize(data)?))
+			}
+			PostConditionPrincipalID::Contract => Ok(Self::Contract(
+				StacksAddress::codec_deserialize(data)?,
+				ContractName::codec_deserialize(data)?,
+			)),
+		}
+	}
+}
+
+/// Comparison between the amount a principal sends and the amount of a post
+/// condition
+#[repr(u8)]
+#[derive(FromRepr, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FungibleConditionCode {
+	/// Sent exactly the amount
+	SentEq = 0x01,
+	/// Sent more than the amount
+	SentGt = 0x02,
+	/// Sent at least the amount
+	SentGe = 0x03,
+	/// Sent less than the amount
+	SentLt = 0x04,
+	/// Sent at most the amount
+	SentLe = 0x05,
+}
+
+/// Asset defined by a contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetInfo {
+	/// Address of the contract defining the asset
+	pub contract_address: StacksAddress,
+	/// Name of the contract defining the asset
+	pub contract_name: ContractName,
+	/// Name of the asset in the contract
+	pub asset_name: ClarityName,
+}
+
+impl Codec for AssetInfo {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		self.contract_address.codec_serialize(dest)?;
+		self.contract_name.codec_serialize(dest)?;
+		self.asset_name.codec_serialize(dest)
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		Ok(Self {
+			contract_address: StacksAddress::codec_deserialize(data)?,
+			contract_name: ContractName::codec_deserialize(data)?,
+			asset_name: ClarityName::codec_deserialize(data)?,
+		})
+	}
+}
+
+#[repr(u8)]
+#[derive(FromRepr, Debug, Clone, Copy)]
+enum AssetInfoID {
+	Stx = 0x00,
+	Fungible = 0x01,
+	Nonfungible = 0x02,
+}
+
+/// Condition on the assets sent by a principal that must hold for the
+/// transaction to be valid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PostCondition {
+	/// Amount of micro-STX sent
+	Stx(PostConditionPrincipal, FungibleConditionCode, u64),
+	/// Amount of a fungible token sent
+	Fungible(
+		PostConditionPrincipal,
+		AssetInfo,
+		FungibleConditionCode,
+		u64,
+	),
+}
+
+impl Codec for PostCondition {
+	fn codec_serialize<W: io::Write>(&self, dest: &mut W) -> io::Result<()> {
+		match self {
+			Self::Stx(principal, code, amount) => {
+				dest.write_all(&[AssetInfoID::Stx as u8])?;
+				principal.codec_serialize(dest)?;
+				dest.write_all(&[*code as u8])?;
+				amount.codec_serialize(dest)
+			}
+			Self::Fungible(principal, asset, code, amount) => {
+				dest.write_all(&[AssetInfoID::Fungible as u8])?;
+				principal.codec_serialize(dest)?;
+				asset.codec_serialize(dest)?;
+				dest.write_all(&[*code as u8])?;
+				amount.codec_serialize(dest)
+			}
+		}
+	}
+
+	fn codec_deserialize<R: io::Read>(data: &mut R) -> io::Result<Self>
+	where
+		Self: Sized,
+	{
+		match read_repr(data, AssetInfoID::from_repr)? {
+			AssetInfoID::Stx => Ok(Self::Stx(
+				PostConditionPrincipal::codec_deserialize(data)?,
+				read_repr(data, FungibleConditionCode::from_repr)?,
+				u64::codec_deserialize(data)?,
+			)),
+			AssetInfoID::Fungible => Ok(Self::Fungible(
+				PostConditionPrincipal::codec_deserialize(data)?,
+				AssetInfo::codec_deserialize(data)?,
+				read_repr(data, FungibleConditionCode::from_repr)?,
+				u64::codec_deserialize(data)?,
+			)),
+			AssetInfoID::Nonfungible => Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"Non-fungible post conditions are not supported",
+			)),
+		}
+	}
+}
@@ -0,0 +1,291 @@
+use bdk::bitcoin::secp256k1::{
+	ecdsa::RecoverableSignature, Message, Secp256k1,
+};
+
+use super::{
+	MessageSignature, PublicKeyEncoding, SinglesigHashMode, Transaction,
+	TransactionAuthFlag, TransactionSpendingCondition,
+};
+use crate::{
+	address::{hash_p2pkh, hash_p2wpkh},
+	crypto::{
+		hash160::Hash160Hasher, sha512::Sha512_256Hasher, Hashing, PrivateKey,
+		PublicKey,
+	},
+	StacksError, StacksResult,
+};
+
+/// Signs the spending conditions of a transaction, keeping track of the
+/// sighash chain between signatures
+#[derive(Debug, Clone)]
+pub struct TransactionSigner {
+	transaction: Transaction,
+	sighash: Sha512_256Hasher,
+}
+
+impl TransactionSigner {
+	/// Creates a signer for the transaction
+	pub fn new(transaction: Transaction) -> Self {
+		let sighash = initial_sighash(&transaction);
+
+		Self {
+			transaction,
+			sighash,
+		}
+	}
+
+	/// Signs the origin spending condition. The private key must belong to
+	/// the origin signer.
+	pub fn sign_origin(
+		&mut self,
+		private_key: &PrivateKey,
+	) -> StacksResult<()> {
+		let auth_flag = self.transaction.auth.flag();
+		let TransactionSpendingCondition::Singlesig(condition) =
+			self.transaction.auth.origin_mut();
+
+		let public_key = private_key.public_key(&Secp256k1::new());
+
+		if signer_hash(
+			condition.hash_mode,
+			condition.key_encoding,
+			&public_key,
+		)? != condition.signer
+		{
+			return Err(StacksError::InvalidArguments(
+				"Private key does not belong to the origin signer",
+			));
+		}
+
+		let presign = presign_sighash(
+			&self.sighash,
+			auth_flag,
+			condition.fee,
+			condition.nonce,
+		);
+		let signature = Secp256k1::new().sign_ecdsa_recoverable(
+			&Message::from_slice(presign.as_bytes())?,
+			private_key,
+		);
+
+		condition.signature = signature.into();
+		self.sighash = postsign_sighash(
+			&presign,
+			condition.key_encoding,
+			&condition.signature,
+		);
+
+		Ok(())
+	}
+
+	/// Transaction being signed
+	pub fn transaction(&self) -> &Transaction {
+		&self.transaction
+	}
+
+	/// Consumes the signer, returning the signed transaction
+	pub fn into_transaction(self) -> Transaction {
+		self.transaction
+	}
+}
+
+impl Transaction {
+	/// Verifies that the origin signature was made by the origin signer over
+	/// this transaction
+	pub fn verify_origin(&self) -> StacksResult<()> {
+		let TransactionSpendingCondition::Singlesig(condition) =
+			self.auth.origin();
+
+		let presign = presign_sighash(
+			&initial_sighash(self),
+			self.auth.flag(),
+			condition.fee,
+			condition.nonce,
+		);
+		let public_key = Secp256k1::new().recover_ecdsa(
+			&Message::from_slice(presign.as_bytes())?,
+			&RecoverableSignature::try_from(&condition.signature)?,
+		)?;
+
+		if signer_hash(
+			condition.hash_mode,
+			condition.key_encoding,
+			&public_key,
+		)? != condition.signer
+		{
+			return Err(StacksError::InvalidSignature(
+				"Signature was not made by the origin signer",
+			));
+		}
+
+		Ok(())
+	}
+}
+
+/// Sighash of the transaction with its spending conditions cleared
+fn initial_sighash(transaction: &Transaction) -> Sha512_256Hasher {
+	let mut transaction = transaction.clone();
+	transaction.auth.origin_mut().clear();
+
+	transaction.txid()
+}
+
+fn presign_sighash(
+	sighash: &Sha512_256Hasher,
+	auth_flag: TransactionAuthFlag,
+	fee: u64,
+	nonce: u64,
+) -> Sha512_256Hasher {
+	let mut data = sighash.as_bytes().to_vec();
+	data.push(auth_flag as u8);
+	data.extend_from_slice(&fee.to_be_bytes());
+	data.extend_from_slice(&nonce.to_be_bytes());
+
+	Sha512_256Hasher::new(data)
+}
+
+fn postsign_sighash(
+	presign: &Sha512_256Hasher,
+	key_encoding: PublicKeyEncoding,
+	signature: &MessageSignature,
+) -> Sha512_256Hasher {
+	let mut data = presign.as_bytes().to_vec();
+	data.push(key_encoding as u8);
+	data.extend_from_slice(&signature.0);
+
+	Sha512_256Hasher::new(data)
+}
+
+fn signer_hash(
+	hash_mode: SinglesigHashMode,
+	key_encoding: PublicKeyEncoding,
+	public_key: &PublicKey,
+) -> StacksResult<Hash160Hasher> {
+	match (hash_mode, key_encoding) {
+		(SinglesigHashMode::P2PKH, PublicKeyEncoding::Compressed) => {
+			Ok(hash_p2pkh(public_key))
+		}
+		(SinglesigHashMode::P2PKH, PublicKeyEncoding::Uncompressed) => {
+			Ok(Hash160Hasher::new(public_key.serialize_uncompressed()))
+		}
+		(SinglesigHashMode::P2WPKH, PublicKeyEncoding::Compressed) => {
+			Ok(hash_p2wpkh(public_key))
+		}
+		(SinglesigHashMode::P2WPKH, PublicKeyEncoding::Uncompressed) => {
+			Err(StacksError::InvalidArguments(
+				"P2WPKH spending conditions require a compressed public key",
+			))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		address::{AddressVersion, StacksAddress},
+		codec::Codec,
+		transaction::{
+			AnchorMode, PostConditionMode, SinglesigSpendingCondition,
+			TransactionAuth, TransactionPayload, TransactionVersion,
+			CHAIN_ID_TESTNET,
+		},
+		utils::PrincipalData,
+	};
+
+	const PRIVATE_KEY_HEX: &str =
+		"edf9aee84d9b7abc145504dde6726c64f369d37ee34ded868fabd876c26570bc";
+	// Must match blockstack_lib's StacksTransactionSigner, which the romeo
+	// stacks client tests check by signing the same transaction with both
+	const SIGNED_TX_HEX: &str = "8080000000040015c31b8c1c11c515e244b75806bac48d1399c775000000000000000500000000000000b40000308f974700c8916b7f7c77a5530c5f644bbd1448893a8a72f205ee99a0800382723bb49151ad86472b56f8963a32273bf5616df36c08eede84b5bda4e76220f703020000000000051a111111111111111111111111111111111111111100000000000003e800000000000000000000000000000000000000000000000000000000000000000000";
+	const SIGNED_TXID_HEX: &str =
+		"74f70bc3608d6a27fd75e2de83e471401f8495eba3150a49f23a42f29154405f";
+
+	fn unsigned_transaction(private_key: &PrivateKey) -> Transaction {
+		let public_key = private_key.public_key(&Secp256k1::new());
+		let recipient = StacksAddress::new(
+			AddressVersion::TestnetSingleSig,
+			[0x11; 20].into(),
+		);
+
+		Transaction {
+			version: TransactionVersion::Testnet,
+			chain_id: CHAIN_ID_TESTNET,
+			auth: TransactionAuth::Standard(
+				TransactionSpendingCondition::Singlesig(
+					SinglesigSpendingCondition {
+						hash_mode: SinglesigHashMode::P2PKH,
+						signer: hash_p2pkh(&public_key),
+						nonce: 5,
+						fee: 180,
+						key_encoding: PublicKeyEncoding::Compressed,
+						signature: MessageSignature::empty(),
+					},
+				),
+			),
+			anchor_mode: AnchorMode::Any,
+			post_condition_mode: PostConditionMode::Deny,
+			post_conditions: vec![],
+			payload: TransactionPayload::TokenTransfer(
+				PrincipalData::from(recipient),
+				1000,
+				[0; 34],
+			),
+		}
+	}
+
+	#[test]
+	fn should_sign_transaction_like_reference_implementation() {
+		let private_key =
+			PrivateKey::from_slice(&hex::decode(PRIVATE_KEY_HEX).unwrap())
+				.unwrap();
+
+		let mut signer =
+			TransactionSigner::new(unsigned_transaction(&private_key));
+		signer.sign_origin(&private_key).unwrap();
+		let transaction = signer.into_transaction();
+
+		assert_eq!(hex::encode(transaction.serialize_to_vec()), SIGNED_TX_HEX);
+		assert_eq!(transaction.txid().to_hex(), SIGNED_TXID_HEX);
+		transaction.verify_origin().unwrap();
+	}
+
+	#[test]
+	fn should_round_trip_signed_transaction() {
+		let bytes = hex::decode(SIGNED_TX_HEX).unwrap();
+		let transaction =
+			Transaction::deserialize(&mut bytes.as_slice()).unwrap();
+
+		transaction.verify_origin().unwrap();
+		assert_eq!(transaction.serialize_to_vec(), bytes);
+	}
+
+	#[test]
+	fn should_reject_tampered_transaction() {
+		let bytes = hex::decode(SIGNED_TX_HEX).unwrap();
+		let mut transaction =
+			Transaction::deserialize(&mut bytes.as_slice()).unwrap();
+
+		let TransactionPayload::TokenTransfer(_, amount, _) =
+			&mut transaction.payload
+		else {
+			panic!("Expected a token transfer");
+		};
+		*amount += 1;
+
+		assert!(transaction.verify_origin().is_err());
+	}
+
+	#[test]
+	fn should_not_sign_with_foreign_key() {
+		let private_key =
+			PrivateKey::from_slice(&hex::decode(PRIVATE_KEY_HEX).unwrap())
+				.unwrap();
+		let foreign_key = PrivateKey::from_slice(&[0x01; 32]).unwrap();
+
+		let mut signer =
+			TransactionSigner::new(unsigned_transaction(&private_key));
+
+		assert!(signer.sign_origin(&foreign_key).is_err());
+	}
+}
@@ -2,7 +2,7 @@ use std::{
 	cmp::Ordering,
 	fmt, io,
 	mem::transmute,
-	ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Not, Shl, Shr, Sub},
+	ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Not, Rem, Shl, Shr, Sub},
 };
 
 use serde::{Deserialize, Serialize};
@@ -180,6 +180,169 @@ impl<const N: usize> Uint<N> {
 		Self::from_be_bytes(hex::decode(data.as_ref())?)
 	}
 
+	/// Build from a decimal string, rejecting non-digit characters and values
+	/// that don't fit
+	pub fn from_dec_str(s: &str) -> StacksResult<Self> {
+		if s.is_empty() {
+			return Err(StacksError::InvalidUintDecimal(s.to_string()));
+		}
+
+		let ten = Self::from(10u64);
+		let max_div_ten = Self::MAX / ten;
+		let max_rem_ten = (Self::MAX - max_div_ten.mul_u32(10)).low_u32();
+
+		let mut ret = Self::MIN;
+
+		for ch in s.chars() {
+			let digit = ch.to_digit(10).ok_or_else(|| {
+				StacksError::InvalidUintDecimal(s.to_string())
+			})?;
+
+			if ret > max_div_ten || (ret == max_div_ten && digit > max_rem_ten)
+			{
+				return Err(StacksError::InvalidUintDecimal(s.to_string()));
+			}
+
+			ret = ret.mul_u32(10) + Self::from(digit);
+		}
+
+		Ok(ret)
+	}
+
+	/// Convert to a decimal string
+	pub fn to_dec_string(&self) -> String {
+		let ten = Self::from(10u64);
+		let mut digits = vec![];
+		let mut value = *self;
+
+		while value != Self::MIN {
+			let (quotient, digit) = value.div_rem(ten);
+
+			digits.push(char::from_digit(digit.low_u32(), 10).unwrap());
+			value = quotient;
+		}
+
+		if digits.is_empty() {
+			return "0".to_string();
+		}
+
+		digits.iter().rev().collect()
+	}
+
+	/// Addition returning the wrapped result and whether it overflowed
+	pub fn overflowing_add(self, other: Self) -> (Self, bool) {
+		let mut ret = [0u64; N];
+		let mut carry = false;
+
+		for i in 0..N {
+			let (sum, overflow_a) = self.0[i].overflowing_add(other.0[i]);
+			let (sum, overflow_b) = sum.overflowing_add(carry as u64);
+
+			ret[i] = sum;
+			carry = overflow_a || overflow_b;
+		}
+
+		(Self(ret), carry)
+	}
+
+	/// Checked addition, returning `None` on overflow
+	pub fn checked_add(self, other: Self) -> Option<Self> {
+		match self.overflowing_add(other) {
+			(ret, false) => Some(ret),
+			(_, true) => None,
+		}
+	}
+
+	/// Checked subtraction, returning `None` on underflow
+	pub fn checked_sub(self, other: Self) -> Option<Self> {
+		if other > self {
+			None
+		} else {
+			Some(self - other)
+		}
+	}
+
+	/// Checked multiplication, returning `None` on overflow
+	pub fn checked_mul(self, other: Self) -> Option<Self> {
+		if other == Self::MIN {
+			return Some(Self::MIN);
+		}
+
+		let ret = self * other;
+
+		// A wrapped product is always smaller than the real one
+		if ret / other == self {
+			Some(ret)
+		} else {
+			None
+		}
+	}
+
+	/// Checked division, returning `None` when dividing by zero
+	pub fn checked_div(self, other: Self) -> Option<Self> {
+		if other == Self::MIN {
+			None
+		} else {
+			Some(self / other)
+		}
+	}
+
+	/// Quotient and remainder of the division, computed in a single pass
+	pub fn div_rem(&self, other: Self) -> (Self, Self) {
+		let mut sub_copy = *self;
+		let mut shift_copy = other;
+		let mut ret = [0u64; N];
+
+		let my_bits = self.bits();
+		let your_bits = other.bits();
+
+		// Check for division by 0
+		assert!(your_bits != 0);
+
+		// Early return in case we are dividing by a larger number than us
+		if my_bits < your_bits {
+			return (Self(ret), sub_copy);
+		}
+
+		// Bitwise long division
+		let mut shift = my_bits - your_bits;
+		shift_copy = shift_copy << shift;
+
+		loop {
+			if sub_copy >= shift_copy {
+				ret[shift / 64] |= 1 << (shift % 64);
+				sub_copy = sub_copy - shift_copy;
+			}
+			shift_copy = shift_copy >> 1;
+
+			if shift == 0 {
+				break;
+			}
+
+			shift -= 1;
+		}
+
+		(Self(ret), sub_copy)
+	}
+
+	/// Wrapping exponentiation by squaring
+	pub fn pow(&self, exp: u32) -> Self {
+		let mut base = *self;
+		let mut exp = exp;
+		let mut ret = Self::one();
+
+		while exp > 0 {
+			if exp & 1 == 1 {
+				ret = ret * base;
+			}
+
+			base = base * base;
+			exp >>= 1;
+		}
+
+		ret
+	}
+
 	/// Wrapping add by one operation
 	pub fn increment(&mut self) {
 		let &mut Uint(ref mut arr) = self;
@@ -300,40 +463,15 @@ impl<const N: usize> Div<Uint<N>> for Uint<N> {
 	type Output = Self;
 
 	fn div(self, other: Self) -> Self {
-		let mut sub_copy = self;
-		let mut shift_copy = other;
-		let mut ret = [0u64; N];
-
-		let my_bits = self.bits();
-		let your_bits = other.bits();
-
-		// Check for division by 0
-		assert!(your_bits != 0);
-
-		// Early return in case we are dividing by a larger number than us
-		if my_bits < your_bits {
-			return Self(ret);
-		}
-
-		// Bitwise long division
-		let mut shift = my_bits - your_bits;
-		shift_copy = shift_copy << shift;
-
-		loop {
-			if sub_copy >= shift_copy {
-				ret[shift / 64] |= 1 << (shift % 64);
-				sub_copy = sub_copy - shift_copy;
-			}
-			shift_copy = shift_copy >> 1;
-
-			if shift == 0 {
-				break;
-			}
+		self.div_rem(other).0
+	}
+}
 
-			shift -= 1;
-		}
+impl<const N: usize> Rem<Uint<N>> for Uint<N> {
+	type Output = Self;
 
-		Self(ret)
+	fn rem(self, other: Self) -> Self {
+		self.div_rem(other).1
 	}
 }
 
@@ -594,6 +732,35 @@ impl<const N: usize> TryFrom<Vec<u64>> for Uint<N> {
 	}
 }
 
+/// Serde helper representing a [`Uint`] as a `0x`-prefixed big-endian hex
+/// string instead of an array of limbs. Opt in with
+/// `#[serde(with = "stacks_core::uint::serde_hex")]`.
+pub mod serde_hex {
+	use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+	use super::Uint;
+
+	/// Serialize as a `0x`-prefixed big-endian hex string
+	pub fn serialize<S: Serializer, const N: usize>(
+		value: &Uint<N>,
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(&format!("0x{}", value.to_be_hex()))
+	}
+
+	/// Deserialize from a `0x`-prefixed big-endian hex string
+	pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+		deserializer: D,
+	) -> Result<Uint<N>, D::Error> {
+		let value = String::deserialize(deserializer)?;
+		let hex = value.strip_prefix("0x").ok_or_else(|| {
+			D::Error::custom(format!("Missing 0x prefix in {}", value))
+		})?;
+
+		Uint::from_be_hex(hex).map_err(D::Error::custom)
+	}
+}
+
 /// A 256-bit unsigned integer
 pub type Uint256 = Uint<4>;
 /// A 512-bit unsigned integer
@@ -978,6 +1145,228 @@ mod tests {
 		assert_eq!(Uint256::from_be_bytes(init.to_be_bytes()).unwrap(), init);
 	}
 
+	#[test]
+	fn decimal_should_round_trip_extremes() {
+		let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+
+		assert_eq!(Uint256::MAX.to_dec_string(), max);
+		assert_eq!(Uint256::from_dec_str(max).unwrap(), Uint256::MAX);
+
+		assert_eq!(Uint256::MIN.to_dec_string(), "0");
+		assert_eq!(Uint256::from_dec_str("0").unwrap(), Uint256::MIN);
+	}
+
+	#[test]
+	fn decimal_should_span_multiple_limbs() {
+		let two_pow_64 = Uint256::from_u64_array([0, 1, 0, 0]);
+		assert_eq!(two_pow_64.to_dec_string(), "18446744073709551616");
+		assert_eq!(
+			Uint256::from_dec_str("18446744073709551616").unwrap(),
+			two_pow_64
+		);
+
+		let u128_max = Uint256::from(u128::MAX);
+		assert_eq!(
+			u128_max.to_dec_string(),
+			"340282366920938463463374607431768211455"
+		);
+
+		let value = Uint256::from_u64_array([
+			0xDEADBEEFDEADBEEF,
+			0x0102030405060708,
+			0xFFFFFFFFFFFFFFFF,
+			0x1,
+		]);
+		assert_eq!(
+			Uint256::from_dec_str(&value.to_dec_string()).unwrap(),
+			value
+		);
+		assert_eq!(
+			Uint256::from_dec_str("000133646").unwrap(),
+			Uint256::from(133646u64)
+		);
+	}
+
+	#[test]
+	fn decimal_should_reject_invalid_strings() {
+		// Uint256::MAX + 1
+		assert!(Uint256::from_dec_str(
+			"115792089237316195423570985008687907853269984665640564039457584007913129639936"
+		)
+		.is_err());
+		assert!(Uint256::from_dec_str(
+			"1157920892373161954235709850086879078532699846656405640394575840079131296399350"
+		)
+		.is_err());
+		assert!(Uint256::from_dec_str("").is_err());
+		assert!(Uint256::from_dec_str("12a").is_err());
+		assert!(Uint256::from_dec_str("-1").is_err());
+		assert!(Uint256::from_dec_str("1_000").is_err());
+	}
+
+	#[test]
+	fn checked_add_should_detect_overflow() {
+		let one = Uint256::from(1u64);
+
+		assert_eq!(Uint256::MAX.checked_add(one), None);
+		assert_eq!(Uint256::MAX.overflowing_add(one), (Uint256::MIN, true));
+		assert_eq!(
+			Uint256::from(u64::MAX).checked_add(one),
+			Some(Uint256::from_u64_array([0, 1, 0, 0]))
+		);
+		assert_eq!(
+			Uint256::from_u64_array([u64::MAX, u64::MAX, u64::MAX, 0])
+				.overflowing_add(one),
+			(Uint256::from_u64_array([0, 0, 0, 1]), false)
+		);
+	}
+
+	#[test]
+	fn checked_sub_should_detect_underflow() {
+		let one = Uint256::from(1u64);
+
+		assert_eq!(Uint256::MIN.checked_sub(one), None);
+		assert_eq!(
+			Uint256::from_u64_array([0, 1, 0, 0]).checked_sub(one),
+			Some(Uint256::from(u64::MAX))
+		);
+		assert_eq!(one.checked_sub(one), Some(Uint256::MIN));
+	}
+
+	#[test]
+	fn checked_mul_should_detect_overflow() {
+		let two = Uint256::from(2u64);
+		let half = Uint256::MAX >> 1;
+
+		assert_eq!(
+			half.checked_mul(two),
+			Some(Uint256::MAX - Uint256::from(1u64))
+		);
+		assert_eq!((half + Uint256::from(1u64)).checked_mul(two), None);
+		assert_eq!(Uint256::MAX.checked_mul(Uint256::MIN), Some(Uint256::MIN));
+		assert_eq!(Uint256::MAX.checked_mul(Uint256::MAX), None);
+
+		let u128_max = Uint256::from(u128::MAX);
+		assert_eq!(u128_max.checked_mul(u128_max), Some(u128_max * u128_max));
+	}
+
+	#[test]
+	fn checked_div_should_detect_division_by_zero() {
+		let x = Uint256::from(1337u64);
+
+		assert_eq!(x.checked_div(Uint256::MIN), None);
+		assert_eq!(
+			x.checked_div(Uint256::from(7u64)),
+			Some(Uint256::from(191u64))
+		);
+	}
+
+	#[test]
+	fn div_rem_should_match_known_values() {
+		let dividend = Uint256::from_be_hex(
+			"000000000000010000000000000000000000000000003039000000000000004d",
+		)
+		.unwrap();
+		let divisor = Uint256::from_be_hex(
+			"0000000000000000000000000000000000000000000000400000000000000003",
+		)
+		.unwrap();
+
+		let (quotient, remainder) = dividend.div_rem(divisor);
+
+		assert_eq!(
+			quotient,
+			Uint256::from_be_hex(
+				"00000000000000000000000000000003ffffffffffffffffd0000000000000c0",
+			)
+			.unwrap()
+		);
+		assert_eq!(
+			remainder,
+			Uint256::from_be_hex(
+				"0000000000000000000000000000000000000000000000398ffffffffffffe0d",
+			)
+			.unwrap()
+		);
+		assert_eq!(dividend / divisor, quotient);
+		assert_eq!(dividend % divisor, remainder);
+		assert_eq!(quotient * divisor + remainder, dividend);
+	}
+
+	#[test]
+	fn div_rem_should_handle_limb_boundaries() {
+		let u128_max = Uint256::from(u128::MAX);
+		let divisor = Uint256::from_u64_array([1, 1, 0, 0]);
+
+		assert_eq!(
+			u128_max.div_rem(divisor),
+			(Uint256::from(u64::MAX), Uint256::MIN)
+		);
+		assert_eq!(
+			Uint256::from(7u64).div_rem(divisor),
+			(Uint256::MIN, Uint256::from(7u64))
+		);
+		assert_eq!(Uint256::MAX % Uint256::from(u64::MAX), Uint256::MIN);
+	}
+
+	#[test]
+	fn pow_should_match_repeated_multiplication() {
+		let three = Uint256::from(3u64);
+		let mut expected = Uint256::from(1u64);
+
+		for exp in 0..200 {
+			assert_eq!(three.pow(exp), expected);
+			expected = expected * three;
+		}
+
+		assert_eq!(
+			three.pow(100),
+			Uint256::from_be_hex(
+				"0000000000000000000000005a4653ca673768565b41f775d6947d55cf3813d1",
+			)
+			.unwrap()
+		);
+		// Wraps around like multiplication does
+		assert_eq!(
+			three.pow(200),
+			Uint256::from_be_hex(
+				"c21a937a76f3432ffd73d97e447606b683ecf6f6e4a7ae225bfaff1eaaf8b0a1",
+			)
+			.unwrap()
+		);
+		assert_eq!(Uint256::MIN.pow(0), Uint256::from(1u64));
+	}
+
+	#[test]
+	fn serde_hex_should_round_trip_through_json() {
+		#[derive(Serialize, Deserialize, Debug, PartialEq)]
+		struct Wrapper {
+			#[serde(with = "serde_hex")]
+			value: Uint256,
+		}
+
+		let wrapper = Wrapper {
+			value: Uint256::from_u64_array([0xdeadbeef, 0, 0, 1]),
+		};
+		let json = serde_json::to_string(&wrapper).unwrap();
+
+		assert_eq!(
+			json,
+			r#"{"value":"0x00000000000000010000000000000000000000000000000000000000deadbeef"}"#
+		);
+		assert_eq!(serde_json::from_str::<Wrapper>(&json).unwrap(), wrapper);
+
+		// The limb representation stays the default
+		assert_eq!(
+			serde_json::to_string(&wrapper.value).unwrap(),
+			"[3735928559,0,0,1]"
+		);
+		assert!(serde_json::from_str::<Wrapper>(
+			r#"{"value":"00000000000000010000000000000000000000000000000000000000deadbeef"}"#
+		)
+		.is_err());
+	}
+
 	#[test]
 	pub fn uint_increment_test() {
 		let mut value = Uint256::from_u64_array([0xffffffffffffffff, 0, 0, 0]);
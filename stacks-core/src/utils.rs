@@ -291,4 +291,23 @@ mod tests {
 			StacksError::InvalidData("Invalid contract name from ST000000000000000000002AMW42H.hello contract: Format should follow the contract name specification".into()).to_string()
 		);
 	}
+
+	#[test]
+	fn should_reject_truncated_contract_principal_data() {
+		let addr = StacksAddress::new(
+			AddressVersion::TestnetSingleSig,
+			Hash160Hasher::default(),
+		);
+		let data = PrincipalData::Contract(
+			StandardPrincipalData(addr.version(), addr),
+			ContractName::new("helloworld").unwrap(),
+		);
+
+		let serialized = data.serialize_to_vec();
+
+		assert!(PrincipalData::deserialize(
+			&mut &serialized[..serialized.len() - 1]
+		)
+		.is_err());
+	}
 }
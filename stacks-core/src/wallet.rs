@@ -1,17 +1,19 @@
 //! Exposes tools to create and manage Stacks credentials.
 
-use std::str::FromStr;
+use std::{ops::Range, str::FromStr};
 
 use bdk::{
 	bitcoin::{
 		secp256k1::Secp256k1,
-		util::bip32::{DerivationPath, ExtendedPrivKey},
+		util::bip32::{
+			ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey,
+		},
 		Address as BitcoinAddress, AddressType as BitcoinAddressType,
 		Network as BitcoinNetwork,
 	},
-	keys::bip39::Mnemonic,
+	keys::bip39::{Mnemonic, WordCount},
 };
-use rand::random;
+use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -33,6 +35,20 @@ pub fn bitcoin_derivation_path(
 	network: BitcoinNetwork,
 	kind: BitcoinAddressType,
 	index: u32,
+) -> StacksResult<DerivationPath> {
+	let account_path = bitcoin_account_derivation_path(network, kind, index)?;
+
+	Ok(account_path.extend([
+		ChildNumber::Normal { index: 0 },
+		ChildNumber::Normal { index: 0 },
+	]))
+}
+
+/// Computes Bitcoin account-level derivation paths
+pub fn bitcoin_account_derivation_path(
+	network: BitcoinNetwork,
+	kind: BitcoinAddressType,
+	account: u32,
 ) -> StacksResult<DerivationPath> {
 	let mut path = "m/".to_string();
 
@@ -52,7 +68,7 @@ pub fn bitcoin_derivation_path(
 		_ => path.push_str("1'/"),
 	}
 
-	path.push_str(&format!("{}'/0/0", index));
+	path.push_str(&format!("{}'", account));
 
 	Ok(DerivationPath::from_str(&path)?)
 }
@@ -89,12 +105,25 @@ impl Wallet {
 		})
 	}
 
-	/// Creates a random wallet
-	pub fn random() -> StacksResult<Self> {
-		let entropy: [u8; 32] = random();
-		let mnemonic = Mnemonic::from_entropy(&entropy)?;
+	/// Creates a wallet from a new mnemonic of the given length, generated
+	/// with the OS RNG. Returns the wallet and its mnemonic phrase.
+	pub fn random(word_count: WordCount) -> StacksResult<(Self, String)> {
+		// BIP39 uses 32 bits of entropy for every 3 words
+		let entropy_length = match word_count {
+			WordCount::Words12 => 16,
+			WordCount::Words15 => 20,
+			WordCount::Words18 => 24,
+			WordCount::Words21 => 28,
+			WordCount::Words24 => 32,
+		};
+
+		let mut entropy = [0u8; 32];
+		OsRng.fill_bytes(&mut entropy[..entropy_length]);
 
-		Self::new(mnemonic.to_string())
+		let phrase =
+			Mnemonic::from_entropy(&entropy[..entropy_length])?.to_string();
+
+		Ok((Self::new(&phrase)?, phrase))
 	}
 
 	/// Returns the mnemonic of the wallet
@@ -129,6 +158,52 @@ impl Wallet {
 	) -> StacksResult<BitcoinCredentials> {
 		BitcoinCredentials::new(network, self.master_key, index)
 	}
+
+	/// Returns the P2TR account extended public key, from which the Bitcoin
+	/// credentials of the account can be derived at `0/0`
+	pub fn account_xpub(
+		&self,
+		network: Network,
+		account: u32,
+	) -> StacksResult<ExtendedPubKey> {
+		let network: BitcoinNetwork = network.into();
+		let account_key = derive_key(
+			self.master_key,
+			bitcoin_account_derivation_path(
+				network,
+				BitcoinAddressType::P2tr,
+				account,
+			)?,
+		);
+
+		let mut xpub =
+			ExtendedPubKey::from_priv(&Secp256k1::new(), &account_key);
+		xpub.network = network;
+
+		Ok(xpub)
+	}
+
+	/// Returns the credentials for each index in the range
+	pub fn credentials_range(
+		&self,
+		network: Network,
+		range: Range<u32>,
+	) -> StacksResult<Vec<Credentials>> {
+		range
+			.map(|index| self.credentials(network, index))
+			.collect()
+	}
+
+	/// Returns the Bitcoin credentials for each index in the range
+	pub fn bitcoin_credentials_range(
+		&self,
+		network: BitcoinNetwork,
+		range: Range<u32>,
+	) -> StacksResult<Vec<BitcoinCredentials>> {
+		range
+			.map(|index| self.bitcoin_credentials(network, index))
+			.collect()
+	}
 }
 
 /// Credentials that can be used to sign transactions
@@ -313,3 +388,85 @@ impl BitcoinCredentials {
 		WIF::new(self.network().into(), self.private_key_p2tr())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn credentials_range_should_match_single_derivations() {
+		let (wallet, _) = Wallet::random(WordCount::Words24).unwrap();
+		let restored = Wallet::new(wallet.mnemonic().to_string()).unwrap();
+
+		let credentials =
+			wallet.credentials_range(Network::Testnet, 0..5).unwrap();
+		let restored_credentials =
+			restored.credentials_range(Network::Testnet, 0..5).unwrap();
+
+		assert_eq!(credentials.len(), 5);
+		for (index, (creds, restored_creds)) in
+			credentials.iter().zip(&restored_credentials).enumerate()
+		{
+			let single =
+				wallet.credentials(Network::Testnet, index as u32).unwrap();
+
+			assert_eq!(creds.address(), single.address());
+			assert_eq!(creds.address(), restored_creds.address());
+		}
+
+		let bitcoin_credentials = wallet
+			.bitcoin_credentials_range(BitcoinNetwork::Testnet, 0..5)
+			.unwrap();
+
+		assert_eq!(bitcoin_credentials.len(), 5);
+		for (index, creds) in bitcoin_credentials.iter().enumerate() {
+			let single = wallet
+				.bitcoin_credentials(BitcoinNetwork::Testnet, index as u32)
+				.unwrap();
+
+			assert_eq!(creds.address_p2pkh(), single.address_p2pkh());
+			assert_eq!(creds.address_p2wpkh(), single.address_p2wpkh());
+			assert_eq!(creds.address_p2tr(), single.address_p2tr());
+		}
+	}
+
+	#[test]
+	fn random_mnemonic_should_restore_the_wallet() {
+		for (word_count, words) in [
+			(WordCount::Words12, 12),
+			(WordCount::Words15, 15),
+			(WordCount::Words18, 18),
+			(WordCount::Words21, 21),
+			(WordCount::Words24, 24),
+		] {
+			let (wallet, phrase) = Wallet::random(word_count).unwrap();
+			let restored = Wallet::new(&phrase).unwrap();
+
+			assert_eq!(phrase.split_whitespace().count(), words);
+			assert_eq!(
+				wallet.credentials(Network::Testnet, 0).unwrap().address(),
+				restored.credentials(Network::Testnet, 0).unwrap().address()
+			);
+		}
+	}
+
+	#[test]
+	fn account_xpub_should_derive_bitcoin_credentials() {
+		let (wallet, _) = Wallet::random(WordCount::Words12).unwrap();
+		let secp = Secp256k1::new();
+		let path = DerivationPath::from_str("m/0/0").unwrap();
+
+		for account in 0..3 {
+			let xpub = wallet.account_xpub(Network::Testnet, account).unwrap();
+			let credentials = wallet
+				.bitcoin_credentials(BitcoinNetwork::Testnet, account)
+				.unwrap();
+
+			assert_eq!(xpub.network, BitcoinNetwork::Testnet);
+			assert_eq!(
+				xpub.derive_pub(&secp, &path).unwrap().public_key,
+				credentials.public_key_p2tr()
+			);
+		}
+	}
+}